@@ -12,6 +12,12 @@ mod cli {
 			"/src/bin/stackl-cc/cli.rs"
 		));
 	}
+	pub mod stackl_ir {
+		include!(concat!(
+			env!("CARGO_MANIFEST_DIR"),
+			"/src/bin/stackl-ir/cli.rs"
+		));
+	}
 	pub mod stackl_vm {
 		include!(concat!(
 			env!("CARGO_MANIFEST_DIR"),
@@ -28,6 +34,7 @@ fn main() -> std::io::Result<()> {
 	std::fs::create_dir_all(MAN_DIR)?;
 	stackl_as(&out_dir)?;
 	stackl_cc(&out_dir)?;
+	stackl_ir()?;
 	stackl_vm()?;
 	Ok(())
 }
@@ -57,6 +64,12 @@ fn stackl_cc(out_dir: &str) -> std::io::Result<()> {
 	Ok(())
 }
 
+fn stackl_ir() -> std::io::Result<()> {
+	let cmd = <cli::stackl_ir::Args as clap::CommandFactory>::command().name("stackl-ir");
+	clap_mangen::generate_to(cmd, MAN_DIR)?;
+	Ok(())
+}
+
 fn stackl_vm() -> std::io::Result<()> {
 	let cmd = <cli::stackl_vm::Args as clap::CommandFactory>::command().name("stackl-vm");
 	clap_mangen::generate_to(cmd, MAN_DIR)?;
@@ -8,4 +8,6 @@ pub struct Args {
 	pub asmfile: path::PathBuf,
 	#[arg(short)]
 	pub outfile: Option<path::PathBuf>,
+	#[arg(short = 'g', help = "Add the global symbols to the debug information")]
+	pub debug: bool,
 }
@@ -11,16 +11,29 @@ use stackl::{
 	StacklFormatV2,
 	asm::ast::*,
 	asm::op,
+	debug::{
+		DebugInfo,
+		FunctionRange,
+		Global,
+		LineEntry,
+		Local,
+	},
 };
 
-pub fn ast_to_fmt2(ast: Vec<Stmt>) -> Result<StacklFormatV2, Box<dyn Error>> {
-	let symtab: HashMap<String, usize> = sym::build_symtab(&ast).unwrap();
+/// Assembles the program, with debug information if `is_debug` is set or
+/// the source has debug directives
+pub fn ast_to_fmt2(ast: Vec<Stmt>, is_debug: bool) -> Result<StacklFormatV2, Box<dyn Error>> {
+	let symtab: HashMap<String, usize> = sym::build_symtab(&ast)?;
 	let mut text = vec![0u8; 8];
 	let mut is_start_global = false;
 	let mut int_vec: i32 = -1;
 	let mut trap_vec: i32 = -1;
 	let mut flags = StacklFlags::empty();
+	let mut debug = DebugInfo::default();
+	let mut has_directives = false;
+	let mut globals: Vec<String> = vec![];
 	for stmt in ast {
+		let address = text.len() as u32;
 		let data: Vec<u8> = match stmt.inst {
 			Inst::Mnemonic(op) => convert_op(&op, &symtab),
 			Inst::DataDecl8(list) => {
@@ -74,6 +87,12 @@ pub fn ast_to_fmt2(ast: Vec<Stmt>) -> Result<StacklFormatV2, Box<dyn Error>> {
 				if !is_start_global {
 					is_start_global = sym.contains(&"_start".to_string());
 				}
+				globals.extend(sym);
+				vec![]
+			}
+			Inst::Directive(directive, args) if is_debug_directive(directive) => {
+				has_directives = true;
+				debug_directive(&mut debug, directive, &args, address, &symtab)?;
 				vec![]
 			}
 			Inst::Directive(Directive::Interrupt, sym) => {
@@ -93,6 +112,7 @@ pub fn ast_to_fmt2(ast: Vec<Stmt>) -> Result<StacklFormatV2, Box<dyn Error>> {
 			Inst::Directive(Directive::Feature, symbols) => {
 				for sym in symbols {
 					match sym.to_ascii_lowercase().as_str() {
+						"gen_io" => flags.set(StacklFlags::FEATURE_GEN_IO, true),
 						"pio_term" => flags.set(StacklFlags::FEATURE_PIO_TERM, true),
 						"dma_term" => flags.set(StacklFlags::FEATURE_DMA_TERM, true),
 						"disk" => flags.set(StacklFlags::FEATURE_DISK, true),
@@ -116,15 +136,112 @@ pub fn ast_to_fmt2(ast: Vec<Stmt>) -> Result<StacklFormatV2, Box<dyn Error>> {
 	text[0..4].copy_from_slice(&int_vec.to_le_bytes());
 	text[4..8].copy_from_slice(&trap_vec.to_le_bytes());
 
+	for name in globals {
+		if debug.find_global(&name).is_none() {
+			debug.globals.push(Global {
+				address: symtab[&name] as u32,
+				name,
+				type_name: String::new(),
+			});
+		}
+	}
+
 	Ok(StacklFormatV2 {
 		magic: [b's', b'l', 0, 0],
 		version: stackl::Version::new(1, 1, 0, 0),
 		flags,
 		stack_size: 1000,
 		text,
+		debug: (is_debug || has_directives).then_some(debug),
 	})
 }
 
+fn is_debug_directive(directive: Directive) -> bool {
+	matches!(
+		directive,
+		Directive::File
+			| Directive::Line
+			| Directive::Func
+			| Directive::EndFunc
+			| Directive::Local
+			| Directive::Object
+	)
+}
+
+/// Records a debug directive found at `address`
+fn debug_directive(
+	debug: &mut DebugInfo,
+	directive: Directive,
+	args: &[String],
+	address: u32,
+	symtab: &HashMap<String, usize>,
+) -> Result<(), Box<dyn Error>> {
+	let invalid = || format!("invalid arguments for [{directive}]: {}", args.join(", "));
+	let number = |index: usize| -> Result<i32, String> {
+		args.get(index)
+			.and_then(|arg| arg.parse().ok())
+			.ok_or_else(invalid)
+	};
+	let string = |index: usize| {
+		args.get(index)
+			.and_then(|arg| unquote(arg))
+			.ok_or_else(invalid)
+	};
+	let symbol = |index: usize| args.get(index).cloned().ok_or_else(invalid);
+	match directive {
+		Directive::File => {
+			let index = number(0)? as usize;
+			if debug.files.len() <= index {
+				debug.files.resize(index + 1, String::new());
+			}
+			debug.files[index] = string(1)?;
+		}
+		Directive::Line => {
+			let entry = LineEntry {
+				address,
+				file: number(0)? as u32,
+				line: number(1)? as u32,
+			};
+			// only the last line of an empty range matters
+			match debug.lines.last_mut() {
+				Some(last) if last.address == address => *last = entry,
+				Some(last) if last.file == entry.file && last.line == entry.line => {}
+				_ => debug.lines.push(entry),
+			}
+		}
+		Directive::Func => debug.functions.push(FunctionRange {
+			name: symbol(0)?,
+			start: address,
+			end: address,
+		}),
+		Directive::EndFunc => {
+			let name = symbol(0)?;
+			let func = debug
+				.functions
+				.iter_mut()
+				.rfind(|func| func.name == name)
+				.ok_or_else(invalid)?;
+			func.end = address;
+		}
+		Directive::Local => {
+			let function = debug.functions.len().checked_sub(1).ok_or_else(invalid)?;
+			debug.locals.push(Local {
+				function: function as u32,
+				name: string(0)?,
+				offset: number(1)?,
+				type_name: string(2)?,
+			});
+		}
+		Directive::Object => debug.globals.push(Global {
+			address: symtab[&symbol(0)?] as u32,
+			name: string(1)?,
+			type_name: string(2)?,
+		}),
+		_ => unreachable!(),
+	}
+	Ok(())
+}
+
 fn convert_op(op: &Opcode, symtab: &HashMap<String, usize>) -> Vec<u8> {
 	let text: Vec<i32> = match op {
 		Opcode::Nop => vec![op::NOP],
@@ -218,6 +335,17 @@ fn convert_op(op: &Opcode, symtab: &HashMap<String, usize>) -> Vec<u8> {
 		Opcode::RotateLeft => vec![op::ROTATE_LEFT],
 		Opcode::RotateRight => vec![op::ROTATE_RIGHT],
 		Opcode::Illegal => vec![op::ILLEGAL],
+		Opcode::Break => vec![op::BREAK],
+		Opcode::FlushTlb => vec![op::FLUSH_TLB],
+		Opcode::AddOvf => vec![op::ADD_OVF],
+		Opcode::SubOvf => vec![op::SUB_OVF],
+		Opcode::MulOvf => vec![op::MUL_OVF],
+		Opcode::NegOvf => vec![op::NEG_OVF],
+		Opcode::Adc => vec![op::ADC],
+		Opcode::Sbb => vec![op::SBB],
+		Opcode::ULt => vec![op::ULT],
+		Opcode::UGt => vec![op::UGT],
+		Opcode::UShiftRight => vec![op::USHIFT_RIGHT],
 		_ => unimplemented!(),
 	};
 
@@ -54,7 +54,15 @@ fn main() -> ExitCode {
 
 	// TODO: add fixup_sections, which will combine section blocks
 
-	let code = code_gen::ast_to_fmt2(ast).unwrap();
+	let code = match code_gen::ast_to_fmt2(ast, args.debug) {
+		Ok(code) => code,
+		Err(err) => {
+			for line in err.to_string().lines() {
+				eprintln!("{}: error: {line}", args.asmfile.display());
+			}
+			return ExitCode::FAILURE;
+		}
+	};
 	let outfile = match args.outfile {
 		Some(o) => o,
 		None => {
@@ -4,15 +4,35 @@ use std::collections::{
 	HashMap,
 	HashSet,
 };
+use std::fmt;
 
 use stackl::asm::ast::*;
 
 #[derive(Debug)]
 pub struct SymTabError {
-	_mis_labels: HashSet<String>,
-	_dup_labels: HashSet<String>,
+	mis_labels: HashSet<String>,
+	dup_labels: HashSet<String>,
 }
 
+impl fmt::Display for SymTabError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut messages: Vec<String> = self
+			.mis_labels
+			.iter()
+			.map(|label| format!("undefined symbol `{label}`"))
+			.chain(
+				self.dup_labels
+					.iter()
+					.map(|label| format!("symbol `{label}` is already defined")),
+			)
+			.collect();
+		messages.sort();
+		write!(f, "{}", messages.join("\n"))
+	}
+}
+
+impl std::error::Error for SymTabError {}
+
 /// On success returns symbol table with corresponding offsets.
 /// On failure returns `SymTabError`.
 pub(crate) fn build_symtab(ast: &[Stmt]) -> Result<HashMap<String, usize>, SymTabError> {
@@ -35,6 +55,27 @@ pub(crate) fn build_symtab(ast: &[Stmt]) -> Result<HashMap<String, usize>, SymTa
 			}
 		}
 
+		let referenced: Vec<&String> = match &stmt.inst {
+			Inst::DataDecl32(list) => list
+				.iter()
+				.filter_map(|atom| match atom {
+					Atom::Label(label) => Some(label),
+					_ => None,
+				})
+				.collect(),
+			Inst::Directive(Directive::Interrupt | Directive::Systrap, args) => {
+				args.iter().collect()
+			}
+			Inst::Directive(Directive::Func | Directive::EndFunc | Directive::Object, args) => {
+				args.iter().take(1).collect()
+			}
+			_ => vec![],
+		};
+		for label in referenced {
+			if !symtab.contains_key(label) {
+				mis_labels.insert(label.clone());
+			}
+		}
 		if let Inst::Mnemonic(op) = &stmt.inst {
 			let some_label = match op {
 				Opcode::JmpUser(Operand::Label(label))
@@ -60,10 +101,10 @@ pub(crate) fn build_symtab(ast: &[Stmt]) -> Result<HashMap<String, usize>, SymTa
 		pos += get_inst_size(&stmt.inst);
 	}
 
-	if !mis_labels.is_empty() {
+	if !mis_labels.is_empty() || !dup_labels.is_empty() {
 		return Err(SymTabError {
-			_mis_labels: mis_labels,
-			_dup_labels: dup_labels,
+			mis_labels,
+			dup_labels,
 		});
 	}
 	Ok(symtab)
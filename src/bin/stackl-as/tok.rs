@@ -205,6 +205,28 @@ pub enum Token {
 	OpRotateRight,
 	#[token("ILLEGAL", ignore(ascii_case))]
 	OpIllegal,
+	#[token("BREAK", ignore(ascii_case))]
+	OpBreak,
+	#[token("FLUSHTLB", ignore(ascii_case))]
+	OpFlushTlb,
+	#[token("ADDV", ignore(ascii_case))]
+	OpAddOvf,
+	#[token("SUBV", ignore(ascii_case))]
+	OpSubOvf,
+	#[token("MULV", ignore(ascii_case))]
+	OpMulOvf,
+	#[token("NEGV", ignore(ascii_case))]
+	OpNegOvf,
+	#[token("ADC", ignore(ascii_case))]
+	OpAdc,
+	#[token("SBB", ignore(ascii_case))]
+	OpSbb,
+	#[token("ULT", ignore(ascii_case))]
+	OpULt,
+	#[token("UGT", ignore(ascii_case))]
+	OpUGt,
+	#[token("USHR", ignore(ascii_case))]
+	OpUShiftRight,
 	// Pseudo Opcodes
 	#[token("DB", ignore(ascii_case))]
 	OpDB,
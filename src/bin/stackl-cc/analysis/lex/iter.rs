@@ -382,6 +382,28 @@ impl PPTokenStack {
 				};
 				return Some(Ok(pp_token));
 			}
+			// register numbers for the intrinsics, in the order of `stackl::asm::ast::Reg`
+			"__STACKL_BP__" | "__STACKL_LP__" | "__STACKL_IP__" | "__STACKL_SP__"
+			| "__STACKL_FP__" | "__STACKL_FLAG__" | "__STACKL_IVEC__" => {
+				let number = match ident.name.as_str() {
+					"__STACKL_BP__" => 0,
+					"__STACKL_LP__" => 1,
+					"__STACKL_IP__" => 2,
+					"__STACKL_SP__" => 3,
+					"__STACKL_FP__" => 4,
+					"__STACKL_FLAG__" => 5,
+					_ => 6,
+				};
+				let kind = tok::PPTokenKind::PPNumber(tok::PPNumber {
+					name: format!("{number}"),
+				});
+				let pp_token = tok::PPToken {
+					kind,
+					leading_space: triple.leading_space,
+					span,
+				};
+				return Some(Ok(pp_token));
+			}
 			// This compiler is freestanding
 			"__STDC_HOSTED__" => {
 				let kind = tok::PPTokenKind::PPNumber(tok::PPNumber {
@@ -24,6 +24,7 @@ impl super::SemanticParser<'_> {
 		let mut is_valid = true;
 		let maybe_ty = self.specifiers_dtype(&mut decl.specifiers, in_func);
 		let maybe_sc = self.specifiers_storage(&mut decl.specifiers);
+		self.specifiers_attributes(&decl.specifiers, false);
 		let (storage, linkage): (sym::StorageClass, sym::Linkage) = match maybe_sc
 			.map(|v| v.kind)
 			.unwrap_or(default_sc)
@@ -102,6 +103,7 @@ impl super::SemanticParser<'_> {
 				is_decl: true,
 			};
 			let key = ident.name.clone();
+			let data_type = new_entry.data_type.clone();
 			if let Err(sym::SymbolTableError::AlreadyExists(prev_entry)) =
 				self.ordinary_table.insert(key.clone(), new_entry.clone())
 			{
@@ -120,6 +122,7 @@ impl super::SemanticParser<'_> {
 				self.data_layouts.as_mut().map(|h| h.insert(layout.clone()));
 				decl.specifiers.storage = Some(sc);
 				decl.specifiers.layout = Some(layout);
+				decl.specifiers.data_type = Some(Box::new(data_type));
 			}
 			self.tree_builder.end_child();
 		}
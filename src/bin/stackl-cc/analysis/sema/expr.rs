@@ -1,5 +1,7 @@
 // Copyright (c) 2024-2026 Jonathan A. Thomason
 
+use stackl::ssa::intrinsic;
+
 use crate::analysis::syn::Constant;
 use crate::diagnostics::*;
 use crate::symtab as sym;
@@ -285,9 +287,77 @@ impl super::SemanticParser<'_> {
 			syn::PostfixKind::Inc => self.tree_builder.begin_child("postfix `++`".to_string()),
 			syn::PostfixKind::Dec => self.tree_builder.begin_child("postfix `--`".to_string()),
 		};
-		self.expr(&mut *unary.expr, context);
+		let mut result = DataType::POISON;
+		let builtin = match (&unary.op.kind, unary.expr.unparen()) {
+			(syn::PostfixKind::ArgExprList(_), syn::Expr::Ident(ident))
+				if ident.name.starts_with(intrinsic::PREFIX) =>
+			{
+				Some(ident.clone())
+			}
+			_ => None,
+		};
+		match (builtin, &mut unary.op.kind) {
+			(Some(ident), syn::PostfixKind::ArgExprList(args)) => {
+				self.tree_builder
+					.add_empty_child(format!("builtin `{}`", ident.name));
+				result = self.builtin_call(&ident, args, context);
+			}
+			_ => {
+				self.expr(&mut *unary.expr, context);
+			}
+		}
 		self.tree_builder.end_child();
-		DataType::POISON
+		result
+	}
+	/// Checks a call of one of the `__builtin_stackl_*` intrinsics against its
+	/// prototype, they need no declaration
+	fn builtin_call(
+		&mut self,
+		ident: &syn::Identifier,
+		args: &mut [syn::Expr],
+		context: &ExprContext,
+	) -> DataType {
+		let Some(builtin) = intrinsic::Intrinsic::from_name(&ident.name) else {
+			let kind = DiagKind::BuiltinUnknown(ident.name.clone());
+			self.diagnostics
+				.push(Diagnostic::error(kind, ident.to_span()));
+			return DataType::POISON;
+		};
+		let params = builtin.params();
+		if args.len() != params.len() {
+			let kind = DiagKind::BuiltinArgCount {
+				prototype: builtin_prototype(builtin),
+				found: args.len(),
+			};
+			self.diagnostics
+				.push(Diagnostic::error(kind, ident.to_span()));
+		}
+		for (arg, param) in args.iter_mut().zip(params) {
+			if *param != intrinsic::Param::Register {
+				self.expr(arg, context);
+				continue;
+			}
+			let span = arg.to_span();
+			match arg.to_u32() {
+				Ok(0..=6) => {}
+				Ok(_) | Err(syn::ConversionError::OutOfRange) => {
+					let error = Diagnostic::error(DiagKind::BuiltinRegister, span);
+					self.diagnostics.push(error);
+				}
+				Err(syn::ConversionError::Expr(_)) => {
+					let error = Diagnostic::error(DiagKind::NonIntConstExpr, span);
+					self.diagnostics.push(error);
+				}
+			}
+		}
+		let kind = match builtin.returns_int() {
+			true => TypeKind::Scalar(ScalarType::SInt),
+			false => TypeKind::Void,
+		};
+		DataType {
+			kind,
+			qual: Default::default(),
+		}
 	}
 	pub(super) fn expr_binary(
 		&mut self,
@@ -496,3 +566,21 @@ impl super::SemanticParser<'_> {
 		}
 	}
 }
+
+/// C declaration of an intrinsic, as shown in diagnostics
+fn builtin_prototype(builtin: intrinsic::Intrinsic) -> String {
+	let params: Vec<&str> = builtin
+		.params()
+		.iter()
+		.map(|param| match param {
+			intrinsic::Param::Register | intrinsic::Param::Int => "int",
+			intrinsic::Param::String => "const char *",
+		})
+		.collect();
+	let params = match params.is_empty() {
+		true => "void".to_owned(),
+		false => params.join(", "),
+	};
+	let result = if builtin.returns_int() { "int" } else { "void" };
+	format!("{result} {}({params})", builtin.name())
+}
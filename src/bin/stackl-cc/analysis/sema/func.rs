@@ -184,6 +184,32 @@ impl super::SemanticParser<'_> {
 			}
 		}
 
+		let is_void_void = matches!(data_type.kind, TypeKind::Void)
+			&& declaration_list
+				.iter()
+				.all(|(_, param_type, _)| matches!(param_type.kind, TypeKind::Void));
+		for handler in self.specifiers_attributes(&decl.specifiers, true) {
+			let name = handler.name.trim_matches('_').to_owned();
+			if !is_void_void {
+				let kind = DiagKind::HandlerSignature(name.clone());
+				let diag = Diagnostic::error(kind, handler.to_span());
+				self.diagnostics.push(diag);
+			}
+			let previous = match name.as_str() {
+				"interrupt" => &mut self.interrupt_handler,
+				_ => &mut self.systrap_handler,
+			};
+			match previous.clone() {
+				Some(previous) => {
+					let kind = DiagKind::HandlerRedefined(name);
+					let mut diag = Diagnostic::error(kind, handler.to_span());
+					diag.push_span(previous, "first handler is here");
+					self.diagnostics.push(diag);
+				}
+				None => *previous = Some(func_ident.to_span()),
+			}
+		}
+
 		self.increase_scope();
 		self.label_table.increase_scope();
 
@@ -379,6 +405,7 @@ impl super::SemanticParser<'_> {
 				self.data_layouts.as_mut().map(|h| h.insert(layout.clone()));
 				param.specifiers.storage = Some(ssa::StorageClass::Automatic);
 				param.specifiers.layout = Some(layout);
+				param.specifiers.data_type = Some(Box::new(param_type.clone()));
 			}
 			result.push((param.ident.clone(), param_type, param.specifiers.to_span()))
 		}
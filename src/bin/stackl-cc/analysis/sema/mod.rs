@@ -39,6 +39,10 @@ pub struct SemanticParser<'a> {
 	warn_lvl: cli::WarnLevel,
 	print_ast: bool,
 	tree_builder: ptree::TreeBuilder,
+	/// Function registered in the interrupt vector
+	interrupt_handler: Option<Span>,
+	/// Function registered in the trap vector
+	systrap_handler: Option<Span>,
 }
 
 impl<'a> SemanticParser<'a> {
@@ -53,6 +57,8 @@ impl<'a> SemanticParser<'a> {
 			warn_lvl: args.warn_lvl,
 			print_ast: args.ast,
 			tree_builder: ptree::TreeBuilder::new("translation-unit".to_string()),
+			interrupt_handler: None,
+			systrap_handler: None,
 		}
 	}
 	pub fn parse(
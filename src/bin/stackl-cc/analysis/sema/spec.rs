@@ -63,6 +63,28 @@ impl super::SemanticParser<'_> {
 		}
 	}
 
+	/// Returns the `interrupt` and `systrap` attributes of a function definition,
+	/// every other attribute is ignored with a warning
+	pub(super) fn specifiers_attributes(
+		&mut self,
+		specifiers: &syn::Specifiers,
+		is_definition: bool,
+	) -> Vec<syn::Identifier> {
+		let mut handlers = vec![];
+		for attribute in specifiers.attributes.iter() {
+			// `__interrupt__` is the same as `interrupt`
+			match attribute.name.trim_matches('_') {
+				"interrupt" | "systrap" if is_definition => handlers.push(attribute.clone()),
+				_ => {
+					let kind = DiagKind::AttributeIgnored(attribute.name.clone());
+					let diag = Diagnostic::warn(kind, attribute.to_span());
+					self.diagnostics.push(diag);
+				}
+			}
+		}
+		handlers
+	}
+
 	pub(super) fn specifiers_dtype(
 		&mut self,
 		specifiers: &mut syn::Specifiers,
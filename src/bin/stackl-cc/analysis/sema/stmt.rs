@@ -3,6 +3,7 @@
 use super::expr::ExprContext;
 use crate::analysis::syn::*;
 use crate::diagnostics as diag;
+use crate::diagnostics::ToSpan;
 
 impl super::SemanticParser<'_> {
 	pub(super) fn compound_stmt(&mut self, stmt: &mut CompoundStmt) {
@@ -47,7 +48,7 @@ impl super::SemanticParser<'_> {
 			}
 			Stmt::Iter(_iter_stmt) => (),
 			Stmt::Jump(inner) => self.jmp_stmt(inner),
-			Stmt::Asm(_asm_stmt) => (),
+			Stmt::Asm(asm_stmt) => is_valid &= self.asm_stmt(asm_stmt),
 			Stmt::Error => {
 				is_valid = false;
 			}
@@ -85,6 +86,86 @@ impl super::SemanticParser<'_> {
 		}
 		self.tree_builder.end_child();
 	}
+	/// Outputs are `"=m"` variables or a single `"=r"` value left on the stack,
+	/// inputs are `"m"` variables, `"r"` values pushed before the template or
+	/// `"i"` constants. Only `"m"` and `"i"` operands may be named in the template.
+	fn asm_stmt(&mut self, stmt: &mut AsmStmt) -> bool {
+		self.tree_builder.begin_child("asm-statement".to_string());
+		let mut errors = vec![];
+		if stmt.qualifiers.contains(&AsmQualifier::Goto) {
+			errors.push(diag::Diagnostic::error(
+				diag::DiagKind::AsmGoto,
+				stmt.template.to_span(),
+			));
+		}
+		let expr_context = ExprContext {
+			in_func: true,
+			is_mut: true,
+			enabled_diag: true,
+		};
+		let mut stack_outputs = 0;
+		let operands = stmt
+			.outputs
+			.iter_mut()
+			.map(|operand| (true, operand))
+			.chain(stmt.inputs.iter_mut().map(|operand| (false, operand)));
+		for (is_output, operand) in operands {
+			let span = operand.constraint.to_span();
+			let kind = match (is_output, operand.constraint.seq.as_str()) {
+				(true, "=r") => {
+					stack_outputs += 1;
+					(stack_outputs > 1).then_some(diag::DiagKind::AsmStackOutputs)
+				}
+				(true, "=m") | (false, "m") => None,
+				(false, "r") => None,
+				(false, "i") => (!matches!(operand.expr.unparen(), Expr::Const(_)))
+					.then_some(diag::DiagKind::NonIntConstExpr),
+				(_, constraint) => Some(diag::DiagKind::AsmConstraint(constraint.to_owned())),
+			};
+			if let Some(kind) = kind {
+				errors.push(diag::Diagnostic::error(kind, span.clone()));
+			}
+			let is_variable = matches!(operand.expr.unparen(), Expr::Ident(_));
+			if operand.constraint.seq != "r" && operand.constraint.seq != "i" && !is_variable {
+				errors.push(diag::Diagnostic::error(
+					diag::DiagKind::AsmNotVariable,
+					operand.expr.to_span(),
+				));
+			}
+			self.expr(&mut operand.expr, &expr_context);
+		}
+		match stmt.pieces() {
+			Ok(pieces) => {
+				for piece in pieces {
+					let reference = match piece {
+						AsmPiece::Index(index) => format!("%{index}"),
+						AsmPiece::Name(name) => format!("%[{name}]"),
+						AsmPiece::Text(_) | AsmPiece::Unique => continue,
+					};
+					let kind = match stmt.operand_index(piece) {
+						None => diag::DiagKind::AsmOperandReference(reference),
+						Some(index) => match stmt.operands().nth(index) {
+							Some(operand) if operand.constraint.seq.ends_with('r') => {
+								diag::DiagKind::AsmStackOperand(reference)
+							}
+							_ => continue,
+						},
+					};
+					errors.push(diag::Diagnostic::error(kind, stmt.template.to_span()));
+				}
+			}
+			Err(reference) => errors.push(diag::Diagnostic::error(
+				diag::DiagKind::AsmOperandReference(reference),
+				stmt.template.to_span(),
+			)),
+		}
+		self.tree_builder.end_child();
+		let is_valid = errors.is_empty();
+		for error in errors {
+			self.diagnostics.push(error);
+		}
+		is_valid
+	}
 	fn stmt_if(&mut self, stmt_cond: &mut Expr) {
 		self.tree_builder
 			.begin_child("if ( expression )".to_string());
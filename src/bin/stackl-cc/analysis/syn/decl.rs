@@ -39,8 +39,12 @@ pub struct Specifiers {
 	pub is_volatile: bool,
 	pub restrict_list: Box<[diag::Span]>,
 	pub inline_list: Box<[diag::Span]>,
+	/// Names in GNU attribute specifiers
+	pub attributes: Box<[Identifier]>,
 	pub storage: Option<ssa::StorageClass>,
 	pub layout: Option<icg::DataLayout>,
+	/// Type of the declared object, as debug information names it
+	pub data_type: Option<Box<DataType>>,
 }
 
 impl From<Vec<SpecifierKind>> for Specifiers {
@@ -52,6 +56,7 @@ impl From<Vec<SpecifierKind>> for Specifiers {
 		let mut type_specifier_list = vec![];
 		let mut restrict_list = vec![];
 		let mut inline_list = vec![];
+		let mut attributes = vec![];
 		for (i, kind) in value.iter().enumerate() {
 			match kind {
 				SpecifierKind::StorageClassSpecifier(inner) => {
@@ -82,12 +87,19 @@ impl From<Vec<SpecifierKind>> for Specifiers {
 					}
 					inline_list.push(span.clone())
 				}
+				SpecifierKind::Attribute(span, list) => {
+					if i == 0 {
+						specifiers.first_span = span.clone();
+					}
+					attributes.extend(list.iter().cloned())
+				}
 			}
 		}
 		specifiers.storage_classes = storage_class_list.into_boxed_slice();
 		specifiers.type_specifiers = type_specifier_list.into_boxed_slice();
 		specifiers.inline_list = inline_list.into_boxed_slice();
 		specifiers.restrict_list = restrict_list.into_boxed_slice();
+		specifiers.attributes = attributes.into_boxed_slice();
 		specifiers
 	}
 }
@@ -107,6 +119,8 @@ pub enum SpecifierKind {
 	TypeQualifier(TypeQualifier),
 	/// (6.7.4) function-specifier
 	Inline(diag::Span),
+	/// GNU attribute-specifier `__attribute__((name, ...))`
+	Attribute(diag::Span, Vec<Identifier>),
 }
 
 /// (6.7) init-declarator
@@ -105,6 +105,13 @@ impl ToSpan for Expr {
 }
 
 impl Expr {
+	/// The expression inside any parentheses
+	pub fn unparen(&self) -> &Self {
+		match self {
+			Self::Paren(inner) => inner.unparen(),
+			other => other,
+		}
+	}
 	#[inline]
 	pub fn with_prefix(op: Prefix, expr: Self) -> Self {
 		Self::UnaryPrefix(UnaryPrefix {
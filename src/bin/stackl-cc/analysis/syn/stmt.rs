@@ -39,15 +39,100 @@ pub enum Stmt {
 	Error,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AsmQualifier {
 	Volatile,
 	Inline,
 	Goto,
 }
 
+/// GNU asm-statement:
+/// `asm qualifiers ( template : outputs : inputs : clobbers ) ;`
 #[derive(Debug)]
-pub struct AsmStmt(pub StringLiteral);
+pub struct AsmStmt {
+	pub qualifiers: Vec<AsmQualifier>,
+	pub template: StringLiteral,
+	pub outputs: Vec<AsmOperand>,
+	pub inputs: Vec<AsmOperand>,
+	pub clobbers: Vec<StringLiteral>,
+}
+
+/// `[name] "constraint" (expression)`
+#[derive(Debug)]
+pub struct AsmOperand {
+	pub name: Option<Identifier>,
+	pub constraint: StringLiteral,
+	pub expr: expr::Expr,
+}
+
+/// Piece of an asm template
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsmPiece<'a> {
+	Text(&'a str),
+	/// `%N`
+	Index(usize),
+	/// `%[name]`
+	Name(&'a str),
+	/// `%=`, a number unique to each instance of the statement
+	Unique,
+}
+
+impl AsmStmt {
+	/// Outputs followed by inputs, in the order `%N` counts them
+	pub fn operands(&self) -> impl Iterator<Item = &AsmOperand> {
+		self.outputs.iter().chain(self.inputs.iter())
+	}
+	/// Index of the operand a piece refers to
+	pub fn operand_index(&self, piece: AsmPiece) -> Option<usize> {
+		match piece {
+			AsmPiece::Index(index) if index < self.outputs.len() + self.inputs.len() => Some(index),
+			AsmPiece::Name(name) => self.operands().position(|operand| {
+				operand
+					.name
+					.as_ref()
+					.is_some_and(|ident| ident.name == name)
+			}),
+			_ => None,
+		}
+	}
+	/// Splits the template, on failure returns the invalid `%` sequence
+	pub fn pieces(&self) -> Result<Vec<AsmPiece<'_>>, String> {
+		let template = self.template.seq.as_str();
+		let mut pieces = vec![];
+		let mut rest = template;
+		while let Some(start) = rest.find('%') {
+			if start != 0 {
+				pieces.push(AsmPiece::Text(&rest[..start]));
+			}
+			let after = &rest[start + 1..];
+			let (piece, len) = match after.chars().next() {
+				Some('%') => (AsmPiece::Text("%"), 1),
+				Some('=') => (AsmPiece::Unique, 1),
+				Some('[') => match after.find(']') {
+					Some(end) => (AsmPiece::Name(&after[1..end]), end + 1),
+					None => return Err(rest[start..].to_owned()),
+				},
+				Some(c) if c.is_ascii_digit() => {
+					let len = after
+						.find(|c: char| !c.is_ascii_digit())
+						.unwrap_or(after.len());
+					match after[..len].parse() {
+						Ok(index) => (AsmPiece::Index(index), len),
+						Err(_) => return Err(format!("%{}", &after[..len])),
+					}
+				}
+				Some(c) => return Err(format!("%{c}")),
+				None => return Err("%".to_owned()),
+			};
+			pieces.push(piece);
+			rest = &after[len..];
+		}
+		if !rest.is_empty() {
+			pieces.push(AsmPiece::Text(rest));
+		}
+		Ok(pieces)
+	}
+}
 
 /// (6.8.1) labeled-statement
 #[derive(Debug)]
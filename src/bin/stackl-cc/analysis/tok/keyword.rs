@@ -8,6 +8,7 @@ use crate::diagnostics::lex;
 #[non_exhaustive]
 pub enum Keyword {
 	Asm,
+	Attribute,
 	Auto,
 	Break,
 	Case,
@@ -49,7 +50,8 @@ impl TryFrom<&str> for Keyword {
 	type Error = lex::TryFromIdentifierError;
 	fn try_from(value: &str) -> Result<Self, Self::Error> {
 		let terminal = match value {
-			"asm" => Keyword::Asm,
+			"asm" | "__asm" | "__asm__" => Keyword::Asm,
+			"__attribute" | "__attribute__" => Keyword::Attribute,
 			"auto" => Keyword::Auto,
 			"break" => Keyword::Break,
 			"case" => Keyword::Case,
@@ -66,7 +68,7 @@ impl TryFrom<&str> for Keyword {
 			"for" => Keyword::For,
 			"goto" => Keyword::Goto,
 			"if" => Keyword::If,
-			"inline" => Keyword::Inline,
+			"inline" | "__inline" | "__inline__" => Keyword::Inline,
 			"int" => Keyword::Int,
 			"long" => Keyword::Long,
 			"register" => Keyword::Register,
@@ -82,7 +84,7 @@ impl TryFrom<&str> for Keyword {
 			"union" => Keyword::Union,
 			"unsigned" => Keyword::Unsigned,
 			"void" => Keyword::Void,
-			"volatile" => Keyword::Volatile,
+			"volatile" | "__volatile" | "__volatile__" => Keyword::Volatile,
 			"while" => Keyword::While,
 			"_Bool" => Keyword::Bool,
 			_ => return Err(lex::TryFromIdentifierError),
@@ -95,6 +97,7 @@ impl fmt::Display for Keyword {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		let keyword = match self {
 			Keyword::Asm => "asm",
+			Keyword::Attribute => "__attribute__",
 			Keyword::Auto => "auto",
 			Keyword::Break => "break",
 			Keyword::Case => "case",
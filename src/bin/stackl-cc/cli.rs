@@ -73,6 +73,23 @@ impl clap::ValueEnum for OptLevel {
 	}
 }
 
+/// Code generation options, given as `-f<option>`
+#[derive(Debug, Clone, Copy, clap::ValueEnum, PartialEq, Eq)]
+pub enum CodegenOption {
+	/// Signed overflow raises the OVF machine check instead of wrapping
+	Trapv,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum, PartialEq, Eq)]
+pub enum Emit {
+	/// Graphviz graph of the control flow of every function
+	CfgDot,
+	/// Textual IR, as read by stackl-ir
+	Ir,
+	/// STACKL assembly, as read by stackl-as
+	Asm,
+}
+
 #[derive(Parser, Debug)]
 #[command(version, about = "Stackl C99 compiler", long_about = None)]
 pub struct Args {
@@ -111,4 +128,12 @@ pub struct Args {
 	pub ast: bool,
 	#[arg(short = 'g', help = "Generate debug information")]
 	pub gen_debug: bool,
+	#[arg(short = 'f', value_enum, help = "Code generation option")]
+	pub codegen: Vec<CodegenOption>,
+	#[arg(
+		long,
+		value_enum,
+		help = "Emit an intermediate form instead of the module"
+	)]
+	pub emit: Option<Emit>,
 }
@@ -85,4 +85,21 @@ pub enum DiagKind {
 	LabeledCompoundEnd,
 	PragmaCxLimitedRange,
 	PragmaIgnored,
+	AsmGoto,
+	AsmConstraint(String),
+	AsmNotVariable,
+	AsmStackOutputs,
+	AsmOperandReference(String),
+	AsmStackOperand(String),
+	BuiltinUnknown(String),
+	BuiltinArgCount {
+		prototype: String,
+		found: usize,
+	},
+	BuiltinRegister,
+	AttributeIgnored(String),
+	HandlerSignature(String),
+	HandlerRedefined(String),
+	/// C the code generator has no lowering for yet
+	Unsupported(String),
 }
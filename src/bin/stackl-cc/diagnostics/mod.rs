@@ -494,6 +494,64 @@ impl DiagnosticEngine {
 				let msg0 = "unrecognized pragma is ignored";
 				self.format_diagnostic(&diag, msg0)
 			}
+			DiagKind::AsmGoto => {
+				let msg0 = "'asm goto' is not supported";
+				self.format_diagnostic(&diag, msg0)
+			}
+			DiagKind::AsmConstraint(constraint) => {
+				let msg0 = format!("invalid constraint '{constraint}' in asm");
+				diag.push_note("outputs take \"=m\" or \"=r\", inputs take \"m\", \"r\" or \"i\"");
+				self.format_diagnostic(&diag, msg0)
+			}
+			DiagKind::AsmNotVariable => {
+				let msg0 = "asm operand must be a variable";
+				self.format_diagnostic(&diag, msg0)
+			}
+			DiagKind::AsmStackOutputs => {
+				let msg0 = "more than one \"=r\" output in asm";
+				diag.push_note("only the top of the stack is returned by the template");
+				self.format_diagnostic(&diag, msg0)
+			}
+			DiagKind::AsmOperandReference(reference) => {
+				let msg0 = format!("invalid operand reference '{reference}' in asm template");
+				self.format_diagnostic(&diag, msg0)
+			}
+			DiagKind::AsmStackOperand(reference) => {
+				let msg0 = format!("'{reference}' names an operand kept on the stack");
+				diag.push_note("\"r\" operands are pushed before the template runs");
+				self.format_diagnostic(&diag, msg0)
+			}
+			DiagKind::BuiltinUnknown(name) => {
+				let msg0 = format!("unknown builtin '{name}'");
+				self.format_diagnostic(&diag, msg0)
+			}
+			DiagKind::BuiltinArgCount { prototype, found } => {
+				let msg0 = format!("wrong number of arguments ({found}) to builtin");
+				diag.push_note(&format!("declared as '{prototype}'"));
+				self.format_diagnostic(&diag, msg0)
+			}
+			DiagKind::BuiltinRegister => {
+				let msg0 = "register number must be a constant from 0 to 6";
+				diag.push_note("use one of __STACKL_BP__, __STACKL_LP__, __STACKL_IP__, __STACKL_SP__, __STACKL_FP__, __STACKL_FLAG__ or __STACKL_IVEC__");
+				self.format_diagnostic(&diag, msg0)
+			}
+			DiagKind::AttributeIgnored(name) => {
+				let msg0 = format!("'{name}' attribute ignored");
+				self.format_diagnostic(&diag, msg0)
+			}
+			DiagKind::HandlerSignature(name) => {
+				let msg0 = format!("'{name}' handler must take and return void");
+				diag.push_note("the handler is entered through a vector, not called");
+				self.format_diagnostic(&diag, msg0)
+			}
+			DiagKind::HandlerRedefined(name) => {
+				let msg0 = format!("more than one '{name}' handler");
+				self.format_diagnostic(&diag, msg0)
+			}
+			DiagKind::Unsupported(what) => {
+				let msg0 = format!("{what} is not supported yet");
+				self.format_diagnostic(&diag, msg0)
+			}
 			DiagKind::CastError { from_type, to_type } => {
 				let msg0 = "cast error";
 				self.format_diagnostic(&diag, msg0)
@@ -24,6 +24,7 @@ use analysis::{
 	tok,
 };
 
+use stackl::ssa;
 use synthesis::icg;
 
 fn main() -> ExitCode {
@@ -119,22 +120,75 @@ fn main() -> ExitCode {
 		return ExitCode::FAILURE;
 	};
 	let codegen_context = icg::IrContext { layouts, unit };
-	let _ssa_module =
-		match icg::SSACodeGen::new(&mut diag_engine, args.is_traced).build(codegen_context) {
+	let is_trapv = args.codegen.contains(&cli::CodegenOption::Trapv);
+	let mut ssa_module =
+		match icg::SSACodeGen::new(&mut diag_engine, args.is_traced, args.gen_debug, is_trapv)
+			.build(codegen_context)
+		{
 			Ok(inner) => inner,
 			Err(fatal) => diag_engine.push_and_exit(fatal),
 		};
 	diag_engine.print_once();
+	if !args.opt_lvl.is_none() {
+		let timer = time::Instant::now();
+		ssa::opt::optimize(&mut ssa_module);
+		let duration = time::Instant::now().duration_since(timer);
+		since_array.push((duration, "optimizer time"));
+	}
 	if args.ast {
 		ptree::print_tree(&tree);
 	}
 	if args.is_timed {
 		print_time(since_array);
 	}
-	println!("{:#?}", _ssa_module);
+	match args.emit {
+		Some(cli::Emit::CfgDot) => {
+			let mut text = String::new();
+			ssa::dot::write_module(&mut text, &ssa_module).unwrap();
+			if let Err(error) = write_output(args.out_file.as_ref(), &text) {
+				eprintln!("error: {error}");
+				return ExitCode::FAILURE;
+			}
+		}
+		Some(cli::Emit::Ir) => {
+			let mut text = String::new();
+			ssa::text::write_module(&mut text, &ssa_module).unwrap();
+			if let Err(error) = write_output(args.out_file.as_ref(), &text) {
+				eprintln!("error: {error}");
+				return ExitCode::FAILURE;
+			}
+		}
+		Some(cli::Emit::Asm) => {
+			let program = match ssa::codegen::emit(&ssa_module) {
+				Ok(program) => program,
+				Err(error) => {
+					eprintln!("error: {error}");
+					return ExitCode::FAILURE;
+				}
+			};
+			let mut text = String::new();
+			ssa::codegen::write_program(&mut text, &program).unwrap();
+			if let Err(error) = write_output(args.out_file.as_ref(), &text) {
+				eprintln!("error: {error}");
+				return ExitCode::FAILURE;
+			}
+		}
+		None => println!("{:#?}", ssa_module),
+	}
 	ExitCode::SUCCESS
 }
 
+/// Writes to the output file, or stdout if there is none
+fn write_output(out_file: Option<&std::path::PathBuf>, text: &str) -> std::io::Result<()> {
+	match out_file {
+		Some(path) => std::fs::write(path, text),
+		None => {
+			print!("{text}");
+			Ok(())
+		}
+	}
+}
+
 fn print_time(since_array: Vec<(Duration, &str)>) {
 	for (duration, name) in since_array {
 		let secs = duration.as_secs();
@@ -0,0 +1,76 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Debug information requested with `-g`
+
+use crate::analysis::syn;
+use crate::diagnostics::{
+	Span,
+	ToSpan,
+};
+
+impl super::SSACodeGen<'_> {
+	/// Marks the code that follows as coming from the span
+	pub(super) fn debug_line(&mut self, span: &Span) {
+		if !self.gen_debug {
+			return;
+		}
+		let Some((line, _, column)) = self.diag_engine.get_location(span) else {
+			return;
+		};
+		let source = match self.sources.get(&span.file_id) {
+			Some(&source) => source,
+			None => {
+				let path = self
+					.diag_engine
+					.get_file_path(span.file_id)
+					.unwrap_or_default();
+				let source = self.builder.source(&path.to_string_lossy());
+				self.sources.insert(span.file_id, source);
+				source
+			}
+		};
+		// outside of a function there is no code to mark
+		let _ = self.builder.line(source, line as u32, column as u32);
+	}
+	/// Gives the source name and type of a variable or parameter
+	pub(super) fn debug_variable(
+		&mut self,
+		id: u32,
+		ident: &syn::Identifier,
+		specifiers: &syn::Specifiers,
+	) {
+		if !self.gen_debug {
+			return;
+		}
+		let type_name = specifiers
+			.data_type
+			.as_ref()
+			.map(|data_type| data_type.to_string())
+			.unwrap_or_default();
+		self.builder.debug_variable(id, &ident.name, &type_name);
+	}
+}
+
+/// Span of the start of a statement that begins with code of its own.
+///
+/// Labeled and compound statements have none, the statements inside them
+/// mark their own lines.
+pub(super) fn stmt_span(stmt: &syn::Stmt) -> Option<Span> {
+	match stmt {
+		syn::Stmt::Expr(syn::ExprStmt(Some(expr))) => Some(expr.to_span()),
+		syn::Stmt::Select(syn::SelectStmt::If { stmt_cond, .. }) => Some(stmt_cond.to_span()),
+		syn::Stmt::Select(syn::SelectStmt::Switch { expr, .. }) => Some(expr.to_span()),
+		syn::Stmt::Iter(syn::IterStmt::While { cond, .. }) => Some(cond.to_span()),
+		syn::Stmt::Iter(syn::IterStmt::ForDecl { init_decl, .. }) => init_decl
+			.init_declarator_list
+			.first()
+			.map(|init_decl| init_decl.identifier.to_span()),
+		syn::Stmt::Iter(syn::IterStmt::ForExpr {
+			init_expr, cond, ..
+		}) => init_expr.as_ref().or(cond.as_ref()).map(ToSpan::to_span),
+		syn::Stmt::Jump(syn::JumpStmt::Goto(ident)) => Some(ident.to_span()),
+		syn::Stmt::Jump(syn::JumpStmt::Return(Some(expr))) => Some(expr.to_span()),
+		syn::Stmt::Asm(stmt) => Some(stmt.template.to_span()),
+		_ => None,
+	}
+}
@@ -1,22 +1,41 @@
 // Copyright (c) 2024-2026 Jonathan A. Thomason
 
 use super::{
+	DataLayout,
 	Diagnostic,
 	syn,
 };
 
 impl super::SSACodeGen<'_> {
 	pub(super) fn declaration(&mut self, decl: &syn::Declaration) -> Result<(), Diagnostic> {
-		let type_id = self.resolve_type(decl.specifiers.layout.as_ref().unwrap());
+		let layout = decl.specifiers.layout.as_ref().unwrap();
+		let type_id = self.resolve_type(layout);
+		if let DataLayout::Function(func_layout) = layout {
+			// a prototype declares a function defined later or elsewhere
+			let control = Self::function_control(&decl.specifiers);
+			for init_decl in &decl.init_declarator_list {
+				let func_id = self.builder.function_declaration(type_id, control);
+				self.builder.name(func_id, &init_decl.identifier.name);
+				self.ordinary_table
+					.insert(init_decl.identifier.name.clone(), func_id);
+				self.function_layouts.insert(func_id, func_layout.clone());
+			}
+			return Ok(());
+		}
 		let storage_class = decl.specifiers.storage.as_ref().unwrap();
-		let mut var_id_list = vec![];
 		for init_decl in &decl.init_declarator_list {
+			if init_decl.initializer.is_some() {
+				self.debug_line(&init_decl.identifier.span);
+			}
 			let init_id = init_decl.initializer.as_ref().map(|i| self.initializer(i));
 			let var_id = self
 				.builder
 				.variable(type_id, storage_class.clone(), init_id)
 				.unwrap();
-			var_id_list.push(var_id);
+			self.ordinary_table
+				.insert(init_decl.identifier.name.clone(), var_id);
+			self.variable_layouts.insert(var_id, layout.clone());
+			self.debug_variable(var_id, &init_decl.identifier, &decl.specifiers);
 		}
 		Ok(())
 	}
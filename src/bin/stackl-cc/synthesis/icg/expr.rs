@@ -7,13 +7,21 @@ use crate::{
 		FloatingKind,
 		IntegerKind,
 	},
+	diagnostics::ToSpan,
 	synthesis::icg::{
+		ArrayLayout,
 		DataLayout,
 		FloatLayout,
+		FunctionLayout,
 		IntegerLayout,
 		PtrLayout,
 	},
 };
+use stackl::ssa::data::StorageClass;
+use stackl::ssa::intrinsic::{
+	Intrinsic,
+	Param,
+};
 use std::mem;
 
 impl super::SSACodeGen<'_> {
@@ -24,29 +32,85 @@ impl super::SSACodeGen<'_> {
 			syn::Expr::UnaryPrefix(inner) => self.unary_prefix(inner),
 			syn::Expr::UnaryPostfix(inner) => self.unary_postfix(inner),
 			syn::Expr::Ternary(inner) => self.ternary(inner),
+			syn::Expr::Ident(inner) => self.identifier(inner),
+			syn::Expr::Paren(inner) => self.expr(inner),
+			syn::Expr::StrLit(inner) => self.string_literal(inner),
+			syn::Expr::Cast(inner) => self.cast(inner),
 			_ => todo!(),
 		}
 	}
 
+	/// Value of a variable or parameter
+	pub(super) fn identifier(&mut self, ident: &syn::Identifier) -> (u32, DataLayout) {
+		let id = *self.ordinary_table.global_lookup(&ident.name).unwrap();
+		if let Some(layout) = self.parameter_layouts.get(&id) {
+			return (id, layout.clone());
+		}
+		let Some(layout) = self.variable_layouts.get(&id).cloned() else {
+			let what = match self.function_layouts.contains_key(&id) {
+				true => format!("using function `{}` as a value", ident.name),
+				false => format!("using `{}` as a value", ident.name),
+			};
+			self.unsupported(what, ident.span.clone())
+		};
+		let result_type = self.resolve_type(&layout);
+		let result_id = self.builder.load(result_type, id).unwrap();
+		(result_id, layout)
+	}
+
+	/// Address of a static array holding the string
+	pub(super) fn string_literal(&mut self, literal: &syn::StringLiteral) -> (u32, DataLayout) {
+		let char_layout = DataLayout::Integer(IntegerLayout {
+			width: 8,
+			is_signed: true,
+		});
+		let array_type = self.resolve_type(&DataLayout::Array(ArrayLayout {
+			component: Box::new(char_layout.clone()),
+			length: literal.seq.len() as u32 + 1,
+		}));
+		let init_id = self.builder.constant_string(array_type, &literal.seq);
+		let var_id = self
+			.builder
+			.variable(array_type, StorageClass::Static, Some(init_id))
+			.unwrap();
+		(
+			var_id,
+			DataLayout::Pointer(PtrLayout(Box::new(char_layout))),
+		)
+	}
+
 	pub(super) fn binary(&mut self, expr: &syn::ExprBinary) -> (u32, DataLayout) {
+		if let syn::expr::BinOpKind::Assign = expr.op.kind {
+			return self.assign(&expr.left, &expr.right);
+		}
 		let lhs = self.expr(&expr.left);
 		let rhs = self.expr(&expr.right);
 		assert!(lhs.1 == rhs.1);
 		let result_type = self.resolve_type(&lhs.1);
+		if let DataLayout::Integer(IntegerLayout { is_signed, .. }) = lhs.1
+			&& let Some(bool_id) = self.comparison(&expr.op.kind, is_signed, lhs.0, rhs.0)
+		{
+			// relational and equality operators yield an int of 0 or 1
+			let int_layout = DataLayout::Integer(IntegerLayout {
+				width: 32,
+				is_signed: true,
+			});
+			let int_type = self.resolve_type(&int_layout);
+			let result_id = self.builder.u_convert(int_type, bool_id).unwrap();
+			return (result_id, int_layout);
+		}
 		let result_id = match (&lhs.1, &expr.op.kind) {
-			(_, syn::expr::BinOpKind::Assign) => {
-				let (id, _layout) = self.assign(&expr.left, &expr.right);
-				id
-			}
-			(DataLayout::Integer(IntegerLayout { width: 32, .. }), syn::expr::BinOpKind::Add) => {
-				self.builder.i_add(result_type, lhs.0, rhs.0).unwrap()
-			}
+			(
+				DataLayout::Integer(IntegerLayout { width: 32 | 64, .. }),
+				syn::expr::BinOpKind::Add,
+			) => self.builder.i_add(result_type, lhs.0, rhs.0).unwrap(),
 			(DataLayout::Float(FloatLayout { width: _ }), syn::expr::BinOpKind::Add) => {
 				self.builder.f_add(result_type, lhs.0, rhs.0).unwrap()
 			}
-			(DataLayout::Integer(IntegerLayout { width: 32, .. }), syn::expr::BinOpKind::Sub) => {
-				self.builder.i_sub(result_type, lhs.0, rhs.0).unwrap()
-			}
+			(
+				DataLayout::Integer(IntegerLayout { width: 32 | 64, .. }),
+				syn::expr::BinOpKind::Sub,
+			) => self.builder.i_sub(result_type, lhs.0, rhs.0).unwrap(),
 			(DataLayout::Float(FloatLayout { width: _ }), syn::expr::BinOpKind::Sub) => {
 				self.builder.f_sub(result_type, lhs.0, rhs.0).unwrap()
 			}
@@ -90,29 +154,82 @@ impl super::SSACodeGen<'_> {
 			(DataLayout::Float(_), syn::expr::BinOpKind::Rem) => {
 				self.builder.f_rem(result_type, lhs.0, rhs.0).unwrap()
 			}
-			_ => {
-				todo!()
-			}
+			_ => self.unsupported("this operator on these operands", expr.op.to_span()),
 		};
 		(result_id, lhs.1)
 	}
 
+	/// Compares two integers, `None` if the operator is not a comparison.
+	/// `a < b` is `b > a` and `a <= b` is `!(a > b)`.
+	fn comparison(
+		&mut self,
+		op: &syn::expr::BinOpKind,
+		is_signed: bool,
+		lhs: u32,
+		rhs: u32,
+	) -> Option<u32> {
+		let bool_type = self.resolve_type(&DataLayout::Bool);
+		let (lhs, rhs, is_negated) = match op {
+			syn::expr::BinOpKind::Equal => {
+				return Some(self.builder.i_equal(bool_type, lhs, rhs).unwrap());
+			}
+			syn::expr::BinOpKind::NotEqual => {
+				return Some(self.builder.i_not_equal(bool_type, lhs, rhs).unwrap());
+			}
+			syn::expr::BinOpKind::Great => (lhs, rhs, false),
+			syn::expr::BinOpKind::Less => (rhs, lhs, false),
+			syn::expr::BinOpKind::LessEqual => (lhs, rhs, true),
+			syn::expr::BinOpKind::GreatEqual => (rhs, lhs, true),
+			_ => return None,
+		};
+		let greater = match is_signed {
+			true => self.builder.s_greater_than(bool_type, lhs, rhs),
+			false => self.builder.u_greater_than(bool_type, lhs, rhs),
+		}
+		.unwrap();
+		match is_negated {
+			true => Some(self.builder.logical_not(bool_type, greater).unwrap()),
+			false => Some(greater),
+		}
+	}
+
+	/// Stores `rhs` in the variable named by `lhs`, the assignment has the
+	/// value stored
 	pub(super) fn assign(&mut self, lhs: &syn::Expr, rhs: &syn::Expr) -> (u32, DataLayout) {
+		let (var_id, layout) = self.lvalue(lhs);
 		let (rhs_id, rhs_layout) = self.expr(rhs);
-		let (lhs_id, lhs_layout) = self.expr(lhs);
-
-		// TODO: One of the asserts is expected to fail. Fix this later.
-		assert!(
-			matches!(lhs_layout, DataLayout::Pointer(_)),
-			"Assignment: left-hand side must be a pointer"
-		);
-		assert!(
-			!matches!(rhs_layout, DataLayout::Pointer(_)),
-			"Assignment: right-hand side must not be a pointer (object only)"
-		);
+		let value_id = match rhs_layout == layout {
+			true => rhs_id,
+			false => match self.convert(rhs_id, &rhs_layout, &layout) {
+				Some(id) => id,
+				None => self.unsupported("assigning a value of another type", rhs.to_span()),
+			},
+		};
+		self.builder.store(var_id, value_id).unwrap();
+		(value_id, layout)
+	}
 
-		self.builder.store(lhs_id, rhs_id);
-		(lhs_id, lhs_layout)
+	/// Variable named by the left-hand side of an assignment
+	fn lvalue(&mut self, expr: &syn::Expr) -> (u32, DataLayout) {
+		let ident = match expr.unparen() {
+			syn::Expr::Ident(ident) => ident,
+			// sema marks the left-hand side as read like any other operand
+			syn::Expr::Cast(syn::ExprCast {
+				kind: syn::CastKind::LValueToRValue,
+				expr,
+				..
+			}) => return self.lvalue(expr),
+			_ => self.unsupported("assigning to anything but a variable", expr.to_span()),
+		};
+		let id = *self.ordinary_table.global_lookup(&ident.name).unwrap();
+		if let Some(layout) = self.variable_layouts.get(&id) {
+			return (id, layout.clone());
+		}
+		let what = match self.parameter_layouts.contains_key(&id) {
+			true => format!("assigning to parameter `{}`", ident.name),
+			false => format!("assigning to `{}`", ident.name),
+		};
+		self.unsupported(what, ident.span.clone())
 	}
 
 	pub(super) fn constant(&mut self, constant: &syn::Constant) -> (u32, DataLayout) {
@@ -138,6 +255,24 @@ impl super::SSACodeGen<'_> {
 				};
 				(id, layout)
 			}
+			&syn::ConstantKind::Integer(IntegerKind::U64(num)) => {
+				let layout = DataLayout::Integer(IntegerLayout {
+					width: 64,
+					is_signed: false,
+				});
+				let result_type = self.resolve_type(&layout);
+				let id = self.builder.constant_bit64(result_type, num);
+				(id, layout)
+			}
+			&syn::ConstantKind::Integer(IntegerKind::I64(num)) => {
+				let layout = DataLayout::Integer(IntegerLayout {
+					width: 64,
+					is_signed: true,
+				});
+				let result_type = self.resolve_type(&layout);
+				let id = self.builder.constant_bit64(result_type, num as u64);
+				(id, layout)
+			}
 			&syn::ConstantKind::Floating(FloatingKind::Float(num)) => {
 				let layout = DataLayout::Float(FloatLayout { width: 32 });
 				let result_type = self.resolve_type(&layout);
@@ -151,6 +286,66 @@ impl super::SSACodeGen<'_> {
 		}
 	}
 
+	pub(super) fn cast(&mut self, expr: &syn::ExprCast) -> (u32, DataLayout) {
+		let to = match &expr.kind {
+			// identifiers are already loaded
+			syn::CastKind::LValueToRValue => return self.expr(&expr.expr),
+			syn::CastKind::SExt(kind) | syn::CastKind::ZExt(kind) | syn::CastKind::Trunc(kind) => {
+				DataLayout::try_from((**kind).clone()).ok()
+			}
+			syn::CastKind::IntToBool => Some(DataLayout::Bool),
+			_ => None,
+		};
+		let (id, from) = self.expr(&expr.expr);
+		if let Some(to) = to
+			&& let Some(result_id) = self.convert(id, &from, &to)
+		{
+			return (result_id, to);
+		}
+		let what = match &expr.kind {
+			syn::CastKind::BitCast => "a bit cast",
+			syn::CastKind::FnToPtr => "converting a function to a pointer",
+			syn::CastKind::PtrToInt | syn::CastKind::IntToPtr => {
+				"converting between pointers and integers"
+			}
+			syn::CastKind::Explicit(_) => "an explicit cast",
+			_ if matches!(from, DataLayout::Float(_)) => "converting a floating value",
+			_ => "converting to a floating type",
+		};
+		self.unsupported(what, expr.span.clone())
+	}
+
+	/// Converts an integer to another integer type or to bool, `None` for
+	/// other types
+	fn convert(&mut self, id: u32, from: &DataLayout, to: &DataLayout) -> Option<u32> {
+		let result_type = self.resolve_type(to);
+		let from_int = match from {
+			DataLayout::Bool => IntegerLayout {
+				width: 1,
+				is_signed: false,
+			},
+			DataLayout::Integer(layout) => layout.clone(),
+			_ => return None,
+		};
+		let result_id = match to {
+			DataLayout::Bool => {
+				let from_type = self.resolve_type(from);
+				let zero = match from_int.width {
+					64 => self.builder.constant_bit64(from_type, 0),
+					_ => self.builder.constant_bit32(from_type, 0),
+				};
+				self.builder.i_not_equal(result_type, id, zero)
+			}
+			// the value of the source type is kept when it is extended
+			DataLayout::Integer(to_int) if from_int.is_signed && from_int.width < to_int.width => {
+				self.builder.s_convert(result_type, id)
+			}
+			DataLayout::Integer(_) => self.builder.u_convert(result_type, id),
+			_ => return None,
+		};
+		Some(result_id.unwrap())
+	}
+
 	pub(super) fn unary_prefix(&mut self, expr: &syn::UnaryPrefix) -> (u32, DataLayout) {
 		match &expr.op.kind {
 			syn::PrefixKind::Plus => todo!("unary plus: return value unchanged"),
@@ -165,6 +360,9 @@ impl super::SSACodeGen<'_> {
 	}
 
 	pub(super) fn unary_postfix(&mut self, expr: &syn::UnaryPostfix) -> (u32, DataLayout) {
+		if let syn::PostfixKind::ArgExprList(args) = &expr.op.kind {
+			return self.function_call(&expr.expr, args);
+		}
 		let inner = self.expr(&expr.expr);
 		let result_type = inner.1;
 		let result_id = match &expr.op.kind {
@@ -173,11 +371,84 @@ impl super::SSACodeGen<'_> {
 			&syn::PostfixKind::Array(_) => todo!("array indexing"),
 			&syn::PostfixKind::Dot(_) => todo!("struct member access"),
 			&syn::PostfixKind::Arrow(_) => todo!("struct pointer member access"),
-			&syn::PostfixKind::ArgExprList(_) => todo!("function call"),
+			&syn::PostfixKind::ArgExprList(_) => unreachable!(),
 		};
 		(result_id, result_type)
 	}
 
+	fn function_call(&mut self, callee: &syn::Expr, args: &[syn::Expr]) -> (u32, DataLayout) {
+		let builtin = match callee.unparen() {
+			syn::Expr::Ident(ident) => Intrinsic::from_name(&ident.name),
+			_ => None,
+		};
+		if let Some(builtin) = builtin {
+			return self.builtin_call(builtin, args);
+		}
+		let func_id = match callee.unparen() {
+			syn::Expr::Ident(ident) => self.ordinary_table.global_lookup(&ident.name).copied(),
+			_ => None,
+		};
+		let Some((func_id, layout)) = func_id.and_then(|id| {
+			let layout = self.function_layouts.get(&id)?;
+			Some((id, layout.clone()))
+		}) else {
+			self.unsupported("calling through a function pointer", callee.to_span())
+		};
+		let arg_ids: Vec<u32> = args.iter().map(|arg| self.expr(arg).0).collect();
+		let result_type = self.resolve_type(&layout.ret);
+		let result_id = self
+			.builder
+			.function_call(result_type, func_id, arg_ids)
+			.unwrap();
+		(result_id, *layout.ret)
+	}
+
+	/// Calls the declaration of the intrinsic, made on its first use
+	fn builtin_call(&mut self, builtin: Intrinsic, args: &[syn::Expr]) -> (u32, DataLayout) {
+		let int_layout = DataLayout::Integer(IntegerLayout {
+			width: 32,
+			is_signed: true,
+		});
+		let ret_layout = match builtin.returns_int() {
+			true => int_layout.clone(),
+			false => DataLayout::Void,
+		};
+		let arg_ids: Vec<u32> = args.iter().map(|arg| self.expr(arg).0).collect();
+		let func_id = match self.intrinsics.get(&builtin) {
+			Some(id) => *id,
+			None => {
+				let params = builtin
+					.params()
+					.iter()
+					.map(|param| match param {
+						Param::Register | Param::Int => int_layout.clone(),
+						Param::String => DataLayout::Pointer(PtrLayout(Box::new(
+							DataLayout::Integer(IntegerLayout {
+								width: 8,
+								is_signed: true,
+							}),
+						))),
+					})
+					.collect();
+				let func_type = self.resolve_type(&DataLayout::Function(FunctionLayout {
+					params,
+					ret: Box::new(ret_layout.clone()),
+					is_variadic: false,
+				}));
+				let id = self.builder.function_declaration(func_type, 0);
+				self.builder.name(id, builtin.name());
+				self.intrinsics.insert(builtin, id);
+				id
+			}
+		};
+		let result_type = self.resolve_type(&ret_layout);
+		let result_id = self
+			.builder
+			.function_call(result_type, func_id, arg_ids)
+			.unwrap();
+		(result_id, ret_layout)
+	}
+
 	pub(super) fn ternary(&mut self, expr: &syn::ExprTernary) -> (u32, DataLayout) {
 		let (cond_id, cond_layout) = self.expr(&expr.expr_cond);
 		let then_label_id = self.builder.id();
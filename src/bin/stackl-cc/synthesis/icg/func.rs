@@ -10,6 +10,7 @@ use super::{
 	Diagnostic,
 	syn,
 };
+use stackl::ssa::data::function_control;
 
 impl super::SSACodeGen<'_> {
 	pub(super) fn function_definition(
@@ -17,14 +18,24 @@ impl super::SSACodeGen<'_> {
 		def: &syn::FunctionDefinition,
 	) -> Result<(), Diagnostic> {
 		let ret_layout = Box::new(def.specifiers.layout.clone().unwrap());
+		let mut function_control = Self::function_control(&def.specifiers);
+		if self.is_trapv {
+			function_control |= function_control::TRAP_OVERFLOW;
+		}
 		match def.declarators.first().as_ref().unwrap() {
 			syn::Declarator::IdentList(syn::IdentList { ident_list, .. }) => {
-				let func_type = self.resolve_type(&DataLayout::Function(FunctionLayout {
+				let layout = FunctionLayout {
 					params: vec![],
 					ret: ret_layout,
 					is_variadic: true,
-				}));
-				let func_id = self.builder.function_begin(func_type, 0).unwrap();
+				};
+				let func_type = self.resolve_type(&DataLayout::Function(layout.clone()));
+				let func_id = self
+					.builder
+					.function_begin(func_type, function_control)
+					.unwrap();
+				self.function_layouts.insert(func_id, layout);
+				self.builder.name(func_id, &def.ident.name);
 				self.ordinary_table.insert(def.ident.name.clone(), func_id);
 				self.increase_scope();
 				self.function_declarations(&def.declaration_list)
@@ -37,12 +48,18 @@ impl super::SSACodeGen<'_> {
 					.iter()
 					.map(|p| p.specifiers.layout.clone().unwrap())
 					.collect();
-				let func_type = self.resolve_type(&DataLayout::Function(FunctionLayout {
+				let layout = FunctionLayout {
 					params,
 					ret: ret_layout,
 					is_variadic: *is_variadic,
-				}));
-				let func_id = self.builder.function_begin(func_type, 0).unwrap();
+				};
+				let func_type = self.resolve_type(&DataLayout::Function(layout.clone()));
+				let func_id = self
+					.builder
+					.function_begin(func_type, function_control)
+					.unwrap();
+				self.function_layouts.insert(func_id, layout);
+				self.builder.name(func_id, &def.ident.name);
 				self.ordinary_table.insert(def.ident.name.clone(), func_id);
 				self.increase_scope();
 				self.function_parameters(param_list);
@@ -65,13 +82,48 @@ impl super::SSACodeGen<'_> {
 		self.builder.function_end();
 		Ok(())
 	}
+	/// An `inline` definition without `extern` is not an external definition (C99 6.7.4),
+	/// neither is a `static` one. Handlers keep their attribute.
+	pub(super) fn function_control(specifiers: &syn::Specifiers) -> u32 {
+		let is_static = specifiers
+			.storage_classes
+			.iter()
+			.any(|specifier| matches!(specifier.kind, syn::StorageClass::Static));
+		let is_extern = specifiers
+			.storage_classes
+			.iter()
+			.any(|specifier| matches!(specifier.kind, syn::StorageClass::Extern));
+		let is_inline = !specifiers.inline_list.is_empty();
+		let mut control = 0;
+		for attribute in specifiers.attributes.iter() {
+			match attribute.name.trim_matches('_') {
+				"interrupt" => control |= function_control::INTERRUPT,
+				"systrap" => control |= function_control::SYSTRAP,
+				_ => {}
+			}
+		}
+		if is_inline {
+			control |= function_control::INLINE;
+		}
+		if is_static || (is_inline && !is_extern) {
+			control |= function_control::INTERNAL;
+		}
+		control
+	}
 	fn function_parameters(&mut self, params: &[syn::ParameterDeclaration]) {
 		for param in params.iter() {
-			let type_id = self.resolve_type(&param.specifiers.layout.as_ref().unwrap());
+			let layout = param.specifiers.layout.as_ref().unwrap();
+			// `(void)` declares no parameters
+			if *layout == DataLayout::Void && param.ident.is_none() {
+				continue;
+			}
+			let type_id = self.resolve_type(layout);
 			let param_id = self.builder.function_parameter(type_id).unwrap();
+			self.parameter_layouts.insert(param_id, layout.clone());
 			if let Some(param_ident) = param.ident.as_ref() {
 				self.ordinary_table
 					.insert(param_ident.name.clone(), param_id);
+				self.debug_variable(param_id, param_ident, &param.specifiers);
 			}
 		}
 	}
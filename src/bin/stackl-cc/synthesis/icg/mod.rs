@@ -3,6 +3,7 @@
 //! Intermediate Code Generation
 
 mod data;
+mod debug;
 mod decl;
 mod expr;
 mod func;
@@ -19,12 +20,14 @@ use crate::diagnostics::{
 	DiagKind,
 	Diagnostic,
 	DiagnosticEngine,
+	Span,
 };
 use crate::symtab::SymbolTable;
 pub use layout::*;
 use stackl::ssa::{
 	builder::Builder,
 	data::Module,
+	intrinsic::Intrinsic,
 };
 
 #[derive(Debug)]
@@ -39,22 +42,47 @@ pub struct SSACodeGen<'a> {
 	label_table: SymbolTable<String, u32>,
 	tag_table: SymbolTable<String, u32>,
 	ordinary_table: SymbolTable<String, u32>,
+	/// Layout of the object of every variable
+	variable_layouts: HashMap<u32, DataLayout>,
+	/// Layout of every parameter
+	parameter_layouts: HashMap<u32, DataLayout>,
+	/// Layout of every function defined or declared
+	function_layouts: HashMap<u32, FunctionLayout>,
+	/// Declaration of every intrinsic called so far
+	intrinsics: HashMap<Intrinsic, u32>,
 	diag_engine: &'a mut DiagnosticEngine,
 	is_traced: bool,
+	gen_debug: bool,
+	/// Functions raise OVF on signed overflow, `-ftrapv`
+	is_trapv: bool,
+	/// `Source` of every file id, declared once code from the file needs it
+	sources: HashMap<usize, u32>,
 	// Track the current loop for continue/break statements
 	current_loop_label: Option<u32>,
 }
 
 impl<'a> SSACodeGen<'a> {
-	pub fn new(diag_engine: &'a mut DiagnosticEngine, is_traced: bool) -> Self {
+	pub fn new(
+		diag_engine: &'a mut DiagnosticEngine,
+		is_traced: bool,
+		gen_debug: bool,
+		is_trapv: bool,
+	) -> Self {
 		Self {
 			builder: Builder::new(),
 			type_map: HashMap::new(),
 			label_table: SymbolTable::new(),
 			tag_table: SymbolTable::new(),
 			ordinary_table: SymbolTable::new(),
+			variable_layouts: HashMap::new(),
+			parameter_layouts: HashMap::new(),
+			function_layouts: HashMap::new(),
+			intrinsics: HashMap::new(),
 			diag_engine,
 			is_traced,
+			gen_debug,
+			is_trapv,
+			sources: HashMap::new(),
 			current_loop_label: None,
 		}
 	}
@@ -63,6 +91,12 @@ impl<'a> SSACodeGen<'a> {
 		self.tag_table.increase_scope();
 		self.ordinary_table.increase_scope();
 	}
+	/// Reports C that has no lowering yet and stops
+	pub(self) fn unsupported(&mut self, what: impl Into<String>, span: Span) -> ! {
+		let kind = DiagKind::Unsupported(what.into());
+		self.diag_engine
+			.push_and_exit(Diagnostic::error(kind, span))
+	}
 	pub(self) fn decrease_scope(&mut self) {
 		self.label_table.decrease_scope();
 		self.tag_table.decrease_scope();
@@ -5,6 +5,7 @@ use crate::{
 	diagnostics::{
 		DiagKind,
 		Diagnostic,
+		ToSpan,
 	},
 	synthesis::icg::{
 		DataLayout,
@@ -14,6 +15,9 @@ use crate::{
 
 impl super::SSACodeGen<'_> {
 	pub(super) fn statement(&mut self, stmt: &syn::Stmt) -> Result<(), Diagnostic> {
+		if let Some(span) = super::debug::stmt_span(stmt) {
+			self.debug_line(&span);
+		}
 		match stmt {
 			syn::Stmt::Label(syn::LabeledStmt::Label(label, stmt)) => {
 				// Check if label already exists in label_table
@@ -149,8 +153,81 @@ impl super::SSACodeGen<'_> {
 				// In a more complete implementation, we'd track the specific loop exit label
 				todo!("break statement - need loop exit label tracking");
 			}
+			syn::Stmt::Asm(asm_stmt) => self.asm_statement(asm_stmt)?,
 			other => todo!("{other:?}"),
 		}
 		Ok(())
 	}
+	/// Lowers to a call of an assembler snippet. `"m"` and `"i"` operands are
+	/// passed as the variable or constant itself so the backend can write its
+	/// frame offset, symbol or value where the template names it. `"r"`
+	/// operands are pushed before the template runs, a `"=r"` output is the
+	/// result of the call.
+	fn asm_statement(&mut self, stmt: &syn::AsmStmt) -> Result<(), Diagnostic> {
+		let mut args = vec![];
+		// argument of every operand, in the order `%N` counts them
+		let mut arg_index = vec![];
+		let mut output = None;
+		for operand in stmt.operands() {
+			let arg = match operand.constraint.seq.as_str() {
+				"=r" => {
+					output = Some(self.asm_variable(&operand.expr, false)?);
+					arg_index.push(None);
+					continue;
+				}
+				"=m" => self.asm_variable(&operand.expr, false)?,
+				"m" => self.asm_variable(&operand.expr, true)?,
+				_ => self.expr(&operand.expr).0,
+			};
+			arg_index.push(Some(args.len()));
+			args.push(arg);
+		}
+		let mut text = String::new();
+		for piece in stmt.pieces().unwrap() {
+			match piece {
+				syn::AsmPiece::Text(inner) => text.push_str(&inner.replace('%', "%%")),
+				syn::AsmPiece::Unique => text.push_str("%="),
+				piece => {
+					let index = stmt
+						.operand_index(piece)
+						.and_then(|index| arg_index[index])
+						.unwrap();
+					text.push_str(&format!("%{index}"));
+				}
+			}
+		}
+		let result_type = match output {
+			Some(variable) => {
+				let layout = self.variable_layouts[&variable].clone();
+				self.resolve_type(&layout)
+			}
+			None => self.resolve_type(&DataLayout::Void),
+		};
+		let snippet = self.builder.assembler(text);
+		let result_id = self
+			.builder
+			.function_call(result_type, snippet, args)
+			.unwrap();
+		if let Some(variable) = output {
+			self.builder.store(variable, result_id).unwrap();
+		}
+		Ok(())
+	}
+	/// Variable named by an asm operand, or a parameter if it is only read
+	fn asm_variable(&mut self, expr: &syn::Expr, is_input: bool) -> Result<u32, Diagnostic> {
+		let not_variable = || Diagnostic::error(DiagKind::AsmNotVariable, expr.to_span());
+		let syn::Expr::Ident(ident) = expr.unparen() else {
+			return Err(not_variable());
+		};
+		let id = *self
+			.ordinary_table
+			.global_lookup(&ident.name)
+			.ok_or_else(not_variable)?;
+		let is_parameter = self.parameter_layouts.contains_key(&id);
+		if self.variable_layouts.contains_key(&id) || (is_input && is_parameter) {
+			Ok(id)
+		} else {
+			Err(not_variable())
+		}
+	}
 }
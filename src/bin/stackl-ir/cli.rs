@@ -0,0 +1,69 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+use std::path::PathBuf;
+
+use clap::Parser;
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum, PartialEq, Eq)]
+pub enum Pass {
+	/// Promote scalar locals to SSA values
+	Mem2reg,
+	/// Inline small and `inline` functions
+	Inline,
+	/// Sparse conditional constant propagation
+	Sccp,
+	/// Dead code elimination
+	Dce,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum, PartialEq, Eq, Default)]
+pub enum Emit {
+	/// Textual IR
+	#[default]
+	Ir,
+	/// Binary IR
+	Binary,
+	/// STACKL assembly
+	Asm,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum, PartialEq, Eq, Default)]
+pub enum Schedule {
+	/// Store every value to a frame slot
+	Naive,
+	/// Keep values on the operand stack when possible
+	#[default]
+	Stack,
+}
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Stackl IR optimizer and disassembler", long_about = None)]
+pub struct Args {
+	#[arg(
+		name = "FILE",
+		required = true,
+		help = "IR module in text or binary form"
+	)]
+	pub in_file: PathBuf,
+	#[arg(long = "output", short = 'o')]
+	pub out_file: Option<PathBuf>,
+	#[arg(
+		short = 'p',
+		long,
+		value_enum,
+		value_delimiter = ',',
+		help = "Passes to run, in order"
+	)]
+	pub passes: Vec<Pass>,
+	#[arg(long, help = "Verify the module after reading it and after every pass")]
+	pub verify: bool,
+	#[arg(long, value_enum, default_value_t = Default::default())]
+	pub emit: Emit,
+	#[arg(
+		long,
+		value_enum,
+		default_value_t = Default::default(),
+		help = "Where STACKL assembly keeps values"
+	)]
+	pub schedule: Schedule,
+}
@@ -1,3 +1,110 @@
 // Copyright (c) 2024-2026 Jonathan A. Thomason
 
-fn main() {}
+mod cli;
+
+use std::fs;
+use std::io::Write;
+use std::process::ExitCode;
+
+use clap::Parser;
+use stackl::ssa::data::Module;
+use stackl::ssa::{
+	binary,
+	codegen,
+	opt,
+	text,
+	verify,
+};
+
+fn main() -> ExitCode {
+	let args = cli::Args::parse();
+	let bytes = match fs::read(&args.in_file) {
+		Ok(bytes) => bytes,
+		Err(err) => {
+			eprintln!(
+				"stackl-ir: fatal: can't open '{}' for reading: {}",
+				args.in_file.display(),
+				err
+			);
+			return ExitCode::FAILURE;
+		}
+	};
+	let module = if binary::is_binary(&bytes) {
+		binary::decode(&bytes).map_err(|err| err.to_string())
+	} else {
+		String::from_utf8(bytes)
+			.map_err(|_| "file is neither binary nor text IR".to_owned())
+			.and_then(|source| text::parse_module(&source).map_err(|err| err.to_string()))
+	};
+	let mut module = match module {
+		Ok(module) => module,
+		Err(err) => {
+			eprintln!("stackl-ir: fatal: {}: {err}", args.in_file.display());
+			return ExitCode::FAILURE;
+		}
+	};
+
+	if args.verify && !check(&module, "input") {
+		return ExitCode::FAILURE;
+	}
+	for pass in args.passes.iter() {
+		let (run, name): (fn(&mut Module), &str) = match pass {
+			cli::Pass::Mem2reg => (opt::mem2reg, "mem2reg"),
+			cli::Pass::Inline => (opt::inline_functions, "inline"),
+			cli::Pass::Sccp => (opt::sccp, "sccp"),
+			cli::Pass::Dce => (opt::dce, "dce"),
+		};
+		run(&mut module);
+		if args.verify && !check(&module, name) {
+			return ExitCode::FAILURE;
+		}
+	}
+
+	let output = match args.emit {
+		cli::Emit::Ir => {
+			let mut out = String::new();
+			text::write_module(&mut out, &module).unwrap();
+			out.into_bytes()
+		}
+		cli::Emit::Binary => binary::encode(&module),
+		cli::Emit::Asm => {
+			let schedule = match args.schedule {
+				cli::Schedule::Naive => codegen::Schedule::Naive,
+				cli::Schedule::Stack => codegen::Schedule::Stack,
+			};
+			match codegen::emit_with(&module, schedule) {
+				Ok(program) => {
+					let mut out = String::new();
+					codegen::write_program(&mut out, &program).unwrap();
+					out.into_bytes()
+				}
+				Err(err) => {
+					eprintln!("stackl-ir: error: {err}");
+					return ExitCode::FAILURE;
+				}
+			}
+		}
+	};
+	let result = match &args.out_file {
+		Some(path) => fs::write(path, output),
+		None => std::io::stdout().write_all(&output),
+	};
+	if let Err(err) = result {
+		eprintln!("stackl-ir: fatal: can't write output: {err}");
+		return ExitCode::FAILURE;
+	}
+	ExitCode::SUCCESS
+}
+
+/// Prints every verifier error, returns true if there was none
+fn check(module: &Module, stage: &str) -> bool {
+	match verify::verify(module) {
+		Ok(()) => true,
+		Err(errors) => {
+			for err in errors {
+				eprintln!("stackl-ir: error: after {stage}: {err}");
+			}
+			false
+		}
+	}
+}
@@ -33,13 +33,57 @@ pub struct Args {
 		help = "Enable the General IO device"
 	)]
 	pub gen_io: bool,
-	// TODO: implement processor delay
+	#[arg(
+		long,
+		value_name = "IMAGE",
+		help = "Attach a disk backed by an image file"
+	)]
+	pub disk: Option<PathBuf>,
+	#[arg(
+		long,
+		requires = "disk",
+		default_value_t = false,
+		help = "Fail every write to the disk image"
+	)]
+	pub read_only: bool,
 	#[arg(
 		long,
 		default_value_t = 33.0,
-		help = "Set the processor speed in megahertz"
+		help = "Set the processor speed in megahertz, 0 runs at full host speed"
+	)]
+	pub mhz: f64,
+	#[arg(
+		long,
+		default_value_t = false,
+		help = "Report the instructions run and the speed reached on exit"
+	)]
+	pub stats: bool,
+	#[arg(
+		long,
+		value_name = "ENTRIES",
+		default_value_t = 0,
+		help = "Cache page table entries in a TLB, FLUSHTLB empties it"
+	)]
+	pub tlb: usize,
+	#[arg(
+		short = 'g',
+		long,
+		default_value_t = false,
+		help = "Run the program under the interactive debugger"
 	)]
-	pub mhz: f32,
-	#[arg(short = 'g', long, default_value_t = false, help = "Run in debug mode")]
 	pub debug: bool,
+	#[arg(
+		short = 'x',
+		long,
+		requires = "debug",
+		help = "Run the debugger commands of a file before reading stdin"
+	)]
+	pub script: Option<PathBuf>,
+	#[arg(
+		long,
+		value_name = "PORT|SOCKET",
+		conflicts_with = "debug",
+		help = "Wait for GDB on a localhost port or a unix socket path"
+	)]
+	pub gdb: Option<String>,
 }
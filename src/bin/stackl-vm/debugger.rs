@@ -0,0 +1,463 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Interactive debugger started with `-g`.
+//!
+//! Commands are read one per line from the script given with `--script` and
+//! then from stdin. Addresses are the ones the program sees, translated
+//! through BP or the page table, and may be written in decimal or with a
+//! `0x` prefix. Symbols and source lines need a binary with debug
+//! information, see `stackl-as -g` and `stackl-cc -g`.
+
+use std::fs;
+use std::io::{
+	self,
+	BufRead,
+	IsTerminal,
+	Write,
+};
+use std::path::Path;
+
+use stackl::asm::op;
+use stackl::debug::DebugInfo;
+
+use crate::Exception;
+use crate::machine::MachineState;
+use crate::machine::flag::{
+	IntVec,
+	MachineCheck,
+	Status,
+};
+use crate::machine::vmem::Access;
+
+const PROMPT: &str = "(stackl) ";
+
+const HELP: &str = "\
+break <addr|symbol|file:line>  stop when IP reaches a location
+delete <n>                     remove breakpoint n
+breakpoints                    list the breakpoints
+step [n]                       run n instructions
+next                           run one instruction, stepping over calls
+continue                       run until a breakpoint, HALT or a machine check
+regs                           show the registers and flags
+x <addr> [len]                 dump memory in hex
+disas [addr] [count]           disassemble instructions
+bt                             list the frames by walking FP
+locals                         show the locals of the current function
+quit                           leave the debugger";
+
+/// Frames deeper than this are assumed to be a corrupted stack
+const MAX_FRAMES: usize = 64;
+
+/// Why the machine stopped running
+enum Stop {
+	/// Ran the requested number of instructions
+	Step,
+	Breakpoint(usize),
+	/// Ran a `BREAK` instruction
+	Break,
+	Halted,
+	Check(MachineCheck),
+}
+
+pub struct Debugger<'a> {
+	cpu: &'a mut MachineState,
+	debug: Option<&'a DebugInfo>,
+	/// Deleted breakpoints stay as `None` to keep the numbers stable
+	breakpoints: Vec<Option<i32>>,
+	/// FP at boot, the frames end there
+	stack_base: i32,
+	running: bool,
+}
+
+impl<'a> Debugger<'a> {
+	pub fn new(cpu: &'a mut MachineState, debug: Option<&'a DebugInfo>) -> Self {
+		let stack_base = cpu.fp;
+		Self {
+			cpu,
+			debug,
+			breakpoints: vec![],
+			stack_base,
+			running: true,
+		}
+	}
+	/// Runs the commands of the script and then the ones typed on stdin
+	pub fn run(&mut self, script: Option<&Path>) -> io::Result<()> {
+		if let Some(path) = script {
+			for line in fs::read_to_string(path)?.lines() {
+				println!("{PROMPT}{line}");
+				if !self.execute(line) {
+					return Ok(());
+				}
+			}
+		}
+		let stdin = io::stdin();
+		let is_interactive = stdin.is_terminal();
+		let mut last = String::new();
+		loop {
+			if is_interactive {
+				print!("{PROMPT}");
+				io::stdout().flush()?;
+			}
+			let mut line = String::new();
+			if stdin.lock().read_line(&mut line)? == 0 {
+				return Ok(());
+			}
+			// an empty line repeats the last command
+			if !line.trim().is_empty() {
+				last = line;
+			}
+			if !self.execute(&last) {
+				return Ok(());
+			}
+		}
+	}
+	/// Returns false when the debugger should exit
+	fn execute(&mut self, line: &str) -> bool {
+		let mut words = line.split_whitespace();
+		let Some(command) = words.next() else {
+			return true;
+		};
+		let args: Vec<&str> = words.collect();
+		let result = match command {
+			"b" | "break" => self.add_breakpoint(&args),
+			"d" | "delete" => self.delete_breakpoint(&args),
+			"breakpoints" => {
+				self.list_breakpoints();
+				Ok(())
+			}
+			"s" | "step" => self.step(&args),
+			"n" | "next" => self.next(),
+			"c" | "continue" => self.resume(None, None),
+			"r" | "regs" => {
+				self.print_registers();
+				Ok(())
+			}
+			"x" => self.dump(&args),
+			"disas" => self.disassemble(&args),
+			"bt" | "backtrace" => {
+				self.backtrace();
+				Ok(())
+			}
+			"locals" => self.locals(),
+			"h" | "help" => {
+				println!("{HELP}");
+				Ok(())
+			}
+			"q" | "quit" => return false,
+			_ => Err(format!("unknown command `{command}`, try `help`")),
+		};
+		if let Err(message) = result {
+			println!("error: {message}");
+		}
+		true
+	}
+	fn add_breakpoint(&mut self, args: &[&str]) -> Result<(), String> {
+		let [location] = args else {
+			return Err("usage: break <addr|symbol|file:line>".to_string());
+		};
+		let address = self.parse_location(location)?;
+		self.breakpoints.push(Some(address));
+		println!(
+			"Breakpoint {} at {address}{}",
+			self.breakpoints.len(),
+			crate::source_location(self.debug, address)
+		);
+		Ok(())
+	}
+	fn delete_breakpoint(&mut self, args: &[&str]) -> Result<(), String> {
+		let [number] = args else {
+			return Err("usage: delete <n>".to_string());
+		};
+		let breakpoint = number
+			.parse::<usize>()
+			.ok()
+			.and_then(|number| self.breakpoints.get_mut(number.checked_sub(1)?))
+			.filter(|breakpoint| breakpoint.is_some())
+			.ok_or_else(|| format!("no breakpoint {number}"))?;
+		*breakpoint = None;
+		Ok(())
+	}
+	fn list_breakpoints(&self) {
+		for (index, address) in self.breakpoints.iter().enumerate() {
+			if let Some(address) = address {
+				println!(
+					"{}: {address}{}",
+					index + 1,
+					crate::source_location(self.debug, *address)
+				);
+			}
+		}
+	}
+	/// Resolves an address, a function or object name or `file:line`
+	fn parse_location(&self, location: &str) -> Result<i32, String> {
+		if let Some(address) = parse_number(location) {
+			return Ok(address);
+		}
+		let Some(debug) = self.debug else {
+			return Err(format!(
+				"no debug information to find `{location}`, build with -g"
+			));
+		};
+		if let Some((file, line)) = location.rsplit_once(':')
+			&& let Ok(line) = line.parse::<u32>()
+		{
+			return debug
+				.lines
+				.iter()
+				.find(|entry| {
+					entry.line == line
+						&& debug
+							.files
+							.get(entry.file as usize)
+							.is_some_and(|path| Path::new(path).ends_with(file))
+				})
+				.map(|entry| entry.address as i32)
+				.ok_or_else(|| format!("no code for line {line} of `{file}`"));
+		}
+		if let Some(func) = debug.functions.iter().find(|func| func.name == location) {
+			return Ok(func.start as i32);
+		}
+		debug
+			.find_global(location)
+			.map(|global| global.address as i32)
+			.ok_or_else(|| format!("no symbol `{location}`"))
+	}
+	fn step(&mut self, args: &[&str]) -> Result<(), String> {
+		let count = match args {
+			[] => 1,
+			[count] => count
+				.parse::<u64>()
+				.map_err(|_| format!("`{count}` is not a count"))?,
+			_ => return Err("usage: step [n]".to_string()),
+		};
+		self.resume(Some(count), None)
+	}
+	/// Steps over CALL and CALLI by running until the callee returns to the
+	/// same frame
+	fn next(&mut self) -> Result<(), String> {
+		let cpu = &*self.cpu;
+		let return_to = match cpu.peek_i32(cpu.ip) {
+			Ok(op::CALL) => Some((cpu.ip + 8, cpu.fp)),
+			Ok(op::CALLI) => Some((cpu.ip + 4, cpu.fp)),
+			_ => None,
+		};
+		match return_to {
+			Some(until) => self.resume(None, Some(until)),
+			None => self.resume(Some(1), None),
+		}
+	}
+	/// Runs until `count` instructions ran, IP and FP match `until` or the
+	/// machine stops on its own
+	fn resume(&mut self, count: Option<u64>, until: Option<(i32, i32)>) -> Result<(), String> {
+		if !self.running {
+			return Err("the program is not running".to_string());
+		}
+		let mut steps = 0;
+		let stop = loop {
+			let cpu = &mut *self.cpu;
+			match crate::step_machine(cpu) {
+				Ok(()) => {}
+				Err(Exception::Check(check)) => break Stop::Check(check),
+				Err(Exception::Break) => {
+					cpu.flag.set_intvec(IntVec::BKPT, false);
+					break Stop::Break;
+				}
+			}
+			if cpu.flag.get_status(Status::HALTED) {
+				break Stop::Halted;
+			}
+			steps += 1;
+			if count.is_some_and(|count| steps >= count)
+				|| until.is_some_and(|until| until == (cpu.ip, cpu.fp))
+			{
+				break Stop::Step;
+			}
+			if let Some(index) = self
+				.breakpoints
+				.iter()
+				.position(|&address| address == Some(cpu.ip))
+			{
+				break Stop::Breakpoint(index);
+			}
+		};
+		let ip = self.cpu.ip;
+		let location = crate::source_location(self.debug, ip);
+		match stop {
+			Stop::Step => {}
+			Stop::Breakpoint(index) => println!("Breakpoint {}{location}", index + 1),
+			Stop::Break => println!("BREAK{location}"),
+			Stop::Halted => {
+				self.running = false;
+				println!("Halted at {ip}{location}");
+				return Ok(());
+			}
+			Stop::Check(check) => {
+				self.running = false;
+				println!("Machine Check: {check} at {ip}{location}");
+				return Ok(());
+			}
+		}
+		self.print_instruction(ip);
+		Ok(())
+	}
+	fn print_instruction(&self, address: i32) {
+		let cpu = &*self.cpu;
+		let inst = cpu
+			.trace_inst(address)
+			.unwrap_or_else(|check| format!("<{check}>"));
+		let marker = if address == cpu.ip { "=>" } else { "  " };
+		println!("{marker} {address:6}: {inst}");
+	}
+	fn print_registers(&self) {
+		let cpu = &*self.cpu;
+		println!(
+			"BP {}  LP {}  IP {}  SP {}  FP {}  IVEC {}  PTBR {}  FAULT {}",
+			cpu.bp, cpu.lp, cpu.ip, cpu.sp, cpu.fp, cpu.ivec, cpu.ptbr, cpu.fault
+		);
+		let names = |names: Vec<&str>| {
+			if names.is_empty() {
+				"-".to_string()
+			} else {
+				names.join(" ")
+			}
+		};
+		println!(
+			"FLAG {:08x}  status: {}  check: {}  intvec: {}",
+			cpu.flag.as_u32(),
+			names(cpu.flag.status.iter_names().map(|(name, _)| name).collect()),
+			names(cpu.flag.check.iter_names().map(|(name, _)| name).collect()),
+			names(cpu.flag.intvec.iter_names().map(|(name, _)| name).collect()),
+		);
+	}
+	fn dump(&self, args: &[&str]) -> Result<(), String> {
+		let (address, len) = match args {
+			[address] => (self.parse_location(address)?, 64),
+			[address, len] => (
+				self.parse_location(address)?,
+				parse_number(len).ok_or_else(|| format!("`{len}` is not a length"))?,
+			),
+			_ => return Err("usage: x <addr> [len]".to_string()),
+		};
+		let start = usize::try_from(address).map_err(|_| "negative address".to_string())?;
+		let len = usize::try_from(len).map_err(|_| "negative length".to_string())?;
+		let cpu = &*self.cpu;
+		let mut bytes = vec![0; len];
+		// pages need not be contiguous, so every byte is translated
+		for (offset, byte) in bytes.iter_mut().enumerate() {
+			let virt = address.wrapping_add(offset as i32);
+			cpu.peek_translate(virt, Access::Read)
+				.and_then(|phys| cpu.mem.peek(phys, std::slice::from_mut(byte)))
+				.map_err(|check| format!("{check} {virt}"))?;
+		}
+		for (row, chunk) in bytes.chunks(16).enumerate() {
+			let hex: Vec<String> = chunk.iter().map(|byte| format!("{byte:02x}")).collect();
+			let text: String = chunk
+				.iter()
+				.map(|&byte| {
+					if byte.is_ascii_graphic() || byte == b' ' {
+						byte as char
+					} else {
+						'.'
+					}
+				})
+				.collect();
+			println!("{:08x}: {:<47}  {text}", start + row * 16, hex.join(" "));
+		}
+		Ok(())
+	}
+	fn disassemble(&self, args: &[&str]) -> Result<(), String> {
+		let (mut address, count) = match args {
+			[] => (self.cpu.ip, 8),
+			[address] => (self.parse_location(address)?, 8),
+			[address, count] => (
+				self.parse_location(address)?,
+				count
+					.parse::<usize>()
+					.map_err(|_| format!("`{count}` is not a count"))?,
+			),
+			_ => return Err("usage: disas [addr] [count]".to_string()),
+		};
+		for _ in 0..count {
+			if let Some(debug) = self.debug
+				&& let Some(func) = debug
+					.functions
+					.iter()
+					.find(|func| func.start as i32 == address)
+			{
+				println!("{}:", func.name);
+			}
+			self.print_instruction(address);
+			let op = self.cpu.peek_i32(address);
+			let Ok(op) = op else {
+				break;
+			};
+			address += inst_len(op);
+		}
+		Ok(())
+	}
+	/// Walks the frames linked by CALL, which leaves the return address at
+	/// `FP - 8` and the caller's FP at `FP - 4`
+	fn backtrace(&self) {
+		let cpu = &*self.cpu;
+		let mut ip = cpu.ip;
+		let mut fp = cpu.fp;
+		for depth in 0..MAX_FRAMES {
+			println!("#{depth} {ip}{}", crate::source_location(self.debug, ip));
+			if fp <= self.stack_base {
+				break;
+			}
+			let (Ok(return_ip), Ok(caller_fp)) = (cpu.peek_i32(fp - 8), cpu.peek_i32(fp - 4))
+			else {
+				break;
+			};
+			if caller_fp >= fp {
+				break;
+			}
+			ip = return_ip;
+			fp = caller_fp;
+		}
+	}
+	fn locals(&self) -> Result<(), String> {
+		let Some(debug) = self.debug else {
+			return Err("no debug information, build with -g".to_string());
+		};
+		let cpu = &*self.cpu;
+		let function = debug
+			.function_of(cpu.ip as u32)
+			.ok_or_else(|| format!("no function at {}", cpu.ip))?;
+		for local in debug.locals_of(function) {
+			let value = cpu
+				.peek_i32(cpu.fp + local.offset)
+				.map_or_else(|check| format!("<{check}>"), |value| value.to_string());
+			println!("{} {} = {value}", local.type_name, local.name);
+		}
+		Ok(())
+	}
+}
+
+fn parse_number(text: &str) -> Option<i32> {
+	match text.strip_prefix("0x") {
+		Some(hex) => u32::from_str_radix(hex, 16).ok().map(|value| value as i32),
+		None => text.parse().ok(),
+	}
+}
+
+/// Size in bytes of the instruction starting with `op`
+fn inst_len(op: i32) -> i32 {
+	match op {
+		op::PUSH
+		| op::JMP
+		| op::JZ
+		| op::PUSHVAR
+		| op::POPVAR
+		| op::ADJSP
+		| op::POPARGS
+		| op::CALL
+		| op::PUSHCVAR
+		| op::POPCVAR
+		| op::PUSHREG
+		| op::POPREG
+		| op::JMPUSER => 8,
+		_ => 4,
+	}
+}
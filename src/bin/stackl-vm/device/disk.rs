@@ -1,11 +1,196 @@
 // Copyright (c) 2024-2026 Jonathan A. Thomason
 
-use crate::machine::MachineState;
-use std::sync::{
-	Once,
-	RwLock,
+//! Block device backed by the image given with `--disk`.
+//!
+//! Writing a START command to CMD begins a transfer of one block between
+//! the image and the buffer at ADDR. The program keeps running for
+//! DISK_LATENCY instructions while the device copies the block, STATUS
+//! reports when it is done.
+
+use super::{
+	Device,
+	Registers,
+	ram_slice,
+	touches,
+};
+use crate::machine::flag::{
+	IntVec,
+	MachineCheck,
 };
+use std::fs::File;
+use std::io::{
+	Read,
+	Seek,
+	SeekFrom,
+	Write,
+};
+
+pub const DISK_BASE: i32 = 0x0D00_0000;
+/// Status register, read-only
+pub const DISK_STATUS: i32 = 0x0D00_0000;
+/// Command register
+pub const DISK_CMD: i32 = 0x0D00_0004;
+/// Absolute address of the buffer
+pub const DISK_ADDR: i32 = 0x0D00_0008;
+/// Block number to transfer
+pub const DISK_BLOCK: i32 = 0x0D00_000C;
+
+pub const DISK_BLOCK_SIZE: usize = 512;
+/// Instructions a transfer takes
+pub const DISK_LATENCY: u64 = 1000;
+
+pub const DISK_STATUS_READ_BUSY: i32 = 0x0000_0001;
+pub const DISK_STATUS_WRITE_BUSY: i32 = 0x0000_0002;
+pub const DISK_STATUS_READ_DONE: i32 = 0x0000_0004;
+pub const DISK_STATUS_WRITE_DONE: i32 = 0x0000_0008;
+pub const DISK_STATUS_WRITE_ERROR: i32 = 0x2000_0000;
+pub const DISK_STATUS_READ_ERROR: i32 = 0x4000_0000;
+pub const DISK_STATUS_ATTN: i32 = 0x8000_0000u32 as i32; // Interrupt occurred
+
+pub const DISK_CMD_START_READ: i32 = 0x0000_0001;
+pub const DISK_CMD_START_WRITE: i32 = 0x0000_0002;
+pub const DISK_CMD_INT_ENA: i32 = 0x8000_0000u32 as i32;
+
+const STATUS: usize = (DISK_STATUS - DISK_BASE) as usize;
+const CMD: usize = (DISK_CMD - DISK_BASE) as usize;
+const ADDR: usize = (DISK_ADDR - DISK_BASE) as usize;
+const BLOCK: usize = (DISK_BLOCK - DISK_BASE) as usize;
+
+#[derive(Debug)]
+pub struct Disk {
+	/// `None` when no image was given, every transfer fails
+	image: Option<File>,
+	is_read_only: bool,
+	regs: Registers<16>,
+	/// Command started and not yet carried out
+	pending: Option<i32>,
+	/// Instruction the pending command completes at, set by the first tick
+	/// after it started
+	due: Option<u64>,
+}
+
+impl Disk {
+	pub fn new(image: Option<File>, is_read_only: bool) -> Self {
+		Self {
+			image,
+			is_read_only,
+			regs: Registers::new(),
+			pending: None,
+			due: None,
+		}
+	}
+
+	/// Takes a command written to CMD and marks it busy
+	fn start_command(&mut self) {
+		let cmd = self.regs.get(CMD);
+		let is_read = cmd & DISK_CMD_START_READ != 0;
+		if !is_read && cmd & DISK_CMD_START_WRITE == 0 {
+			return;
+		}
+		// a read wins over a write requested at the same time
+		let (cmd, busy) = match is_read {
+			true => (cmd & !DISK_CMD_START_WRITE, DISK_STATUS_READ_BUSY),
+			false => (cmd, DISK_STATUS_WRITE_BUSY),
+		};
+		let idle = cmd & !(DISK_CMD_START_READ | DISK_CMD_START_WRITE);
+		self.regs.set(CMD, idle);
+		// a new command clears the results of the last one
+		self.regs.set(STATUS, busy);
+		self.pending = Some(cmd);
+		self.due = None;
+	}
+
+	/// Seeks to a block that lies within the image
+	fn seek_block(&mut self, block: i32) -> Option<&mut File> {
+		let image = self.image.as_mut()?;
+		let len = image.metadata().ok()?.len();
+		let offset = u64::try_from(block).ok()? * DISK_BLOCK_SIZE as u64;
+		if offset + DISK_BLOCK_SIZE as u64 > len {
+			return None;
+		}
+		image.seek(SeekFrom::Start(offset)).ok()?;
+		Some(image)
+	}
+
+	fn read_block(&mut self, ram: &mut [u8]) -> Result<(), MachineCheck> {
+		let buf = ram_slice(ram, self.regs.get(ADDR), DISK_BLOCK_SIZE as i32)?;
+		let image = self
+			.seek_block(self.regs.get(BLOCK))
+			.ok_or(MachineCheck::HW_FAILURE)?;
+		image.read_exact(buf).or(Err(MachineCheck::HW_FAILURE))
+	}
+
+	fn write_block(&mut self, ram: &mut [u8]) -> Result<(), MachineCheck> {
+		if self.is_read_only {
+			return Err(MachineCheck::PROT_INST);
+		}
+		let buf = ram_slice(ram, self.regs.get(ADDR), DISK_BLOCK_SIZE as i32)?;
+		let image = self
+			.seek_block(self.regs.get(BLOCK))
+			.ok_or(MachineCheck::HW_FAILURE)?;
+		image.write_all(buf).or(Err(MachineCheck::HW_FAILURE))?;
+		image.flush().or(Err(MachineCheck::HW_FAILURE))
+	}
+}
 
-pub fn run_device(_machine_lock: &RwLock<MachineState>, _state: &Once) {
-	todo!()
+impl Device for Disk {
+	fn size(&self) -> usize {
+		16
+	}
+	fn interrupt_line(&self) -> IntVec {
+		IntVec::DISK
+	}
+	fn peek(&self, offset: usize, buf: &mut [u8]) {
+		self.regs.peek(offset, buf);
+	}
+	fn write(&mut self, offset: usize, data: &[u8]) {
+		// STATUS is read-only
+		for (offset, &byte) in (offset..).zip(data) {
+			if !touches(offset, 1, STATUS) {
+				self.regs.write(offset, &[byte]);
+			}
+		}
+		if touches(offset, data.len(), CMD) {
+			self.start_command();
+		}
+	}
+	fn tick(&mut self, now: u64, ram: &mut [u8]) -> bool {
+		let Some(cmd) = self.pending else {
+			return false;
+		};
+		let due = *self.due.get_or_insert(now + DISK_LATENCY);
+		if now < due {
+			return false;
+		}
+		self.pending = None;
+		self.due = None;
+		let is_write = cmd & DISK_CMD_START_WRITE != 0;
+		let result = match is_write {
+			true => self.write_block(ram),
+			false => self.read_block(ram),
+		};
+		let (busy, done, error) = match is_write {
+			true => (
+				DISK_STATUS_WRITE_BUSY,
+				DISK_STATUS_WRITE_DONE,
+				DISK_STATUS_WRITE_ERROR,
+			),
+			false => (
+				DISK_STATUS_READ_BUSY,
+				DISK_STATUS_READ_DONE,
+				DISK_STATUS_READ_ERROR,
+			),
+		};
+		let mut status = self.regs.get(STATUS) & !busy;
+		status |= if result.is_ok() { done } else { error };
+		let int_ena = cmd & DISK_CMD_INT_ENA != 0;
+		if int_ena {
+			status |= DISK_STATUS_ATTN;
+		}
+		self.regs.set(STATUS, status);
+		int_ena
+	}
+	fn next_tick(&self, now: u64) -> Option<u64> {
+		self.pending.map(|_| self.due.unwrap_or(now + 1))
+	}
 }
@@ -1,11 +1,207 @@
 // Copyright (c) 2024-2026 Jonathan A. Thomason
 
-use crate::machine::MachineState;
-use std::sync::{
-	Once,
-	RwLock,
+//! Terminal moving whole buffers between memory and the host's stdin and
+//! stdout.
+//!
+//! Where pio_term interrupts for every character, the program hands the
+//! DMA terminal a buffer and hears back once the transfer is over. Receive
+//! and transmit run independently of each other.
+
+use super::{
+	Device,
+	Registers,
+	ram_slice,
+	touches,
 };
+use crate::io;
+use crate::machine::flag::IntVec;
+use std::sync::mpsc::TryRecvError;
+
+pub const DMA_T_BASE: i32 = 0x0F00_0000;
+/// Status register, read-only
+pub const DMA_T_STATUS: i32 = 0x0F00_0000;
+/// Command register
+pub const DMA_T_CMD: i32 = 0x0F00_0004;
+/// Absolute address of the receive buffer
+pub const DMA_T_RX_ADDR: i32 = 0x0F00_0008;
+/// Size of the receive buffer
+pub const DMA_T_RX_SIZE: i32 = 0x0F00_000C;
+/// Characters received into the buffer so far, read-only
+pub const DMA_T_RX_COUNT: i32 = 0x0F00_0010;
+/// Absolute address of the characters to send
+pub const DMA_T_TX_ADDR: i32 = 0x0F00_0014;
+/// Number of characters to send
+pub const DMA_T_TX_SIZE: i32 = 0x0F00_0018;
+
+pub const DMA_T_STATUS_READ_BUSY: i32 = 0x0000_0001;
+pub const DMA_T_STATUS_WRITE_BUSY: i32 = 0x0000_0002;
+pub const DMA_T_STATUS_READ_DONE: i32 = 0x0000_0004;
+pub const DMA_T_STATUS_WRITE_DONE: i32 = 0x0000_0008;
+pub const DMA_T_STATUS_WRITE_ERROR: i32 = 0x2000_0000;
+pub const DMA_T_STATUS_READ_ERROR: i32 = 0x4000_0000;
+pub const DMA_T_STATUS_ATTN: i32 = 0x8000_0000u32 as i32; // Interrupt occurred
+
+pub const DMA_T_CMD_START_READ: i32 = 0x0000_0001;
+pub const DMA_T_CMD_START_WRITE: i32 = 0x0000_0002;
+pub const DMA_T_CMD_LINE: i32 = 0x0000_0004; // Receive stops after a newline
+pub const DMA_T_CMD_INT_ENA: i32 = 0x8000_0000u32 as i32;
+
+const STATUS: usize = (DMA_T_STATUS - DMA_T_BASE) as usize;
+const CMD: usize = (DMA_T_CMD - DMA_T_BASE) as usize;
+const RX_ADDR: usize = (DMA_T_RX_ADDR - DMA_T_BASE) as usize;
+const RX_SIZE: usize = (DMA_T_RX_SIZE - DMA_T_BASE) as usize;
+const RX_COUNT: usize = (DMA_T_RX_COUNT - DMA_T_BASE) as usize;
+const TX_ADDR: usize = (DMA_T_TX_ADDR - DMA_T_BASE) as usize;
+const TX_SIZE: usize = (DMA_T_TX_SIZE - DMA_T_BASE) as usize;
+
+/// A receive in progress
+#[derive(Debug)]
+struct Receive {
+	count: usize,
+	is_line: bool,
+	int_ena: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct DmaTerm {
+	regs: Registers<32>,
+	receive: Option<Receive>,
+	/// Whether the transmit started interrupts when it is over
+	transmit: Option<bool>,
+	/// Opened by the first receive
+	input: Option<io::Input>,
+}
+
+impl DmaTerm {
+	/// Takes the start bits off CMD and marks the transfers busy
+	fn start_command(&mut self) {
+		let cmd = self.regs.get(CMD);
+		let start = DMA_T_CMD_START_READ | DMA_T_CMD_START_WRITE;
+		if cmd & start == 0 {
+			return;
+		}
+		self.regs.set(CMD, cmd & !start);
+		let int_ena = cmd & DMA_T_CMD_INT_ENA != 0;
+		if cmd & DMA_T_CMD_START_READ != 0 {
+			self.start(
+				DMA_T_STATUS_READ_BUSY,
+				DMA_T_STATUS_READ_DONE,
+				DMA_T_STATUS_READ_ERROR,
+			);
+			self.regs.set(RX_COUNT, 0);
+			self.receive = Some(Receive {
+				count: 0,
+				is_line: cmd & DMA_T_CMD_LINE != 0,
+				int_ena,
+			});
+		}
+		if cmd & DMA_T_CMD_START_WRITE != 0 {
+			self.start(
+				DMA_T_STATUS_WRITE_BUSY,
+				DMA_T_STATUS_WRITE_DONE,
+				DMA_T_STATUS_WRITE_ERROR,
+			);
+			self.transmit = Some(int_ena);
+		}
+	}
+
+	/// Marks a transfer busy, clearing the results of the last one that went
+	/// the same way
+	fn start(&mut self, busy: i32, done: i32, error: i32) {
+		let status = self.regs.get(STATUS);
+		let status = status & !(done | error | DMA_T_STATUS_ATTN) | busy;
+		self.regs.set(STATUS, status);
+	}
+
+	/// Ends a transfer with `result`, returns whether it interrupts
+	fn finish(&mut self, busy: i32, result: i32, int_ena: bool) -> bool {
+		let mut status = self.regs.get(STATUS) & !busy | result;
+		if int_ena {
+			status |= DMA_T_STATUS_ATTN;
+		}
+		self.regs.set(STATUS, status);
+		int_ena
+	}
+
+	/// Copies the characters that arrived into the buffer, returns whether
+	/// the receive interrupts
+	fn step_receive(&mut self, ram: &mut [u8]) -> bool {
+		let Some(rx) = &mut self.receive else {
+			return false;
+		};
+		let int_ena = rx.int_ena;
+		let Ok(buf) = ram_slice(ram, self.regs.get(RX_ADDR), self.regs.get(RX_SIZE)) else {
+			self.receive = None;
+			return self.finish(DMA_T_STATUS_READ_BUSY, DMA_T_STATUS_READ_ERROR, int_ena);
+		};
+		let input = self.input.get_or_insert_with(io::Input::new);
+		let mut is_done = rx.count == buf.len();
+		while !is_done {
+			let byte = match input.next() {
+				Ok(byte) => byte,
+				Err(TryRecvError::Empty) => break,
+				// the end of the input ends the receive short
+				Err(TryRecvError::Disconnected) => {
+					is_done = true;
+					break;
+				}
+			};
+			buf[rx.count] = byte;
+			rx.count += 1;
+			is_done = rx.count == buf.len() || (rx.is_line && byte == b'\n');
+		}
+		self.regs.set(RX_COUNT, rx.count as i32);
+		if !is_done {
+			return false;
+		}
+		self.receive = None;
+		self.finish(DMA_T_STATUS_READ_BUSY, DMA_T_STATUS_READ_DONE, int_ena)
+	}
+
+	/// Sends the transmit buffer, returns whether the transmit interrupts
+	fn step_transmit(&mut self, ram: &mut [u8]) -> bool {
+		let Some(int_ena) = self.transmit.take() else {
+			return false;
+		};
+		let result = match ram_slice(ram, self.regs.get(TX_ADDR), self.regs.get(TX_SIZE)) {
+			Ok(buf) => {
+				io::write_bytes(buf);
+				DMA_T_STATUS_WRITE_DONE
+			}
+			Err(_) => DMA_T_STATUS_WRITE_ERROR,
+		};
+		self.finish(DMA_T_STATUS_WRITE_BUSY, result, int_ena)
+	}
+}
 
-pub fn run_device(_machine_lock: &RwLock<MachineState>, _state: &Once) {
-	todo!()
+impl Device for DmaTerm {
+	fn size(&self) -> usize {
+		32
+	}
+	fn interrupt_line(&self) -> IntVec {
+		IntVec::DMA_T
+	}
+	fn peek(&self, offset: usize, buf: &mut [u8]) {
+		self.regs.peek(offset, buf);
+	}
+	fn write(&mut self, offset: usize, data: &[u8]) {
+		// STATUS and RX_COUNT are read-only
+		for (offset, &byte) in (offset..).zip(data) {
+			if !touches(offset, 1, STATUS) && !touches(offset, 1, RX_COUNT) {
+				self.regs.write(offset, &[byte]);
+			}
+		}
+		if touches(offset, data.len(), CMD) {
+			self.start_command();
+		}
+	}
+	fn tick(&mut self, _now: u64, ram: &mut [u8]) -> bool {
+		let received = self.step_receive(ram);
+		let sent = self.step_transmit(ram);
+		received || sent
+	}
+	fn next_tick(&self, now: u64) -> Option<u64> {
+		let is_busy = self.receive.is_some() || self.transmit.is_some();
+		is_busy.then_some(now + 1)
+	}
 }
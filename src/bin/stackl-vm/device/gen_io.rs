@@ -1,20 +1,31 @@
 // Copyright (c) 2024-2026 Jonathan A. Thomason
 
+use super::{
+	Device,
+	Registers,
+	ram_slice,
+	touches,
+};
 use crate::io;
-use crate::machine::MachineState;
 use crate::machine::flag;
-use std::sync;
-use std::sync::RwLock;
-use std::thread;
-use std::time::Duration;
+use stackl::{
+	StacklFormatV1,
+	StacklFormatV2,
+};
+use std::ffi::CStr;
+use std::fs;
+use std::mem;
+use std::str::FromStr;
+use std::sync::mpsc::TryRecvError;
 
+pub const GEN_IO_BASE: i32 = 0x0B000000;
 /// Control/Status register
 pub const GEN_IO_CSR: i32 = 0x0B000000;
 /// Buffer address register
 pub const GEN_IO_BUFF: i32 = 0x0B000004;
 /// Buffer size register
 pub const GEN_IO_SIZE: i32 = 0x0B000008;
-/// Bunber of characters sent
+/// Number of characters moved by the last operation
 pub const GEN_IO_COUNT: i32 = 0x0B00000C;
 
 pub const GEN_IO_CSR_IE: i32 = 0x00010000; // Interrupt enable
@@ -29,56 +40,183 @@ pub const GEN_IO_OP_GETL: i32 = 3;
 pub const GEN_IO_OP_GETI: i32 = 4;
 pub const GEN_IO_OP_EXEC: i32 = 5;
 
-const INTERRUPT_MASK: i32 = GEN_IO_CSR_IE | GEN_IO_CSR_DONE;
+const CSR: usize = (GEN_IO_CSR - GEN_IO_BASE) as usize;
+const BUFF: usize = (GEN_IO_BUFF - GEN_IO_BASE) as usize;
+const SIZE: usize = (GEN_IO_SIZE - GEN_IO_BASE) as usize;
+const COUNT: usize = (GEN_IO_COUNT - GEN_IO_BASE) as usize;
 
-pub fn run_device(machine_lock: &RwLock<MachineState>, state: &sync::Once) {
-	while !state.is_completed() {
-		thread::sleep(Duration::from_micros(100));
-		if execute_operation(machine_lock).is_err() {
-			let mut cpu = machine_lock.write().unwrap();
-			let mut csr = cpu.load_abs_i32(GEN_IO_CSR).unwrap();
-			csr |= GEN_IO_CSR_ERR;
-			cpu.store_i32(csr, GEN_IO_CSR).unwrap();
+#[derive(Debug, Default)]
+pub struct GenIo {
+	regs: Registers<16>,
+	/// An operation was written to CSR and is not done yet
+	is_pending: bool,
+	/// Part of the line GETL or GETI waits for
+	line: Vec<u8>,
+	/// Opened by the first operation reading input
+	input: Option<io::Input>,
+}
+
+impl GenIo {
+	/// Collects a line of input, `None` until its newline or the end of the
+	/// input arrives
+	fn read_line(&mut self) -> Option<Vec<u8>> {
+		let input = self.input.get_or_insert_with(io::Input::new);
+		loop {
+			match input.next() {
+				Ok(byte) => {
+					self.line.push(byte);
+					if byte == b'\n' {
+						break;
+					}
+				}
+				Err(TryRecvError::Empty) => return None,
+				Err(TryRecvError::Disconnected) => break,
+			}
 		}
+		Some(mem::take(&mut self.line))
 	}
-}
 
-fn execute_operation(machine_lock: &RwLock<MachineState>) -> Result<(), flag::MachineCheck> {
-	let cpu = machine_lock.read().unwrap();
-	let mut csr = cpu.load_abs_i32(GEN_IO_CSR).unwrap();
-	// If done don't check anything else.
-	if csr & GEN_IO_CSR_DONE != 0 {
-		return Ok(());
+	/// Runs `op` and returns the value for the COUNT register, `None` while
+	/// it waits for input
+	fn execute_operation(
+		&mut self,
+		op: i32,
+		ram: &mut [u8],
+	) -> Option<Result<i32, flag::MachineCheck>> {
+		let addr = self.regs.get(BUFF);
+		let size = self.regs.get(SIZE);
+		let result = match op {
+			GEN_IO_OP_PRINTS => ram_slice(ram, addr, size).map(|buf| io::try_print(buf) as i32),
+			// BUFF holds the character itself
+			GEN_IO_OP_PRINTC => {
+				io::write_byte(addr as u8);
+				Ok(1)
+			}
+			GEN_IO_OP_GETL => {
+				let mut buf = self.read_line()?;
+				Self::getl(&mut buf, addr, size, ram)
+			}
+			GEN_IO_OP_GETI => {
+				let line = self.read_line()?;
+				Self::geti(&line, addr, ram)
+			}
+			GEN_IO_OP_EXEC => Self::exec(addr, size, ram),
+			_ => {
+				// report error
+				Err(flag::MachineCheck::ILLEGAL_INST)
+			}
+		};
+		Some(result)
 	}
-	let addr = cpu.load_abs_i32(GEN_IO_BUFF).unwrap();
-	let size = cpu.load_abs_i32(GEN_IO_SIZE).unwrap();
-	drop(cpu);
-	match csr & 0xFF {
-		GEN_IO_OP_PRINTS => {
-			let cpu = machine_lock.read().unwrap();
-			let max_addr = (addr + size) as usize;
-			let buf = cpu.mem.get((addr as usize)..max_addr)?;
-			let count = io::try_print(buf);
-			drop(cpu);
-			let mut cpu = machine_lock.write().unwrap();
-			cpu.store_abs_i32(count as i32, GEN_IO_COUNT)?;
-			csr |= GEN_IO_CSR_DONE;
-			cpu.store_abs_i32(csr, 0x0B00_0000).unwrap();
+
+	/// Stores the line with a terminating NUL, keeping at most `size - 1`
+	/// characters
+	fn getl(
+		buf: &mut Vec<u8>,
+		addr: i32,
+		size: i32,
+		ram: &mut [u8],
+	) -> Result<i32, flag::MachineCheck> {
+		let Some(max_len) = usize::try_from(size)
+			.ok()
+			.and_then(|size| size.checked_sub(1))
+		else {
+			return Err(flag::MachineCheck::ILLEGAL_ADDR);
+		};
+		buf.truncate(max_len);
+		let count = buf.len() as i32;
+		buf.push(b'\0');
+		ram_slice(ram, addr, buf.len() as i32)?.copy_from_slice(buf);
+		Ok(count)
+	}
+
+	fn geti(line: &[u8], addr: i32, ram: &mut [u8]) -> Result<i32, flag::MachineCheck> {
+		let Some(deci) = str::from_utf8(line)
+			.ok()
+			.and_then(|line| i32::from_str(line.trim()).ok())
+		else {
+			return Err(flag::MachineCheck::ILLEGAL_INST);
+		};
+		if addr % 4 != 0 {
+			return Err(flag::MachineCheck::ILLEGAL_ADDR);
 		}
-		GEN_IO_OP_PRINTC => todo!(),
-		GEN_IO_OP_GETL => todo!(),
-		GEN_IO_OP_GETI => todo!(),
-		GEN_IO_OP_EXEC => todo!(),
-		_ => {
-			// report error
+		ram_slice(ram, addr, 4)?.copy_from_slice(&deci.to_le_bytes());
+		Ok(1)
+	}
+
+	/// Loads the program named at `addr` with its base at `bp` and returns
+	/// the end of it
+	fn exec(addr: i32, bp: i32, ram: &mut [u8]) -> Result<i32, flag::MachineCheck> {
+		let addr = usize::try_from(addr).or(Err(flag::MachineCheck::ILLEGAL_ADDR))?;
+		let bytes = ram.get(addr..).ok_or(flag::MachineCheck::ILLEGAL_ADDR)?;
+		let Ok(c_str) = CStr::from_bytes_until_nul(bytes) else {
+			return Err(flag::MachineCheck::ILLEGAL_ADDR);
+		};
+		let Ok(filepath) = c_str.to_str() else {
 			return Err(flag::MachineCheck::ILLEGAL_INST);
+		};
+		let Ok(content) = fs::read(filepath) else {
+			return Err(flag::MachineCheck::ILLEGAL_INST);
+		};
+
+		let program = match StacklFormatV2::try_from(content.as_slice()) {
+			Ok(data) => data,
+			Err(stackl::ErrorKind::InvalidMagic) => StacklFormatV1::try_from(content.as_slice())
+				.and_then(StacklFormatV2::try_from)
+				.or(Err(flag::MachineCheck::ILLEGAL_INST))?,
+			Err(_) => return Err(flag::MachineCheck::ILLEGAL_INST),
+		};
+		let text_len = program.text.len() as i32;
+		// the program and its stack size just above it must fit before
+		// anything is copied
+		let image = ram_slice(ram, bp, text_len + 4)?;
+		let (text, stack_size) = image.split_at_mut(text_len as usize);
+		text.copy_from_slice(&program.text);
+		stack_size.copy_from_slice(&program.stack_size.to_le_bytes());
+		Ok(bp + text_len)
+	}
+}
+
+impl Device for GenIo {
+	fn size(&self) -> usize {
+		16
+	}
+	fn interrupt_line(&self) -> flag::IntVec {
+		flag::IntVec::GEN_IO
+	}
+	fn peek(&self, offset: usize, buf: &mut [u8]) {
+		self.regs.peek(offset, buf);
+	}
+	fn write(&mut self, offset: usize, data: &[u8]) {
+		self.regs.write(offset, data);
+		if touches(offset, data.len(), CSR) {
+			let csr = self.regs.get(CSR);
+			// If done or idle don't check anything else.
+			self.is_pending = csr & GEN_IO_CSR_DONE == 0 && csr & 0xFF != 0;
+		}
+	}
+	fn tick(&mut self, _now: u64, ram: &mut [u8]) -> bool {
+		if !self.is_pending {
+			return false;
+		}
+		let csr = self.regs.get(CSR);
+		let Some(result) = self.execute_operation(csr & 0xFF, ram) else {
+			return false;
+		};
+		self.is_pending = false;
+		let mut csr = csr | GEN_IO_CSR_DONE;
+		match result {
+			Ok(count) => self.regs.set(COUNT, count),
+			Err(_) => csr |= GEN_IO_CSR_ERR,
+		}
+		let is_raised = csr & GEN_IO_CSR_IE != 0;
+		if is_raised {
+			csr |= GEN_IO_CSR_INT;
 		}
+		self.regs.set(CSR, csr);
+		is_raised
 	}
-	if (csr & INTERRUPT_MASK) == INTERRUPT_MASK {
-		let mut cpu = machine_lock.write().unwrap();
-		csr |= GEN_IO_CSR_INT;
-		cpu.store_abs_i32(csr, GEN_IO_CSR)?;
-		cpu.flag.set_intvec(flag::IntVec::GEN_IO, true);
+	fn next_tick(&self, now: u64) -> Option<u64> {
+		self.is_pending.then_some(now + 1)
 	}
-	Ok(())
 }
@@ -1,7 +1,178 @@
 // Copyright (c) 2024-2026 Jonathan A. Thomason
 
+//! Devices the program reaches through memory mapped registers.
+//!
+//! A device implements `Device` and is mapped onto the `Bus` at a base
+//! address, every access of the program inside its window goes to the
+//! device instead of memory.
+
+use crate::machine::flag::{
+	IntVec,
+	MachineCheck,
+};
+use std::fmt;
+
 pub mod disk;
 pub mod dma_term;
 pub mod gen_io;
 pub mod inp;
 pub mod pio_term;
+pub mod timer;
+
+pub trait Device: fmt::Debug + Send {
+	/// Bytes taken by the registers
+	fn size(&self) -> usize;
+	/// Line the device raises its interrupt on
+	fn interrupt_line(&self) -> IntVec;
+	/// Reads the registers at `offset` without side effects, for debuggers
+	/// and traces
+	fn peek(&self, offset: usize, buf: &mut [u8]);
+	/// Reads the registers at `offset` for the program
+	fn read(&mut self, offset: usize, buf: &mut [u8]) {
+		self.peek(offset, buf);
+	}
+	/// Writes the registers at `offset` for the program
+	fn write(&mut self, offset: usize, data: &[u8]);
+	/// Advances the device to instruction `now`, returns true to raise the
+	/// interrupt line
+	fn tick(&mut self, now: u64, ram: &mut [u8]) -> bool;
+	/// Instruction the device wants its next tick at, `None` sleeps until
+	/// the program accesses the registers
+	fn next_tick(&self, now: u64) -> Option<u64> {
+		Some(now + 1)
+	}
+}
+
+#[derive(Debug)]
+struct Window {
+	base: usize,
+	device: Box<dyn Device>,
+	next_tick: Option<u64>,
+}
+
+/// Address decoder in front of memory
+#[derive(Debug, Default)]
+pub struct Bus {
+	windows: Vec<Window>,
+}
+
+impl Bus {
+	/// Maps `device` at `base`, the window must not overlap another
+	pub fn map(&mut self, base: usize, device: Box<dyn Device>) {
+		let end = base + device.size();
+		let overlaps = self
+			.windows
+			.iter()
+			.any(|window| base < window.base + window.device.size() && window.base < end);
+		assert!(!overlaps, "device mapped over another at {base:#010x}");
+		self.windows.push(Window {
+			base,
+			device,
+			next_tick: Some(0),
+		});
+	}
+	/// Finds the device at `addr` and the offset into its registers, `None`
+	/// is regular memory. An access must not cross the edge of a window.
+	fn find(&self, addr: usize, len: usize) -> Result<Option<(usize, usize)>, MachineCheck> {
+		for (index, window) in self.windows.iter().enumerate() {
+			let end = window.base + window.device.size();
+			if addr + len <= window.base || end <= addr {
+				continue;
+			}
+			if addr < window.base || end < addr + len {
+				return Err(MachineCheck::ILLEGAL_ADDR);
+			}
+			return Ok(Some((index, addr - window.base)));
+		}
+		Ok(None)
+	}
+	/// Returns false when `addr` is regular memory
+	pub fn peek(&self, addr: usize, buf: &mut [u8]) -> Result<bool, MachineCheck> {
+		let Some((index, offset)) = self.find(addr, buf.len())? else {
+			return Ok(false);
+		};
+		self.windows[index].device.peek(offset, buf);
+		Ok(true)
+	}
+	/// Returns false when `addr` is regular memory
+	pub fn read(&mut self, addr: usize, buf: &mut [u8]) -> Result<bool, MachineCheck> {
+		let Some((index, offset)) = self.find(addr, buf.len())? else {
+			return Ok(false);
+		};
+		let window = &mut self.windows[index];
+		window.device.read(offset, buf);
+		// an access may have woken the device
+		window.next_tick = Some(0);
+		Ok(true)
+	}
+	/// Returns false when `addr` is regular memory
+	pub fn write(&mut self, addr: usize, data: &[u8]) -> Result<bool, MachineCheck> {
+		let Some((index, offset)) = self.find(addr, data.len())? else {
+			return Ok(false);
+		};
+		let window = &mut self.windows[index];
+		window.device.write(offset, data);
+		window.next_tick = Some(0);
+		Ok(true)
+	}
+	/// Ticks the devices due at instruction `now`, returns the interrupt
+	/// lines they raised
+	pub fn tick(&mut self, now: u64, ram: &mut [u8]) -> IntVec {
+		let mut lines = IntVec::empty();
+		for window in &mut self.windows {
+			if window.next_tick.is_none_or(|next_tick| now < next_tick) {
+				continue;
+			}
+			if window.device.tick(now, ram) {
+				lines |= window.device.interrupt_line();
+			}
+			window.next_tick = window.device.next_tick(now);
+		}
+		lines
+	}
+}
+
+/// Register file of a device, the device accesses it in words and the
+/// program in any width
+#[derive(Debug)]
+pub struct Registers<const N: usize> {
+	bytes: [u8; N],
+}
+
+impl<const N: usize> Registers<N> {
+	pub const fn new() -> Self {
+		Self { bytes: [0; N] }
+	}
+	pub fn get(&self, offset: usize) -> i32 {
+		i32::from_le_bytes(self.bytes[offset..offset + 4].try_into().unwrap())
+	}
+	pub fn set(&mut self, offset: usize, value: i32) {
+		self.bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+	}
+	pub fn peek(&self, offset: usize, buf: &mut [u8]) {
+		buf.copy_from_slice(&self.bytes[offset..offset + buf.len()]);
+	}
+	pub fn write(&mut self, offset: usize, data: &[u8]) {
+		self.bytes[offset..offset + data.len()].copy_from_slice(data);
+	}
+}
+
+impl<const N: usize> Default for Registers<N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// True when an access of `len` bytes at `offset` touches the word register
+/// at `reg`
+pub fn touches(offset: usize, len: usize, reg: usize) -> bool {
+	offset < reg + 4 && reg < offset + len
+}
+
+/// Slice of `ram` holding `size` bytes at the absolute address `addr`
+pub fn ram_slice(ram: &mut [u8], addr: i32, size: i32) -> Result<&mut [u8], MachineCheck> {
+	let addr = usize::try_from(addr).or(Err(MachineCheck::ILLEGAL_ADDR))?;
+	let size = usize::try_from(size).or(Err(MachineCheck::ILLEGAL_ADDR))?;
+	ram.get_mut(addr..addr + size)
+		.ok_or(MachineCheck::ILLEGAL_ADDR)
+}
@@ -1,11 +1,132 @@
 // Copyright (c) 2024-2026 Jonathan A. Thomason
 
-use crate::machine::MachineState;
-use std::sync::{
-	Once,
-	RwLock,
-};
-
-pub fn run_device(_machine_lock: &RwLock<MachineState>, _state: &Once) {
-	todo!()
+//! Terminal moving one character at a time between the program and the
+//! host's stdin and stdout
+
+use super::Device;
+use crate::io;
+use crate::machine::flag::IntVec;
+
+pub const PIO_T_BASE: i32 = 0x0E00_0000;
+/// Offset of RDR, the received character. Writes to the same address go to
+/// XDR, the character to send.
+pub const PIO_T_RDR: usize = 0;
+/// Interrupt enable register, takes IID_RECV and IID_XMIT
+pub const PIO_T_IER: usize = 1;
+/// Interrupt identification register, read-only
+pub const PIO_T_IIR: usize = 2;
+
+/// An enabled condition is set
+pub const PIO_T_IID_INT: u8 = 0x01;
+/// RDR holds a character that was not read yet
+pub const PIO_T_IID_RECV: u8 = 0x02;
+/// XDR is empty and takes the next character
+pub const PIO_T_IID_XMIT: u8 = 0x04;
+
+#[derive(Debug)]
+pub struct PioTerm {
+	regs: [u8; 16],
+	/// Character written to XDR
+	xdr: Option<u8>,
+	/// Enabled conditions at the last tick
+	was_pending: u8,
+	input: io::Input,
+}
+
+impl PioTerm {
+	pub fn new() -> Self {
+		let mut regs = [0; 16];
+		regs[PIO_T_IIR] = PIO_T_IID_XMIT;
+		Self {
+			regs,
+			xdr: None,
+			was_pending: 0,
+			input: io::Input::new(),
+		}
+	}
+
+	/// Takes the next character of input into RDR once the last one was
+	/// read.
+	///
+	/// Input is only taken when the program reads RDR or enables IID_RECV, so
+	/// a program gets the same characters at the same instructions on every
+	/// run. Polling IIR alone never waits for input.
+	fn receive(&mut self) {
+		if self.regs[PIO_T_IIR] & PIO_T_IID_RECV == 0
+			&& let Ok(byte) = self.input.next()
+		{
+			self.regs[PIO_T_RDR] = byte;
+			self.regs[PIO_T_IIR] |= PIO_T_IID_RECV;
+		}
+	}
+
+	fn is_waiting_for_input(&self) -> bool {
+		self.regs[PIO_T_IER] & PIO_T_IID_RECV != 0 && self.regs[PIO_T_IIR] & PIO_T_IID_RECV == 0
+	}
+}
+
+impl Device for PioTerm {
+	fn size(&self) -> usize {
+		16
+	}
+	fn interrupt_line(&self) -> IntVec {
+		IntVec::PIO_T
+	}
+	fn peek(&self, offset: usize, buf: &mut [u8]) {
+		buf.copy_from_slice(&self.regs[offset..offset + buf.len()]);
+	}
+	fn read(&mut self, offset: usize, buf: &mut [u8]) {
+		// the registers are single bytes
+		if (offset..offset + buf.len()).contains(&PIO_T_RDR) {
+			self.receive();
+		}
+		self.peek(offset, buf);
+		// reading RDR consumes the character
+		if offset == PIO_T_RDR {
+			self.regs[PIO_T_IIR] &= !PIO_T_IID_RECV;
+			self.was_pending &= !PIO_T_IID_RECV;
+		}
+	}
+	fn write(&mut self, offset: usize, data: &[u8]) {
+		for (offset, &byte) in (offset..).zip(data) {
+			match offset {
+				PIO_T_RDR => {
+					self.xdr = Some(byte);
+					self.regs[PIO_T_IIR] &= !PIO_T_IID_XMIT;
+					self.was_pending &= !PIO_T_IID_XMIT;
+				}
+				PIO_T_IIR => {}
+				_ => self.regs[offset] = byte,
+			}
+		}
+	}
+	/// Moves a character each way and raises the interrupt when an enabled
+	/// condition sets
+	fn tick(&mut self, _now: u64, _ram: &mut [u8]) -> bool {
+		if let Some(byte) = self.xdr.take() {
+			io::write_byte(byte);
+			self.regs[PIO_T_IIR] |= PIO_T_IID_XMIT;
+		}
+		if self.is_waiting_for_input() {
+			self.receive();
+		}
+		let mut iir = self.regs[PIO_T_IIR];
+		let pending = iir & self.regs[PIO_T_IER] & (PIO_T_IID_RECV | PIO_T_IID_XMIT);
+		if pending != 0 {
+			iir |= PIO_T_IID_INT;
+		} else {
+			iir &= !PIO_T_IID_INT;
+		}
+		self.regs[PIO_T_IIR] = iir;
+		// a condition the program consumed interrupts again once it sets
+		let is_raised = pending & !self.was_pending != 0;
+		self.was_pending = pending;
+		is_raised
+	}
+	/// Sleeps until the program accesses the registers, unless a character
+	/// waits to be sent or a receive interrupt waits for input
+	fn next_tick(&self, now: u64) -> Option<u64> {
+		let is_busy = self.xdr.is_some() || self.is_waiting_for_input();
+		is_busy.then_some(now + 1)
+	}
 }
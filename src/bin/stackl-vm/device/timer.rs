@@ -0,0 +1,65 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Timer counting executed instructions, so its interrupts land on the same
+//! instruction on every run
+
+use super::{
+	Device,
+	Registers,
+};
+use crate::machine::flag::IntVec;
+
+pub const TIMER_BASE: i32 = 0x0C00_0000;
+/// Control/Status register
+pub const TIMER_CSR: i32 = 0x0C00_0000;
+/// Instructions since the last interrupt
+pub const TIMER_COUNT: i32 = 0x0C00_0004;
+/// COUNT that raises the interrupt, 0 stops the count
+pub const TIMER_LIMIT: i32 = 0x0C00_0008;
+/// Instructions since boot
+pub const TIMER_TIME: i32 = 0x0C00_000C;
+
+pub const TIMER_CSR_IE: i32 = 0x0000_0001; // Interrupt enable
+pub const TIMER_CSR_INT: i32 = 0x8000_0000u32 as i32; // COUNT reached LIMIT, cleared by software
+
+const CSR: usize = (TIMER_CSR - TIMER_BASE) as usize;
+const COUNT: usize = (TIMER_COUNT - TIMER_BASE) as usize;
+const LIMIT: usize = (TIMER_LIMIT - TIMER_BASE) as usize;
+const TIME: usize = (TIMER_TIME - TIMER_BASE) as usize;
+
+#[derive(Debug, Default)]
+pub struct Timer {
+	regs: Registers<16>,
+}
+
+impl Device for Timer {
+	fn size(&self) -> usize {
+		16
+	}
+	fn interrupt_line(&self) -> IntVec {
+		IntVec::TIMER
+	}
+	fn peek(&self, offset: usize, buf: &mut [u8]) {
+		self.regs.peek(offset, buf);
+	}
+	fn write(&mut self, offset: usize, data: &[u8]) {
+		self.regs.write(offset, data);
+	}
+	fn tick(&mut self, _now: u64, _ram: &mut [u8]) -> bool {
+		let regs = &mut self.regs;
+		regs.set(TIME, regs.get(TIME).wrapping_add(1));
+		let limit = regs.get(LIMIT);
+		if limit == 0 {
+			return false;
+		}
+		let count = regs.get(COUNT).wrapping_add(1);
+		if (count as u32) < (limit as u32) {
+			regs.set(COUNT, count);
+			return false;
+		}
+		regs.set(COUNT, 0);
+		let csr = regs.get(CSR) | TIMER_CSR_INT;
+		regs.set(CSR, csr);
+		csr & TIMER_CSR_IE != 0
+	}
+}
@@ -0,0 +1,473 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! GDB remote serial protocol stub started with `--gdb`.
+//!
+//! A port number listens on localhost and anything else is taken as the
+//! path of a unix socket. Only one debugger is served and the program waits
+//! for it before running. Breakpoints are kept by the stub instead of being
+//! patched into memory, so the program never sees them.
+
+use std::collections::{
+	HashSet,
+	VecDeque,
+};
+use std::io::{
+	self,
+	Read,
+	Write,
+};
+use std::net::{
+	Ipv4Addr,
+	TcpListener,
+};
+use std::sync::mpsc::{
+	Receiver,
+	TryRecvError,
+	channel,
+};
+use std::thread;
+
+use stackl::debug::DebugInfo;
+
+use crate::Exception;
+use crate::machine::MachineState;
+use crate::machine::flag::{
+	IntVec,
+	MachineCheck,
+	MachineFlags,
+	Status,
+};
+
+/// Register set in the order of the `g` packet
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+	<feature name="org.stackl.core">
+		<reg name="bp" bitsize="32" type="data_ptr"/>
+		<reg name="lp" bitsize="32" type="data_ptr"/>
+		<reg name="ip" bitsize="32" type="code_ptr"/>
+		<reg name="sp" bitsize="32" type="data_ptr"/>
+		<reg name="fp" bitsize="32" type="data_ptr"/>
+		<reg name="flag" bitsize="32" type="uint32"/>
+		<reg name="ivec" bitsize="32" type="uint32"/>
+		<reg name="ptbr" bitsize="32" type="data_ptr"/>
+		<reg name="fault" bitsize="32" type="data_ptr"/>
+	</feature>
+</target>
+"#;
+
+const REGISTER_COUNT: usize = 9;
+
+/// Instructions run between checks for an interrupt from the debugger
+const POLL_INTERVAL: u32 = 1024;
+
+// GDB signal numbers
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+const SIGFPE: u8 = 8;
+const SIGBUS: u8 = 10;
+const SIGSEGV: u8 = 11;
+
+/// Waits for a debugger on `target` and serves it until it kills the
+/// program, detaches or disconnects
+pub fn serve(cpu: &mut MachineState, debug: Option<&DebugInfo>, target: &str) -> io::Result<()> {
+	let stub = Stub {
+		cpu,
+		debug,
+		breakpoints: HashSet::new(),
+		pending: VecDeque::new(),
+		no_ack: false,
+	};
+	if let Ok(port) = target.parse::<u16>() {
+		let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+		eprintln!("Waiting for GDB on port {port}");
+		let (stream, _) = listener.accept()?;
+		stream.set_nodelay(true)?;
+		let reader = stream.try_clone()?;
+		return stub.run(reader, stream);
+	}
+	serve_unix(stub, target)
+}
+
+#[cfg(unix)]
+fn serve_unix(stub: Stub, path: &str) -> io::Result<()> {
+	use std::os::unix::net::UnixListener;
+	let listener = UnixListener::bind(path)?;
+	eprintln!("Waiting for GDB on {path}");
+	let accepted = listener.accept();
+	let _ = std::fs::remove_file(path);
+	let (stream, _) = accepted?;
+	let reader = stream.try_clone()?;
+	stub.run(reader, stream)
+}
+
+#[cfg(not(unix))]
+fn serve_unix(_: Stub, path: &str) -> io::Result<()> {
+	Err(io::Error::new(
+		io::ErrorKind::Unsupported,
+		format!("`{path}` is not a port and unix sockets are not supported"),
+	))
+}
+
+/// Message from the debugger
+enum Event {
+	Packet(String),
+	/// Ctrl-C sent while the program runs
+	Interrupt,
+}
+
+/// Why the machine stopped running
+enum Stop {
+	Step,
+	Breakpoint,
+	Interrupt,
+	Halted,
+	Check(MachineCheck),
+}
+
+struct Stub<'a> {
+	cpu: &'a mut MachineState,
+	debug: Option<&'a DebugInfo>,
+	breakpoints: HashSet<i32>,
+	/// Bytes received while the program ran
+	pending: VecDeque<u8>,
+	no_ack: bool,
+}
+
+impl Stub<'_> {
+	fn run(
+		mut self,
+		mut reader: impl Read + Send + 'static,
+		mut writer: impl Write,
+	) -> io::Result<()> {
+		// reads on their own thread so a running program can be interrupted
+		let (byte_send, bytes) = channel();
+		thread::spawn(move || {
+			let mut buf = [0; 256];
+			while let Ok(len @ 1..) = reader.read(&mut buf) {
+				if buf[..len].iter().any(|&byte| byte_send.send(byte).is_err()) {
+					break;
+				}
+			}
+		});
+		loop {
+			let Some(event) = self.next_event(&bytes, &mut writer)? else {
+				// the debugger went away
+				return Ok(());
+			};
+			let Event::Packet(packet) = event else {
+				continue;
+			};
+			let reply = match packet.as_str() {
+				"?" => format!("S{SIGTRAP:02x}"),
+				"g" => self.read_registers(),
+				"k" => return Ok(()),
+				"D" => {
+					self.send(&mut writer, "OK")?;
+					crate::run_machine(self.cpu, self.debug);
+					return Ok(());
+				}
+				"QStartNoAckMode" => {
+					self.send(&mut writer, "OK")?;
+					self.no_ack = true;
+					continue;
+				}
+				"qAttached" => "1".to_string(),
+				"qfThreadInfo" => "m1".to_string(),
+				"qsThreadInfo" => "l".to_string(),
+				"qC" => "QC1".to_string(),
+				_ if packet.starts_with("qSupported") => {
+					"PacketSize=4000;qXfer:features:read+;QStartNoAckMode+;swbreak+".to_string()
+				}
+				_ if packet.starts_with("qXfer:features:read:target.xml:") => {
+					read_features(&packet["qXfer:features:read:target.xml:".len()..])
+				}
+				_ if packet.starts_with('H') => "OK".to_string(),
+				_ if packet.starts_with('G') => self.write_registers(&packet[1..]),
+				_ if packet.starts_with('p') => self.read_register(&packet[1..]),
+				_ if packet.starts_with('P') => self.write_register(&packet[1..]),
+				_ if packet.starts_with('m') => self.read_memory(&packet[1..]),
+				_ if packet.starts_with('M') => self.write_memory(&packet[1..]),
+				_ if packet.starts_with("Z0,") => self.set_breakpoint(&packet[3..], true),
+				_ if packet.starts_with("z0,") => self.set_breakpoint(&packet[3..], false),
+				_ if packet.starts_with('s') || packet.starts_with('c') => {
+					if packet.len() > 1 {
+						let Some(address) = parse_hex(&packet[1..]) else {
+							self.send(&mut writer, "E01")?;
+							continue;
+						};
+						self.cpu.ip = address as i32;
+					}
+					let stop = self.resume(&bytes, packet.starts_with('s'));
+					stop_reply(stop)
+				}
+				// unsupported packets get an empty reply
+				_ => String::new(),
+			};
+			self.send(&mut writer, &reply)?;
+		}
+	}
+	/// Reads the next packet or interrupt, acknowledging packets unless
+	/// acks were turned off. Returns `None` once the connection closed.
+	fn next_event(
+		&mut self,
+		bytes: &Receiver<u8>,
+		writer: &mut impl Write,
+	) -> io::Result<Option<Event>> {
+		loop {
+			match self.next_byte(bytes) {
+				None => return Ok(None),
+				Some(0x03) => return Ok(Some(Event::Interrupt)),
+				Some(b'$') => {}
+				// acks and noise between packets
+				Some(_) => continue,
+			}
+			let mut data = vec![];
+			loop {
+				match self.next_byte(bytes) {
+					None => return Ok(None),
+					Some(b'#') => break,
+					Some(byte) => data.push(byte),
+				}
+			}
+			let (Some(high), Some(low)) = (self.next_byte(bytes), self.next_byte(bytes)) else {
+				return Ok(None);
+			};
+			let checksum = std::str::from_utf8(&[high, low])
+				.ok()
+				.and_then(|text| u8::from_str_radix(text, 16).ok());
+			let is_valid = checksum == Some(checksum_of(&data));
+			if !self.no_ack {
+				writer.write_all(if is_valid { b"+" } else { b"-" })?;
+				writer.flush()?;
+			}
+			if is_valid {
+				return Ok(Some(Event::Packet(
+					String::from_utf8_lossy(&data).into_owned(),
+				)));
+			}
+		}
+	}
+	fn next_byte(&mut self, bytes: &Receiver<u8>) -> Option<u8> {
+		self.pending.pop_front().or_else(|| bytes.recv().ok())
+	}
+	fn send(&self, writer: &mut impl Write, data: &str) -> io::Result<()> {
+		write!(writer, "${data}#{:02x}", checksum_of(data.as_bytes()))?;
+		writer.flush()
+	}
+	/// Runs one instruction or until something stops the machine
+	fn resume(&mut self, bytes: &Receiver<u8>, is_step: bool) -> Stop {
+		let mut until_poll = POLL_INTERVAL;
+		loop {
+			let cpu = &mut *self.cpu;
+			if cpu.flag.get_status(Status::HALTED) {
+				return Stop::Halted;
+			}
+			match crate::step_machine(cpu) {
+				Ok(()) => {}
+				Err(Exception::Check(check)) => return Stop::Check(check),
+				Err(Exception::Break) => {
+					// reported as SIGTRAP, like a finished step
+					cpu.flag.set_intvec(IntVec::BKPT, false);
+					return Stop::Step;
+				}
+			}
+			if cpu.flag.get_status(Status::HALTED) {
+				return Stop::Halted;
+			}
+			if is_step {
+				return Stop::Step;
+			}
+			if self.breakpoints.contains(&cpu.ip) {
+				return Stop::Breakpoint;
+			}
+			until_poll -= 1;
+			if until_poll == 0 {
+				until_poll = POLL_INTERVAL;
+				loop {
+					match bytes.try_recv() {
+						Ok(0x03) => return Stop::Interrupt,
+						Ok(byte) => self.pending.push_back(byte),
+						Err(TryRecvError::Empty | TryRecvError::Disconnected) => break,
+					}
+				}
+			}
+		}
+	}
+	fn registers(&self) -> [i32; REGISTER_COUNT] {
+		let cpu = &*self.cpu;
+		[
+			cpu.bp,
+			cpu.lp,
+			cpu.ip,
+			cpu.sp,
+			cpu.fp,
+			cpu.flag.as_u32() as i32,
+			cpu.ivec,
+			cpu.ptbr,
+			cpu.fault,
+		]
+	}
+	fn set_register(&mut self, index: usize, value: i32) -> bool {
+		let cpu = &mut *self.cpu;
+		match index {
+			0 => cpu.bp = value,
+			1 => cpu.lp = value,
+			2 => cpu.ip = value,
+			3 => cpu.sp = value,
+			4 => cpu.fp = value,
+			5 => cpu.flag = MachineFlags::from(value as u32),
+			6 => cpu.ivec = value,
+			7 => {
+				cpu.ptbr = value;
+				cpu.tlb.flush();
+			}
+			8 => cpu.fault = value,
+			_ => return false,
+		}
+		true
+	}
+	fn read_registers(&self) -> String {
+		self.registers()
+			.iter()
+			.map(|value| encode_hex(&value.to_le_bytes()))
+			.collect()
+	}
+	fn write_registers(&mut self, data: &str) -> String {
+		let Some(bytes) = decode_hex(data) else {
+			return "E01".to_string();
+		};
+		if bytes.len() != REGISTER_COUNT * 4 {
+			return "E01".to_string();
+		}
+		for (index, value) in bytes.chunks_exact(4).enumerate() {
+			let value = i32::from_le_bytes(value.try_into().unwrap());
+			self.set_register(index, value);
+		}
+		"OK".to_string()
+	}
+	fn read_register(&self, data: &str) -> String {
+		parse_hex(data)
+			.and_then(|index| self.registers().get(index as usize).copied())
+			.map_or_else(
+				|| "E01".to_string(),
+				|value| encode_hex(&value.to_le_bytes()),
+			)
+	}
+	fn write_register(&mut self, data: &str) -> String {
+		let Some((index, value)) = data.split_once('=') else {
+			return "E01".to_string();
+		};
+		let value = decode_hex(value).and_then(|value| <[u8; 4]>::try_from(value).ok());
+		match (parse_hex(index), value) {
+			(Some(index), Some(value))
+				if self.set_register(index as usize, i32::from_le_bytes(value)) =>
+			{
+				"OK".to_string()
+			}
+			_ => "E01".to_string(),
+		}
+	}
+	fn read_memory(&self, data: &str) -> String {
+		let Some((start, len)) = parse_range(data) else {
+			return "E01".to_string();
+		};
+		if len == 0 {
+			return String::new();
+		}
+		let mut bytes = vec![0; len];
+		self.cpu
+			.mem
+			.peek(start, &mut bytes)
+			.map_or_else(|_| "E01".to_string(), |()| encode_hex(&bytes))
+	}
+	fn write_memory(&mut self, data: &str) -> String {
+		let Some((range, data)) = data.split_once(':') else {
+			return "E01".to_string();
+		};
+		let (Some((start, len)), Some(bytes)) = (parse_range(range), decode_hex(data)) else {
+			return "E01".to_string();
+		};
+		if bytes.len() != len {
+			return "E01".to_string();
+		}
+		if len == 0 {
+			return "OK".to_string();
+		}
+		match self.cpu.mem.set(start..start + len, &bytes) {
+			Ok(()) => "OK".to_string(),
+			Err(_) => "E01".to_string(),
+		}
+	}
+	fn set_breakpoint(&mut self, data: &str, is_insert: bool) -> String {
+		let Some(address) = data.split(',').next().and_then(parse_hex) else {
+			return "E01".to_string();
+		};
+		if is_insert {
+			self.breakpoints.insert(address as i32);
+		} else {
+			self.breakpoints.remove(&(address as i32));
+		}
+		"OK".to_string()
+	}
+}
+
+fn stop_reply(stop: Stop) -> String {
+	let signal = match stop {
+		Stop::Step => SIGTRAP,
+		Stop::Breakpoint => return format!("T{SIGTRAP:02x}swbreak:;"),
+		Stop::Interrupt => SIGINT,
+		Stop::Halted => return "W00".to_string(),
+		Stop::Check(check) => match check {
+			MachineCheck::ILLEGAL_INST | MachineCheck::PROT_INST => SIGILL,
+			MachineCheck::ILLEGAL_ADDR => SIGSEGV,
+			MachineCheck::DIVIDE_ZERO | MachineCheck::OVF | MachineCheck::FPE => SIGFPE,
+			_ => SIGBUS,
+		},
+	};
+	format!("S{signal:02x}")
+}
+
+/// Answers `qXfer:features:read:target.xml:offset,length`
+fn read_features(range: &str) -> String {
+	let Some((offset, len)) = parse_range(range) else {
+		return "E01".to_string();
+	};
+	let Some(rest) = TARGET_XML.get(offset..) else {
+		return "l".to_string();
+	};
+	if rest.len() <= len {
+		format!("l{rest}")
+	} else {
+		format!("m{}", &rest[..len])
+	}
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+	data.iter().fold(0, |sum, &byte| sum.wrapping_add(byte))
+}
+
+fn parse_hex(text: &str) -> Option<u32> {
+	u32::from_str_radix(text, 16).ok()
+}
+
+/// Parses `address,length`
+fn parse_range(text: &str) -> Option<(usize, usize)> {
+	let (start, len) = text.split_once(',')?;
+	Some((parse_hex(start)? as usize, parse_hex(len)? as usize))
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+	bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+	if !text.len().is_multiple_of(2) {
+		return None;
+	}
+	(0..text.len())
+		.step_by(2)
+		.map(|index| u8::from_str_radix(text.get(index..index + 2)?, 16).ok())
+		.collect()
+}
@@ -1,9 +1,13 @@
 // Copyright (c) 2024-2026 Jonathan A. Thomason
 
 use std::io;
-use std::io::Write;
+use std::io::{
+	IsTerminal,
+	Read,
+	Write,
+};
+use std::sync::mpsc;
 use std::thread;
-use std::time;
 
 // This function does not check alignment
 pub fn try_print(buf: &[u8]) -> usize {
@@ -11,9 +15,6 @@ pub fn try_print(buf: &[u8]) -> usize {
 	for chunk in buf.utf8_chunks() {
 		for ch in chunk.valid().chars() {
 			let ch_len = ch.len_utf8();
-			for _ in 0..ch_len {
-				thread::sleep(time::Duration::from_micros(100));
-			}
 			consumed_bytes += ch_len;
 			if ch == '\0' {
 				return consumed_bytes;
@@ -22,7 +23,6 @@ pub fn try_print(buf: &[u8]) -> usize {
 			io::stdout().flush().unwrap();
 		}
 		for byte in chunk.invalid() {
-			thread::sleep(time::Duration::from_micros(100));
 			consumed_bytes += 1;
 			print!("\\x{:02X}", byte);
 			io::stdout().flush().unwrap();
@@ -35,3 +35,97 @@ pub fn read_line() -> io::Result<String> {
 	let mut buf = String::new();
 	io::stdin().read_line(&mut buf).map(|_| buf)
 }
+
+pub fn write_byte(byte: u8) {
+	write_bytes(&[byte]);
+}
+
+pub fn write_bytes(buf: &[u8]) {
+	let mut stdout = io::stdout();
+	stdout.write_all(buf).unwrap();
+	stdout.flush().unwrap();
+}
+
+/// Stdin read by a thread of its own.
+///
+/// Input from a file or a pipe is waited for, so the program gets every byte
+/// at the same instruction on every run. A terminal is only polled, typing
+/// can not be repeated anyway.
+#[derive(Debug)]
+pub struct Input {
+	recv: mpsc::Receiver<u8>,
+	is_terminal: bool,
+}
+
+impl Input {
+	pub fn new() -> Self {
+		let (send, recv) = mpsc::channel();
+		thread::spawn(move || {
+			let mut buf = [0; 256];
+			while let Ok(len @ 1..) = io::stdin().read(&mut buf) {
+				if buf[..len].iter().any(|&byte| send.send(byte).is_err()) {
+					break;
+				}
+			}
+		});
+		Self {
+			recv,
+			is_terminal: io::stdin().is_terminal(),
+		}
+	}
+	/// Next byte of input, `Empty` while nothing was typed on the terminal
+	/// and `Disconnected` at the end of the input
+	pub fn next(&self) -> Result<u8, mpsc::TryRecvError> {
+		match self.is_terminal {
+			true => self.recv.try_recv(),
+			false => self.recv.recv().or(Err(mpsc::TryRecvError::Disconnected)),
+		}
+	}
+}
+
+/// Turns off line buffering and echo of a terminal on stdin until dropped,
+/// so characters arrive as they are typed. Input from a pipe is left as is.
+pub struct RawMode {
+	#[cfg(unix)]
+	saved: libc::termios,
+}
+
+impl RawMode {
+	#[cfg(unix)]
+	pub fn enable() -> Option<Self> {
+		if !io::stdin().is_terminal() {
+			return None;
+		}
+		let mut termios = std::mem::MaybeUninit::<libc::termios>::uninit();
+		// SAFETY: tcgetattr fills in the termios when it succeeds
+		let saved = unsafe {
+			if libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) != 0 {
+				return None;
+			}
+			termios.assume_init()
+		};
+		let mut raw = saved;
+		raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+		raw.c_cc[libc::VMIN] = 1;
+		raw.c_cc[libc::VTIME] = 0;
+		// SAFETY: raw is a valid termios
+		if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+			return None;
+		}
+		Some(Self { saved })
+	}
+	#[cfg(not(unix))]
+	pub fn enable() -> Option<Self> {
+		None
+	}
+}
+
+impl Drop for RawMode {
+	fn drop(&mut self) {
+		#[cfg(unix)]
+		// SAFETY: saved is the termios read in enable
+		unsafe {
+			libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.saved);
+		}
+	}
+}
@@ -13,6 +13,8 @@ bitflags! {
 		const INT_DIS          = 1 << 3;
 		const VMEM_MODE        = 1 << 4;
 		const FPU_ENABLE       = 1 << 5;
+		/// Carry out of the last ADD or ADC, borrow of SUB or SBB
+		const CARRY            = 1 << 6;
 		const _                = !0;
 	}
 }
@@ -32,9 +34,9 @@ bitflags! {
 		const PROT_INST    = 1 << 4;
 		/// Divide by zero
 		const DIVIDE_ZERO  = 1 << 5;
-		// TODO: Overflow exception
+		/// Signed overflow of a checked instruction or a division
 		const OVF          = 1 << 6;
-		// TODO: Floating point arithmetic exception
+		/// Floating point arithmetic exception
 		const FPE          = 1 << 7;
 	}
 }
@@ -47,6 +49,9 @@ impl fmt::Display for MachineCheck {
 			MachineCheck::HW_FAILURE => "Hardware Failure".to_string(),
 			MachineCheck::HW_WARNING => "Hardware Warning".to_string(),
 			MachineCheck::PROT_INST => "Protected Instruction".to_string(),
+			MachineCheck::DIVIDE_ZERO => "Divide by Zero".to_string(),
+			MachineCheck::OVF => "Overflow".to_string(),
+			MachineCheck::FPE => "Floating Point Exception".to_string(),
 			_ => "Illegal Operation".to_string(),
 		};
 		write!(f, "{kind}")
@@ -62,8 +67,11 @@ bitflags! {
 		const TIMER         = 1 << 3;
 		const DMA_T         = 1 << 4;
 		const PIO_T         = 1 << 5;
+		/// Access to a page the page table does not allow, FAULT holds the
+		/// address
+		const PAGE_FAULT    = 1 << 6;
 		const GEN_IO        = 1 << 8;
-		// TODO: Breakpoint caused by `BREAK` instruction
+		/// Breakpoint caused by `BREAK` instruction
 		const BKPT          = 1 << 9;
 		const _ = !0;
 	}
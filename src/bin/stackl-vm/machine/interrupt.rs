@@ -11,13 +11,13 @@ impl MachineState {
 		let vector = if is_trap {
 			TRAP_VECTOR
 		} else {
-			// Find highest priority pending interrupt
-			let pending_interrupt = self.flag.intvec.iter().enumerate().next();
-
-			let Some((vector, int_flag)) = pending_interrupt else {
-				// no pending interrupts
+			// Find highest priority pending interrupt, the lowest bit is
+			// vector 0
+			if self.flag.intvec.is_empty() {
 				return Ok(());
-			};
+			}
+			let vector = self.flag.intvec.bits().trailing_zeros() as usize;
+			let int_flag = IntVec::from_bits_retain(1 << vector);
 
 			// turn off pending bit for HW interrupts
 			self.flag.intvec.set(int_flag, false);
@@ -39,7 +39,7 @@ impl MachineState {
 		self.flag.set_status(Status::USR_MODE, false);
 		self.flag.set_status(Status::INT_MODE, true);
 
-		if was_user {
+		if was_user && !self.flag.get_status(Status::VMEM_MODE) {
 			// switch fp and sp to absolute addresses
 			self.fp += self.bp;
 			self.sp += self.bp;
@@ -1,6 +1,10 @@
 // Copyright (c) 2024-2026 Jonathan A. Thomason
 
-use super::flag::MachineCheck;
+use super::flag::{
+	IntVec,
+	MachineCheck,
+};
+use crate::device::Bus;
 use std::ops::{
 	Bound,
 	RangeBounds,
@@ -8,28 +12,20 @@ use std::ops::{
 
 #[derive(Debug)]
 pub struct MachineMemory {
-	/// mapped addr: 0x0B00_0000..=0x0B00_000F
-	gen_io: [u8; 16],
-	/// mapped addr: 0x0C00_0000..=0x0C00_000F
-	timer: [u8; 16],
-	/// mapped addr: 0x0D00_0000..=0x0D00_000F
-	disk: [u8; 16],
-	/// mapped addr: 0x0E00_0000..=0x0E00_000F
-	pio_term: [u8; 16],
+	/// devices mapped over the address space
+	pub bus: Bus,
 	ram: Vec<u8>,
 }
 
 impl MachineMemory {
 	pub fn new(size: usize) -> Self {
 		Self {
-			gen_io: [0; 16],
-			timer: [0; 16],
-			disk: [0; 16],
-			pio_term: [0; 16],
+			bus: Bus::default(),
 			ram: vec![0x79; size],
 		}
 	}
 
+	/// Regular memory, devices are left out
 	#[inline]
 	pub fn get<I>(&self, index: I) -> Result<&[u8], MachineCheck>
 	where
@@ -45,35 +41,22 @@ impl MachineMemory {
 			Bound::Excluded(&i) => i - 1,
 			Bound::Included(&i) => i,
 		};
-		match start {
-			0x0B00_0000..=0x0B00_000F => {
-				let start = start - 0x0B00_0000;
-				let end = end - 0x0B00_0000;
-				return self
-					.gen_io
-					.get(start..=end)
-					.ok_or(MachineCheck::ILLEGAL_ADDR);
-			}
-			0x0C00_0000..=0x0C00_000F => {
-				return self
-					.timer
-					.get(start..=end)
-					.ok_or(MachineCheck::ILLEGAL_ADDR);
-			}
-			0x0D00_0000..=0x0D00_000F => {
-				return self.disk.get(start..=end).ok_or(MachineCheck::ILLEGAL_ADDR);
-			}
-			0x0E00_0000..=0x0E00_000F => {
-				return self
-					.pio_term
-					.get(start..=end)
-					.ok_or(MachineCheck::ILLEGAL_ADDR);
-			}
-			// regular memory
-			_ => (),
-		}
 		self.ram.get(start..=end).ok_or(MachineCheck::ILLEGAL_ADDR)
 	}
+	/// Copies memory or device registers at `addr` without side effects
+	pub fn peek(&self, addr: usize, buf: &mut [u8]) -> Result<(), MachineCheck> {
+		if !self.bus.peek(addr, buf)? {
+			buf.copy_from_slice(self.get(addr..addr + buf.len())?);
+		}
+		Ok(())
+	}
+	/// Copies memory or device registers at `addr` for the program
+	pub fn read(&mut self, addr: usize, buf: &mut [u8]) -> Result<(), MachineCheck> {
+		if !self.bus.read(addr, buf)? {
+			buf.copy_from_slice(self.get(addr..addr + buf.len())?);
+		}
+		Ok(())
+	}
 	pub fn set<I>(&mut self, index: I, value: &[u8]) -> Result<(), MachineCheck>
 	where
 		I: RangeBounds<usize>,
@@ -89,42 +72,22 @@ impl MachineMemory {
 			Bound::Included(&i) => i,
 		};
 
-		debug_assert!((end - start) != value.len());
+		debug_assert!((end + 1 - start) == value.len());
 
-		for (addr, &data) in (start..=end).zip(value) {
-			match addr {
-				// gen_io
-				0x0B00_0000..=0x0B00_000F => {
-					let offset = addr - 0x0B00_0000;
-					self.gen_io[offset] = data;
-				}
-				// timer
-				0x0C00_0000..=0x0C00_000F => {
-					let offset = addr - 0x0C00_0000;
-					self.timer[offset] = data;
-				}
-				// disk
-				0x0D00_0000..=0x0D00_000F => {
-					let offset = addr - 0x0D00_0000;
-					self.disk[offset] = data;
-				}
-				// pio term
-				0x0E00_0000..=0x0E00_000F => {
-					let offset = addr - 0x0E00_0000;
-					self.pio_term[offset] = data;
-				}
-				// regular memory
-				_ => {
-					let ram = self.ram.get_mut(addr).ok_or(MachineCheck::ILLEGAL_ADDR);
-					if let Err(e) = ram {
-						panic!("issue: {:?}", e);
-					}
-					*(ram?) = data;
-				}
-			}
+		if self.bus.write(start, value)? {
+			return Ok(());
 		}
+		self.ram
+			.get_mut(start..=end)
+			.ok_or(MachineCheck::ILLEGAL_ADDR)?
+			.copy_from_slice(value);
 		Ok(())
 	}
+	/// Advances the devices to instruction `now`, returns the interrupt
+	/// lines they raised
+	pub fn tick(&mut self, now: u64) -> IntVec {
+		self.bus.tick(now, &mut self.ram)
+	}
 	pub fn len(&self) -> usize {
 		self.ram.len()
 	}
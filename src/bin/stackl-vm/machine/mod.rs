@@ -1,8 +1,5 @@
 // Copyright (c) 2024-2026 Jonathan A. Thomason
 
-use std::ffi;
-use std::sync::mpsc::Sender;
-
 use flag::{
 	MachineCheck,
 	MachineFlags,
@@ -15,13 +12,19 @@ use stackl::{
 	StacklFormatV2,
 	asm::op,
 };
+use throttle::Throttle;
+use vmem::{
+	Access,
+	Tlb,
+};
 
 pub mod flag;
 mod interrupt;
 pub mod memory;
 pub mod step;
-mod timer;
+pub mod throttle;
 mod trace;
+pub mod vmem;
 
 #[derive(Debug)]
 pub struct MachineState {
@@ -39,9 +42,20 @@ pub struct MachineState {
 	pub ivec: i32,
 	/// page table base register
 	pub ptbr: i32,
+	/// address of the last page fault
+	pub fault: i32,
 	pub mem: MachineMemory,
 	pub meta: MetaFlags,
 	pub last_trace: u8,
+	/// Instructions run since boot, the time devices are scheduled on
+	pub clock: u64,
+	/// Processor cycles spent since boot
+	pub cycles: u64,
+	/// Holds the machine to a processor speed, `None` runs at host speed
+	pub throttle: Option<Throttle>,
+	pub tlb: Tlb,
+	/// Address the running instruction faulted on
+	pub page_fault: Option<i32>,
 }
 
 impl MachineState {
@@ -55,9 +69,15 @@ impl MachineState {
 			flag: MachineFlags::new(),
 			ivec: 0,
 			ptbr: 0,
+			fault: 0,
 			mem: MachineMemory::new(mem_size),
 			meta: MetaFlags::empty(),
 			last_trace: 0,
+			clock: 0,
+			cycles: 0,
+			throttle: None,
+			tlb: Tlb::default(),
+			page_fault: None,
 		}
 	}
 	pub fn store_program(
@@ -133,61 +153,94 @@ impl MachineState {
 		let offset = i32_to_offset(offset)?;
 		self.mem.set(offset..offset + val.len(), val)
 	}
-	pub fn load_cstr(&self, offset: i32) -> Result<&ffi::CStr, MachineCheck> {
-		let offset = i32_to_offset(offset)?;
-		let bytes = self.mem.get(offset..)?;
-		let Ok(c_str) = ffi::CStr::from_bytes_until_nul(bytes) else {
-			return Err(MachineCheck::ILLEGAL_ADDR);
-		};
-		Ok(c_str)
+	/// Stores bytes for the program one at a time, so they may span pages
+	pub fn store_bytes(&mut self, val: &[u8], offset: i32) -> Result<(), MachineCheck> {
+		for (offset, &byte) in (offset..).zip(val) {
+			self.store_u8(byte, offset)?;
+		}
+		Ok(())
 	}
+	/// Loads the string at `offset` for the program, up to and including its
+	/// NUL
+	pub fn load_string(&mut self, offset: i32) -> Result<Vec<u8>, MachineCheck> {
+		let mut bytes = Vec::new();
+		for offset in offset.. {
+			let byte = self.load_u8(offset)?;
+			bytes.push(byte);
+			if byte == 0 {
+				break;
+			}
+		}
+		Ok(bytes)
+	}
+	/// Loads a word without the side effects a read of a device has
 	pub fn load_abs_i32(&self, offset: i32) -> Result<i32, MachineCheck> {
 		check_align(offset)?;
 		let offset = i32_to_offset(offset)?;
-		let mem = self.mem.get(offset..=(offset + 3))?;
-		mem.try_into()
-			.map(i32::from_le_bytes)
-			.or(Err(MachineCheck::ILLEGAL_ADDR))
+		let mut bytes = [0; 4];
+		self.mem.peek(offset, &mut bytes)?;
+		Ok(i32::from_le_bytes(bytes))
 	}
 	pub fn store_abs_i32(&mut self, val: i32, offset: i32) -> Result<(), MachineCheck> {
 		check_align(offset)?;
 		let bytes = i32::to_le_bytes(val);
 		self.store_slice(&bytes, offset)
 	}
-	pub fn load_i32(&self, offset: i32) -> Result<i32, MachineCheck> {
-		let offset = if self.is_user() {
-			offset + self.bp
-		} else {
-			offset
-		};
-		self.load_abs_i32(offset)
+	pub fn load_i32(&mut self, offset: i32) -> Result<i32, MachineCheck> {
+		check_align(offset)?;
+		let offset = self.translate(offset, Access::Read)?;
+		self.load_abs_i32(offset as i32)
+	}
+	/// Loads a word the way the program sees it without touching the TLB,
+	/// for looking at memory from outside the program
+	pub fn peek_i32(&self, offset: i32) -> Result<i32, MachineCheck> {
+		check_align(offset)?;
+		let offset = self.peek_translate(offset, Access::Read)?;
+		self.load_abs_i32(offset as i32)
 	}
 	pub fn store_i32(&mut self, val: i32, offset: i32) -> Result<(), MachineCheck> {
-		let offset = if self.is_user() {
-			offset + self.bp
-		} else {
-			offset
-		};
-		self.store_abs_i32(val, offset)
+		check_align(offset)?;
+		let offset = self.translate(offset, Access::Write)?;
+		self.store_abs_i32(val, offset as i32)
 	}
 	// This function does not check alignment
-	pub fn load_u8(&self, offset: i32) -> Result<u8, MachineCheck> {
-		let offset = if self.is_user() {
-			offset + self.bp
-		} else {
-			offset
-		};
-		let offset = i32_to_offset(offset)?;
-		self.mem.get(offset..=offset).map(|x| x[0])
+	pub fn load_u8(&mut self, offset: i32) -> Result<u8, MachineCheck> {
+		let offset = self.translate(offset, Access::Read)?;
+		let mut byte = [0];
+		self.mem.peek(offset, &mut byte)?;
+		Ok(byte[0])
+	}
+	/// Loads a word for the program, a device sees the read
+	pub fn read_i32(&mut self, offset: i32) -> Result<i32, MachineCheck> {
+		check_align(offset)?;
+		let offset = self.translate(offset, Access::Read)?;
+		let mut bytes = [0; 4];
+		self.mem.read(offset, &mut bytes)?;
+		Ok(i32::from_le_bytes(bytes))
+	}
+	/// Loads a byte for the program, a device sees the read
+	// This function does not check alignment
+	pub fn read_u8(&mut self, offset: i32) -> Result<u8, MachineCheck> {
+		let offset = self.translate(offset, Access::Read)?;
+		let mut byte = [0];
+		self.mem.read(offset, &mut byte)?;
+		Ok(byte[0])
+	}
+	/// Waits for the host clock to catch up with the cycles spent
+	pub fn pace(&mut self) {
+		if let Some(throttle) = &mut self.throttle {
+			throttle.pace(self.cycles);
+		}
+	}
+	/// Advances the devices by one instruction and raises their interrupts
+	pub fn tick_devices(&mut self) {
+		self.clock += 1;
+		let lines = self.mem.tick(self.clock);
+		self.flag.intvec |= lines;
 	}
 	// This function does not check alignment
 	pub fn store_u8(&mut self, val: u8, offset: i32) -> Result<(), MachineCheck> {
-		let offset = if self.is_user() {
-			offset + self.bp
-		} else {
-			offset
-		};
-		let offset = i32_to_offset(offset)?;
+		let offset = self.translate(offset, Access::Write)?;
 		self.mem.set(offset..=offset, &[val])
 	}
 }
@@ -1,38 +1,128 @@
 // Copyright (c) 2024-2026 Jonathan A. Thomason
 
-use crate::device::inp::Request;
+use crate::device::inp;
 use crate::io;
 
+use super::flag::IntVec;
 use super::*;
 
-pub fn next_opcode(
-	cpu: &mut MachineState,
-	request_send: &Sender<Request>,
-) -> Result<(), MachineCheck> {
+/// Cycles taking an interrupt costs, the same as a TRAP saving the
+/// registers
+const INTERRUPT_CYCLES: u64 = 8;
+
+/// Cycles an instruction costs. Memory accesses cost more than stack
+/// arithmetic, division and the instructions saving or restoring registers
+/// more still.
+fn cycles(op: i32) -> u64 {
+	match op {
+		op::PUSHVAR
+		| op::POPVAR
+		| op::PUSHCVAR
+		| op::POPCVAR
+		| op::PUSHVARIND
+		| op::POPVARIND
+		| op::PUSHCVARIND
+		| op::POPCVARIND => 2,
+		op::MUL | op::MUL_OVF => 4,
+		op::CALL | op::CALLI | op::RET | op::RETV | op::POPARGS => 4,
+		op::TRAP | op::RTI | op::JMPUSER => INTERRUPT_CYCLES,
+		op::DIV | op::MOD => 12,
+		// host I/O, charged like a slow device
+		op::OUTS | op::INP => 20,
+		_ => 1,
+	}
+}
+
+/// Registers an instruction may change, put back when it faults on a page so
+/// it runs again once the handler returns
+struct Saved {
+	bp: i32,
+	lp: i32,
+	ip: i32,
+	sp: i32,
+	fp: i32,
+	flag: MachineFlags,
+	ivec: i32,
+	ptbr: i32,
+}
+
+impl Saved {
+	fn new(cpu: &MachineState) -> Self {
+		Self {
+			bp: cpu.bp,
+			lp: cpu.lp,
+			ip: cpu.ip,
+			sp: cpu.sp,
+			fp: cpu.fp,
+			flag: cpu.flag,
+			ivec: cpu.ivec,
+			ptbr: cpu.ptbr,
+		}
+	}
+	fn restore(self, cpu: &mut MachineState) {
+		cpu.bp = self.bp;
+		cpu.lp = self.lp;
+		cpu.ip = self.ip;
+		cpu.sp = self.sp;
+		cpu.fp = self.fp;
+		// an interrupt being taken or raised meanwhile stays pending
+		cpu.flag = MachineFlags {
+			intvec: self.flag.intvec | cpu.flag.intvec,
+			..self.flag
+		};
+		cpu.ivec = self.ivec;
+		cpu.ptbr = self.ptbr;
+	}
+}
+
+/// Runs the next instruction or takes a pending interrupt. On a page fault
+/// the registers are rolled back, `cpu.page_fault` holds the address and the
+/// error is ILLEGAL_ADDR.
+pub fn next_opcode(cpu: &mut MachineState) -> Result<(), MachineCheck> {
+	let saved = Saved::new(cpu);
+	cpu.page_fault = None;
+	let result = execute(cpu);
+	if result.is_err() && cpu.page_fault.is_some() {
+		saved.restore(cpu);
+	}
+	result
+}
+
+fn execute(cpu: &mut MachineState) -> Result<(), MachineCheck> {
 	if !cpu.flag.intvec.is_empty()
 		&& !cpu.flag.get_status(Status::INT_MODE)
 		&& !cpu.flag.get_status(Status::INT_DIS)
 	{
+		cpu.cycles += INTERRUPT_CYCLES;
 		return cpu.interrupt(false);
 	}
 
 	if cpu.meta.contains(MetaFlags::TRACE) {
-		cpu.print_trace()?;
+		// an instruction that can not be fetched is reported by the fetch
+		let _ = cpu.print_trace();
 	}
+	cpu.tick_devices();
 
 	let op: i32 = cpu.load_i32(cpu.ip)?;
+	cpu.cycles += cycles(op);
 
 	match op {
 		op::NOP => {}
-		op::ADD => {
-			let rhs = cpu.pop_i32()?;
-			let lhs = cpu.pop_i32()?;
-			cpu.push_i32(lhs.wrapping_add(rhs))?;
-		}
-		op::SUB => {
-			let rhs = cpu.pop_i32()?;
-			let lhs = cpu.pop_i32()?;
-			cpu.push_i32(lhs.wrapping_sub(rhs))?;
+		op::ADD | op::ADC => {
+			let rhs = cpu.pop_i32()? as u32;
+			let lhs = cpu.pop_i32()? as u32;
+			let carry = op == op::ADC && cpu.flag.get_status(Status::CARRY);
+			let (sum, carry) = lhs.carrying_add(rhs, carry);
+			cpu.flag.set_status(Status::CARRY, carry);
+			cpu.push_i32(sum as i32)?;
+		}
+		op::SUB | op::SBB => {
+			let rhs = cpu.pop_i32()? as u32;
+			let lhs = cpu.pop_i32()? as u32;
+			let borrow = op == op::SBB && cpu.flag.get_status(Status::CARRY);
+			let (difference, borrow) = lhs.borrowing_sub(rhs, borrow);
+			cpu.flag.set_status(Status::CARRY, borrow);
+			cpu.push_i32(difference as i32)?;
 		}
 		op::MUL => {
 			let rhs = cpu.pop_i32()?;
@@ -42,19 +132,21 @@ pub fn next_opcode(
 		op::DIV => {
 			let rhs = cpu.pop_i32()?;
 			let lhs = cpu.pop_i32()?;
-			if let Some(result) = lhs.checked_div(rhs) {
-				cpu.push_i32(result)?;
-			} else {
-				cpu.machine_check(MachineCheck::DIVIDE_ZERO);
+			match lhs.checked_div(rhs) {
+				Some(result) => cpu.push_i32(result)?,
+				None if rhs == 0 => cpu.machine_check(MachineCheck::DIVIDE_ZERO),
+				// i32::MIN / -1
+				None => cpu.machine_check(MachineCheck::OVF),
 			}
 		}
 		op::MOD => {
 			let rhs = cpu.pop_i32()?;
 			let lhs = cpu.pop_i32()?;
-			if let Some(result) = lhs.checked_rem_euclid(rhs) {
-				cpu.push_i32(result)?;
-			} else {
+			if rhs == 0 {
 				cpu.machine_check(MachineCheck::DIVIDE_ZERO);
+			} else {
+				// the remainder of i32::MIN / -1 is 0
+				cpu.push_i32(lhs.wrapping_rem_euclid(rhs))?;
 			}
 		}
 		op::EQ => {
@@ -87,6 +179,16 @@ pub fn next_opcode(
 			let lhs = cpu.pop_i32()?;
 			cpu.push_i32((lhs <= rhs) as i32)?;
 		}
+		op::ULT => {
+			let rhs = cpu.pop_i32()? as u32;
+			let lhs = cpu.pop_i32()? as u32;
+			cpu.push_i32((lhs < rhs) as i32)?;
+		}
+		op::UGT => {
+			let rhs = cpu.pop_i32()? as u32;
+			let lhs = cpu.pop_i32()? as u32;
+			cpu.push_i32((lhs > rhs) as i32)?;
+		}
 		op::AND => {
 			let rhs = cpu.pop_i32()?;
 			let lhs = cpu.pop_i32()?;
@@ -138,20 +240,20 @@ pub fn next_opcode(
 		}
 		op::NEG => {
 			let val = cpu.pop_i32()?;
-			cpu.push_i32(-val)?;
+			cpu.push_i32(val.wrapping_neg())?;
 		}
 		op::PUSHCVARIND => {
 			let offset = cpu.pop_i32()?;
-			let val = cpu.load_u8(offset)?;
+			let val = cpu.read_u8(offset)?;
 			cpu.push_i32(val as i32)?;
 		}
 		op::OUTS => {
 			if cpu.is_user() {
 				return Err(MachineCheck::PROT_INST);
 			}
-			let offset = cpu.pop_i32()? as usize;
-			let buf = cpu.mem.get(offset..)?;
-			io::try_print(buf);
+			let offset = cpu.pop_i32()?;
+			let buf = cpu.load_string(offset)?;
+			io::try_print(&buf);
 		}
 		op::INP => {
 			if !cpu.meta.contains(MetaFlags::FEATURE_INP) {
@@ -161,14 +263,7 @@ pub fn next_opcode(
 				return Err(MachineCheck::PROT_INST);
 			}
 			let offset = cpu.pop_i32()?;
-			let request = Request {
-				offset,
-				op: cpu.load_i32(offset)?,
-				param1: cpu.load_i32(offset + 4)?,
-				param2: cpu.load_i32(offset + 8)?,
-				bp: cpu.bp,
-			};
-			request_send.send(request).unwrap();
+			inp::execute(cpu, offset)?;
 		}
 		op::PUSHFP => {
 			cpu.push_i32(cpu.fp)?;
@@ -207,6 +302,8 @@ pub fn next_opcode(
 				4 => cpu.push_i32(cpu.fp)?,
 				5 => cpu.push_i32(cpu.flag.as_u32() as i32)?,
 				6 => cpu.push_i32(cpu.ivec)?,
+				7 => cpu.push_i32(cpu.ptbr)?,
+				8 => cpu.push_i32(cpu.fault)?,
 				_ => return Err(MachineCheck::ILLEGAL_INST),
 			}
 		}
@@ -230,6 +327,11 @@ pub fn next_opcode(
 					cpu.flag = MachineFlags::from(val)
 				}
 				6 => cpu.ivec = cpu.pop_i32()?,
+				7 => {
+					cpu.ptbr = cpu.pop_i32()?;
+					cpu.tlb.flush();
+				}
+				8 => cpu.fault = cpu.pop_i32()?,
 				_ => return Err(MachineCheck::ILLEGAL_INST),
 			}
 		}
@@ -260,9 +362,14 @@ pub fn next_opcode(
 			let lhs = cpu.pop_i32()?;
 			cpu.push_i32(lhs.wrapping_shr(rhs as u32))?;
 		}
+		op::USHIFT_RIGHT => {
+			let rhs = cpu.pop_i32()?;
+			let lhs = cpu.pop_i32()? as u32;
+			cpu.push_i32(lhs.wrapping_shr(rhs as u32) as i32)?;
+		}
 		op::PUSHVARIND => {
 			let offset = cpu.pop_i32()?;
-			let val = cpu.load_i32(offset)?;
+			let val = cpu.read_i32(offset)?;
 			cpu.push_i32(val)?;
 		}
 		op::POPCVARIND => {
@@ -302,7 +409,7 @@ pub fn next_opcode(
 		op::PUSHVAR => {
 			cpu.ip += 4;
 			let offset = cpu.load_i32(cpu.ip)?;
-			let val = cpu.load_i32(cpu.fp + offset)?;
+			let val = cpu.read_i32(cpu.fp + offset)?;
 			cpu.push_i32(val)?;
 		}
 		op::POPVAR => {
@@ -331,7 +438,7 @@ pub fn next_opcode(
 		op::PUSHCVAR => {
 			cpu.ip += 4;
 			let offset = cpu.load_i32(cpu.ip)?;
-			let val = cpu.load_u8(cpu.fp + offset)?;
+			let val = cpu.read_u8(cpu.fp + offset)?;
 			cpu.push_i32(val.into())?;
 		}
 		op::POPCVAR => {
@@ -372,7 +479,38 @@ pub fn next_opcode(
 			let lhs = cpu.pop_i32()?;
 			cpu.push_i32(lhs.rotate_right(rhs as u32))?;
 		}
-		57..=i32::MAX | i32::MIN..0 => return Err(MachineCheck::ILLEGAL_INST),
+		op::BREAK => {
+			// a debugger takes the interrupt before it is dispatched
+			cpu.flag.set_intvec(IntVec::BKPT, true);
+		}
+		op::FLUSH_TLB => {
+			if cpu.is_user() {
+				return Err(MachineCheck::PROT_INST);
+			}
+			cpu.tlb.flush();
+		}
+		op::ADD_OVF | op::SUB_OVF | op::MUL_OVF => {
+			let rhs = cpu.pop_i32()?;
+			let lhs = cpu.pop_i32()?;
+			let (result, is_overflow) = match op {
+				op::ADD_OVF => lhs.overflowing_add(rhs),
+				op::SUB_OVF => lhs.overflowing_sub(rhs),
+				_ => lhs.overflowing_mul(rhs),
+			};
+			cpu.push_i32(result)?;
+			if is_overflow {
+				cpu.machine_check(MachineCheck::OVF);
+			}
+		}
+		op::NEG_OVF => {
+			let val = cpu.pop_i32()?;
+			let (result, is_overflow) = val.overflowing_neg();
+			cpu.push_i32(result)?;
+			if is_overflow {
+				cpu.machine_check(MachineCheck::OVF);
+			}
+		}
+		op::ILLEGAL | 69..=i32::MAX | i32::MIN..0 => return Err(MachineCheck::ILLEGAL_INST),
 	}
 	cpu.ip += 4;
 	Ok(())
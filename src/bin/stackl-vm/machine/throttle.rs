@@ -0,0 +1,59 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Paces the machine to a processor speed against the host clock
+
+use std::thread;
+use std::time::{
+	Duration,
+	Instant,
+};
+
+/// Cycles run between two looks at the host clock
+const CHECK_CYCLES: u64 = 1000;
+/// Shorter waits are left for a later check, the host can not sleep that
+/// precisely
+const MIN_SLEEP: Duration = Duration::from_millis(1);
+/// Lagging further behind starts the pace over instead of rushing to catch
+/// up, so a stop in the debugger or a wait for input is not followed by a
+/// burst
+const MAX_LAG: Duration = Duration::from_millis(50);
+
+#[derive(Debug)]
+pub struct Throttle {
+	/// Cycles per second
+	hz: f64,
+	/// Host time and cycle count the pace is measured from
+	start: Instant,
+	start_cycles: u64,
+	/// Cycle count of the next look at the host clock
+	next_check: u64,
+}
+
+impl Throttle {
+	pub fn new(mhz: f64) -> Self {
+		Self {
+			hz: mhz * 1_000_000.0,
+			start: Instant::now(),
+			start_cycles: 0,
+			next_check: CHECK_CYCLES,
+		}
+	}
+	/// Sleeps while the machine at `cycles` runs ahead of the host clock
+	pub fn pace(&mut self, cycles: u64) {
+		if cycles < self.next_check {
+			return;
+		}
+		self.next_check = cycles + CHECK_CYCLES;
+		let target = Duration::from_secs_f64((cycles - self.start_cycles) as f64 / self.hz);
+		let elapsed = self.start.elapsed();
+		match target.checked_sub(elapsed) {
+			Some(ahead) if ahead >= MIN_SLEEP => thread::sleep(ahead),
+			Some(_) => {}
+			None if elapsed - target > MAX_LAG => {
+				self.start = Instant::now();
+				self.start_cycles = cycles;
+			}
+			None => {}
+		}
+	}
+}
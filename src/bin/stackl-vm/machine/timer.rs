@@ -1 +0,0 @@
-// Copyright (c) 2024-2026 Jonathan A. Thomason
@@ -26,7 +26,7 @@ impl MachineState {
 		Ok(())
 	}
 	pub fn trace_inst(&self, offset: i32) -> Result<String, MachineCheck> {
-		let op = self.load_i32(offset)?;
+		let op = self.peek_i32(offset)?;
 		let name = match op {
 			op::NOP => "NOP",
 			op::ADD => "ADD",
@@ -66,16 +66,16 @@ impl MachineState {
 			op::SHIFT_LEFT => "SHIFT_LEFT",
 			op::SHIFT_RIGHT => "SHIFT_RIGHT",
 			op::PUSHVARIND => {
-				let offset = self.load_i32(self.sp - 4)?;
-				let temp1 = self.load_i32(offset)?;
-				let temp2 = self.load_i32(self.sp - 4)?;
+				let offset = self.peek_i32(self.sp - 4)?;
+				let temp1 = self.peek_i32(offset)?;
+				let temp2 = self.peek_i32(self.sp - 4)?;
 				&format!("PUSHVARIND {temp2} {temp1}")
 			}
 			op::POPCVARIND => "POPCVARIND ",
 			op::POPVARIND => {
 				// DEBUG("POPVARIND %d %d", GET_INTVAL(SP, -2), GET_INTVAL(SP, -1));
-				let temp1 = self.load_i32(self.sp - 8)?;
-				let temp2 = self.load_i32(self.sp - 4)?;
+				let temp1 = self.peek_i32(self.sp - 8)?;
+				let temp2 = self.peek_i32(self.sp - 4)?;
 				&format!("POPVARIND {temp1} {temp2}")
 			}
 			op::COMP => "COMP",
@@ -95,21 +95,32 @@ impl MachineState {
 			op::SET_INT_DIS => "SET_INT_DIS",
 			op::ROTATE_LEFT => "ROTATE_LEFT",
 			op::ROTATE_RIGHT => "ROTATE_RIGHT",
+			op::BREAK => "BREAK",
+			op::FLUSH_TLB => "FLUSH_TLB",
+			op::ADD_OVF => "ADD_OVF",
+			op::SUB_OVF => "SUB_OVF",
+			op::MUL_OVF => "MUL_OVF",
+			op::NEG_OVF => "NEG_OVF",
+			op::ADC => "ADC",
+			op::SBB => "SBB",
+			op::ULT => "ULT",
+			op::UGT => "UGT",
+			op::USHIFT_RIGHT => "USHIFT_RIGHT",
 			_ => "ILLEGAL",
 		};
 		let mut inst = String::from(name);
 		match op {
 			op::POPARGS | op::PUSH | op::JMP | op::JMPUSER | op::ADJSP | op::CALL => {
-				let operand = self.load_i32(offset + 4)?;
+				let operand = self.peek_i32(offset + 4)?;
 				inst.push_str(&operand.to_string());
 			}
 			op::JZ => {
-				let cond = self.load_i32(self.sp - 4)?;
-				let operand = self.load_i32(offset + 4)?;
+				let cond = self.peek_i32(self.sp - 4)?;
+				let operand = self.peek_i32(offset + 4)?;
 				inst.push_str(&format!("{cond} {operand}"));
 			}
 			op::PUSHREG | op::POPREG => {
-				let operand = self.load_i32(offset + 4)?;
+				let operand = self.peek_i32(offset + 4)?;
 				let reg = match operand {
 					0 => "BP",
 					1 => "LP",
@@ -118,18 +129,20 @@ impl MachineState {
 					4 => "FP",
 					5 => "FLAG",
 					6 => "IVEC",
+					7 => "PTBR",
+					8 => "FAULT",
 					_ => &format!("{operand}"),
 				};
 				inst.push_str(reg);
 			}
 			op::PUSHVAR | op::POPVAR => {
-				let operand = self.load_i32(offset + 4)?;
+				let operand = self.peek_i32(offset + 4)?;
 				inst.push_str(&operand.to_string());
-				let value = self.load_i32(self.fp + operand)?;
+				let value = self.peek_i32(self.fp + operand)?;
 				inst.push(' ');
 				inst.push_str(&value.to_string());
 			}
-			57..=i32::MAX | i32::MIN..0 => {
+			op::ILLEGAL | 59..=i32::MAX | i32::MIN..0 => {
 				inst.push('(');
 				inst.push_str(&op.to_string());
 				inst.push(')');
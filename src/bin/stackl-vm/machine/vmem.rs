@@ -0,0 +1,139 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Paged virtual memory.
+//!
+//! With VMEM_MODE set every address the program uses goes through a two
+//! level page table rooted at PTBR. The top ten bits of an address pick an
+//! entry of the page directory, the next ten an entry of the page table it
+//! points to, and the low twelve the byte within the page. Both levels hold
+//! words of the form `frame | flags`, and the flags of both must allow the
+//! access. PTBR and the frames are physical addresses.
+
+use super::MachineState;
+use super::flag::{
+	MachineCheck,
+	Status,
+};
+
+pub const PAGE_SIZE: i32 = 4096;
+/// The entry maps a page, or a page table in the directory
+pub const PTE_VALID: i32 = 0x1;
+/// The page may be written
+pub const PTE_WRITABLE: i32 = 0x2;
+/// The page may be accessed in user mode
+pub const PTE_USER: i32 = 0x4;
+/// Physical address of the frame the entry points to
+pub const PTE_FRAME: i32 = !(PAGE_SIZE - 1);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Access {
+	Read,
+	Write,
+}
+
+/// Direct-mapped cache of page table entries, filled by the program's
+/// accesses and emptied by FLUSHTLB or a write to PTBR. A changed page table
+/// is not seen through entries the TLB still holds.
+#[derive(Debug, Default)]
+pub struct Tlb {
+	/// Virtual page number and entry, indexed by the page number
+	entries: Vec<Option<(u32, i32)>>,
+}
+
+impl Tlb {
+	/// A TLB of `size` entries, 0 leaves it out
+	pub fn new(size: usize) -> Self {
+		Self {
+			entries: vec![None; size],
+		}
+	}
+	fn lookup(&self, page: u32) -> Option<i32> {
+		if self.entries.is_empty() {
+			return None;
+		}
+		match self.entries[page as usize % self.entries.len()] {
+			Some((tag, entry)) if tag == page => Some(entry),
+			_ => None,
+		}
+	}
+	fn insert(&mut self, page: u32, entry: i32) {
+		if self.entries.is_empty() {
+			return;
+		}
+		let index = page as usize % self.entries.len();
+		self.entries[index] = Some((page, entry));
+	}
+	pub fn flush(&mut self) {
+		self.entries.fill(None);
+	}
+}
+
+impl MachineState {
+	/// Physical address of `offset` for the program: mapped through the page
+	/// table with VMEM_MODE, relative to BP in user mode otherwise.
+	///
+	/// A page fault records the address for the step to raise and stops the
+	/// instruction with ILLEGAL_ADDR.
+	pub fn translate(&mut self, offset: i32, access: Access) -> Result<usize, MachineCheck> {
+		if !self.flag.get_status(Status::VMEM_MODE) {
+			return self.relocate(offset);
+		}
+		let page = offset as u32 / PAGE_SIZE as u32;
+		let entry = match self.tlb.lookup(page) {
+			Some(entry) => Some(entry),
+			None => self.walk(offset),
+		};
+		let Some(entry) = entry.filter(|&entry| self.allows(entry, access)) else {
+			self.page_fault = Some(offset);
+			return Err(MachineCheck::ILLEGAL_ADDR);
+		};
+		self.tlb.insert(page, entry);
+		Ok(physical(entry, offset))
+	}
+	/// Same as `translate` for looking at memory from outside the program,
+	/// leaves the TLB and the fault alone
+	pub fn peek_translate(&self, offset: i32, access: Access) -> Result<usize, MachineCheck> {
+		if !self.flag.get_status(Status::VMEM_MODE) {
+			return self.relocate(offset);
+		}
+		let page = offset as u32 / PAGE_SIZE as u32;
+		self.tlb
+			.lookup(page)
+			.or_else(|| self.walk(offset))
+			.filter(|&entry| self.allows(entry, access))
+			.map(|entry| physical(entry, offset))
+			.ok_or(MachineCheck::ILLEGAL_ADDR)
+	}
+	fn relocate(&self, offset: i32) -> Result<usize, MachineCheck> {
+		let offset = if self.is_user() {
+			offset + self.bp
+		} else {
+			offset
+		};
+		offset.try_into().or(Err(MachineCheck::ILLEGAL_ADDR))
+	}
+	/// Walks the page table, returns the entry of the page with the flags of
+	/// both levels combined
+	fn walk(&self, offset: i32) -> Option<i32> {
+		let offset = offset as u32;
+		let dir_entry = self.load_table_entry(self.ptbr, offset >> 22)?;
+		let entry = self.load_table_entry(dir_entry & PTE_FRAME, (offset >> 12) & 0x3FF)?;
+		Some(entry & (dir_entry | PTE_FRAME))
+	}
+	/// Valid entry `index` of the table at `table`, tables live in RAM
+	fn load_table_entry(&self, table: i32, index: u32) -> Option<i32> {
+		let addr = usize::try_from(table).ok()? + index as usize * 4;
+		let bytes = self.mem.get(addr..addr + 4).ok()?;
+		let entry = i32::from_le_bytes(bytes.try_into().unwrap());
+		(entry & PTE_VALID != 0).then_some(entry)
+	}
+	fn allows(&self, entry: i32, access: Access) -> bool {
+		let is_writable = access == Access::Read || entry & PTE_WRITABLE != 0;
+		let is_reachable = !self.is_user() || entry & PTE_USER != 0;
+		is_writable && is_reachable
+	}
+}
+
+fn physical(entry: i32, offset: i32) -> usize {
+	((entry & PTE_FRAME) | (offset & (PAGE_SIZE - 1))) as u32 as usize
+}
@@ -1,32 +1,33 @@
 // Copyright (c) 2024-2026 Jonathan A. Thomason
 
 mod cli;
+mod debugger;
 mod device;
+mod gdb;
 mod io;
 mod machine;
 
+use std::fs;
 use std::process::ExitCode;
-use std::sync::RwLock;
-use std::sync::mpsc::{
-	Sender,
-	channel,
-};
-use std::{
-	fs,
-	sync,
-	thread,
+use std::time::{
+	Duration,
+	Instant,
 };
 
 use clap::Parser;
 use machine::MachineState;
 use machine::flag::{
 	IntVec,
+	MachineCheck,
 	Status,
 };
+use machine::throttle::Throttle;
+use machine::vmem::Tlb;
 use stackl::{
 	StacklFlags,
 	StacklFormatV1,
 	StacklFormatV2,
+	debug::DebugInfo,
 };
 
 fn main() -> ExitCode {
@@ -63,70 +64,206 @@ fn main() -> ExitCode {
 		data.flags.set(StacklFlags::FEATURE_GEN_IO, true);
 	}
 
-	// copy to local variable to handle threading later.
+	let image = match &args.disk {
+		Some(path) => {
+			// force the disk to be enabled regardless of binary
+			data.flags.set(StacklFlags::FEATURE_DISK, true);
+			let image = fs::OpenOptions::new()
+				.read(true)
+				.write(!args.read_only)
+				.open(path);
+			match image {
+				Ok(image) => Some(image),
+				Err(err) => {
+					eprintln!("Failed to open `{}`:{:?}", path.display(), err);
+					return ExitCode::FAILURE;
+				}
+			}
+		}
+		None => None,
+	};
 	let flags = data.flags;
+	let debug = data.debug.take();
 
 	let mut machine = MachineState::new(args.memory);
 	machine.store_program(data, true, -1).unwrap();
 	machine.set_trace(args.trace);
-	let machine_lock = &RwLock::new(machine);
-
-	let (request_send, request_recv) = channel::<device::inp::Request>();
-	thread::scope(|f| {
-		static RUNNING_STATE: sync::Once = sync::Once::new();
-		f.spawn(|| {
-			RUNNING_STATE.call_once(|| {
-				run_machine(machine_lock, request_send);
-			});
-		});
-		if flags.contains(StacklFlags::FEATURE_INP) {
-			f.spawn(|| {
-				device::inp::run_device(machine_lock, request_recv);
-			});
-		}
-		if flags.contains(StacklFlags::FEATURE_GEN_IO) {
-			f.spawn(|| {
-				device::gen_io::run_device(machine_lock, &RUNNING_STATE);
-			});
-		}
-		if flags.contains(StacklFlags::FEATURE_PIO_TERM) {
-			f.spawn(|| {
-				device::pio_term::run_device(machine_lock, &RUNNING_STATE);
-			});
-		}
-		if flags.contains(StacklFlags::FEATURE_DISK) {
-			f.spawn(|| {
-				device::disk::run_device(machine_lock, &RUNNING_STATE);
-			});
+	map_devices(&mut machine, flags, image, args.read_only);
+	machine.tlb = Tlb::new(args.tlb);
+	if args.mhz > 0.0 {
+		machine.throttle = Some(Throttle::new(args.mhz));
+	}
+	// restores the terminal when the program ends
+	let _raw_mode = flags
+		.contains(StacklFlags::FEATURE_PIO_TERM)
+		.then(io::RawMode::enable)
+		.flatten();
+
+	let started = Instant::now();
+	if let Some(target) = &args.gdb {
+		if let Err(err) = gdb::serve(&mut machine, debug.as_ref(), target) {
+			eprintln!("GDB stub failed: {err}");
 		}
-		if flags.contains(StacklFlags::FEATURE_DMA_TERM) {
-			f.spawn(|| {
-				device::dma_term::run_device(machine_lock, &RUNNING_STATE);
-			});
+	} else if args.debug {
+		let mut debugger = debugger::Debugger::new(&mut machine, debug.as_ref());
+		if let Err(err) = debugger.run(args.script.as_deref()) {
+			eprintln!("Debugger failed: {err}");
 		}
-	});
+	} else {
+		run_machine(&mut machine, debug.as_ref());
+	}
+	if args.stats {
+		print_stats(&machine, started.elapsed());
+	}
 	ExitCode::SUCCESS
 }
 
-pub fn run_machine(
-	machine_lock: &RwLock<MachineState>,
-	request_send: Sender<device::inp::Request>,
+/// Reports the work done by the machine and the speed it ran at
+fn print_stats(cpu: &MachineState, elapsed: Duration) {
+	let seconds = elapsed.as_secs_f64();
+	let mhz = match seconds > 0.0 {
+		true => cpu.cycles as f64 / seconds / 1_000_000.0,
+		false => 0.0,
+	};
+	eprintln!(
+		"{} instructions, {} cycles in {seconds:.3} s ({mhz:.2} MHz)",
+		cpu.clock, cpu.cycles
+	);
+}
+
+/// Maps the timer and the devices the program asks for onto the bus
+fn map_devices(
+	machine: &mut MachineState,
+	flags: StacklFlags,
+	image: Option<fs::File>,
+	is_read_only: bool,
 ) {
+	use device::*;
+	let bus = &mut machine.mem.bus;
+	bus.map(
+		timer::TIMER_BASE as usize,
+		Box::new(timer::Timer::default()),
+	);
+	if flags.contains(StacklFlags::FEATURE_GEN_IO) {
+		bus.map(
+			gen_io::GEN_IO_BASE as usize,
+			Box::new(gen_io::GenIo::default()),
+		);
+	}
+	if flags.contains(StacklFlags::FEATURE_DISK) {
+		bus.map(
+			disk::DISK_BASE as usize,
+			Box::new(disk::Disk::new(image, is_read_only)),
+		);
+	}
+	if flags.contains(StacklFlags::FEATURE_PIO_TERM) {
+		bus.map(
+			pio_term::PIO_T_BASE as usize,
+			Box::new(pio_term::PioTerm::new()),
+		);
+	}
+	if flags.contains(StacklFlags::FEATURE_DMA_TERM) {
+		bus.map(
+			dma_term::DMA_T_BASE as usize,
+			Box::new(dma_term::DmaTerm::default()),
+		);
+	}
+}
+
+/// Runs the program until it halts or a machine check stops it. Devices
+/// advance with the instructions, so the same input always gives the same
+/// run.
+pub fn run_machine(cpu: &mut MachineState, debug: Option<&DebugInfo>) {
 	loop {
-		let mut cpu = machine_lock.write().unwrap();
 		if cpu.flag.get_status(Status::HALTED) {
 			return;
 		}
-		if let Err(check) = machine::step::next_opcode(&mut cpu, &request_send) {
-			if cpu.ivec == 0 && cpu.load_abs_i32(0).unwrap() == -1 {
-				// Default machine check
-				eprintln!("Machine Check: {check} at {}", cpu.ip);
+		match step_machine(cpu) {
+			Ok(()) => {}
+			Err(Exception::Check(check)) => {
+				eprintln!(
+					"Machine Check: {check} at {}{}",
+					cpu.ip,
+					source_location(debug, cpu.ip)
+				);
 				return;
-			} else {
-				cpu.flag.check.set(check, true);
-				cpu.flag.intvec.set(IntVec::MACHINE_CHECK, true);
-				cpu.interrupt(false).unwrap();
 			}
+			Err(Exception::Break) => {
+				// without a debugger or a handler BREAK does nothing
+				if !has_vectors(cpu) {
+					cpu.flag.set_intvec(IntVec::BKPT, false);
+				}
+			}
+		}
+	}
+}
+
+/// What stopped `step_machine`
+pub enum Exception {
+	/// Machine check the program installed no handler for
+	Check(MachineCheck),
+	/// `BREAK` ran and left BKPT pending, a debugger clears it and stops
+	Break,
+}
+
+/// Runs one instruction and raises the machine checks it causes
+pub fn step_machine(cpu: &mut MachineState) -> Result<(), Exception> {
+	let was_break = cpu.flag.get_intvec(IntVec::BKPT);
+	let ip = cpu.ip;
+	let was_check = cpu.flag.get_intvec(IntVec::MACHINE_CHECK);
+	if let Err(check) = machine::step::next_opcode(cpu) {
+		if !has_vectors(cpu) {
+			// Default machine check
+			return Err(Exception::Check(check));
+		}
+		if let Some(address) = cpu.page_fault {
+			// the instruction runs again once the handler returns
+			cpu.fault = address;
+			cpu.flag.intvec.set(IntVec::PAGE_FAULT, true);
+		} else {
+			cpu.flag.check.set(check, true);
+			cpu.flag.intvec.set(IntVec::MACHINE_CHECK, true);
+		}
+		// a fault while saving the registers leaves nothing to return to
+		if cpu.interrupt(false).is_err() {
+			return Err(Exception::Check(check));
 		}
 	}
+	if !was_check && cpu.flag.get_intvec(IntVec::MACHINE_CHECK) && !has_vectors(cpu) {
+		// DIV or a checked instruction raised the check, report it at the
+		// instruction instead of failing on the missing vector
+		let check = cpu.flag.check;
+		cpu.flag.check = MachineCheck::empty();
+		cpu.flag.set_intvec(IntVec::MACHINE_CHECK, false);
+		cpu.ip = ip;
+		return Err(Exception::Check(check));
+	}
+	cpu.pace();
+	if !was_break && cpu.flag.get_intvec(IntVec::BKPT) {
+		return Err(Exception::Break);
+	}
+	Ok(())
+}
+
+/// A program without an interrupt vector table leaves IVEC at 0 and the
+/// machine check vector at -1
+fn has_vectors(cpu: &MachineState) -> bool {
+	cpu.ivec != 0 || cpu.load_abs_i32(0).unwrap() != -1
+}
+
+/// Describes the function and line of an address, if the debug information
+/// knows them
+fn source_location(debug: Option<&DebugInfo>, address: i32) -> String {
+	let Some(debug) = debug else {
+		return String::new();
+	};
+	let address = address as u32;
+	let mut result = String::new();
+	if let Some(index) = debug.function_of(address) {
+		result += &format!(" in {}", debug.functions[index].name);
+	}
+	if let Some((file, line)) = debug.line_of(address) {
+		result += &format!(" ({file}:{line})");
+	}
+	result
 }
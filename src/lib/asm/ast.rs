@@ -1,5 +1,7 @@
 // Copyright (c) 2024-2026 Jonathan A. Thomason
 
+use std::fmt;
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Stmt {
 	pub labels: Vec<String>,
@@ -26,6 +28,8 @@ pub enum Inst {
 	Directive(Directive, Vec<String>),
 	DataDecl8(Vec<Atom>),
 	DataDecl32(Vec<Atom>),
+	/// Source passed through unchanged, such as inline assembly
+	Verbatim(String),
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -45,6 +49,17 @@ pub enum Directive {
 	Interrupt,
 	Systrap,
 	Feature,
+	/// `[file index, "path"]`, a source file named by `line`
+	File,
+	/// `[line file, line]`, the source of the code that follows
+	Line,
+	/// `[func symbol]` and `[endfunc symbol]` enclose the code of a function
+	Func,
+	EndFunc,
+	/// `[local "name", offset, "type"]`, a variable of the enclosing function
+	Local,
+	/// `[object symbol, "name", "type"]`, the source name of a static object
+	Object,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -62,6 +77,10 @@ pub enum Reg {
 	FP = 4,
 	Flag = 5,
 	IVec = 6,
+	/// Page table base register
+	Ptbr = 7,
+	/// Address of the last page fault
+	Fault = 8,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -125,4 +144,254 @@ pub enum Opcode {
 	RotateLeft,
 	RotateRight,
 	Illegal,
+	Break,
+	FlushTlb,
+	AddOvf,
+	SubOvf,
+	MulOvf,
+	NegOvf,
+	Adc,
+	Sbb,
+	ULt,
+	UGt,
+	UShiftRight,
+}
+
+/// Formats as assembler source: one line per label, then the instruction
+impl fmt::Display for Stmt {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for label in self.labels.iter() {
+			writeln!(f, "{label}:")?;
+		}
+		match self.inst {
+			Inst::Directive(..) => write!(f, "{}", self.inst),
+			_ => write!(f, "\t{}", self.inst),
+		}
+	}
+}
+
+impl fmt::Display for Inst {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let (name, atoms) = match self {
+			Self::Mnemonic(opcode) => return write!(f, "{opcode}"),
+			Self::Directive(directive, args) => {
+				return write!(f, "[{directive} {}]", args.join(", "));
+			}
+			Self::DataDecl8(atoms) => ("DB", atoms),
+			Self::DataDecl32(atoms) => ("DD", atoms),
+			Self::Verbatim(text) => return write!(f, "{text}"),
+		};
+		write!(f, "{name}")?;
+		for (index, atom) in atoms.iter().enumerate() {
+			let separator = if index == 0 { " " } else { ", " };
+			write!(f, "{separator}{atom}")?;
+		}
+		Ok(())
+	}
+}
+
+impl fmt::Display for Atom {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::String(text) => {
+				let quote = ['"', '\'', '`']
+					.into_iter()
+					.find(|quote| !text.contains(*quote))
+					.unwrap_or('"');
+				write!(f, "{quote}")?;
+				for c in text.chars() {
+					match c {
+						'\t' => write!(f, "\\t")?,
+						'\n' => write!(f, "\\n")?,
+						'\r' => write!(f, "\\r")?,
+						'\x07' => write!(f, "\\a")?,
+						'\x08' => write!(f, "\\b")?,
+						'\x0b' => write!(f, "\\v")?,
+						'\x0c' => write!(f, "\\f")?,
+						'\x1b' => write!(f, "\\e")?,
+						_ => write!(f, "{c}")?,
+					}
+				}
+				write!(f, "{quote}")
+			}
+			Self::Int(value) => write!(f, "{value}"),
+			Self::Label(label) => write!(f, "{label}"),
+		}
+	}
+}
+
+/// Reads a directive argument written as a quoted [`Atom::String`]
+pub fn unquote(arg: &str) -> Option<String> {
+	let mut chars = arg.chars();
+	let quote = chars
+		.next()
+		.filter(|quote| ['"', '\'', '`'].contains(quote))?;
+	let inner = chars.as_str().strip_suffix(quote)?;
+	let mut result = String::new();
+	let mut chars = inner.chars();
+	while let Some(c) = chars.next() {
+		if c != '\\' {
+			result.push(c);
+			continue;
+		}
+		let escaped = match chars.next()? {
+			't' => '\t',
+			'n' => '\n',
+			'r' => '\r',
+			'a' => '\x07',
+			'b' => '\x08',
+			'v' => '\x0b',
+			'f' => '\x0c',
+			'e' => '\x1b',
+			// unknown escapes are kept as written
+			other => {
+				result.push(c);
+				other
+			}
+		};
+		result.push(escaped);
+	}
+	Some(result)
+}
+
+impl fmt::Display for Directive {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let name = match self {
+			Self::Segment => "section",
+			Self::Extern => "extern",
+			Self::Global => "global",
+			Self::Interrupt => "interrupt",
+			Self::Systrap => "systrap",
+			Self::Feature => "feature",
+			Self::File => "file",
+			Self::Line => "line",
+			Self::Func => "func",
+			Self::EndFunc => "endfunc",
+			Self::Local => "local",
+			Self::Object => "object",
+		};
+		write!(f, "{name}")
+	}
+}
+
+impl fmt::Display for Operand {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Int(value) => write!(f, "{value}"),
+			Self::Label(label) => write!(f, "{label}"),
+		}
+	}
+}
+
+impl TryFrom<i32> for Reg {
+	type Error = ();
+	fn try_from(value: i32) -> Result<Self, Self::Error> {
+		match value {
+			0 => Ok(Self::BP),
+			1 => Ok(Self::LP),
+			2 => Ok(Self::IP),
+			3 => Ok(Self::SP),
+			4 => Ok(Self::FP),
+			5 => Ok(Self::Flag),
+			6 => Ok(Self::IVec),
+			7 => Ok(Self::Ptbr),
+			8 => Ok(Self::Fault),
+			_ => Err(()),
+		}
+	}
+}
+
+impl fmt::Display for Reg {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let name = match self {
+			Self::BP => "BP",
+			Self::LP => "LP",
+			Self::IP => "IP",
+			Self::SP => "SP",
+			Self::FP => "FP",
+			Self::Flag => "FLAG",
+			Self::IVec => "IVEC",
+			Self::Ptbr => "PTBR",
+			Self::Fault => "FAULT",
+		};
+		write!(f, "{name}")
+	}
+}
+
+/// Formats with the mnemonic accepted by the assembler
+impl fmt::Display for Opcode {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let name = match self {
+			Self::Nop => "NOP",
+			Self::Add => "ADD",
+			Self::Sub => "SUB",
+			Self::Mul => "MUL",
+			Self::Div => "DIV",
+			Self::Mod => "MOD",
+			Self::Eq => "EQ",
+			Self::Ne => "NE",
+			Self::Gt => "GT",
+			Self::Lt => "LT",
+			Self::Ge => "GE",
+			Self::Le => "LE",
+			Self::And => "AND",
+			Self::Or => "OR",
+			Self::Not => "NOT",
+			Self::Swap => "SWAP",
+			Self::Dup => "DUP",
+			Self::Halt => "HALT",
+			Self::Pop => "POP",
+			Self::Ret => "RET",
+			Self::Retv => "RETV",
+			Self::Neg => "NEG",
+			Self::PushCVarInd => "PUSHCVARIND",
+			Self::Outs => "OUTS",
+			Self::Inp => "INP",
+			Self::PushFP => "PUSHFP",
+			Self::JmpUser(operand) => return write!(f, "JMPUSER {operand}"),
+			Self::Trap => "TRAP",
+			Self::Rti => "RTI",
+			Self::Calli => "CALLI",
+			Self::PushReg(reg) => return write!(f, "PUSHREG {reg}"),
+			Self::PopReg(reg) => return write!(f, "POPREG {reg}"),
+			Self::BAnd => "BAND",
+			Self::BOr => "BOR",
+			Self::BXOr => "BXOR",
+			Self::ShiftLeft => "SHL",
+			Self::ShiftRight => "SHR",
+			Self::PushVarInd => "PUSHVARIND",
+			Self::PopCVarInd => "POPCVARIND",
+			Self::PopVarInd => "POPVARIND",
+			Self::Comp => "COMP",
+			Self::Push(operand) => return write!(f, "PUSH {operand}"),
+			Self::Jmp(operand) => return write!(f, "JMP {operand}"),
+			Self::Jz(operand) => return write!(f, "JZ {operand}"),
+			Self::PushVar(operand) => return write!(f, "PUSHVAR {operand}"),
+			Self::PopVar(operand) => return write!(f, "POPVAR {operand}"),
+			Self::AdjSP(operand) => return write!(f, "ADJSP {operand}"),
+			Self::PopArgs(operand) => return write!(f, "POPARGS {operand}"),
+			Self::Call(operand) => return write!(f, "CALL {operand}"),
+			Self::PushCVar(operand) => return write!(f, "PUSHCVAR {operand}"),
+			Self::PopCVar(operand) => return write!(f, "POPCVAR {operand}"),
+			Self::SetTrace => "SET_TRACE",
+			Self::ClrTrace => "CLR_TRACE",
+			Self::ClrIntDis => "CLID",
+			Self::SetIntDis => "SEID",
+			Self::RotateLeft => "ROL",
+			Self::RotateRight => "ROR",
+			Self::Illegal => "ILLEGAL",
+			Self::Break => "BREAK",
+			Self::FlushTlb => "FLUSHTLB",
+			Self::AddOvf => "ADDV",
+			Self::SubOvf => "SUBV",
+			Self::MulOvf => "MULV",
+			Self::NegOvf => "NEGV",
+			Self::Adc => "ADC",
+			Self::Sbb => "SBB",
+			Self::ULt => "ULT",
+			Self::UGt => "UGT",
+			Self::UShiftRight => "USHR",
+		};
+		write!(f, "{name}")
+	}
 }
@@ -58,7 +58,16 @@ pub const SET_INT_DIS: i32 = 54;
 pub const ROTATE_LEFT: i32 = 55;
 pub const ROTATE_RIGHT: i32 = 56;
 pub const ILLEGAL: i32 = 57;
-
-// TODO: new opcodes
-// add with carry: a + b + in-carry => c, out-carry
-// pub const ADC: i32;
+pub const BREAK: i32 = 58;
+pub const FLUSH_TLB: i32 = 59;
+// Raise OVF when the signed result does not fit
+pub const ADD_OVF: i32 = 60;
+pub const SUB_OVF: i32 = 61;
+pub const MUL_OVF: i32 = 62;
+pub const NEG_OVF: i32 = 63;
+// Multi-word arithmetic: a + b + carry => c, carry out
+pub const ADC: i32 = 64;
+pub const SBB: i32 = 65;
+pub const ULT: i32 = 66;
+pub const UGT: i32 = 67;
+pub const USHIFT_RIGHT: i32 = 68;
@@ -0,0 +1,230 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Debug information of STACKL binaries.
+//!
+//! The section follows the text when [`crate::StacklFlags::DEBUG_INFO`] is set
+//! and ends with its own length as a word, so the text keeps its offset. Every
+//! field is a little endian word, strings are a byte length followed by the
+//! bytes padded to a whole word and lists are a count followed by the items:
+//!
+//! ```text
+//! files     [path]
+//! lines     [address, file, line]
+//! functions [name, start, end]
+//! locals    [function, name, offset, type]
+//! globals   [name, address, type]
+//! ```
+//!
+//! Addresses are offsets into the text, which is loaded at address 0.
+
+use crate::ErrorKind;
+
+/// Start of the code of a source line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineEntry {
+	pub address: u32,
+	/// Index into [`DebugInfo::files`]
+	pub file: u32,
+	pub line: u32,
+}
+
+/// Addresses from `start` up to, but not including, `end`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionRange {
+	pub name: String,
+	pub start: u32,
+	pub end: u32,
+}
+
+/// Automatic variable or parameter at a fixed offset from FP
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Local {
+	/// Index into [`DebugInfo::functions`]
+	pub function: u32,
+	pub name: String,
+	pub offset: i32,
+	pub type_name: String,
+}
+
+/// Function or static object, `type_name` is empty when unknown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Global {
+	pub name: String,
+	pub address: u32,
+	pub type_name: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DebugInfo {
+	pub files: Vec<String>,
+	/// Sorted by address
+	pub lines: Vec<LineEntry>,
+	pub functions: Vec<FunctionRange>,
+	pub locals: Vec<Local>,
+	pub globals: Vec<Global>,
+}
+
+impl DebugInfo {
+	pub fn is_empty(&self) -> bool {
+		self.lines.is_empty() && self.functions.is_empty() && self.globals.is_empty()
+	}
+	/// Returns the file and line of the code at an address
+	pub fn line_of(&self, address: u32) -> Option<(&str, u32)> {
+		let index = self
+			.lines
+			.partition_point(|entry| entry.address <= address)
+			.checked_sub(1)?;
+		let entry = &self.lines[index];
+		let file = self.files.get(entry.file as usize)?;
+		Some((file, entry.line))
+	}
+	/// Returns the index of the function containing an address
+	pub fn function_of(&self, address: u32) -> Option<usize> {
+		self.functions
+			.iter()
+			.position(|func| func.start <= address && address < func.end)
+	}
+	/// Locals of the function at an index
+	pub fn locals_of(&self, function: usize) -> impl Iterator<Item = &Local> + '_ {
+		self.locals
+			.iter()
+			.filter(move |local| local.function as usize == function)
+	}
+	pub fn find_global(&self, name: &str) -> Option<&Global> {
+		self.globals.iter().find(|global| global.name == name)
+	}
+	/// Appends the information of text placed at `base`, as a linker does
+	/// when it concatenates objects
+	pub fn append(&mut self, other: DebugInfo, base: u32) {
+		let file_base = self.files.len() as u32;
+		let function_base = self.functions.len() as u32;
+		self.files.extend(other.files);
+		self.lines
+			.extend(other.lines.into_iter().map(|entry| LineEntry {
+				address: entry.address + base,
+				file: entry.file + file_base,
+				line: entry.line,
+			}));
+		self.lines.sort_by_key(|entry| entry.address);
+		self.functions
+			.extend(other.functions.into_iter().map(|func| FunctionRange {
+				start: func.start + base,
+				end: func.end + base,
+				..func
+			}));
+		self.locals
+			.extend(other.locals.into_iter().map(|local| Local {
+				function: local.function + function_base,
+				..local
+			}));
+		self.globals
+			.extend(other.globals.into_iter().map(|global| Global {
+				address: global.address + base,
+				..global
+			}));
+	}
+	pub fn to_vec(&self) -> Vec<u8> {
+		let mut ret = vec![];
+		let word = |ret: &mut Vec<u8>, value: u32| ret.extend(value.to_le_bytes());
+		let text = |ret: &mut Vec<u8>, value: &str| {
+			word(ret, value.len() as u32);
+			ret.extend(value.as_bytes());
+			ret.resize(ret.len().next_multiple_of(4), 0);
+		};
+		word(&mut ret, self.files.len() as u32);
+		for file in self.files.iter() {
+			text(&mut ret, file);
+		}
+		word(&mut ret, self.lines.len() as u32);
+		for entry in self.lines.iter() {
+			word(&mut ret, entry.address);
+			word(&mut ret, entry.file);
+			word(&mut ret, entry.line);
+		}
+		word(&mut ret, self.functions.len() as u32);
+		for func in self.functions.iter() {
+			text(&mut ret, &func.name);
+			word(&mut ret, func.start);
+			word(&mut ret, func.end);
+		}
+		word(&mut ret, self.locals.len() as u32);
+		for local in self.locals.iter() {
+			word(&mut ret, local.function);
+			text(&mut ret, &local.name);
+			word(&mut ret, local.offset as u32);
+			text(&mut ret, &local.type_name);
+		}
+		word(&mut ret, self.globals.len() as u32);
+		for global in self.globals.iter() {
+			text(&mut ret, &global.name);
+			word(&mut ret, global.address);
+			text(&mut ret, &global.type_name);
+		}
+		ret
+	}
+}
+
+impl TryFrom<&[u8]> for DebugInfo {
+	type Error = ErrorKind;
+	fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+		let mut reader = Reader(value);
+		let mut info = DebugInfo::default();
+		for _ in 0..reader.word()? {
+			info.files.push(reader.text()?);
+		}
+		for _ in 0..reader.word()? {
+			info.lines.push(LineEntry {
+				address: reader.word()?,
+				file: reader.word()?,
+				line: reader.word()?,
+			});
+		}
+		for _ in 0..reader.word()? {
+			info.functions.push(FunctionRange {
+				name: reader.text()?,
+				start: reader.word()?,
+				end: reader.word()?,
+			});
+		}
+		for _ in 0..reader.word()? {
+			info.locals.push(Local {
+				function: reader.word()?,
+				name: reader.text()?,
+				offset: reader.word()? as i32,
+				type_name: reader.text()?,
+			});
+		}
+		for _ in 0..reader.word()? {
+			info.globals.push(Global {
+				name: reader.text()?,
+				address: reader.word()?,
+				type_name: reader.text()?,
+			});
+		}
+		Ok(info)
+	}
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+	fn word(&mut self) -> Result<u32, ErrorKind> {
+		let (head, tail) = self
+			.0
+			.split_first_chunk::<4>()
+			.ok_or(ErrorKind::InvalidDebugInfo)?;
+		self.0 = tail;
+		Ok(u32::from_le_bytes(*head))
+	}
+	fn text(&mut self) -> Result<String, ErrorKind> {
+		let len = self.word()? as usize;
+		let padded = len.next_multiple_of(4);
+		if self.0.len() < padded {
+			return Err(ErrorKind::InvalidDebugInfo);
+		}
+		let text =
+			String::from_utf8(self.0[..len].to_vec()).map_err(|_| ErrorKind::InvalidDebugInfo)?;
+		self.0 = &self.0[padded..];
+		Ok(text)
+	}
+}
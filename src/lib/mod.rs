@@ -8,6 +8,7 @@ use std::{
 use bitflags::bitflags;
 
 pub mod asm;
+pub mod debug;
 pub mod lnk;
 pub mod ssa;
 
@@ -19,6 +20,8 @@ bitflags! {
 		const FEATURE_DMA_TERM = 1 << 2;
 		const FEATURE_DISK     = 1 << 3;
 		const FEATURE_INP      = 1 << 4;
+		/// A [`debug::DebugInfo`] section follows the text
+		const DEBUG_INFO       = 1 << 16;
 		const _ = !0;
 	}
 }
@@ -144,15 +147,24 @@ pub struct StacklFormatV2 {
 	pub flags: StacklFlags,
 	pub stack_size: i32,
 	pub text: Vec<u8>,
+	pub debug: Option<debug::DebugInfo>,
 }
 
 impl StacklFormatV2 {
 	pub fn to_vec(self) -> Vec<u8> {
+		let mut flags = self.flags;
+		flags.set(StacklFlags::DEBUG_INFO, self.debug.is_some());
 		let mut ret = Vec::from(self.magic);
 		ret.extend(self.version.0.to_le_bytes());
-		ret.extend(self.flags.bits().to_le_bytes());
+		ret.extend(flags.bits().to_le_bytes());
 		ret.extend(self.stack_size.to_le_bytes());
 		ret.extend(self.text);
+		if let Some(debug) = self.debug {
+			let section = debug.to_vec();
+			let len = section.len() as u32;
+			ret.extend(section);
+			ret.extend(len.to_le_bytes());
+		}
 		ret
 	}
 }
@@ -164,6 +176,7 @@ pub enum ErrorKind {
 	InvalidMagic,
 	InvalidFeature,
 	InvalidStackSize,
+	InvalidDebugInfo,
 }
 
 impl TryFrom<&[u8]> for StacklFormatV2 {
@@ -188,12 +201,29 @@ impl TryFrom<&[u8]> for StacklFormatV2 {
 			});
 		}
 
+		let flags = StacklFlags::from_bits_retain(flags);
+		let mut text = &value[16..];
+		let mut debug = None;
+		if flags.contains(StacklFlags::DEBUG_INFO) {
+			// the section ends with its length
+			let (rest, len) = text
+				.split_last_chunk::<4>()
+				.ok_or(ErrorKind::InvalidDebugInfo)?;
+			let start = rest
+				.len()
+				.checked_sub(u32::from_le_bytes(*len) as usize)
+				.ok_or(ErrorKind::InvalidDebugInfo)?;
+			debug = Some(debug::DebugInfo::try_from(&rest[start..])?);
+			text = &rest[..start];
+		}
+
 		Ok(StacklFormatV2 {
 			magic,
 			version: Version(version),
-			flags: StacklFlags::from_bits_retain(flags),
+			flags,
 			stack_size,
-			text: Vec::from(&value[16..]),
+			text: Vec::from(text),
+			debug,
 		})
 	}
 }
@@ -207,6 +237,7 @@ impl TryFrom<StacklFormatV1> for StacklFormatV2 {
 			flags: value.flags()?,
 			stack_size: value.stack_size()?,
 			text: value.text,
+			debug: None,
 		})
 	}
 }
@@ -0,0 +1,296 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Binary form of SSA modules.
+//!
+//! The module is a stream of little endian 32 bit words, starting with
+//! [`MAGIC`], the format version and the id bound. The type list and every
+//! section follow as counted lists of instructions. An instruction is encoded
+//! as a header word holding the opcode in the low half and presence bits for
+//! the result type and id in the high half, the optional result type and id,
+//! the operand count and then each operand as a kind word followed by its
+//! payload. Text is stored as a byte length followed by the bytes padded to a
+//! whole word.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::data::{
+	DataKind,
+	Function,
+	Instruction,
+	Module,
+	Opcode,
+	Operand,
+	StorageClass,
+};
+
+/// First bytes of every binary module
+pub const MAGIC: [u8; 4] = *b"SLIR";
+pub const VERSION: u32 = 1;
+
+const HAS_TYPE: u32 = 1 << 16;
+const HAS_ID: u32 = 1 << 17;
+const HAS_END: u32 = 1;
+
+const KIND_ID_REF: u32 = 0;
+const KIND_STRING: u32 = 1;
+const KIND_BIT32: u32 = 2;
+const KIND_BIT64: u32 = 3;
+const KIND_BIT128: u32 = 4;
+const KIND_STORAGE_CLASS: u32 = 5;
+const KIND_FUNCTION_CONTROL: u32 = 6;
+const KIND_TEXT: u32 = 7;
+
+const TAG_DATA: u32 = 0;
+const TAG_FUNCTION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+	InvalidMagic,
+	UnsupportedVersion(u32),
+	UnexpectedEof,
+	InvalidOpcode(u32),
+	InvalidOperand(u32),
+	InvalidTag(u32),
+	InvalidText,
+}
+
+impl fmt::Display for DecodeError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::InvalidMagic => write!(f, "not a binary module"),
+			Self::UnsupportedVersion(version) => write!(f, "unsupported version {version}"),
+			Self::UnexpectedEof => write!(f, "unexpected end of module"),
+			Self::InvalidOpcode(opcode) => write!(f, "invalid opcode {opcode}"),
+			Self::InvalidOperand(kind) => write!(f, "invalid operand kind {kind}"),
+			Self::InvalidTag(tag) => write!(f, "invalid section entry {tag}"),
+			Self::InvalidText => write!(f, "text is not valid UTF-8"),
+		}
+	}
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Returns true if the bytes start like a binary module
+pub fn is_binary(bytes: &[u8]) -> bool {
+	bytes.starts_with(&MAGIC)
+}
+
+pub fn encode(module: &Module) -> Vec<u8> {
+	let mut writer = Writer(Vec::from(MAGIC));
+	writer.word(VERSION);
+	writer.word(module.bound);
+	writer.word(module.type_list.len() as u32);
+	for inst in module.type_list.iter() {
+		writer.instruction(inst);
+	}
+	let names = module.section_names();
+	writer.word(names.len() as u32);
+	for name in names {
+		writer.text(&name);
+		let section = &module.sections[&name];
+		writer.word(section.len() as u32);
+		for data in section.iter() {
+			match data {
+				DataKind::Data(inst) => {
+					writer.word(TAG_DATA);
+					writer.instruction(inst);
+				}
+				DataKind::Func(func) => {
+					writer.word(TAG_FUNCTION);
+					writer.instruction(&func.begin);
+					writer.word(func.params.len() as u32);
+					for inst in func.params.iter() {
+						writer.instruction(inst);
+					}
+					writer.word(func.body.len() as u32);
+					for inst in func.body.iter() {
+						writer.instruction(inst);
+					}
+					match &func.end {
+						Some(end) => {
+							writer.word(HAS_END);
+							writer.instruction(end);
+						}
+						None => writer.word(0),
+					}
+				}
+			}
+		}
+	}
+	writer.0
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Module, DecodeError> {
+	if !is_binary(bytes) {
+		return Err(DecodeError::InvalidMagic);
+	}
+	let mut reader = Reader {
+		bytes,
+		position: MAGIC.len(),
+	};
+	let version = reader.word()?;
+	if version != VERSION {
+		return Err(DecodeError::UnsupportedVersion(version));
+	}
+	let bound = reader.word()?;
+	let type_list = reader.instructions()?.into_boxed_slice();
+	let mut sections = HashMap::new();
+	for _ in 0..reader.word()? {
+		let name = reader.text()?;
+		let mut section = vec![];
+		for _ in 0..reader.word()? {
+			let data = match reader.word()? {
+				TAG_DATA => DataKind::Data(reader.instruction()?),
+				TAG_FUNCTION => {
+					let mut func = Function::new(reader.instruction()?);
+					func.params = reader.instructions()?;
+					func.body = reader.instructions()?;
+					if reader.word()? & HAS_END != 0 {
+						func.end = Some(reader.instruction()?);
+					}
+					DataKind::Func(func)
+				}
+				tag => return Err(DecodeError::InvalidTag(tag)),
+			};
+			section.push(data);
+		}
+		sections.insert(name, section);
+	}
+	Ok(Module {
+		type_list,
+		sections,
+		bound,
+	})
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+	fn word(&mut self, word: u32) {
+		self.0.extend(word.to_le_bytes());
+	}
+	fn text(&mut self, text: &str) {
+		self.word(text.len() as u32);
+		self.0.extend(text.as_bytes());
+		self.0.resize(self.0.len().next_multiple_of(4), 0);
+	}
+	fn instruction(&mut self, inst: &Instruction) {
+		let mut header = inst.opcode as u32;
+		if inst.result_type.is_some() {
+			header |= HAS_TYPE;
+		}
+		if inst.result_id.is_some() {
+			header |= HAS_ID;
+		}
+		self.word(header);
+		for id in inst.result_type.iter().chain(inst.result_id.iter()) {
+			self.word(*id);
+		}
+		self.word(inst.operands.len() as u32);
+		for operand in inst.operands.iter() {
+			match operand {
+				Operand::IdRef(id) => {
+					self.word(KIND_ID_REF);
+					self.word(*id);
+				}
+				Operand::LiteralString => self.word(KIND_STRING),
+				Operand::LiteralBit32(value) => {
+					self.word(KIND_BIT32);
+					self.word(*value);
+				}
+				Operand::LiteralBit64(value) => {
+					self.word(KIND_BIT64);
+					self.0.extend(value.to_le_bytes());
+				}
+				Operand::LiteralBit128(value) => {
+					self.word(KIND_BIT128);
+					self.0.extend(value.to_le_bytes());
+				}
+				Operand::StorageClass(storage_class) => {
+					self.word(KIND_STORAGE_CLASS);
+					self.word(storage_class.clone() as u32);
+				}
+				Operand::FunctionControl(control) => {
+					self.word(KIND_FUNCTION_CONTROL);
+					self.word(*control);
+				}
+				Operand::Text(text) => {
+					self.word(KIND_TEXT);
+					self.text(text);
+				}
+			}
+		}
+	}
+}
+
+struct Reader<'a> {
+	bytes: &'a [u8],
+	position: usize,
+}
+
+impl Reader<'_> {
+	fn bytes<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+		let bytes = self
+			.bytes
+			.get(self.position..self.position + N)
+			.ok_or(DecodeError::UnexpectedEof)?;
+		self.position += N;
+		Ok(bytes.try_into().unwrap())
+	}
+	fn word(&mut self) -> Result<u32, DecodeError> {
+		self.bytes().map(u32::from_le_bytes)
+	}
+	fn text(&mut self) -> Result<String, DecodeError> {
+		let len = self.word()? as usize;
+		let bytes = self
+			.bytes
+			.get(self.position..self.position + len)
+			.ok_or(DecodeError::UnexpectedEof)?;
+		self.position += len.next_multiple_of(4);
+		String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidText)
+	}
+	fn instructions(&mut self) -> Result<Vec<Instruction>, DecodeError> {
+		(0..self.word()?).map(|_| self.instruction()).collect()
+	}
+	fn instruction(&mut self) -> Result<Instruction, DecodeError> {
+		let header = self.word()?;
+		let opcode = *Opcode::ALL
+			.get((header & 0xffff) as usize)
+			.ok_or(DecodeError::InvalidOpcode(header & 0xffff))?;
+		let result_type = match header & HAS_TYPE {
+			0 => None,
+			_ => Some(self.word()?),
+		};
+		let result_id = match header & HAS_ID {
+			0 => None,
+			_ => Some(self.word()?),
+		};
+		let operands = (0..self.word()?)
+			.map(|_| self.operand())
+			.collect::<Result<Box<[Operand]>, DecodeError>>()?;
+		Ok(Instruction {
+			opcode,
+			result_type,
+			result_id,
+			operands,
+		})
+	}
+	fn operand(&mut self) -> Result<Operand, DecodeError> {
+		Ok(match self.word()? {
+			KIND_ID_REF => Operand::IdRef(self.word()?),
+			KIND_STRING => Operand::LiteralString,
+			KIND_BIT32 => Operand::LiteralBit32(self.word()?),
+			KIND_BIT64 => Operand::LiteralBit64(u64::from_le_bytes(self.bytes()?)),
+			KIND_BIT128 => Operand::LiteralBit128(u128::from_le_bytes(self.bytes()?)),
+			KIND_STORAGE_CLASS => match self.word()? {
+				0 => Operand::StorageClass(StorageClass::Automatic),
+				1 => Operand::StorageClass(StorageClass::Static),
+				_ => return Err(DecodeError::InvalidOperand(KIND_STORAGE_CLASS)),
+			},
+			KIND_FUNCTION_CONTROL => Operand::FunctionControl(self.word()?),
+			KIND_TEXT => Operand::Text(self.text()?),
+			kind => return Err(DecodeError::InvalidOperand(kind)),
+		})
+	}
+}
@@ -26,6 +26,8 @@ pub struct Builder {
 	in_func: bool,
 	// default to .code and .data until explicitly mentioned
 	curr_section: Option<String>,
+	/// Functions without a body, put at the start of .code by `build`
+	declarations: Vec<data::Function>,
 }
 
 impl Default for Builder {
@@ -45,6 +47,7 @@ impl Builder {
 			next_id: 0,
 			in_func: false,
 			curr_section: None,
+			declarations: vec![],
 		}
 	}
 	/// Returns the next unused id
@@ -236,6 +239,30 @@ impl Builder {
 		self.add_instruction_to_section(instruction, ".code")?;
 		Ok(id)
 	}
+	pub fn s_convert(&mut self, result_type: u32, operand: u32) -> Result<u32, Error> {
+		let id = self.id();
+		let instruction = data::Instruction {
+			opcode: data::Opcode::SConvert,
+			result_id: Some(id),
+			result_type: Some(result_type),
+			operands: [Operand::IdRef(operand)].into(),
+		};
+		return_if_detached!(self.in_func, instruction);
+		self.add_instruction_to_section(instruction, ".code")?;
+		Ok(id)
+	}
+	pub fn u_convert(&mut self, result_type: u32, operand: u32) -> Result<u32, Error> {
+		let id = self.id();
+		let instruction = data::Instruction {
+			opcode: data::Opcode::UConvert,
+			result_id: Some(id),
+			result_type: Some(result_type),
+			operands: [Operand::IdRef(operand)].into(),
+		};
+		return_if_detached!(self.in_func, instruction);
+		self.add_instruction_to_section(instruction, ".code")?;
+		Ok(id)
+	}
 	pub fn f_neg(&mut self, result_type: u32, operand: u32) -> Result<u32, Error> {
 		let id = self.id();
 		let instruction = data::Instruction {
@@ -339,10 +366,16 @@ impl Builder {
 		self.add_instruction_to_section(instruction, ".code")?;
 		Ok(())
 	}
-	pub fn build(self) -> data::Module {
+	pub fn build(mut self) -> data::Module {
+		let code = self.sections.get_mut(".code").unwrap();
+		code.splice(
+			0..0,
+			self.declarations.into_iter().map(data::DataKind::Func),
+		);
 		data::Module {
 			type_list: self.type_list.into_boxed_slice(),
 			sections: self.sections,
+			bound: self.next_id,
 		}
 	}
 	pub fn type_bool(&mut self) -> u32 {
@@ -480,6 +513,11 @@ impl Builder {
 			result_type: Some(result_type),
 			operands: operands.into_boxed_slice(),
 		};
+		if self.in_func {
+			// local variables belong to the body of the enclosing function
+			self.add_instruction_to_section(instruction, ".code")?;
+			return Ok(id);
+		}
 		match self
 			.curr_section
 			.as_ref()
@@ -509,6 +547,7 @@ impl Builder {
 	/// DontInline = 2
 	/// Pure = 4
 	/// Const = 8
+	/// Internal = 16
 	pub fn function_begin(
 		&mut self,
 		result_type: u32,
@@ -540,6 +579,24 @@ impl Builder {
 		self.in_func = true;
 		Ok(id)
 	}
+	/// Declares a function defined elsewhere, also while building another one
+	pub fn function_declaration(&mut self, result_type: u32, function_control: u32) -> u32 {
+		let id = self.id();
+		let mut func = data::Function::new(data::Instruction {
+			opcode: data::Opcode::Function,
+			result_id: Some(id),
+			result_type: Some(result_type),
+			operands: [Operand::FunctionControl(function_control)].into(),
+		});
+		func.end = Some(data::Instruction {
+			opcode: data::Opcode::FunctionEnd,
+			result_id: None,
+			result_type: None,
+			operands: [].into(),
+		});
+		self.declarations.push(func);
+		id
+	}
 	pub fn function_parameter(&mut self, result_type: u32) -> Result<u32, Error> {
 		let id = self.id();
 		let instruction = data::Instruction {
@@ -610,6 +667,27 @@ impl Builder {
 		});
 		id
 	}
+	pub fn constant_bit64(&mut self, result_type: u32, value: u64) -> u32 {
+		let id = self.id();
+		self.type_list.push(data::Instruction {
+			opcode: data::Opcode::Constant,
+			result_id: Some(id),
+			result_type: Some(result_type),
+			operands: [Operand::LiteralBit64(value)].into(),
+		});
+		id
+	}
+	/// Bytes of a string without its terminating null, for arrays of `i8`
+	pub fn constant_string(&mut self, result_type: u32, text: &str) -> u32 {
+		let id = self.id();
+		self.type_list.push(data::Instruction {
+			opcode: data::Opcode::Constant,
+			result_id: Some(id),
+			result_type: Some(result_type),
+			operands: [Operand::Text(text.to_owned())].into(),
+		});
+		id
+	}
 	pub fn assembler(&mut self, text: String) -> u32 {
 		let id = self.id();
 		self.type_list.push(data::Instruction {
@@ -620,6 +698,55 @@ impl Builder {
 		});
 		id
 	}
+	/// Gives a name to a function or variable
+	pub fn name(&mut self, target: u32, name: &str) {
+		self.type_list.push(data::Instruction {
+			opcode: data::Opcode::Name,
+			result_id: None,
+			result_type: None,
+			operands: [Operand::IdRef(target), Operand::Text(name.to_owned())].into(),
+		});
+	}
+	/// Declares a source file for `line`
+	pub fn source(&mut self, path: &str) -> u32 {
+		let id = self.id();
+		self.type_list.push(data::Instruction {
+			opcode: data::Opcode::Source,
+			result_id: Some(id),
+			result_type: None,
+			operands: [Operand::Text(path.to_owned())].into(),
+		});
+		id
+	}
+	/// Marks where the instructions that follow come from
+	pub fn line(&mut self, source: u32, line: u32, column: u32) -> Result<(), Error> {
+		let instruction = data::Instruction {
+			opcode: data::Opcode::Line,
+			result_id: None,
+			result_type: None,
+			operands: [
+				Operand::IdRef(source),
+				Operand::LiteralBit32(line),
+				Operand::LiteralBit32(column),
+			]
+			.into(),
+		};
+		self.add_instruction_to_section(instruction, ".code")
+	}
+	/// Gives the source name and type of a variable or parameter
+	pub fn debug_variable(&mut self, target: u32, name: &str, type_name: &str) {
+		self.type_list.push(data::Instruction {
+			opcode: data::Opcode::DebugVariable,
+			result_id: None,
+			result_type: None,
+			operands: [
+				Operand::IdRef(target),
+				Operand::Text(name.to_owned()),
+				Operand::Text(type_name.to_owned()),
+			]
+			.into(),
+		});
+	}
 	pub fn undef(&mut self, result_type: u32) -> Result<u32, Error> {
 		let id = self.id();
 		let instruction = data::Instruction {
@@ -1008,7 +1135,6 @@ impl Builder {
 		self.add_instruction_to_section(instruction, ".code")?;
 		Ok(())
 	}
-	#[must_use]
 	pub fn phi(
 		&mut self,
 		result_type: u32,
@@ -0,0 +1,308 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Control flow graph of a function body.
+//!
+//! A function body is a flat list of instructions where `Label` starts a block.
+//! The graph makes every edge explicit: fall-through becomes a `Branch`, code
+//! following a terminator gets its own (unreachable) block, and the entry block
+//! never has predecessors.
+
+use std::collections::HashMap;
+
+use super::data::{
+	Instruction,
+	Opcode,
+	Operand,
+};
+
+#[derive(Debug, Clone)]
+pub struct BasicBlock {
+	pub label: u32,
+	/// Instructions of the block, not including the label
+	pub body: Vec<Instruction>,
+}
+
+impl BasicBlock {
+	pub fn new(label: u32) -> Self {
+		Self {
+			label,
+			body: vec![],
+		}
+	}
+	pub fn terminator(&self) -> Option<&Instruction> {
+		self.body.last().filter(|inst| inst.opcode.is_terminator())
+	}
+	/// Labels of the blocks this block may jump to, without duplicates
+	pub fn successors(&self) -> Vec<u32> {
+		let mut result: Vec<u32> = vec![];
+		let Some(term) = self.terminator() else {
+			return result;
+		};
+		let targets: Vec<u32> = match term.opcode {
+			Opcode::Branch => term.id_refs().collect(),
+			Opcode::BranchConditional => term.id_refs().skip(1).collect(),
+			// selector, default, then (literal, label) pairs
+			Opcode::Switch => term
+				.operands
+				.iter()
+				.enumerate()
+				.filter(|(index, _)| *index == 1 || (*index > 1 && index % 2 == 1))
+				.filter_map(|(_, operand)| match operand {
+					Operand::IdRef(id) => Some(*id),
+					_ => None,
+				})
+				.collect(),
+			_ => vec![],
+		};
+		for target in targets {
+			if !result.contains(&target) {
+				result.push(target);
+			}
+		}
+		result
+	}
+}
+
+pub fn branch(target_label: u32) -> Instruction {
+	Instruction {
+		opcode: Opcode::Branch,
+		result_id: None,
+		result_type: None,
+		operands: [Operand::IdRef(target_label)].into(),
+	}
+}
+
+pub fn label(id: u32) -> Instruction {
+	Instruction {
+		opcode: Opcode::Label,
+		result_id: Some(id),
+		result_type: None,
+		operands: [].into(),
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct ControlFlowGraph {
+	/// `FunctionParameter` instructions, which precede every block
+	pub params: Vec<Instruction>,
+	/// Basic blocks in layout order. The first block is the entry.
+	pub blocks: Vec<BasicBlock>,
+}
+
+impl ControlFlowGraph {
+	/// Splits a function body into basic blocks. New labels are taken from `bound`.
+	pub fn new(body: Vec<Instruction>, bound: &mut u32) -> Self {
+		let mut next_id = || {
+			let id = *bound;
+			*bound += 1;
+			id
+		};
+		let mut params = vec![];
+		let mut blocks: Vec<BasicBlock> = vec![];
+		for inst in body {
+			match inst.opcode {
+				Opcode::FunctionParameter => params.push(inst),
+				Opcode::Label => {
+					let label_id = inst.result_id.unwrap();
+					if let Some(prev) = blocks.last_mut()
+						&& prev.terminator().is_none()
+					{
+						prev.body.push(branch(label_id));
+					}
+					blocks.push(BasicBlock::new(label_id));
+				}
+				_ => {
+					let needs_block = match blocks.last() {
+						None => true,
+						Some(prev) => prev.terminator().is_some(),
+					};
+					if needs_block {
+						blocks.push(BasicBlock::new(next_id()));
+					}
+					blocks.last_mut().unwrap().body.push(inst);
+				}
+			}
+		}
+		if blocks.is_empty() {
+			blocks.push(BasicBlock::new(next_id()));
+		}
+		let last = blocks.last_mut().unwrap();
+		if last.terminator().is_none() {
+			// falling off the end of a function returns
+			last.body.push(Instruction {
+				opcode: Opcode::Ret,
+				result_id: None,
+				result_type: None,
+				operands: [].into(),
+			});
+		}
+		let entry_label = blocks[0].label;
+		if blocks
+			.iter()
+			.any(|block| block.successors().contains(&entry_label))
+		{
+			let mut entry = BasicBlock::new(next_id());
+			entry.body.push(branch(entry_label));
+			blocks.insert(0, entry);
+		}
+		Self { params, blocks }
+	}
+
+	/// Flattens the graph back into a function body
+	pub fn into_body(self) -> Vec<Instruction> {
+		let mut body = self.params;
+		for block in self.blocks {
+			body.push(label(block.label));
+			body.extend(block.body);
+		}
+		body
+	}
+
+	/// Maps block labels to block indices
+	pub fn label_map(&self) -> HashMap<u32, usize> {
+		self.blocks
+			.iter()
+			.enumerate()
+			.map(|(index, block)| (block.label, index))
+			.collect()
+	}
+
+	/// Successor indices of every block
+	pub fn successors(&self) -> Vec<Vec<usize>> {
+		let label_map = self.label_map();
+		self.blocks
+			.iter()
+			.map(|block| {
+				block
+					.successors()
+					.iter()
+					.filter_map(|label| label_map.get(label).copied())
+					.collect()
+			})
+			.collect()
+	}
+
+	/// Predecessor indices of every block
+	pub fn predecessors(&self) -> Vec<Vec<usize>> {
+		let mut preds = vec![vec![]; self.blocks.len()];
+		for (index, succs) in self.successors().into_iter().enumerate() {
+			for succ in succs {
+				preds[succ].push(index);
+			}
+		}
+		preds
+	}
+
+	/// Reachable blocks in reverse postorder, starting with the entry
+	pub fn reverse_postorder(&self) -> Vec<usize> {
+		let succs = self.successors();
+		let mut visited = vec![false; self.blocks.len()];
+		let mut postorder = vec![];
+		// (block, next successor to visit)
+		let mut stack = vec![(0usize, 0usize)];
+		visited[0] = true;
+		while let Some((block, next)) = stack.last_mut() {
+			if let Some(&succ) = succs[*block].get(*next) {
+				*next += 1;
+				if !visited[succ] {
+					visited[succ] = true;
+					stack.push((succ, 0));
+				}
+			} else {
+				postorder.push(*block);
+				stack.pop();
+			}
+		}
+		postorder.reverse();
+		postorder
+	}
+
+	/// Immediate dominator of every block, `None` for the entry and unreachable blocks.
+	///
+	/// Uses the iterative algorithm by Cooper, Harvey and Kennedy.
+	pub fn immediate_dominators(&self) -> Vec<Option<usize>> {
+		let preds = self.predecessors();
+		let rpo = self.reverse_postorder();
+		let mut order = vec![usize::MAX; self.blocks.len()];
+		for (position, &block) in rpo.iter().enumerate() {
+			order[block] = position;
+		}
+		let mut idom: Vec<Option<usize>> = vec![None; self.blocks.len()];
+		idom[0] = Some(0);
+		let mut changed = true;
+		while changed {
+			changed = false;
+			for &block in rpo.iter().skip(1) {
+				let mut new_idom: Option<usize> = None;
+				for &pred in preds[block].iter() {
+					if idom[pred].is_none() {
+						continue;
+					}
+					new_idom = Some(match new_idom {
+						None => pred,
+						Some(other) => intersect(&idom, &order, pred, other),
+					});
+				}
+				if new_idom.is_some() && idom[block] != new_idom {
+					idom[block] = new_idom;
+					changed = true;
+				}
+			}
+		}
+		idom[0] = None;
+		idom
+	}
+
+	/// Dominance frontier of every block
+	pub fn dominance_frontiers(&self, idom: &[Option<usize>]) -> Vec<Vec<usize>> {
+		let preds = self.predecessors();
+		let mut frontiers: Vec<Vec<usize>> = vec![vec![]; self.blocks.len()];
+		for (block, block_preds) in preds.iter().enumerate() {
+			if block_preds.len() < 2 || (block != 0 && idom[block].is_none()) {
+				continue;
+			}
+			for &pred in block_preds.iter() {
+				if pred != 0 && idom[pred].is_none() {
+					// unreachable predecessor
+					continue;
+				}
+				let mut runner = pred;
+				while Some(runner) != idom[block] {
+					if !frontiers[runner].contains(&block) {
+						frontiers[runner].push(block);
+					}
+					match idom[runner] {
+						Some(next) => runner = next,
+						None => break,
+					}
+				}
+			}
+		}
+		frontiers
+	}
+
+	/// Children of every block in the dominator tree
+	pub fn dominator_tree(idom: &[Option<usize>]) -> Vec<Vec<usize>> {
+		let mut children = vec![vec![]; idom.len()];
+		for (block, parent) in idom.iter().enumerate() {
+			if let Some(parent) = parent {
+				children[*parent].push(block);
+			}
+		}
+		children
+	}
+}
+
+fn intersect(idom: &[Option<usize>], order: &[usize], lhs: usize, rhs: usize) -> usize {
+	let mut finger1 = lhs;
+	let mut finger2 = rhs;
+	while finger1 != finger2 {
+		while order[finger1] > order[finger2] {
+			finger1 = idom[finger1].unwrap();
+		}
+		while order[finger2] > order[finger1] {
+			finger2 = idom[finger2].unwrap();
+		}
+	}
+	finger1
+}
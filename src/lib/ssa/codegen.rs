@@ -0,0 +1,2159 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Lowering of SSA modules to STACKL assembly.
+//!
+//! With [`Schedule::Naive`] every value lives in its own frame slot: an
+//! instruction pushes its operands from their slots, computes, and pops the
+//! result into its slot. [`Schedule::Stack`] leaves values on the operand stack
+//! for their use where the order of the block allows it and only gives slots
+//! to the others. Phis are copied into their slots at the end of each
+//! predecessor, all values are pushed before any slot is written so phis may
+//! read each other.
+//!
+//! Functions follow the STACKL calling convention. Arguments are pushed in
+//! order before `CALL`, so the last one sits right below the return address at
+//! `FP - 12`. Automatic variables and value slots follow `FP`. Values narrower
+//! than 32 bits are kept sign or zero extended according to their type.
+//!
+//! 64-bit integers always live in a slot of two words, the low word first.
+//! They are computed a word at a time, the carry of `ADD` and `SUB` flows into
+//! `ADC` and `SBB` for the high word. They cannot be passed to or returned
+//! from functions.
+//!
+//! A call of an `Assembler` snippet pastes its text in place of the call.
+//! `%N` in the text stands for argument `N`: the frame offset of an automatic
+//! variable or parameter, the symbol of a static variable or function, or the
+//! value of a constant. The arguments the text does not name are pushed in
+//! order before it, and a snippet with a result leaves it on top of the stack.
+//! `%%` is a percent sign and `%=` a number unique to the call.
+//!
+//! Functions with [`function_control::INTERRUPT`] or
+//! [`function_control::SYSTRAP`] are registered in their vector and return with
+//! `RTI` once SP is back at the state saved on entry. In functions with
+//! [`function_control::TRAP_OVERFLOW`] signed word arithmetic becomes `ADDV`,
+//! `SUBV`, `MULV` and `NEGV`.
+//!
+//! Calls of the functions in [`super::intrinsic`] become the privileged
+//! instruction they name. Functions without a body are only declarations and
+//! emit nothing.
+//!
+//! Modules with `Source` instructions get the debug directives of the
+//! assembler: `Line` becomes `[line]`, every function is enclosed in
+//! `[func]` and `[endfunc]` and each `DebugVariable` whose variable still has
+//! a frame offset or symbol becomes `[local]` or `[object]`.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt;
+
+use super::cfg::ControlFlowGraph;
+use super::data::{
+	DataKind,
+	Function,
+	Instruction,
+	Module,
+	Opcode,
+	Operand,
+	StorageClass,
+	function_control,
+};
+use super::fold::{
+	literal,
+	mask,
+	sign_extend,
+};
+use super::intrinsic::{
+	Intrinsic,
+	Param,
+};
+use crate::asm::ast::{
+	Atom,
+	Directive,
+	Inst,
+	Opcode as Op,
+	Operand as AsmOperand,
+	Reg,
+	Stmt,
+};
+
+/// Size of a pointer and of every stack slot in bytes
+const WORD_SIZE: u32 = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+	/// The opcode has no lowering, for example floating point arithmetic
+	Unsupported(Opcode),
+	/// Values of the type do not fit in a stack slot
+	UnsupportedType(u32),
+	UndefinedId(u32),
+	/// `CopyMemorySized` with a size only known at run time
+	DynamicCopySize,
+	/// Assembler text with an invalid `%` sequence or argument number
+	InvalidAssembler(String),
+	/// A value without an address or constant value named in assembler text
+	AssemblerOperand(u32),
+	/// The register argument of an intrinsic is not a constant from 0 to 6
+	RegisterOperand(u32),
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Unsupported(opcode) => write!(f, "{opcode:?} cannot be lowered to STACKL"),
+			Self::UnsupportedType(ty) => write!(f, "values of type %{ty} do not fit in a word"),
+			Self::UndefinedId(id) => write!(f, "%{id} is not defined"),
+			Self::DynamicCopySize => write!(f, "memory copies must have a constant size"),
+			Self::InvalidAssembler(text) => write!(f, "invalid `%` sequence in `{text}`"),
+			Self::AssemblerOperand(id) => {
+				write!(f, "%{id} cannot be named in assembler text")
+			}
+			Self::RegisterOperand(id) => write!(f, "%{id} is not a constant register number"),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Debug, Clone, Copy)]
+enum Type {
+	Int {
+		width: u32,
+		is_signed: bool,
+	},
+	Float(u32),
+	Pointer(u32),
+	Void,
+	/// Arrays, structs and functions: only their size matters
+	Aggregate(u32),
+}
+
+/// Where values are kept between their definition and their uses
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+	/// Every value is stored to a frame slot
+	Naive,
+	/// Values stay on the operand stack when possible
+	#[default]
+	Stack,
+}
+
+/// Lowers the whole module into assembler statements
+pub fn emit(module: &Module) -> Result<Vec<Stmt>, Error> {
+	emit_with(module, Schedule::default())
+}
+
+/// Lowers the whole module with the given schedule
+pub fn emit_with(module: &Module, schedule: Schedule) -> Result<Vec<Stmt>, Error> {
+	let context = Context::new(module);
+	let mut program = vec![];
+	let mut files: Vec<(&u32, &(u32, &str))> = context.sources.iter().collect();
+	files.sort();
+	for (_, (index, path)) in files {
+		program.push(Stmt::new(Inst::Directive(
+			Directive::File,
+			vec![
+				index.to_string(),
+				Atom::String(path.to_string()).to_string(),
+			],
+		)));
+	}
+	let mut statics = vec![];
+	for name in module.section_names() {
+		let mut section = vec![];
+		for data in module.sections[&name].iter() {
+			match data {
+				DataKind::Func(func) if func.is_declaration() => {}
+				DataKind::Func(func) => {
+					section.extend(FunctionEmitter::new(&context, func, schedule).emit()?);
+					statics.extend(func.body.iter().filter(|inst| {
+						inst.result_id
+							.is_some_and(|id| context.statics.contains_key(&id))
+					}));
+				}
+				DataKind::Data(inst) if inst.opcode == Opcode::Variable => {
+					section.extend(context.static_variable(inst)?);
+				}
+				DataKind::Data(_) => {}
+			}
+		}
+		if name == ".data" {
+			for inst in std::mem::take(&mut statics) {
+				section.extend(context.static_variable(inst)?);
+			}
+		}
+		if !section.is_empty() {
+			program.push(Stmt::new(Inst::Directive(Directive::Segment, vec![name])));
+			program.extend(section);
+		}
+	}
+	if !statics.is_empty() {
+		program.push(Stmt::new(Inst::Directive(
+			Directive::Segment,
+			vec![".data".to_owned()],
+		)));
+		for inst in statics {
+			program.extend(context.static_variable(inst)?);
+		}
+	}
+	Ok(program)
+}
+
+/// Writes the statements as assembler source
+pub fn write_program(out: &mut impl fmt::Write, program: &[Stmt]) -> fmt::Result {
+	for stmt in program {
+		writeln!(out, "{stmt}")?;
+	}
+	Ok(())
+}
+
+struct Context<'a> {
+	module: &'a Module,
+	/// Next id for labels the lowering adds, shared so they stay unique
+	bound: Cell<u32>,
+	types: HashMap<u32, Type>,
+	/// Type and bits of every constant
+	constants: HashMap<u32, (u32, u128)>,
+	/// Bytes of every string constant
+	strings: HashMap<u32, &'a str>,
+	/// Symbol of every static variable
+	statics: HashMap<u32, String>,
+	/// Symbol of every function
+	functions: HashMap<u32, String>,
+	/// Text of every assembler snippet
+	assembler: HashMap<u32, &'a str>,
+	/// Declarations of intrinsics
+	intrinsics: HashMap<u32, Intrinsic>,
+	/// Index and path of every source file
+	sources: HashMap<u32, (u32, &'a str)>,
+	/// Source name and type of variables and parameters
+	debug_variables: HashMap<u32, (&'a str, &'a str)>,
+}
+
+impl<'a> Context<'a> {
+	fn new(module: &'a Module) -> Self {
+		let mut context = Self {
+			module,
+			bound: Cell::new(module.bound),
+			types: HashMap::new(),
+			constants: HashMap::new(),
+			strings: HashMap::new(),
+			statics: HashMap::new(),
+			functions: HashMap::new(),
+			assembler: HashMap::new(),
+			intrinsics: HashMap::new(),
+			sources: HashMap::new(),
+			debug_variables: HashMap::new(),
+		};
+		for inst in module.type_list.iter() {
+			if let [
+				Operand::IdRef(target),
+				Operand::Text(name),
+				Operand::Text(ty),
+			] = &inst.operands[..]
+				&& inst.opcode == Opcode::DebugVariable
+			{
+				context.debug_variables.insert(*target, (name, ty));
+			}
+			let Some(id) = inst.result_id else {
+				continue;
+			};
+			match inst.opcode {
+				Opcode::Source => {
+					if let Some(Operand::Text(path)) = inst.operands.first() {
+						let index = context.sources.len() as u32;
+						context.sources.insert(id, (index, path));
+					}
+				}
+				Opcode::Constant if matches!(inst.operands.first(), Some(Operand::Text(_))) => {
+					if let Some(Operand::Text(text)) = inst.operands.first() {
+						context.strings.insert(id, text);
+					}
+				}
+				Opcode::Constant => {
+					let bits = literal(inst.operands.first()).unwrap_or(0);
+					context
+						.constants
+						.insert(id, (inst.result_type.unwrap(), bits));
+				}
+				Opcode::Assembler => {
+					if let Some(Operand::Text(text)) = inst.operands.first() {
+						context.assembler.insert(id, text);
+					}
+				}
+				_ => {
+					let ty = context.resolve_type(inst);
+					context.types.insert(id, ty);
+				}
+			}
+		}
+		for func in module.functions() {
+			let intrinsic = module
+				.name_of(func.id())
+				.and_then(Intrinsic::from_name)
+				.filter(|_| func.is_declaration());
+			if let Some(intrinsic) = intrinsic {
+				context.intrinsics.insert(func.id(), intrinsic);
+				continue;
+			}
+			let name = context.symbol(func.id(), "F");
+			context.functions.insert(func.id(), name);
+		}
+		let variables = module
+			.sections
+			.values()
+			.flatten()
+			.flat_map(|data| match data {
+				DataKind::Data(inst) => vec![inst],
+				DataKind::Func(func) => func.body.iter().collect(),
+			});
+		for inst in variables {
+			let is_static = inst.opcode == Opcode::Variable
+				&& !matches!(
+					inst.operands.first(),
+					Some(Operand::StorageClass(StorageClass::Automatic))
+				);
+			if is_static {
+				let id = inst.result_id.unwrap();
+				let name = context.symbol(id, "V");
+				context.statics.insert(id, name);
+			}
+		}
+		context
+	}
+	/// Name given to the id, or a local symbol made from it
+	fn symbol(&self, id: u32, prefix: &str) -> String {
+		match self.module.name_of(id) {
+			Some(name) => name.to_owned(),
+			None => format!(".{prefix}{id}"),
+		}
+	}
+	fn resolve_type(&self, inst: &Instruction) -> Type {
+		let width = || inst.id_operand(0).unwrap_or(32);
+		match inst.opcode {
+			Opcode::TypeBool => Type::Int {
+				width: 1,
+				is_signed: false,
+			},
+			Opcode::TypeInt => Type::Int {
+				width: width(),
+				is_signed: literal(inst.operands.get(1)).is_some_and(|sign| sign != 0),
+			},
+			Opcode::TypeFloat => Type::Float(width()),
+			Opcode::TypePointer => Type::Pointer(inst.id_operand(0).unwrap()),
+			Opcode::TypeVoid => Type::Void,
+			Opcode::TypeArray | Opcode::TypeRuntimeArray => {
+				let length = literal(inst.operands.get(1)).unwrap_or(0) as u32;
+				let element = inst.id_operand(0).map_or(0, |ty| self.size_of(ty));
+				Type::Aggregate(element * length)
+			}
+			Opcode::TypeStruct => {
+				Type::Aggregate(inst.id_refs().map(|member| self.size_of(member)).sum())
+			}
+			_ => Type::Aggregate(0),
+		}
+	}
+	fn size_of(&self, ty: u32) -> u32 {
+		match self.types.get(&ty) {
+			Some(Type::Int { width, .. } | Type::Float(width)) => width.div_ceil(8),
+			Some(Type::Pointer(_)) => WORD_SIZE,
+			Some(Type::Aggregate(size)) => *size,
+			Some(Type::Void) | None => 0,
+		}
+	}
+	fn is_void(&self, ty: Option<u32>) -> bool {
+		matches!(
+			ty.and_then(|ty| self.types.get(&ty)),
+			None | Some(Type::Void)
+		)
+	}
+	/// Width and signedness of values of a type that fits in a word
+	fn scalar(&self, ty: u32) -> Result<(u32, bool), Error> {
+		match self.types.get(&ty) {
+			Some(Type::Int { width, is_signed }) if *width <= 32 => Ok((*width, *is_signed)),
+			Some(Type::Pointer(_)) => Ok((32, false)),
+			_ => Err(Error::UnsupportedType(ty)),
+		}
+	}
+	/// Returns true for integers kept in two words
+	fn is_double(&self, ty: u32) -> bool {
+		matches!(self.types.get(&ty), Some(Type::Int { width: 64, .. }))
+	}
+	fn static_variable(&self, inst: &Instruction) -> Result<Vec<Stmt>, Error> {
+		let id = inst.result_id.unwrap();
+		let ty = inst.result_type.unwrap();
+		let size = self.size_of(ty);
+		let string = inst.id_operand(1).and_then(|init| self.strings.get(&init));
+		let bits = match inst.id_operand(1) {
+			Some(_) if string.is_some() => 0,
+			Some(init) => self.constants.get(&init).ok_or(Error::UndefinedId(init))?.1,
+			None => 0,
+		};
+		let inst = if let Some(string) = string {
+			// the rest of the array is zero, which includes the terminator
+			let bytes = (0..size.next_multiple_of(WORD_SIZE).max(WORD_SIZE) as usize)
+				.map(|index| Atom::Int(string.as_bytes().get(index).copied().unwrap_or(0) as i32))
+				.collect();
+			Inst::DataDecl8(bytes)
+		} else if size == WORD_SIZE {
+			Inst::DataDecl32(vec![Atom::Int(bits as u32 as i32)])
+		} else {
+			let bytes = (0..size.next_multiple_of(WORD_SIZE).max(WORD_SIZE))
+				.map(|index| match index {
+					0..16 if index < size => Atom::Int((bits >> (index * 8)) as u8 as i32),
+					_ => Atom::Int(0),
+				})
+				.collect();
+			Inst::DataDecl8(bytes)
+		};
+		let name = self.statics[&id].clone();
+		let mut result = vec![];
+		if self.module.name_of(id).is_some() {
+			result.push(Stmt::new(Inst::Directive(
+				Directive::Global,
+				vec![name.clone()],
+			)));
+		}
+		if let Some(&(source_name, ty)) = self.debug_variables.get(&id) {
+			result.push(Stmt::new(Inst::Directive(
+				Directive::Object,
+				vec![name.clone(), quote(source_name), quote(ty)],
+			)));
+		}
+		result.push(Stmt::with_labels(vec![name], inst));
+		Ok(result)
+	}
+	/// Returns true if the module asks for debug directives
+	fn is_debug(&self) -> bool {
+		!self.sources.is_empty()
+	}
+}
+
+/// Piece of the text of an assembler snippet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AsmPiece<'t> {
+	Text(&'t str),
+	/// `%N`
+	Arg(usize),
+	/// `%=`
+	Unique,
+}
+
+fn asm_pieces(text: &str) -> Result<Vec<AsmPiece<'_>>, Error> {
+	let invalid = || Error::InvalidAssembler(text.to_owned());
+	let mut pieces = vec![];
+	let mut rest = text;
+	while let Some(start) = rest.find('%') {
+		pieces.push(AsmPiece::Text(&rest[..start]));
+		let after = &rest[start + 1..];
+		let digits = after
+			.find(|c: char| !c.is_ascii_digit())
+			.unwrap_or(after.len());
+		let (piece, len) = match after.chars().next() {
+			Some('%') => (AsmPiece::Text("%"), 1),
+			Some('=') => (AsmPiece::Unique, 1),
+			_ if digits != 0 => {
+				let index = after[..digits].parse().map_err(|_| invalid())?;
+				(AsmPiece::Arg(index), digits)
+			}
+			_ => return Err(invalid()),
+		};
+		pieces.push(piece);
+		rest = &after[len..];
+	}
+	pieces.push(AsmPiece::Text(rest));
+	Ok(pieces)
+}
+
+/// Writes text as a quoted directive argument
+fn quote(text: &str) -> String {
+	Atom::String(text.to_owned()).to_string()
+}
+
+/// Label of a basic block
+fn block_label(label: u32) -> String {
+	format!(".L{label}")
+}
+
+/// Label of the phi copies on the edge between two blocks
+fn edge_label(from: u32, to: u32) -> String {
+	format!(".L{from}_{to}")
+}
+
+/// How a value that is not simply stored to its slot is kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placement {
+	/// Left on the stack for its only use
+	Stack,
+	/// Stored to its slot with `DUP`, left on the stack for its first use
+	Tee,
+	/// Never used, dropped right away
+	Discard,
+}
+
+/// An operand pushed once by the lowering of an instruction
+#[derive(Debug, Clone, Copy)]
+struct Pushed {
+	id: u32,
+	/// Extension as signed or unsigned, see [`FunctionEmitter::push_as`]
+	as_signed: Option<bool>,
+}
+
+/// Which operands of an instruction are taken from the stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Arrangement {
+	/// The first operands are on the stack in order, the others are pushed
+	Prefix(usize),
+	/// Both operands are on the stack in reverse order
+	Swapped,
+	/// Only the second operand is on the stack, the first is pushed below it
+	Second,
+}
+
+/// Takes the operands of an instruction from the values on the stack.
+///
+/// `operands` are in push order, `None` for those that must be pushed anyway.
+/// The values used are removed from the stack along with those in the way,
+/// which are returned as they cannot stay on the stack.
+fn arrange(stack: &mut Vec<u32>, operands: &[Option<u32>]) -> (Arrangement, Vec<u32>) {
+	let is_operand = |id: &u32| operands.contains(&Some(*id));
+	let Some(lowest) = stack.iter().position(is_operand) else {
+		return (Arrangement::Prefix(0), vec![]);
+	};
+	let (found, mut in_the_way): (Vec<u32>, Vec<u32>) = stack.drain(lowest..).partition(is_operand);
+	let arrangement = match (operands, found.as_slice()) {
+		([Some(first), Some(second)], [lower, upper]) if lower == second && upper == first => {
+			Arrangement::Swapped
+		}
+		([_, Some(second)], [only]) if only == second => Arrangement::Second,
+		_ => {
+			// the longest run of leading operands at the top of the stack
+			let count = (0..=found.len())
+				.rev()
+				.find(|count| {
+					found[found.len() - count..]
+						.iter()
+						.zip(operands)
+						.all(|(id, operand)| Some(*id) == *operand)
+				})
+				.unwrap_or(0);
+			in_the_way.extend_from_slice(&found[..found.len() - count]);
+			Arrangement::Prefix(count)
+		}
+	};
+	(arrangement, in_the_way)
+}
+
+/// Returns true if the instruction may be moved later within its block
+fn is_movable(opcode: Opcode) -> bool {
+	matches!(
+		opcode,
+		Opcode::Undef
+			| Opcode::IAdd
+			| Opcode::ISub
+			| Opcode::IMul
+			| Opcode::SNeg
+			| Opcode::SConvert
+			| Opcode::UConvert
+			| Opcode::BitwiseNot
+			| Opcode::BitwiseAnd
+			| Opcode::BitwiseOr
+			| Opcode::BitwiseXor
+			| Opcode::ArithmeticShiftLeft
+			| Opcode::LogicalShiftLeft
+			| Opcode::ArithmeticShiftRight
+			| Opcode::LogicalShiftRight
+			| Opcode::UGreaterThan
+			| Opcode::SGreaterThan
+			| Opcode::IEqual
+			| Opcode::INotEqual
+			| Opcode::PtrEqual
+			| Opcode::PtrNotEqual
+			| Opcode::LogicalEqual
+			| Opcode::LogicalNotEqual
+			| Opcode::LogicalAnd
+			| Opcode::LogicalOr
+			| Opcode::LogicalNot
+	)
+}
+
+/// Returns true if the order of the two operands does not matter
+fn is_commutative(opcode: Opcode) -> bool {
+	matches!(
+		opcode,
+		Opcode::IAdd
+			| Opcode::IMul
+			| Opcode::BitwiseAnd
+			| Opcode::BitwiseOr
+			| Opcode::BitwiseXor
+			| Opcode::IEqual
+			| Opcode::INotEqual
+			| Opcode::PtrEqual
+			| Opcode::PtrNotEqual
+			| Opcode::LogicalEqual
+			| Opcode::LogicalNotEqual
+			| Opcode::LogicalAnd
+			| Opcode::LogicalOr
+	)
+}
+
+struct FunctionEmitter<'a> {
+	context: &'a Context<'a>,
+	func: &'a Function,
+	schedule: Schedule,
+	/// Frame offset of the slot of every value
+	slots: HashMap<u32, i32>,
+	/// Frame offset of every automatic variable
+	variables: HashMap<u32, i32>,
+	/// Type of every value of the function
+	value_types: HashMap<u32, u32>,
+	/// Values that do not simply live in their slot
+	placements: HashMap<u32, Placement>,
+	/// Values on the operand stack waiting for their use, the last on top
+	stack: Vec<u32>,
+	frame_size: i32,
+	out: Vec<Stmt>,
+	/// Labels of the next statement
+	labels: Vec<String>,
+	/// File and line of the last `[line]` directive
+	line: Option<(u32, u32)>,
+}
+
+impl<'a> FunctionEmitter<'a> {
+	fn new(context: &'a Context<'a>, func: &'a Function, schedule: Schedule) -> Self {
+		Self {
+			context,
+			func,
+			schedule,
+			slots: HashMap::new(),
+			variables: HashMap::new(),
+			value_types: HashMap::new(),
+			placements: HashMap::new(),
+			stack: vec![],
+			frame_size: 0,
+			out: vec![],
+			labels: vec![],
+			line: None,
+		}
+	}
+
+	fn emit(mut self) -> Result<Vec<Stmt>, Error> {
+		let mut bound = self.context.bound.get();
+		let body: Vec<Instruction> = self
+			.func
+			.params
+			.iter()
+			.chain(self.func.body.iter())
+			.cloned()
+			.collect();
+		let mut cfg = ControlFlowGraph::new(body, &mut bound);
+		self.context.bound.set(bound);
+		if let Some(ty) = cfg
+			.params
+			.iter()
+			.filter_map(|param| param.result_type)
+			.find(|&ty| self.context.is_double(ty))
+		{
+			return Err(Error::UnsupportedType(ty));
+		}
+		self.allocate_variables(&cfg);
+		if self.schedule == Schedule::Stack {
+			self.schedule_blocks(&mut cfg)?;
+		}
+		self.allocate_slots(&cfg);
+
+		let name = self.context.functions[&self.func.id()].clone();
+		if self.func.control() & function_control::INTERNAL == 0 {
+			self.out.push(Stmt::new(Inst::Directive(
+				Directive::Global,
+				vec![name.clone()],
+			)));
+		}
+		let directive = match self.func.control() {
+			control if control & function_control::INTERRUPT != 0 => Some(Directive::Interrupt),
+			control if control & function_control::SYSTRAP != 0 => Some(Directive::Systrap),
+			_ => None,
+		};
+		if let Some(directive) = directive {
+			self.out
+				.push(Stmt::new(Inst::Directive(directive, vec![name.clone()])));
+		}
+		if self.context.is_debug() {
+			self.out.push(Stmt::new(Inst::Directive(
+				Directive::Func,
+				vec![name.clone()],
+			)));
+			self.debug_locals(&cfg);
+		}
+		self.labels.push(name.clone());
+		if self.func.control() & function_control::SYSTRAP != 0 {
+			// a trap keeps the FP of the code that trapped
+			self.op(Op::PushReg(Reg::SP));
+			self.op(Op::PopReg(Reg::FP));
+		}
+		if self.frame_size != 0 {
+			self.op(Op::AdjSP(AsmOperand::Int(self.frame_size)));
+		}
+		for (index, block) in cfg.blocks.iter().enumerate() {
+			let next = cfg.blocks.get(index + 1).map(|block| block.label);
+			self.labels.push(block_label(block.label));
+			for inst in block.body.iter() {
+				self.instruction(&cfg, block.label, next, inst)?;
+			}
+			debug_assert!(self.stack.is_empty(), "values left on the stack");
+		}
+		if self.context.is_debug() {
+			self.out
+				.push(Stmt::new(Inst::Directive(Directive::EndFunc, vec![name])));
+		}
+		Ok(self.out)
+	}
+
+	/// Emits `[local]` for the parameters and variables that have a frame offset
+	fn debug_locals(&mut self, cfg: &ControlFlowGraph) {
+		let ids = cfg
+			.params
+			.iter()
+			.chain(cfg.blocks.iter().flat_map(|block| block.body.iter()))
+			.filter_map(|inst| inst.result_id);
+		let mut locals = vec![];
+		for id in ids {
+			let offset = self.variables.get(&id).or_else(|| {
+				cfg.params
+					.iter()
+					.any(|param| param.result_id == Some(id))
+					.then(|| self.slots.get(&id))
+					.flatten()
+			});
+			if let (Some(offset), Some((name, ty))) =
+				(offset, self.context.debug_variables.get(&id))
+			{
+				locals.push(vec![quote(name), offset.to_string(), quote(ty)]);
+			}
+		}
+		for args in locals {
+			self.out
+				.push(Stmt::new(Inst::Directive(Directive::Local, args)));
+		}
+	}
+
+	/// Assigns frame offsets to parameters and variables, records value types
+	fn allocate_variables(&mut self, cfg: &ControlFlowGraph) {
+		// `f(void)` has a single void parameter
+		let params: Vec<&Instruction> = cfg
+			.params
+			.iter()
+			.filter(|param| !self.context.is_void(param.result_type))
+			.collect();
+		let param_count = params.len() as i32;
+		for (index, param) in params.into_iter().enumerate() {
+			let id = param.result_id.unwrap();
+			let offset = -12 - 4 * (param_count - 1 - index as i32);
+			self.slots.insert(id, offset);
+			self.value_types
+				.extend(param.result_type.map(|ty| (id, ty)));
+		}
+		for inst in cfg.blocks.iter().flat_map(|block| block.body.iter()) {
+			let Some(id) = inst.result_id else {
+				continue;
+			};
+			if let Some(ty) = inst.result_type {
+				self.value_types.insert(id, ty);
+			}
+			if inst.opcode == Opcode::Variable && !self.context.statics.contains_key(&id) {
+				let size = self.context.size_of(inst.result_type.unwrap());
+				self.variables.insert(id, self.frame_size);
+				self.frame_size += size.next_multiple_of(WORD_SIZE) as i32;
+			}
+		}
+	}
+
+	/// Assigns frame slots to the values that need one
+	fn allocate_slots(&mut self, cfg: &ControlFlowGraph) {
+		for inst in cfg.blocks.iter().flat_map(|block| block.body.iter()) {
+			let Some(id) = inst.result_id else {
+				continue;
+			};
+			let needs_slot = match inst.opcode {
+				Opcode::Variable | Opcode::Label => false,
+				_ if self.context.is_void(inst.result_type) => false,
+				_ => matches!(self.placements.get(&id), None | Some(Placement::Tee)),
+			};
+			if needs_slot {
+				self.slots.insert(id, self.frame_size);
+				self.frame_size += match self.is_double(id) {
+					true => 2 * WORD_SIZE as i32,
+					false => WORD_SIZE as i32,
+				};
+			}
+		}
+	}
+
+	/// Returns true if the lowering of the instruction leaves a value to store
+	fn produces_value(&self, inst: &Instruction) -> bool {
+		!matches!(inst.opcode, Opcode::Variable | Opcode::Label | Opcode::Phi)
+			&& inst.result_id.is_some()
+			&& !self.context.is_void(inst.result_type)
+	}
+
+	/// Chooses the placement of values and the order of instructions.
+	///
+	/// A value used once in its block, by something other than a phi, stays on
+	/// the stack if every value pushed after it is used before, values used
+	/// more often are stored with `DUP` so the first use in the block still
+	/// finds them on the stack. Pure values whose operands are not on the stack
+	/// are moved right before their use first, so they do not get in the way.
+	fn schedule_blocks(&mut self, cfg: &mut ControlFlowGraph) -> Result<(), Error> {
+		let mut def_blocks: HashMap<u32, usize> = HashMap::new();
+		// block of every use, `None` for phis
+		let mut uses: HashMap<u32, Vec<Option<usize>>> = HashMap::new();
+		for (index, block) in cfg.blocks.iter().enumerate() {
+			for inst in block.body.iter() {
+				if self.produces_value(inst) {
+					def_blocks.insert(inst.result_id.unwrap(), index);
+				}
+				let block = (inst.opcode != Opcode::Phi).then_some(index);
+				for id in inst.id_refs() {
+					uses.entry(id).or_default().push(block);
+				}
+			}
+		}
+		let used_once_in = |id: u32, index: usize| {
+			uses.get(&id)
+				.is_some_and(|uses| uses.as_slice() == [Some(index)])
+		};
+
+		for index in 0..cfg.blocks.len() {
+			let body = std::mem::take(&mut cfg.blocks[index].body);
+			let mut movable: HashMap<u32, Instruction> = HashMap::new();
+			let mut scheduled = vec![];
+			for inst in body {
+				if let Some(id) = inst.result_id
+					&& is_movable(inst.opcode)
+					&& used_once_in(id, index)
+					&& inst.id_refs().all(|operand| {
+						// values of other blocks or used more than once have a slot
+						movable.contains_key(&operand)
+							|| def_blocks.get(&operand) != Some(&index)
+							|| !used_once_in(operand, index)
+					}) {
+					movable.insert(id, inst);
+					continue;
+				}
+				self.place(inst, &mut movable, &mut scheduled)?;
+			}
+			cfg.blocks[index].body = scheduled;
+
+			let mut pending: Vec<u32> = vec![];
+			for inst in cfg.blocks[index].body.iter() {
+				let operands = match self.stack_operands(inst)? {
+					Some(operands) => self.candidates(&operands)?,
+					None => vec![],
+				};
+				let (_, in_the_way) = arrange(&mut pending, &operands);
+				let unused = pending
+					.extract_if(.., |id| inst.id_refs().any(|operand| operand == *id))
+					.collect::<Vec<u32>>();
+				for id in in_the_way.into_iter().chain(unused) {
+					self.placements.remove(&id);
+				}
+				if !self.produces_value(inst) || self.is_double(inst.result_id.unwrap()) {
+					continue;
+				}
+				let id = inst.result_id.unwrap();
+				let block_uses = uses.get(&id).map_or(&[][..], |uses| uses.as_slice());
+				if block_uses.is_empty() {
+					self.placements.insert(id, Placement::Discard);
+				} else if used_once_in(id, index) {
+					self.placements.insert(id, Placement::Stack);
+					pending.push(id);
+				} else if block_uses.contains(&Some(index)) {
+					self.placements.insert(id, Placement::Tee);
+					pending.push(id);
+				}
+			}
+			for id in pending {
+				self.placements.remove(&id);
+			}
+		}
+		Ok(())
+	}
+
+	/// Appends the instruction after the movable values it uses, in push order
+	fn place(
+		&self,
+		inst: Instruction,
+		movable: &mut HashMap<u32, Instruction>,
+		scheduled: &mut Vec<Instruction>,
+	) -> Result<(), Error> {
+		let operands: Vec<u32> = match self.stack_operands(&inst)? {
+			Some(operands) => operands.iter().map(|operand| operand.id).collect(),
+			None => inst.id_refs().collect(),
+		};
+		for id in operands {
+			if let Some(def) = movable.remove(&id) {
+				self.place(def, movable, scheduled)?;
+			}
+		}
+		scheduled.push(inst);
+		Ok(())
+	}
+
+	/// Operands the lowering of the instruction pushes exactly once, in order,
+	/// `None` if it handles its operands in some other way
+	fn stack_operands(&self, inst: &Instruction) -> Result<Option<Vec<Pushed>>, Error> {
+		let operand = |index: usize| inst.id_operand(index).unwrap();
+		let plain = |id: u32| Pushed {
+			id,
+			as_signed: None,
+		};
+		let extended = |id: u32, is_signed: bool| Pushed {
+			id,
+			as_signed: Some(is_signed),
+		};
+		if self.has_double(inst) {
+			return Ok(None);
+		}
+		let operands = match inst.opcode {
+			Opcode::Load => match self.context.size_of(inst.result_type.unwrap()) {
+				1 | 4 => vec![plain(operand(0))],
+				_ => return Ok(None),
+			},
+			Opcode::Store => match self.store_size(operand(1)) {
+				1 | 4 => vec![plain(operand(1)), plain(operand(0))],
+				_ => return Ok(None),
+			},
+			Opcode::FunctionCall if self.context.assembler.contains_key(&operand(0)) => {
+				let named: Vec<usize> = asm_pieces(self.context.assembler[&operand(0)])?
+					.into_iter()
+					.filter_map(|piece| match piece {
+						AsmPiece::Arg(index) => Some(index),
+						_ => None,
+					})
+					.collect();
+				inst.id_refs()
+					.skip(1)
+					.enumerate()
+					.filter(|(index, _)| !named.contains(index))
+					.map(|(_, id)| plain(id))
+					.collect()
+			}
+			Opcode::FunctionCall if self.context.intrinsics.contains_key(&operand(0)) => {
+				let params = self.context.intrinsics[&operand(0)].params();
+				inst.id_refs()
+					.skip(1)
+					.zip(params)
+					.filter(|(id, param)| match param {
+						Param::Register => false,
+						// a constant picks the instruction
+						Param::Int => !self.context.constants.contains_key(id),
+						Param::String => true,
+					})
+					.map(|(id, _)| plain(id))
+					.collect()
+			}
+			Opcode::FunctionCall => {
+				let mut operands: Vec<Pushed> = inst.id_refs().skip(1).map(plain).collect();
+				if !self.context.functions.contains_key(&operand(0)) {
+					operands.push(plain(operand(0)));
+				}
+				operands
+			}
+			Opcode::BranchConditional | Opcode::RetValue | Opcode::LogicalNot => {
+				vec![plain(operand(0))]
+			}
+			Opcode::SNeg | Opcode::BitwiseNot => vec![plain(operand(0))],
+			Opcode::SConvert => vec![extended(operand(0), true)],
+			Opcode::UConvert => vec![extended(operand(0), false)],
+			Opcode::IAdd
+			| Opcode::ISub
+			| Opcode::IMul
+			| Opcode::BitwiseAnd
+			| Opcode::BitwiseOr
+			| Opcode::BitwiseXor
+			| Opcode::ArithmeticShiftLeft
+			| Opcode::LogicalShiftLeft
+			| Opcode::IEqual
+			| Opcode::INotEqual
+			| Opcode::PtrEqual
+			| Opcode::PtrNotEqual
+			| Opcode::LogicalEqual
+			| Opcode::LogicalNotEqual
+			| Opcode::LogicalAnd
+			| Opcode::LogicalOr => vec![plain(operand(0)), plain(operand(1))],
+			Opcode::SDiv | Opcode::SGreaterThan => {
+				vec![extended(operand(0), true), extended(operand(1), true)]
+			}
+			Opcode::ArithmeticShiftRight => {
+				vec![extended(operand(0), true), plain(operand(1))]
+			}
+			Opcode::LogicalShiftRight => {
+				vec![extended(operand(0), false), plain(operand(1))]
+			}
+			Opcode::UGreaterThan => {
+				vec![extended(operand(0), false), extended(operand(1), false)]
+			}
+			Opcode::UDiv | Opcode::URem => {
+				let (width, _) = self.scalar_of(operand(0))?;
+				if width >= 32 {
+					return Ok(None);
+				}
+				vec![extended(operand(0), false), extended(operand(1), false)]
+			}
+			_ => return Ok(None),
+		};
+		Ok(Some(operands))
+	}
+
+	/// Operands that may be taken from the stack: used once by the
+	/// instruction and pushed as they are
+	fn candidates(&self, operands: &[Pushed]) -> Result<Vec<Option<u32>>, Error> {
+		let mut result = vec![];
+		for (index, operand) in operands.iter().enumerate() {
+			let is_repeated = operands[..index].iter().any(|other| other.id == operand.id);
+			let converts = match operand.as_signed {
+				Some(is_signed) if !self.is_address(operand.id) => {
+					let (width, type_signed) = self.scalar_of(operand.id)?;
+					width < 32 && type_signed != is_signed
+				}
+				_ => false,
+			};
+			result.push((!is_repeated && !converts).then_some(operand.id));
+		}
+		Ok(result)
+	}
+
+	/// Pushes the operands of an instruction, taking values from the stack
+	fn push_operands(&mut self, opcode: Opcode, operands: &[Pushed]) -> Result<(), Error> {
+		let candidates = self.candidates(operands)?;
+		let (arrangement, in_the_way) = arrange(&mut self.stack, &candidates);
+		debug_assert!(in_the_way.is_empty(), "stack values out of order");
+		match arrangement {
+			Arrangement::Prefix(count) => {
+				for operand in operands[count..].iter() {
+					self.push_operand(operand)?;
+				}
+			}
+			Arrangement::Swapped if is_commutative(opcode) => {}
+			Arrangement::Swapped => self.op(Op::Swap),
+			Arrangement::Second => {
+				self.push_operand(&operands[0])?;
+				if !is_commutative(opcode) {
+					self.op(Op::Swap);
+				}
+			}
+		}
+		Ok(())
+	}
+
+	fn push_operand(&mut self, operand: &Pushed) -> Result<(), Error> {
+		match operand.as_signed {
+			Some(is_signed) => self.push_as(operand.id, is_signed),
+			None => self.push_value(operand.id),
+		}
+	}
+
+	/// Size of the store of an object, addresses are words
+	fn store_size(&self, object: u32) -> u32 {
+		match self.type_of(object) {
+			Some(ty) if !self.is_address(object) => self.context.size_of(ty),
+			_ => WORD_SIZE,
+		}
+	}
+
+	fn op(&mut self, opcode: Op) {
+		let labels = std::mem::take(&mut self.labels);
+		self.out
+			.push(Stmt::with_labels(labels, Inst::Mnemonic(opcode)));
+	}
+
+	fn push_int(&mut self, value: i32) {
+		self.op(Op::Push(AsmOperand::Int(value)));
+	}
+
+	fn type_of(&self, id: u32) -> Option<u32> {
+		self.value_types
+			.get(&id)
+			.copied()
+			.or_else(|| self.context.constants.get(&id).map(|(ty, _)| *ty))
+	}
+
+	/// Returns true if the id stands for an address: a variable or a function
+	fn is_address(&self, id: u32) -> bool {
+		self.variables.contains_key(&id)
+			|| self.context.statics.contains_key(&id)
+			|| self.context.functions.contains_key(&id)
+	}
+
+	/// Returns true if the value is an integer kept in two words
+	fn is_double(&self, id: u32) -> bool {
+		!self.is_address(id)
+			&& self
+				.type_of(id)
+				.is_some_and(|ty| self.context.is_double(ty))
+	}
+
+	/// Returns true if the instruction has a result or an operand of two words
+	fn has_double(&self, inst: &Instruction) -> bool {
+		inst.result_type
+			.is_some_and(|ty| self.context.is_double(ty))
+			|| inst.id_refs().any(|id| self.is_double(id))
+	}
+
+	/// Width and signedness of a value, addresses are unsigned words
+	fn scalar_of(&self, id: u32) -> Result<(u32, bool), Error> {
+		if self.is_address(id) {
+			return Ok((32, false));
+		}
+		let ty = self.type_of(id).ok_or(Error::UndefinedId(id))?;
+		self.context.scalar(ty)
+	}
+
+	fn push_value(&mut self, id: u32) -> Result<(), Error> {
+		if let Some(&(ty, bits)) = self.context.constants.get(&id) {
+			let (width, is_signed) = self.context.scalar(ty)?;
+			let value = match is_signed {
+				true => sign_extend(bits & mask(width), width) as i32,
+				false => (bits & mask(width)) as u32 as i32,
+			};
+			self.push_int(value);
+		} else if let Some(&offset) = self.slots.get(&id) {
+			self.op(Op::PushVar(AsmOperand::Int(offset)));
+		} else if let Some(&offset) = self.variables.get(&id) {
+			self.op(Op::PushFP);
+			if offset != 0 {
+				self.push_int(offset);
+				self.op(Op::Add);
+			}
+		} else if let Some(name) = self
+			.context
+			.statics
+			.get(&id)
+			.or_else(|| self.context.functions.get(&id))
+		{
+			self.op(Op::Push(AsmOperand::Label(name.clone())));
+		} else {
+			return Err(Error::UndefinedId(id));
+		}
+		Ok(())
+	}
+
+	/// Pushes a value extended as signed or unsigned regardless of its type
+	fn push_as(&mut self, id: u32, is_signed: bool) -> Result<(), Error> {
+		let (width, type_signed) = self.scalar_of(id)?;
+		if let Some(&(_, bits)) = self.context.constants.get(&id) {
+			let value = match is_signed {
+				true => sign_extend(bits & mask(width), width) as i32,
+				false => (bits & mask(width)) as u32 as i32,
+			};
+			self.push_int(value);
+			return Ok(());
+		}
+		self.push_value(id)?;
+		if width < 32 && type_signed != is_signed {
+			self.extend(width, is_signed);
+		}
+		Ok(())
+	}
+
+	/// Pushes the low or high word of a value of two words
+	fn push_half(&mut self, id: u32, is_high: bool) -> Result<(), Error> {
+		let shift = if is_high { 32 } else { 0 };
+		if let Some(&(_, bits)) = self.context.constants.get(&id) {
+			self.push_int((bits >> shift) as u32 as i32);
+		} else if let Some(&offset) = self.slots.get(&id) {
+			self.op(Op::PushVar(AsmOperand::Int(offset + shift / 8)));
+		} else {
+			return Err(Error::UndefinedId(id));
+		}
+		Ok(())
+	}
+
+	/// Pushes the low word of a shift amount
+	fn push_shift_amount(&mut self, amount: u32) -> Result<(), Error> {
+		match self.is_double(amount) {
+			true => self.push_half(amount, false),
+			false => self.push_value(amount),
+		}
+	}
+
+	/// Pushes the bits of a shift amount that move within a word
+	fn push_shift_count(&mut self, amount: u32) -> Result<(), Error> {
+		if let Some(&(_, bits)) = self.context.constants.get(&amount) {
+			self.push_int((bits & 31) as i32);
+			return Ok(());
+		}
+		self.push_shift_amount(amount)?;
+		self.push_int(31);
+		self.op(Op::BAnd);
+		Ok(())
+	}
+
+	/// Pushes `31` less the shift count, one bit of the other word is shifted
+	/// ahead so a count of 0 carries nothing over
+	fn push_shift_rest(&mut self, amount: u32) -> Result<(), Error> {
+		if let Some(&(_, bits)) = self.context.constants.get(&amount) {
+			self.push_int(31 - (bits & 31) as i32);
+			return Ok(());
+		}
+		self.push_int(31);
+		self.push_shift_count(amount)?;
+		self.op(Op::Sub);
+		Ok(())
+	}
+
+	/// Pushes all ones if the shift amount moves a whole word and zero if it
+	/// does not, the other way around when `is_inverted`
+	fn push_shift_mask(&mut self, amount: u32, is_inverted: bool) -> Result<(), Error> {
+		if let Some(&(_, bits)) = self.context.constants.get(&amount) {
+			let is_whole = bits & 32 != 0;
+			self.push_int(if is_whole != is_inverted { -1 } else { 0 });
+			return Ok(());
+		}
+		self.push_shift_amount(amount)?;
+		self.push_int(5);
+		self.op(Op::UShiftRight);
+		self.push_int(1);
+		self.op(Op::BAnd);
+		self.op(Op::Neg);
+		if is_inverted {
+			self.op(Op::Comp);
+		}
+		Ok(())
+	}
+
+	/// Pushes the unsigned quotient of two words. DIV is signed, so half the
+	/// dividend is divided and the quotient doubled, which leaves it at most
+	/// one short. A divisor with the top bit set goes in once or not at all.
+	fn push_udiv(&mut self, lhs: u32, rhs: u32) -> Result<(), Error> {
+		let is_large = self
+			.context
+			.constants
+			.get(&rhs)
+			.map(|&(_, bits)| bits & 0x8000_0000 != 0);
+		if is_large != Some(true) {
+			self.push_half_quotient(lhs, rhs)?;
+			self.push_int(1);
+			self.op(Op::Add);
+			// the remainder left by the doubled quotient
+			self.push_as(lhs, false)?;
+			self.push_half_quotient(lhs, rhs)?;
+			self.push_as(rhs, false)?;
+			self.op(Op::Mul);
+			self.op(Op::Sub);
+			self.push_as(rhs, false)?;
+			self.op(Op::ULt);
+			self.op(Op::Sub);
+			if is_large.is_none() {
+				self.push_sign_mask(rhs)?;
+				self.op(Op::Comp);
+				self.op(Op::BAnd);
+			}
+		}
+		if is_large != Some(false) {
+			self.push_int(1);
+			self.push_as(lhs, false)?;
+			self.push_as(rhs, false)?;
+			self.op(Op::ULt);
+			self.op(Op::Sub);
+			if is_large.is_none() {
+				self.push_sign_mask(rhs)?;
+				self.op(Op::BAnd);
+				self.op(Op::BOr);
+			}
+		}
+		Ok(())
+	}
+
+	/// Pushes all ones if the top bit of the word is set and zero if it is not
+	fn push_sign_mask(&mut self, id: u32) -> Result<(), Error> {
+		self.push_as(id, false)?;
+		self.push_int(31);
+		self.op(Op::ShiftRight);
+		Ok(())
+	}
+
+	/// Pushes twice the signed quotient of half the dividend
+	fn push_half_quotient(&mut self, lhs: u32, rhs: u32) -> Result<(), Error> {
+		self.push_as(lhs, false)?;
+		self.push_int(1);
+		self.op(Op::UShiftRight);
+		self.push_as(rhs, false)?;
+		self.op(Op::Div);
+		self.push_int(1);
+		self.op(Op::ShiftLeft);
+		Ok(())
+	}
+
+	/// Pops the high and then the low word of the result to its slot
+	fn pop_double(&mut self, inst: &Instruction) {
+		let offset = self.slots[&inst.result_id.unwrap()];
+		self.op(Op::PopVar(AsmOperand::Int(offset + WORD_SIZE as i32)));
+		self.op(Op::PopVar(AsmOperand::Int(offset)));
+	}
+
+	/// Sign or zero extends the top of the stack from `width` bits
+	fn extend(&mut self, width: u32, is_signed: bool) {
+		if width == 1 || width >= 32 {
+			return;
+		}
+		if is_signed {
+			self.push_int(32 - width as i32);
+			self.op(Op::ShiftLeft);
+			self.push_int(32 - width as i32);
+			self.op(Op::ShiftRight);
+		} else {
+			self.push_int(mask(width) as i32);
+			self.op(Op::BAnd);
+		}
+	}
+
+	/// Pushes the address plus a byte offset
+	fn push_address(&mut self, id: u32, offset: u32) -> Result<(), Error> {
+		self.push_value(id)?;
+		if offset != 0 {
+			self.push_int(offset as i32);
+			self.op(Op::Add);
+		}
+		Ok(())
+	}
+
+	fn pop_result(&mut self, inst: &Instruction) {
+		let Some(id) = inst.result_id else {
+			return;
+		};
+		match self.placements.get(&id) {
+			Some(Placement::Stack) => self.stack.push(id),
+			Some(Placement::Tee) => {
+				self.op(Op::Dup);
+				self.op(Op::PopVar(AsmOperand::Int(self.slots[&id])));
+				self.stack.push(id);
+			}
+			Some(Placement::Discard) => self.op(Op::Pop),
+			None => {
+				if let Some(&offset) = self.slots.get(&id) {
+					self.op(Op::PopVar(AsmOperand::Int(offset)));
+				}
+			}
+		}
+	}
+
+	/// Pastes the text of the snippet a call refers to
+	fn assembler(&mut self, cfg: &ControlFlowGraph, inst: &Instruction) -> Result<(), Error> {
+		let text = self.context.assembler[&inst.id_operand(0).unwrap()];
+		let args: Vec<u32> = inst.id_refs().skip(1).collect();
+		let mut source = String::new();
+		for piece in asm_pieces(text)? {
+			match piece {
+				AsmPiece::Text(inner) => source.push_str(inner),
+				AsmPiece::Unique => source.push_str(&inst.result_id.unwrap_or(0).to_string()),
+				AsmPiece::Arg(index) => {
+					let id = *args
+						.get(index)
+						.ok_or_else(|| Error::InvalidAssembler(text.to_owned()))?;
+					source.push_str(&self.asm_operand(cfg, id)?);
+				}
+			}
+		}
+		for line in source
+			.lines()
+			.map(str::trim)
+			.filter(|line| !line.is_empty())
+		{
+			let labels = std::mem::take(&mut self.labels);
+			self.out
+				.push(Stmt::with_labels(labels, Inst::Verbatim(line.to_owned())));
+		}
+		Ok(())
+	}
+
+	/// Returns true if the function is entered through a vector
+	fn is_handler(&self) -> bool {
+		self.func.control() & (function_control::INTERRUPT | function_control::SYSTRAP) != 0
+	}
+
+	/// Returns true if the arithmetic of the instruction uses the
+	/// instructions raising OVF: signed words of a `TRAP_OVERFLOW` function
+	fn traps_overflow(&self, inst: &Instruction) -> Result<bool, Error> {
+		if self.func.control() & function_control::TRAP_OVERFLOW == 0 {
+			return Ok(false);
+		}
+		let (width, is_signed) = self.context.scalar(inst.result_type.unwrap())?;
+		Ok(width == 32 && is_signed)
+	}
+
+	/// Lowers a call of an intrinsic once its operands are pushed
+	fn intrinsic(&mut self, block: u32, inst: &Instruction) -> Result<(), Error> {
+		let intrinsic = self.context.intrinsics[&inst.id_operand(0).unwrap()];
+		let arg = inst.id_operand(1);
+		let register = || {
+			let id = arg.unwrap_or_default();
+			self.context
+				.constants
+				.get(&id)
+				.and_then(|&(_, bits)| Reg::try_from(bits as u32 as i32).ok())
+				.ok_or(Error::RegisterOperand(id))
+		};
+		match intrinsic {
+			Intrinsic::Trap => self.op(Op::Trap),
+			Intrinsic::Rti => self.op(Op::Rti),
+			Intrinsic::Halt => self.op(Op::Halt),
+			Intrinsic::GetReg => self.op(Op::PushReg(register()?)),
+			Intrinsic::SetReg => self.op(Op::PopReg(register()?)),
+			Intrinsic::SetIntDis => self.op(Op::SetIntDis),
+			Intrinsic::ClrIntDis => self.op(Op::ClrIntDis),
+			Intrinsic::Outs => self.op(Op::Outs),
+			Intrinsic::Break => self.op(Op::Break),
+			Intrinsic::Trace => match arg.and_then(|id| self.context.constants.get(&id)) {
+				Some((_, 0)) => self.op(Op::ClrTrace),
+				Some(_) => self.op(Op::SetTrace),
+				None => {
+					// the length of the output is unique within the function
+					let off = format!(".T{block}_{}", self.out.len());
+					let end = format!("{off}_end");
+					self.op(Op::Jz(AsmOperand::Label(off.clone())));
+					self.op(Op::SetTrace);
+					self.op(Op::Jmp(AsmOperand::Label(end.clone())));
+					self.labels.push(off);
+					self.op(Op::ClrTrace);
+					self.labels.push(end);
+				}
+			},
+		}
+		if intrinsic.returns_int() {
+			match self.context.is_void(inst.result_type) {
+				true => self.op(Op::Pop),
+				false => self.pop_result(inst),
+			}
+		}
+		Ok(())
+	}
+
+	/// What `%N` stands for in assembler text
+	fn asm_operand(&self, cfg: &ControlFlowGraph, id: u32) -> Result<String, Error> {
+		if let Some(&(ty, bits)) = self.context.constants.get(&id) {
+			let (width, is_signed) = self.context.scalar(ty)?;
+			let value = match is_signed {
+				true => sign_extend(bits & mask(width), width) as i32,
+				false => (bits & mask(width)) as u32 as i32,
+			};
+			return Ok(value.to_string());
+		}
+		let is_param = cfg.params.iter().any(|param| param.result_id == Some(id));
+		let offset = match is_param {
+			true => self.slots.get(&id),
+			false => self.variables.get(&id),
+		};
+		if let Some(offset) = offset {
+			return Ok(offset.to_string());
+		}
+		self.context
+			.statics
+			.get(&id)
+			.or_else(|| self.context.functions.get(&id))
+			.cloned()
+			.ok_or(Error::AssemblerOperand(id))
+	}
+
+	/// Size of the object a pointer refers to
+	fn pointee_size(&self, id: u32) -> u32 {
+		let variable_type =
+			self.func
+				.body
+				.iter()
+				.chain(self.context.module.sections.values().flatten().filter_map(
+					|data| match data {
+						DataKind::Data(inst) => Some(inst),
+						DataKind::Func(_) => None,
+					},
+				))
+				.find(|inst| inst.opcode == Opcode::Variable && inst.result_id == Some(id))
+				.and_then(|inst| inst.result_type);
+		if let Some(ty) = variable_type {
+			return self.context.size_of(ty);
+		}
+		match self.type_of(id).and_then(|ty| self.context.types.get(&ty)) {
+			Some(Type::Pointer(pointee)) => self.context.size_of(*pointee),
+			_ => 0,
+		}
+	}
+
+	fn instruction(
+		&mut self,
+		cfg: &ControlFlowGraph,
+		block: u32,
+		next: Option<u32>,
+		inst: &Instruction,
+	) -> Result<(), Error> {
+		let operand = |index: usize| inst.id_operand(index).unwrap();
+		if self.has_double(inst) {
+			return self.double(inst);
+		}
+		let operands = self.stack_operands(inst)?;
+		match inst.opcode {
+			Opcode::Nop
+			| Opcode::LifetimeStart
+			| Opcode::LifetimeEnd
+			| Opcode::LoopMerge
+			| Opcode::Name
+			| Opcode::Decorate
+			| Opcode::MemberDecorate
+			| Opcode::DecorateId
+			| Opcode::DecorateString
+			| Opcode::MemberDecorateString
+			| Opcode::Phi => {}
+			Opcode::Line => {
+				let source = inst
+					.id_operand(0)
+					.and_then(|id| self.context.sources.get(&id));
+				let line = literal(inst.operands.get(1)).unwrap_or(0) as u32;
+				if let Some(&(file, _)) = source
+					&& self.line != Some((file, line))
+				{
+					self.line = Some((file, line));
+					self.out.push(Stmt::new(Inst::Directive(
+						Directive::Line,
+						vec![file.to_string(), line.to_string()],
+					)));
+				}
+			}
+			Opcode::Undef => {
+				self.push_int(0);
+				self.pop_result(inst);
+			}
+			Opcode::Variable => {
+				let id = inst.result_id.unwrap();
+				if let Some(init) = inst.id_operand(1)
+					&& self.variables.contains_key(&id)
+				{
+					let size = self.context.size_of(inst.result_type.unwrap());
+					self.store(id, init, size)?;
+				}
+			}
+			Opcode::Load => {
+				let ty = inst.result_type.unwrap();
+				let (width, is_signed) = self.context.scalar(ty)?;
+				match self.context.size_of(ty) {
+					1 => {
+						self.push_operands(inst.opcode, &operands.unwrap())?;
+						self.op(Op::PushCVarInd);
+					}
+					2 => {
+						self.push_value(operand(0))?;
+						self.op(Op::PushCVarInd);
+						self.push_address(operand(0), 1)?;
+						self.op(Op::PushCVarInd);
+						self.push_int(8);
+						self.op(Op::ShiftLeft);
+						self.op(Op::BOr);
+					}
+					4 => {
+						self.push_operands(inst.opcode, &operands.unwrap())?;
+						self.op(Op::PushVarInd);
+					}
+					_ => return Err(Error::UnsupportedType(ty)),
+				}
+				if is_signed {
+					self.extend(width, true);
+				}
+				self.pop_result(inst);
+			}
+			Opcode::Store => match operands {
+				Some(operands) => {
+					self.push_operands(inst.opcode, &operands)?;
+					let store = match self.store_size(operand(1)) {
+						1 => Op::PopCVarInd,
+						_ => Op::PopVarInd,
+					};
+					self.op(store);
+				}
+				None => self.store(operand(0), operand(1), self.store_size(operand(1)))?,
+			},
+			Opcode::CopyMemory | Opcode::CopyMemorySized => {
+				let size = match inst.opcode {
+					Opcode::CopyMemorySized => {
+						let (_, bits) = self
+							.context
+							.constants
+							.get(&operand(2))
+							.ok_or(Error::DynamicCopySize)?;
+						*bits as u32
+					}
+					_ => self.pointee_size(operand(0)),
+				};
+				let mut offset = 0;
+				while offset < size {
+					let (load, store, step) = match size - offset {
+						4.. => (Op::PushVarInd, Op::PopVarInd, 4),
+						_ => (Op::PushCVarInd, Op::PopCVarInd, 1),
+					};
+					self.push_address(operand(1), offset)?;
+					self.op(load);
+					self.push_address(operand(0), offset)?;
+					self.op(store);
+					offset += step;
+				}
+			}
+			Opcode::FunctionCall if self.context.assembler.contains_key(&operand(0)) => {
+				self.push_operands(inst.opcode, &operands.unwrap())?;
+				self.assembler(cfg, inst)?;
+				self.pop_result(inst);
+			}
+			Opcode::FunctionCall if self.context.intrinsics.contains_key(&operand(0)) => {
+				self.push_operands(inst.opcode, &operands.unwrap())?;
+				self.intrinsic(block, inst)?;
+			}
+			Opcode::FunctionCall => {
+				let callee = operand(0);
+				let args = inst.id_refs().skip(1).count();
+				self.push_operands(inst.opcode, &operands.unwrap())?;
+				match self.context.functions.get(&callee) {
+					Some(name) => self.op(Op::Call(AsmOperand::Label(name.clone()))),
+					None => self.op(Op::Calli),
+				}
+				let args_size = (args as u32 * WORD_SIZE) as i32;
+				let is_discarded = inst
+					.result_id
+					.is_some_and(|id| self.placements.get(&id) == Some(&Placement::Discard));
+				if self.context.is_void(inst.result_type) {
+					if args_size != 0 {
+						self.op(Op::AdjSP(AsmOperand::Int(-args_size)));
+					}
+				} else if is_discarded {
+					// the result sits above the arguments
+					self.op(Op::AdjSP(AsmOperand::Int(-args_size - WORD_SIZE as i32)));
+				} else {
+					if args_size != 0 {
+						self.op(Op::PopArgs(AsmOperand::Int(args_size)));
+					}
+					self.pop_result(inst);
+				}
+			}
+			Opcode::Branch => self.edge(cfg, block, operand(0), next)?,
+			Opcode::BranchConditional => {
+				let (on_true, on_false) = (operand(1), operand(2));
+				self.push_operands(inst.opcode, &operands.unwrap())?;
+				let false_target = self.edge_target(cfg, block, on_false);
+				self.op(Op::Jz(AsmOperand::Label(false_target.clone())));
+				if false_target == block_label(on_false) {
+					self.edge(cfg, block, on_true, next)?;
+				} else {
+					self.edge(cfg, block, on_true, None)?;
+					self.labels.push(false_target);
+					self.edge(cfg, block, on_false, next)?;
+				}
+			}
+			Opcode::Switch => {
+				let selector = operand(0);
+				let mut stubs = vec![];
+				for pair in inst.operands[2..].chunks(2) {
+					let [value, Operand::IdRef(target)] = pair else {
+						continue;
+					};
+					let value = literal(Some(value)).unwrap_or(0) as u32 as i32;
+					self.push_value(selector)?;
+					self.push_int(value);
+					self.op(Op::Ne);
+					let target_label = self.edge_target(cfg, block, *target);
+					self.op(Op::Jz(AsmOperand::Label(target_label.clone())));
+					if target_label != block_label(*target) && !stubs.contains(target) {
+						stubs.push(*target);
+					}
+				}
+				let default_next = if stubs.is_empty() { next } else { None };
+				self.edge(cfg, block, operand(1), default_next)?;
+				for (index, target) in stubs.iter().enumerate() {
+					let stub_next = if index + 1 == stubs.len() { next } else { None };
+					self.labels.push(edge_label(block, *target));
+					self.edge(cfg, block, *target, stub_next)?;
+				}
+			}
+			Opcode::Ret if self.is_handler() => {
+				// the saved state starts at FP
+				self.op(Op::PushReg(Reg::FP));
+				self.op(Op::PopReg(Reg::SP));
+				self.op(Op::Rti);
+			}
+			Opcode::Ret => self.op(Op::Ret),
+			Opcode::RetValue if self.is_handler() => return Err(Error::Unsupported(inst.opcode)),
+			Opcode::RetValue => {
+				self.push_operands(inst.opcode, &operands.unwrap())?;
+				self.op(Op::Retv);
+			}
+			Opcode::Unreachable => self.op(Op::Illegal),
+			Opcode::Halt => self.op(Op::Halt),
+			Opcode::IAdd
+			| Opcode::ISub
+			| Opcode::IMul
+			| Opcode::BitwiseAnd
+			| Opcode::BitwiseOr
+			| Opcode::BitwiseXor
+			| Opcode::ArithmeticShiftLeft
+			| Opcode::LogicalShiftLeft => {
+				let traps = self.traps_overflow(inst)?;
+				let opcode = match inst.opcode {
+					Opcode::IAdd if traps => Op::AddOvf,
+					Opcode::ISub if traps => Op::SubOvf,
+					Opcode::IMul if traps => Op::MulOvf,
+					Opcode::IAdd => Op::Add,
+					Opcode::ISub => Op::Sub,
+					Opcode::IMul => Op::Mul,
+					Opcode::BitwiseAnd => Op::BAnd,
+					Opcode::BitwiseOr => Op::BOr,
+					Opcode::BitwiseXor => Op::BXOr,
+					_ => Op::ShiftLeft,
+				};
+				self.push_operands(inst.opcode, &operands.unwrap())?;
+				self.op(opcode);
+				self.normalize(inst)?;
+				self.pop_result(inst);
+			}
+			Opcode::SNeg | Opcode::BitwiseNot => {
+				let traps = self.traps_overflow(inst)?;
+				self.push_operands(inst.opcode, &operands.unwrap())?;
+				self.op(match inst.opcode {
+					Opcode::SNeg if traps => Op::NegOvf,
+					Opcode::SNeg => Op::Neg,
+					_ => Op::Comp,
+				});
+				self.normalize(inst)?;
+				self.pop_result(inst);
+			}
+			Opcode::SConvert | Opcode::UConvert => {
+				self.push_operands(inst.opcode, &operands.unwrap())?;
+				self.normalize(inst)?;
+				self.pop_result(inst);
+			}
+			Opcode::SDiv => {
+				self.push_operands(inst.opcode, &operands.unwrap())?;
+				self.op(Op::Div);
+				self.normalize(inst)?;
+				self.pop_result(inst);
+			}
+			Opcode::SRem => {
+				// the remainder of MOD is never negative, C truncates
+				self.push_as(operand(0), true)?;
+				self.push_as(operand(0), true)?;
+				self.push_as(operand(1), true)?;
+				self.op(Op::Div);
+				self.push_as(operand(1), true)?;
+				self.op(Op::Mul);
+				self.op(Op::Sub);
+				self.normalize(inst)?;
+				self.pop_result(inst);
+			}
+			Opcode::UDiv | Opcode::URem => {
+				match operands {
+					// narrow values are never negative as words
+					Some(operands) => {
+						self.push_operands(inst.opcode, &operands)?;
+						self.op(match inst.opcode {
+							Opcode::UDiv => Op::Div,
+							_ => Op::Mod,
+						});
+					}
+					None if inst.opcode == Opcode::UDiv => {
+						self.push_udiv(operand(0), operand(1))?;
+					}
+					None => {
+						self.push_as(operand(0), false)?;
+						self.push_udiv(operand(0), operand(1))?;
+						self.push_as(operand(1), false)?;
+						self.op(Op::Mul);
+						self.op(Op::Sub);
+					}
+				}
+				self.normalize(inst)?;
+				self.pop_result(inst);
+			}
+			Opcode::ArithmeticShiftRight => {
+				self.push_operands(inst.opcode, &operands.unwrap())?;
+				self.op(Op::ShiftRight);
+				self.normalize(inst)?;
+				self.pop_result(inst);
+			}
+			Opcode::LogicalShiftRight => {
+				self.push_operands(inst.opcode, &operands.unwrap())?;
+				self.op(Op::UShiftRight);
+				self.normalize(inst)?;
+				self.pop_result(inst);
+			}
+			Opcode::UGreaterThan => {
+				self.push_operands(inst.opcode, &operands.unwrap())?;
+				self.op(Op::UGt);
+				self.pop_result(inst);
+			}
+			Opcode::SGreaterThan => {
+				self.push_operands(inst.opcode, &operands.unwrap())?;
+				self.op(Op::Gt);
+				self.pop_result(inst);
+			}
+			Opcode::IEqual
+			| Opcode::INotEqual
+			| Opcode::PtrEqual
+			| Opcode::PtrNotEqual
+			| Opcode::LogicalEqual
+			| Opcode::LogicalNotEqual
+			| Opcode::LogicalAnd
+			| Opcode::LogicalOr => {
+				self.push_operands(inst.opcode, &operands.unwrap())?;
+				self.op(match inst.opcode {
+					Opcode::IEqual | Opcode::PtrEqual | Opcode::LogicalEqual => Op::Eq,
+					Opcode::LogicalAnd => Op::And,
+					Opcode::LogicalOr => Op::Or,
+					_ => Op::Ne,
+				});
+				self.pop_result(inst);
+			}
+			Opcode::LogicalNot => {
+				self.push_operands(inst.opcode, &operands.unwrap())?;
+				self.op(Op::Not);
+				self.pop_result(inst);
+			}
+			opcode => return Err(Error::Unsupported(opcode)),
+		}
+		Ok(())
+	}
+
+	/// Lowers an instruction on values of two words
+	fn double(&mut self, inst: &Instruction) -> Result<(), Error> {
+		let operand = |index: usize| inst.id_operand(index).unwrap();
+		match inst.opcode {
+			Opcode::Phi => {}
+			Opcode::Undef => {
+				self.push_int(0);
+				self.push_int(0);
+				self.pop_double(inst);
+			}
+			Opcode::Variable => {
+				let id = inst.result_id.unwrap();
+				if let Some(init) = inst.id_operand(1)
+					&& self.variables.contains_key(&id)
+				{
+					self.store(id, init, 2 * WORD_SIZE)?;
+				}
+			}
+			Opcode::Load => {
+				self.push_value(operand(0))?;
+				self.op(Op::PushVarInd);
+				self.push_address(operand(0), WORD_SIZE)?;
+				self.op(Op::PushVarInd);
+				self.pop_double(inst);
+			}
+			Opcode::Store => self.store(operand(0), operand(1), 2 * WORD_SIZE)?,
+			Opcode::IAdd | Opcode::ISub => {
+				let (low, high) = match inst.opcode {
+					Opcode::IAdd => (Op::Add, Op::Adc),
+					_ => (Op::Sub, Op::Sbb),
+				};
+				self.push_half(operand(0), false)?;
+				self.push_half(operand(1), false)?;
+				self.op(low);
+				self.push_half(operand(0), true)?;
+				self.push_half(operand(1), true)?;
+				self.op(high);
+				self.pop_double(inst);
+			}
+			Opcode::SNeg => {
+				self.push_int(0);
+				self.push_half(operand(0), false)?;
+				self.op(Op::Sub);
+				self.push_int(0);
+				self.push_half(operand(0), true)?;
+				self.op(Op::Sbb);
+				self.pop_double(inst);
+			}
+			Opcode::BitwiseAnd | Opcode::BitwiseOr | Opcode::BitwiseXor => {
+				for is_high in [false, true] {
+					self.push_half(operand(0), is_high)?;
+					self.push_half(operand(1), is_high)?;
+					self.op(match inst.opcode {
+						Opcode::BitwiseAnd => Op::BAnd,
+						Opcode::BitwiseOr => Op::BOr,
+						_ => Op::BXOr,
+					});
+				}
+				self.pop_double(inst);
+			}
+			Opcode::BitwiseNot => {
+				for is_high in [false, true] {
+					self.push_half(operand(0), is_high)?;
+					self.op(Op::Comp);
+				}
+				self.pop_double(inst);
+			}
+			Opcode::SConvert | Opcode::UConvert => {
+				let is_signed = inst.opcode == Opcode::SConvert;
+				let is_wide = inst
+					.result_type
+					.is_some_and(|ty| self.context.is_double(ty));
+				match (self.is_double(operand(0)), is_wide) {
+					(true, true) => {
+						self.push_half(operand(0), false)?;
+						self.push_half(operand(0), true)?;
+						self.pop_double(inst);
+					}
+					// truncating keeps the low word
+					(true, false) => {
+						self.push_half(operand(0), false)?;
+						self.normalize(inst)?;
+						self.pop_result(inst);
+					}
+					_ => {
+						self.push_as(operand(0), is_signed)?;
+						match is_signed {
+							true => {
+								self.push_as(operand(0), true)?;
+								self.push_int(31);
+								self.op(Op::ShiftRight);
+							}
+							false => self.push_int(0),
+						}
+						self.pop_double(inst);
+					}
+				}
+			}
+			Opcode::ArithmeticShiftLeft
+			| Opcode::LogicalShiftLeft
+			| Opcode::LogicalShiftRight
+			| Opcode::ArithmeticShiftRight
+				if !self.is_double(operand(0)) =>
+			{
+				// a word shifted by a wide amount
+				let (is_signed, shift) = match inst.opcode {
+					Opcode::ArithmeticShiftRight => (true, Op::ShiftRight),
+					Opcode::LogicalShiftRight => (false, Op::UShiftRight),
+					_ => (false, Op::ShiftLeft),
+				};
+				self.push_as(operand(0), is_signed)?;
+				self.push_half(operand(1), false)?;
+				self.op(shift);
+				self.normalize(inst)?;
+				self.pop_result(inst);
+			}
+			Opcode::ArithmeticShiftLeft | Opcode::LogicalShiftLeft => {
+				let (value, amount) = (operand(0), operand(1));
+				// the low word is shifted, or cleared once whole words are
+				self.push_half(value, false)?;
+				self.push_shift_count(amount)?;
+				self.op(Op::ShiftLeft);
+				self.push_shift_mask(amount, true)?;
+				self.op(Op::BAnd);
+				// the high word takes the bits shifted out of the low word, or
+				// the shifted low word once whole words are
+				self.push_half(value, true)?;
+				self.push_shift_count(amount)?;
+				self.op(Op::ShiftLeft);
+				self.push_half(value, false)?;
+				self.push_int(1);
+				self.op(Op::UShiftRight);
+				self.push_shift_rest(amount)?;
+				self.op(Op::UShiftRight);
+				self.op(Op::BOr);
+				self.push_shift_mask(amount, true)?;
+				self.op(Op::BAnd);
+				self.push_half(value, false)?;
+				self.push_shift_count(amount)?;
+				self.op(Op::ShiftLeft);
+				self.push_shift_mask(amount, false)?;
+				self.op(Op::BAnd);
+				self.op(Op::BOr);
+				self.pop_double(inst);
+			}
+			Opcode::LogicalShiftRight | Opcode::ArithmeticShiftRight => {
+				let (value, amount) = (operand(0), operand(1));
+				let is_signed = inst.opcode == Opcode::ArithmeticShiftRight;
+				let shift = match is_signed {
+					true => Op::ShiftRight,
+					false => Op::UShiftRight,
+				};
+				// the low word takes the bits shifted out of the high word, or
+				// the shifted high word once whole words are
+				self.push_half(value, false)?;
+				self.push_shift_count(amount)?;
+				self.op(Op::UShiftRight);
+				self.push_half(value, true)?;
+				self.push_int(1);
+				self.op(Op::ShiftLeft);
+				self.push_shift_rest(amount)?;
+				self.op(Op::ShiftLeft);
+				self.op(Op::BOr);
+				self.push_shift_mask(amount, true)?;
+				self.op(Op::BAnd);
+				self.push_half(value, true)?;
+				self.push_shift_count(amount)?;
+				self.op(shift.clone());
+				self.push_shift_mask(amount, false)?;
+				self.op(Op::BAnd);
+				self.op(Op::BOr);
+				// the high word is shifted, or filled with the sign once whole
+				// words are
+				self.push_half(value, true)?;
+				self.push_shift_count(amount)?;
+				self.op(shift);
+				self.push_shift_mask(amount, true)?;
+				self.op(Op::BAnd);
+				if is_signed {
+					self.push_half(value, true)?;
+					self.push_int(31);
+					self.op(Op::ShiftRight);
+					self.push_shift_mask(amount, false)?;
+					self.op(Op::BAnd);
+					self.op(Op::BOr);
+				}
+				self.pop_double(inst);
+			}
+			Opcode::IEqual | Opcode::INotEqual => {
+				let (compare, combine) = match inst.opcode {
+					Opcode::IEqual => (Op::Eq, Op::And),
+					_ => (Op::Ne, Op::Or),
+				};
+				for is_high in [false, true] {
+					self.push_half(operand(0), is_high)?;
+					self.push_half(operand(1), is_high)?;
+					self.op(compare.clone());
+				}
+				self.op(combine);
+				self.pop_result(inst);
+			}
+			Opcode::UGreaterThan | Opcode::SGreaterThan => {
+				// `rhs - lhs` borrows when `lhs` is greater
+				self.push_half(operand(1), false)?;
+				self.push_half(operand(0), false)?;
+				self.op(Op::Sub);
+				self.op(Op::Pop);
+				for index in [1, 0] {
+					self.push_half(operand(index), true)?;
+					if inst.opcode == Opcode::SGreaterThan {
+						// flipping the sign bit orders signed values as unsigned
+						self.push_int(i32::MIN);
+						self.op(Op::BXOr);
+					}
+				}
+				self.op(Op::Sbb);
+				self.op(Op::Pop);
+				self.push_int(0);
+				self.push_int(0);
+				self.op(Op::Adc);
+				self.pop_result(inst);
+			}
+			Opcode::FunctionCall | Opcode::RetValue => {
+				let ty = inst
+					.result_type
+					.filter(|&ty| self.context.is_double(ty))
+					.or_else(|| {
+						inst.id_refs()
+							.find(|&id| self.is_double(id))
+							.and_then(|id| self.type_of(id))
+					})
+					.unwrap_or_default();
+				return Err(Error::UnsupportedType(ty));
+			}
+			opcode => return Err(Error::Unsupported(opcode)),
+		}
+		Ok(())
+	}
+
+	/// Extends the result of a computation to the width of its type
+	fn normalize(&mut self, inst: &Instruction) -> Result<(), Error> {
+		let (width, is_signed) = self.context.scalar(inst.result_type.unwrap())?;
+		self.extend(width, is_signed);
+		Ok(())
+	}
+
+	fn store(&mut self, pointer: u32, object: u32, size: u32) -> Result<(), Error> {
+		match size {
+			1 => {
+				self.push_value(object)?;
+				self.push_value(pointer)?;
+				self.op(Op::PopCVarInd);
+			}
+			2 => {
+				self.push_value(object)?;
+				self.push_value(pointer)?;
+				self.op(Op::PopCVarInd);
+				self.push_value(object)?;
+				self.push_int(8);
+				self.op(Op::ShiftRight);
+				self.push_address(pointer, 1)?;
+				self.op(Op::PopCVarInd);
+			}
+			4 => {
+				self.push_value(object)?;
+				self.push_value(pointer)?;
+				self.op(Op::PopVarInd);
+			}
+			8 if self.is_double(object) => {
+				self.push_half(object, false)?;
+				self.push_value(pointer)?;
+				self.op(Op::PopVarInd);
+				self.push_half(object, true)?;
+				self.push_address(pointer, WORD_SIZE)?;
+				self.op(Op::PopVarInd);
+			}
+			_ => {
+				let ty = self.type_of(object).unwrap_or_default();
+				return Err(Error::UnsupportedType(ty));
+			}
+		}
+		Ok(())
+	}
+
+	/// Phis of `to` with their value coming from `from`
+	fn phi_copies(&self, cfg: &ControlFlowGraph, from: u32, to: u32) -> Vec<(u32, u32)> {
+		let Some(block) = cfg.blocks.iter().find(|block| block.label == to) else {
+			return vec![];
+		};
+		block
+			.body
+			.iter()
+			.take_while(|inst| inst.opcode == Opcode::Phi)
+			.filter_map(|phi| {
+				let value = phi.operands.chunks(2).find_map(|pair| match pair {
+					[Operand::IdRef(value), Operand::IdRef(label)] if *label == from => {
+						Some(*value)
+					}
+					_ => None,
+				})?;
+				Some((phi.result_id.unwrap(), value))
+			})
+			.collect()
+	}
+
+	/// Label to jump to for the edge, a stub when phis need copies
+	fn edge_target(&self, cfg: &ControlFlowGraph, from: u32, to: u32) -> String {
+		match self.phi_copies(cfg, from, to).is_empty() {
+			true => block_label(to),
+			false => edge_label(from, to),
+		}
+	}
+
+	/// Copies the phis of the target and jumps to it unless it comes next
+	fn edge(
+		&mut self,
+		cfg: &ControlFlowGraph,
+		from: u32,
+		to: u32,
+		next: Option<u32>,
+	) -> Result<(), Error> {
+		let copies = self.phi_copies(cfg, from, to);
+		for (phi, value) in copies.iter() {
+			if self.is_double(*phi) {
+				self.push_half(*value, false)?;
+				self.push_half(*value, true)?;
+			} else {
+				self.push_value(*value)?;
+			}
+		}
+		for (phi, _) in copies.iter().rev() {
+			let offset = self.slots[phi];
+			if self.is_double(*phi) {
+				self.op(Op::PopVar(AsmOperand::Int(offset + WORD_SIZE as i32)));
+			}
+			self.op(Op::PopVar(AsmOperand::Int(offset)));
+		}
+		if next != Some(to) {
+			self.op(Op::Jmp(AsmOperand::Label(block_label(to))));
+		}
+		Ok(())
+	}
+}
@@ -1,8 +1,9 @@
 // Copyright (c) 2024-2026 Jonathan A. Thomason
 
 use std::collections::HashMap;
+use std::fmt;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Opcode {
 	Nop,
 	Undef,
@@ -69,6 +70,7 @@ pub enum Opcode {
 	Branch,
 	BranchConditional,
 	Unreachable,
+	Name,
 	Decorate,
 	MemberDecorate,
 	DecorateId,
@@ -77,6 +79,140 @@ pub enum Opcode {
 	Variable,
 	Constant,
 	Assembler,
+	/// `%id = Source "path"`, a source file named by `Line`
+	Source,
+	/// `Line %source line column`, the source of the instructions that follow
+	Line,
+	/// `DebugVariable %variable "name" "type"`, kept by the type list
+	DebugVariable,
+	/// Sign extends or truncates an integer to the width of the result
+	SConvert,
+	/// Zero extends or truncates an integer to the width of the result
+	UConvert,
+}
+
+impl Opcode {
+	/// Every opcode, in declaration order
+	pub const ALL: [Self; 79] = [
+		Self::Nop,
+		Self::Undef,
+		Self::IAdd,
+		Self::FAdd,
+		Self::ISub,
+		Self::FSub,
+		Self::IMul,
+		Self::FMul,
+		Self::SDiv,
+		Self::UDiv,
+		Self::FDiv,
+		Self::SRem,
+		Self::URem,
+		Self::FRem,
+		Self::SNeg,
+		Self::FNeg,
+		Self::Ret,
+		Self::RetValue,
+		Self::Store,
+		Self::Load,
+		Self::LogicalEqual,
+		Self::LogicalNotEqual,
+		Self::LogicalOr,
+		Self::LogicalAnd,
+		Self::LogicalNot,
+		Self::LogicalShiftRight,
+		Self::LogicalShiftLeft,
+		Self::BitwiseNot,
+		Self::BitwiseOr,
+		Self::BitwiseXor,
+		Self::BitwiseAnd,
+		Self::ArithmeticShiftRight,
+		Self::ArithmeticShiftLeft,
+		Self::IEqual,
+		Self::INotEqual,
+		Self::UGreaterThan,
+		Self::SGreaterThan,
+		Self::PtrEqual,
+		Self::PtrNotEqual,
+		Self::TypeVoid,
+		Self::TypeBool,
+		Self::TypeInt,
+		Self::TypeFloat,
+		Self::TypeArray,
+		Self::TypeRuntimeArray,
+		Self::TypePointer,
+		Self::TypeFunction,
+		Self::TypeVariadicFunction,
+		Self::TypeStruct,
+		Self::Halt,
+		Self::LifetimeStart,
+		Self::LifetimeEnd,
+		Self::Function,
+		Self::FunctionParameter,
+		Self::FunctionEnd,
+		Self::FunctionCall,
+		Self::CopyMemory,
+		Self::CopyMemorySized,
+		Self::Phi,
+		Self::LoopMerge,
+		Self::Label,
+		Self::Switch,
+		Self::Branch,
+		Self::BranchConditional,
+		Self::Unreachable,
+		Self::Name,
+		Self::Decorate,
+		Self::MemberDecorate,
+		Self::DecorateId,
+		Self::DecorateString,
+		Self::MemberDecorateString,
+		Self::Variable,
+		Self::Constant,
+		Self::Assembler,
+		Self::Source,
+		Self::Line,
+		Self::DebugVariable,
+		Self::SConvert,
+		Self::UConvert,
+	];
+	/// Returns true if the opcode ends a basic block
+	pub const fn is_terminator(self) -> bool {
+		matches!(
+			self,
+			Self::Branch
+				| Self::BranchConditional
+				| Self::Switch
+				| Self::Ret | Self::RetValue
+				| Self::Unreachable
+				| Self::Halt
+		)
+	}
+}
+
+impl std::str::FromStr for Opcode {
+	type Err = String;
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::ALL
+			.into_iter()
+			.find(|opcode| format!("{opcode:?}") == s)
+			.ok_or_else(|| format!("unknown opcode `{s}`"))
+	}
+}
+
+/// Bits of `Operand::FunctionControl`
+pub mod function_control {
+	pub const INLINE: u32 = 1;
+	pub const DONT_INLINE: u32 = 2;
+	pub const PURE: u32 = 4;
+	pub const CONST: u32 = 8;
+	/// The function has no external definition and may be dropped once nothing refers to it
+	pub const INTERNAL: u32 = 16;
+	/// Entered through the interrupt vector and left with `RTI`, never called
+	pub const INTERRUPT: u32 = 32;
+	/// Entered through the trap vector and left with `RTI`, never called
+	pub const SYSTRAP: u32 = 64;
+	/// Signed 32-bit addition, subtraction, multiplication and negation raise
+	/// the OVF machine check instead of wrapping
+	pub const TRAP_OVERFLOW: u32 = 128;
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -98,6 +234,21 @@ pub enum Operand {
 	Text(String),
 }
 
+impl fmt::Display for Operand {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::IdRef(id) => write!(f, "%{id}"),
+			Self::LiteralString => write!(f, "String"),
+			Self::LiteralBit32(value) => write!(f, "{value}u32"),
+			Self::LiteralBit64(value) => write!(f, "{value}u64"),
+			Self::LiteralBit128(value) => write!(f, "{value}u128"),
+			Self::StorageClass(storage_class) => write!(f, "{storage_class:?}"),
+			Self::FunctionControl(control) => write!(f, "Control({control})"),
+			Self::Text(text) => write!(f, "{text:?}"),
+		}
+	}
+}
+
 #[derive(Debug, Clone)]
 pub struct Instruction {
 	pub opcode: Opcode,
@@ -106,10 +257,121 @@ pub struct Instruction {
 	pub operands: Box<[Operand]>,
 }
 
+impl Instruction {
+	/// Iterates over every id referenced by the operands
+	pub fn id_refs(&self) -> impl Iterator<Item = u32> + '_ {
+		self.operands.iter().filter_map(|operand| match operand {
+			Operand::IdRef(id) => Some(*id),
+			_ => None,
+		})
+	}
+	pub fn id_refs_mut(&mut self) -> impl Iterator<Item = &mut u32> + '_ {
+		self.operands
+			.iter_mut()
+			.filter_map(|operand| match operand {
+				Operand::IdRef(id) => Some(id),
+				_ => None,
+			})
+	}
+	/// Returns the id of the n-th operand if it is an `IdRef`
+	pub fn id_operand(&self, index: usize) -> Option<u32> {
+		match self.operands.get(index) {
+			Some(Operand::IdRef(id)) => Some(*id),
+			_ => None,
+		}
+	}
+}
+
+/// Formats as `%id: %type = Opcode operands...`
+impl fmt::Display for Instruction {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match (self.result_id, self.result_type) {
+			(Some(id), Some(ty)) => write!(f, "%{id}: %{ty} = ")?,
+			(Some(id), None) => write!(f, "%{id} = ")?,
+			(None, Some(ty)) => write!(f, "_: %{ty} = ")?,
+			(None, None) => {}
+		}
+		write!(f, "{:?}", self.opcode)?;
+		for operand in self.operands.iter() {
+			write!(f, " {operand}")?;
+		}
+		Ok(())
+	}
+}
+
 #[derive(Debug)]
 pub struct Module {
 	pub type_list: Box<[Instruction]>,
 	pub sections: HashMap<String, Vec<DataKind>>,
+	/// Every id in the module is less than the bound
+	pub bound: u32,
+}
+
+impl Module {
+	/// Returns the next unused id
+	pub fn id(&mut self) -> u32 {
+		let result = self.bound;
+		self.bound += 1;
+		result
+	}
+	/// Looks up a type or constant declared in the type list
+	pub fn find_type(&self, id: u32) -> Option<&Instruction> {
+		self.type_list
+			.iter()
+			.find(|inst| inst.result_id == Some(id))
+	}
+	/// Looks up the id given a name by a `Name` instruction
+	pub fn find_name(&self, name: &str) -> Option<u32> {
+		self.type_list
+			.iter()
+			.find_map(|inst| match &inst.operands[..] {
+				[Operand::IdRef(target), Operand::Text(text)]
+					if inst.opcode == Opcode::Name && text == name =>
+				{
+					Some(*target)
+				}
+				_ => None,
+			})
+	}
+	pub fn name_of(&self, id: u32) -> Option<&str> {
+		self.type_list
+			.iter()
+			.find_map(|inst| match &inst.operands[..] {
+				[Operand::IdRef(target), Operand::Text(text)]
+					if inst.opcode == Opcode::Name && *target == id =>
+				{
+					Some(text.as_str())
+				}
+				_ => None,
+			})
+	}
+	pub fn find_function(&self, id: u32) -> Option<&Function> {
+		self.functions().find(|func| func.id() == id)
+	}
+	/// Section names in a stable order, so passes visit functions deterministically
+	pub fn section_names(&self) -> Vec<String> {
+		let mut names: Vec<String> = self.sections.keys().cloned().collect();
+		names.sort();
+		names
+	}
+	pub fn functions(&self) -> impl Iterator<Item = &Function> + '_ {
+		self.section_names().into_iter().flat_map(move |name| {
+			self.sections[&name].iter().filter_map(|data| match data {
+				DataKind::Func(func) => Some(func),
+				DataKind::Data(_) => None,
+			})
+		})
+	}
+	pub fn functions_mut(&mut self) -> impl Iterator<Item = &mut Function> + '_ {
+		let mut sections: Vec<(&String, &mut Vec<DataKind>)> = self.sections.iter_mut().collect();
+		sections.sort_by(|lhs, rhs| lhs.0.cmp(rhs.0));
+		sections.into_iter().flat_map(|(_, section)| {
+			section.iter_mut().filter_map(|data| match data {
+				DataKind::Func(func) => Some(func),
+				DataKind::Data(_) => None,
+			})
+		})
+	}
 }
 
 #[derive(Debug)]
@@ -135,4 +397,19 @@ impl Function {
 			end: None,
 		}
 	}
+	pub fn id(&self) -> u32 {
+		self.begin.result_id.unwrap()
+	}
+	pub fn control(&self) -> u32 {
+		match self.begin.operands.first() {
+			Some(Operand::FunctionControl(control)) => *control,
+			_ => 0,
+		}
+	}
+	/// Returns true if the function has no blocks, only its parameters
+	pub fn is_declaration(&self) -> bool {
+		self.body
+			.iter()
+			.all(|inst| inst.opcode == Opcode::FunctionParameter)
+	}
 }
@@ -0,0 +1,105 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Graphviz export of control flow graphs.
+//!
+//! Every function becomes a `digraph` with one node per basic block listing its
+//! instructions. Conditional edges are labeled `true`/`false`, switch edges with
+//! their case value.
+
+use std::fmt;
+
+use super::cfg::ControlFlowGraph;
+use super::data::{
+	Function,
+	Module,
+	Opcode,
+	Operand,
+};
+use super::fold::literal;
+
+/// Writes one graph per function of the module
+pub fn write_module(out: &mut impl fmt::Write, module: &Module) -> fmt::Result {
+	for func in module.functions() {
+		write_function(out, module, func)?;
+	}
+	Ok(())
+}
+
+pub fn write_function(out: &mut impl fmt::Write, module: &Module, func: &Function) -> fmt::Result {
+	let name = match module.name_of(func.id()) {
+		Some(name) => name.to_owned(),
+		None => format!("%{}", func.id()),
+	};
+	// labels added for fall-through are only needed for display
+	let mut bound = module.bound;
+	let cfg = ControlFlowGraph::new(func.body.clone(), &mut bound);
+	writeln!(out, "digraph \"{}\" {{", escape(&name))?;
+	writeln!(out, "\tnode [shape=box, fontname=monospace];")?;
+	let mut header = format!("{}\\l", escape(&func.begin.to_string()));
+	for param in cfg.params.iter() {
+		header.push_str(&format!("{}\\l", escape(&param.to_string())));
+	}
+	writeln!(out, "\tentry [shape=plaintext, label=\"{header}\"];")?;
+	writeln!(out, "\tentry -> block{};", cfg.blocks[0].label)?;
+	for block in cfg.blocks.iter() {
+		let mut listing = format!("%{}:\\l", block.label);
+		for inst in block.body.iter() {
+			listing.push_str(&format!("  {}\\l", escape(&inst.to_string())));
+		}
+		writeln!(out, "\tblock{} [label=\"{listing}\"];", block.label)?;
+		let Some(terminator) = block.terminator() else {
+			continue;
+		};
+		let edges: Vec<(u32, Option<String>)> = match terminator.opcode {
+			Opcode::BranchConditional => vec![
+				(terminator.id_operand(1).unwrap(), Some("true".to_owned())),
+				(terminator.id_operand(2).unwrap(), Some("false".to_owned())),
+			],
+			Opcode::Switch => {
+				let mut edges = vec![(
+					terminator.id_operand(1).unwrap(),
+					Some("default".to_owned()),
+				)];
+				for pair in terminator.operands[2..].chunks(2) {
+					if let [value, Operand::IdRef(label)] = pair {
+						let value = literal(Some(value))
+							.map_or(value.to_string(), |value| value.to_string());
+						edges.push((*label, Some(value)));
+					}
+				}
+				edges
+			}
+			_ => block
+				.successors()
+				.into_iter()
+				.map(|label| (label, None))
+				.collect(),
+		};
+		for (target, label) in edges {
+			match label {
+				Some(label) => writeln!(
+					out,
+					"\tblock{} -> block{target} [label=\"{label}\"];",
+					block.label
+				)?,
+				None => writeln!(out, "\tblock{} -> block{target};", block.label)?,
+			}
+		}
+	}
+	writeln!(out, "}}")
+}
+
+fn escape(text: &str) -> String {
+	let mut result = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'"' | '\\' => {
+				result.push('\\');
+				result.push(c);
+			}
+			'\n' => result.push_str("\\l"),
+			_ => result.push(c),
+		}
+	}
+	result
+}
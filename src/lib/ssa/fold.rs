@@ -0,0 +1,91 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Integer arithmetic shared by constant folding and the interpreter.
+//!
+//! Values are the bits of an integer of the given width, zero extended to 128 bits.
+//! Results must be masked to the width of their type.
+
+use super::data::{
+	Opcode,
+	Operand,
+};
+
+pub(crate) fn mask(width: u32) -> u128 {
+	match width {
+		128.. => u128::MAX,
+		_ => (1 << width) - 1,
+	}
+}
+
+pub(crate) fn sign_extend(value: u128, width: u32) -> i128 {
+	let shift = 128 - width.min(128);
+	((value << shift) as i128) >> shift
+}
+
+pub(crate) fn literal(operand: Option<&Operand>) -> Option<u128> {
+	match operand? {
+		Operand::LiteralBit32(value) => Some(*value as u128),
+		Operand::LiteralBit64(value) => Some(*value as u128),
+		Operand::LiteralBit128(value) => Some(*value),
+		_ => None,
+	}
+}
+
+/// Returns true if the signed result of the arithmetic does not fit in `width`
+/// bits, like `MIN / -1`
+pub(crate) fn overflows(opcode: Opcode, width: u32, args: &[u128]) -> bool {
+	let sign = |value: u128| sign_extend(value, width);
+	let exact = match (opcode, args) {
+		(Opcode::IAdd, [lhs, rhs]) => sign(*lhs).checked_add(sign(*rhs)),
+		(Opcode::ISub, [lhs, rhs]) => sign(*lhs).checked_sub(sign(*rhs)),
+		(Opcode::IMul, [lhs, rhs]) => sign(*lhs).checked_mul(sign(*rhs)),
+		(Opcode::SDiv, [_, 0]) => return false,
+		(Opcode::SDiv, [lhs, rhs]) => sign(*lhs).checked_div(sign(*rhs)),
+		(Opcode::SNeg, [value]) => sign(*value).checked_neg(),
+		_ => return false,
+	};
+	exact.is_none_or(|exact| sign(exact as u128 & mask(width)) != exact)
+}
+
+/// Evaluates an integer or bool instruction, `None` if the result is not defined
+pub(crate) fn fold(opcode: Opcode, width: u32, args: &[u128]) -> Option<u128> {
+	let sign = |value: u128| sign_extend(value, width);
+	let result = match (opcode, args) {
+		(Opcode::IAdd, [lhs, rhs]) => lhs.wrapping_add(*rhs),
+		(Opcode::ISub, [lhs, rhs]) => lhs.wrapping_sub(*rhs),
+		(Opcode::IMul, [lhs, rhs]) => lhs.wrapping_mul(*rhs),
+		(Opcode::UDiv, [lhs, rhs]) => lhs.checked_div(*rhs)?,
+		(Opcode::URem, [lhs, rhs]) => lhs.checked_rem(*rhs)?,
+		// i128 is wide enough that MIN / -1 of any narrower type does not overflow,
+		// masking the result wraps it back to MIN.
+		(Opcode::SDiv, [_, 0]) | (Opcode::SRem, [_, 0]) => return None,
+		(Opcode::SDiv, [lhs, rhs]) => sign(*lhs).wrapping_div(sign(*rhs)) as u128,
+		(Opcode::SRem, [lhs, rhs]) => sign(*lhs).wrapping_rem(sign(*rhs)) as u128,
+		(Opcode::SNeg, [value]) => value.wrapping_neg(),
+		(Opcode::SConvert, [value]) => sign(*value) as u128,
+		(Opcode::UConvert, [value]) => *value,
+		(Opcode::BitwiseNot, [value]) => !value,
+		(Opcode::BitwiseAnd, [lhs, rhs]) => lhs & rhs,
+		(Opcode::BitwiseOr, [lhs, rhs]) => lhs | rhs,
+		(Opcode::BitwiseXor, [lhs, rhs]) => lhs ^ rhs,
+		(
+			Opcode::LogicalShiftLeft
+			| Opcode::ArithmeticShiftLeft
+			| Opcode::LogicalShiftRight
+			| Opcode::ArithmeticShiftRight,
+			[_, rhs],
+		) if *rhs >= width as u128 => return None,
+		(Opcode::LogicalShiftLeft | Opcode::ArithmeticShiftLeft, [lhs, rhs]) => lhs << rhs,
+		(Opcode::LogicalShiftRight, [lhs, rhs]) => lhs >> rhs,
+		(Opcode::ArithmeticShiftRight, [lhs, rhs]) => (sign(*lhs) >> rhs) as u128,
+		(Opcode::IEqual | Opcode::LogicalEqual, [lhs, rhs]) => (lhs == rhs) as u128,
+		(Opcode::INotEqual | Opcode::LogicalNotEqual, [lhs, rhs]) => (lhs != rhs) as u128,
+		(Opcode::UGreaterThan, [lhs, rhs]) => (lhs > rhs) as u128,
+		(Opcode::SGreaterThan, [lhs, rhs]) => (sign(*lhs) > sign(*rhs)) as u128,
+		(Opcode::LogicalAnd, [lhs, rhs]) => (*lhs != 0 && *rhs != 0) as u128,
+		(Opcode::LogicalOr, [lhs, rhs]) => (*lhs != 0 || *rhs != 0) as u128,
+		(Opcode::LogicalNot, [value]) => (*value == 0) as u128,
+		_ => return None,
+	};
+	Some(result)
+}
@@ -0,0 +1,650 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Interpreter for SSA modules.
+//!
+//! Runs a module without going through the backend, so front end output can be
+//! checked on its own. Memory is a flat little endian byte array with 32 bit
+//! pointers, like STACKL. Inline assembly cannot be interpreted and is handed
+//! to a hook instead.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::data::{
+	DataKind,
+	Function,
+	Instruction,
+	Module,
+	Opcode,
+	Operand,
+	StorageClass,
+};
+use super::fold::{
+	fold,
+	literal,
+	mask,
+};
+
+/// Size of a pointer in bytes
+const POINTER_SIZE: u32 = 4;
+/// Calls nested deeper than this overflow the stack
+const MAX_DEPTH: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+	/// Integers, bools and pointers, zero extended
+	Int(u128),
+	Float(f64),
+	Void,
+}
+
+impl Value {
+	pub fn as_int(self) -> Option<u128> {
+		match self {
+			Self::Int(value) => Some(value),
+			_ => None,
+		}
+	}
+	pub fn as_float(self) -> Option<f64> {
+		match self {
+			Self::Float(value) => Some(value),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Trap {
+	UnknownFunction(String),
+	/// A call to a function without a body
+	UndefinedFunction(u32),
+	/// Inline assembly was reached and no hook handled it
+	Assembler(String),
+	DivideByZero,
+	ShiftOverflow,
+	InvalidAddress(u32),
+	/// An operand names an id that has no value yet
+	UnknownValue(u32),
+	/// A function was called with more or fewer arguments than it has parameters
+	ArgumentCount {
+		function: u32,
+		expected: usize,
+		found: usize,
+	},
+	StackOverflow,
+	StepLimit,
+	Unreachable,
+	/// Execution stopped at a `Halt` instruction
+	Halt,
+	Unsupported(Opcode),
+}
+
+impl fmt::Display for Trap {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::UnknownFunction(name) => write!(f, "no function named `{name}`"),
+			Self::UndefinedFunction(id) => write!(f, "function %{id} has no definition"),
+			Self::Assembler(text) => write!(f, "cannot interpret assembly `{text}`"),
+			Self::DivideByZero => write!(f, "divide by zero"),
+			Self::ShiftOverflow => write!(f, "shift amount exceeds the width of the type"),
+			Self::InvalidAddress(address) => write!(f, "invalid address {address:#010x}"),
+			Self::UnknownValue(id) => write!(f, "%{id} has no value"),
+			Self::ArgumentCount {
+				function,
+				expected,
+				found,
+			} => write!(
+				f,
+				"%{function} takes {expected} arguments but was given {found}"
+			),
+			Self::StackOverflow => write!(f, "stack overflow"),
+			Self::StepLimit => write!(f, "step limit reached"),
+			Self::Unreachable => write!(f, "reached unreachable code"),
+			Self::Halt => write!(f, "halted"),
+			Self::Unsupported(opcode) => write!(f, "cannot interpret {opcode:?}"),
+		}
+	}
+}
+
+impl std::error::Error for Trap {}
+
+/// Byte addressed memory. Address 0 is never allocated.
+#[derive(Debug)]
+pub struct Memory {
+	bytes: Vec<u8>,
+}
+
+impl Memory {
+	fn new() -> Self {
+		Self { bytes: vec![0; 4] }
+	}
+	fn allocate(&mut self, size: u32) -> u32 {
+		// keep every object word aligned
+		let address = self.bytes.len().next_multiple_of(4);
+		self.bytes.resize(address + size.max(1) as usize, 0);
+		address as u32
+	}
+	fn release(&mut self, mark: usize) {
+		self.bytes.truncate(mark);
+	}
+	pub fn read(&self, address: u32, size: u32) -> Result<&[u8], Trap> {
+		let start = address as usize;
+		match self.bytes.get(start..start + size as usize) {
+			Some(bytes) if address != 0 => Ok(bytes),
+			_ => Err(Trap::InvalidAddress(address)),
+		}
+	}
+	pub fn write(&mut self, address: u32, bytes: &[u8]) -> Result<(), Trap> {
+		let start = address as usize;
+		match self.bytes.get_mut(start..start + bytes.len()) {
+			Some(dest) if address != 0 => {
+				dest.copy_from_slice(bytes);
+				Ok(())
+			}
+			_ => Err(Trap::InvalidAddress(address)),
+		}
+	}
+	pub fn read_int(&self, address: u32, size: u32) -> Result<u128, Trap> {
+		let mut buf = [0u8; 16];
+		buf[..size as usize].copy_from_slice(self.read(address, size)?);
+		Ok(u128::from_le_bytes(buf))
+	}
+	pub fn write_int(&mut self, address: u32, size: u32, value: u128) -> Result<(), Trap> {
+		self.write(address, &value.to_le_bytes()[..size as usize])
+	}
+}
+
+type AssemblerHook<'a> = Box<dyn FnMut(&mut Memory, &str, &[Value]) -> Result<Value, Trap> + 'a>;
+
+#[derive(Debug, Clone, Copy)]
+enum Type {
+	Int(u32),
+	Float(u32),
+	Pointer(u32),
+	Void,
+	/// Arrays, structs and functions: only their size matters
+	Aggregate(u32),
+}
+
+pub struct Interpreter<'a> {
+	module: &'a Module,
+	functions: HashMap<u32, &'a Function>,
+	types: HashMap<u32, Type>,
+	/// Constants and the address of every static variable
+	globals: HashMap<u32, Value>,
+	/// Types of constants and values of every function
+	value_types: HashMap<u32, u32>,
+	assembler: HashMap<u32, &'a str>,
+	memory: Memory,
+	hook: Option<AssemblerHook<'a>>,
+	steps: u64,
+	step_limit: u64,
+	depth: usize,
+}
+
+impl<'a> Interpreter<'a> {
+	pub fn new(module: &'a Module) -> Result<Self, Trap> {
+		let mut interp = Self {
+			module,
+			functions: module.functions().map(|func| (func.id(), func)).collect(),
+			types: HashMap::new(),
+			globals: HashMap::new(),
+			value_types: HashMap::new(),
+			assembler: HashMap::new(),
+			memory: Memory::new(),
+			hook: None,
+			steps: 0,
+			step_limit: u64::MAX,
+			depth: 0,
+		};
+		for inst in module.type_list.iter() {
+			let Some(id) = inst.result_id else {
+				continue;
+			};
+			match inst.opcode {
+				Opcode::Constant => {
+					let ty = inst.result_type.unwrap();
+					interp.value_types.insert(id, ty);
+					let bits = literal(inst.operands.first()).unwrap_or(0);
+					let value = interp.value_from_bits(ty, bits);
+					interp.globals.insert(id, value);
+				}
+				Opcode::Assembler => {
+					if let Some(Operand::Text(text)) = inst.operands.first() {
+						interp.assembler.insert(id, text);
+					}
+				}
+				_ => {
+					let ty = interp.resolve_type(inst);
+					interp.types.insert(id, ty);
+				}
+			}
+		}
+		for func in module.functions() {
+			for inst in func.body.iter() {
+				if let (Some(id), Some(ty)) = (inst.result_id, inst.result_type) {
+					interp.value_types.insert(id, ty);
+				}
+			}
+		}
+		// static variables live for the whole run
+		let statics: Vec<&Instruction> = module
+			.section_names()
+			.iter()
+			.flat_map(|name| module.sections[name].iter())
+			.flat_map(|data| match data {
+				DataKind::Data(inst) => vec![inst],
+				DataKind::Func(func) => func.body.iter().collect(),
+			})
+			.filter(|inst| inst.opcode == Opcode::Variable)
+			.filter(|inst| {
+				!matches!(
+					inst.operands.first(),
+					Some(Operand::StorageClass(StorageClass::Automatic))
+				)
+			})
+			.collect();
+		for inst in statics {
+			let address = interp.allocate_variable(inst, &HashMap::new())?;
+			interp
+				.globals
+				.insert(inst.result_id.unwrap(), Value::Int(address as u128));
+		}
+		Ok(interp)
+	}
+
+	/// Handles inline assembly, which is given its text and arguments
+	pub fn on_assembler(
+		&mut self,
+		hook: impl FnMut(&mut Memory, &str, &[Value]) -> Result<Value, Trap> + 'a,
+	) -> &mut Self {
+		self.hook = Some(Box::new(hook));
+		self
+	}
+
+	/// Stops runaway programs after executing this many instructions
+	pub fn step_limit(&mut self, limit: u64) -> &mut Self {
+		self.step_limit = limit;
+		self
+	}
+
+	pub fn memory(&mut self) -> &mut Memory {
+		&mut self.memory
+	}
+
+	/// Runs the function with the given name
+	pub fn run(&mut self, name: &str, args: &[Value]) -> Result<Value, Trap> {
+		let id = self
+			.module
+			.find_name(name)
+			.ok_or_else(|| Trap::UnknownFunction(name.to_owned()))?;
+		self.call(id, args)
+	}
+
+	pub fn call(&mut self, id: u32, args: &[Value]) -> Result<Value, Trap> {
+		if let Some(text) = self.assembler.get(&id) {
+			return self.assembly(text, args);
+		}
+		let func = *self.functions.get(&id).ok_or(Trap::UndefinedFunction(id))?;
+		if self.depth == MAX_DEPTH {
+			return Err(Trap::StackOverflow);
+		}
+		self.depth += 1;
+		let mark = self.memory.bytes.len();
+		let result = self.execute(func, args);
+		self.memory.release(mark);
+		self.depth -= 1;
+		result
+	}
+
+	fn assembly(&mut self, text: &str, args: &[Value]) -> Result<Value, Trap> {
+		match self.hook.as_mut() {
+			Some(hook) => hook(&mut self.memory, text, args),
+			None => Err(Trap::Assembler(text.to_owned())),
+		}
+	}
+
+	fn resolve_type(&self, inst: &Instruction) -> Type {
+		let width = || inst.id_operand(0).unwrap_or(32);
+		match inst.opcode {
+			Opcode::TypeBool => Type::Int(1),
+			Opcode::TypeInt => Type::Int(width()),
+			Opcode::TypeFloat => Type::Float(width()),
+			Opcode::TypePointer => Type::Pointer(inst.id_operand(0).unwrap()),
+			Opcode::TypeVoid => Type::Void,
+			Opcode::TypeArray | Opcode::TypeRuntimeArray => {
+				let length = literal(inst.operands.get(1)).unwrap_or(0) as u32;
+				let element = inst.id_operand(0).map_or(0, |ty| self.size_of(ty));
+				Type::Aggregate(element * length)
+			}
+			Opcode::TypeStruct => {
+				Type::Aggregate(inst.id_refs().map(|member| self.size_of(member)).sum())
+			}
+			_ => Type::Aggregate(0),
+		}
+	}
+
+	fn size_of(&self, ty: u32) -> u32 {
+		match self.types.get(&ty) {
+			Some(Type::Int(width) | Type::Float(width)) => width.div_ceil(8),
+			Some(Type::Pointer(_)) => POINTER_SIZE,
+			Some(Type::Aggregate(size)) => *size,
+			Some(Type::Void) | None => 0,
+		}
+	}
+
+	fn value_from_bits(&self, ty: u32, bits: u128) -> Value {
+		match self.types.get(&ty) {
+			Some(Type::Float(32)) => Value::Float(f32::from_bits(bits as u32) as f64),
+			Some(Type::Float(_)) => Value::Float(f64::from_bits(bits as u64)),
+			Some(Type::Int(width)) => Value::Int(bits & mask(*width)),
+			Some(Type::Pointer(_)) => Value::Int(bits & mask(POINTER_SIZE * 8)),
+			_ => Value::Int(bits),
+		}
+	}
+
+	fn value_to_bits(&self, ty: u32, value: Value) -> u128 {
+		match (self.types.get(&ty), value) {
+			(Some(Type::Float(32)), Value::Float(value)) => (value as f32).to_bits() as u128,
+			(_, Value::Float(value)) => value.to_bits() as u128,
+			(_, Value::Int(bits)) => bits,
+			(_, Value::Void) => 0,
+		}
+	}
+
+	fn allocate_variable(
+		&mut self,
+		inst: &Instruction,
+		values: &HashMap<u32, Value>,
+	) -> Result<u32, Trap> {
+		let ty = inst.result_type.unwrap();
+		let size = self.size_of(ty);
+		let address = self.memory.allocate(size);
+		if let Some(init) = inst.id_operand(1) {
+			let value = self.value(values, init)?;
+			let bits = self.value_to_bits(ty, value);
+			self.memory.write_int(address, size.min(16), bits)?;
+		}
+		Ok(address)
+	}
+
+	fn value(&self, values: &HashMap<u32, Value>, id: u32) -> Result<Value, Trap> {
+		values
+			.get(&id)
+			.or_else(|| self.globals.get(&id))
+			.copied()
+			.ok_or(Trap::UnknownValue(id))
+	}
+
+	/// Size of the object a pointer refers to
+	fn pointee_size(&self, frame: &Frame, id: u32) -> u32 {
+		if let Some(ty) = frame.variables.get(&id) {
+			return self.size_of(*ty);
+		}
+		match self.value_types.get(&id).and_then(|ty| self.types.get(ty)) {
+			Some(Type::Pointer(pointee)) => self.size_of(*pointee),
+			_ => self.value_types.get(&id).map_or(0, |ty| self.size_of(*ty)),
+		}
+	}
+
+	fn address(&self, frame: &Frame, id: u32) -> Result<u32, Trap> {
+		match self.value(&frame.values, id)? {
+			Value::Int(address) => Ok(address as u32),
+			_ => Ok(0),
+		}
+	}
+
+	fn execute(&mut self, func: &Function, args: &[Value]) -> Result<Value, Trap> {
+		let body = &func.body;
+		let labels: HashMap<u32, usize> = body
+			.iter()
+			.enumerate()
+			.filter(|(_, inst)| inst.opcode == Opcode::Label)
+			.map(|(index, inst)| (inst.result_id.unwrap(), index))
+			.collect();
+		let mut frame = Frame {
+			values: HashMap::new(),
+			variables: HashMap::new(),
+		};
+		let expected = body
+			.iter()
+			.filter(|inst| inst.opcode == Opcode::FunctionParameter)
+			.count();
+		if expected != args.len() {
+			return Err(Trap::ArgumentCount {
+				function: func.id(),
+				expected,
+				found: args.len(),
+			});
+		}
+		let mut params = args.iter();
+		let mut block = None;
+		let mut ip = 0;
+		loop {
+			let Some(inst) = body.get(ip) else {
+				return Ok(Value::Void);
+			};
+			ip += 1;
+			self.steps += 1;
+			if self.steps > self.step_limit {
+				return Err(Trap::StepLimit);
+			}
+			let target = match inst.opcode {
+				Opcode::Label => inst.result_id,
+				Opcode::Branch => inst.id_operand(0),
+				Opcode::BranchConditional => {
+					let condition = self.value(&frame.values, inst.id_operand(0).unwrap())?;
+					let index = if condition == Value::Int(0) { 2 } else { 1 };
+					inst.id_operand(index)
+				}
+				Opcode::Switch => {
+					let selector = self.value(&frame.values, inst.id_operand(0).unwrap())?;
+					let case = inst.operands[2..]
+						.chunks(2)
+						.find(|pair| literal(pair.first()).map(Value::Int) == Some(selector));
+					match case {
+						Some([_, Operand::IdRef(label)]) => Some(*label),
+						_ => inst.id_operand(1),
+					}
+				}
+				Opcode::Ret => return Ok(Value::Void),
+				Opcode::RetValue => {
+					return self.value(&frame.values, inst.id_operand(0).unwrap());
+				}
+				Opcode::Unreachable => return Err(Trap::Unreachable),
+				Opcode::Halt => return Err(Trap::Halt),
+				Opcode::FunctionParameter => {
+					// there is an argument for every parameter
+					let value = *params.next().unwrap();
+					frame.values.insert(inst.result_id.unwrap(), value);
+					None
+				}
+				_ => {
+					self.instruction(inst, &mut frame)?;
+					None
+				}
+			};
+			let Some(target) = target else {
+				continue;
+			};
+			// phis read the values of the predecessor all at once
+			let pred = block.replace(target);
+			ip = labels[&target] + 1;
+			let mut incoming = vec![];
+			while let Some(phi) = body.get(ip).filter(|inst| inst.opcode == Opcode::Phi) {
+				let value = phi
+					.operands
+					.chunks(2)
+					.find(|pair| matches!(pair[1], Operand::IdRef(label) if Some(label) == pred))
+					.map_or(Ok(Value::Int(0)), |pair| match pair[0] {
+						Operand::IdRef(id) => self.value(&frame.values, id),
+						_ => Ok(Value::Int(0)),
+					})?;
+				incoming.push((phi.result_id.unwrap(), value));
+				ip += 1;
+			}
+			frame.values.extend(incoming);
+		}
+	}
+
+	fn instruction(&mut self, inst: &Instruction, frame: &mut Frame) -> Result<(), Trap> {
+		let operand = |index: usize| inst.id_operand(index).unwrap();
+		let result = match inst.opcode {
+			Opcode::Nop
+			| Opcode::LifetimeStart
+			| Opcode::LifetimeEnd
+			| Opcode::LoopMerge
+			| Opcode::Name
+			| Opcode::Decorate
+			| Opcode::MemberDecorate
+			| Opcode::DecorateId
+			| Opcode::DecorateString
+			| Opcode::MemberDecorateString
+			| Opcode::Line => return Ok(()),
+			Opcode::Undef => Value::Int(0),
+			Opcode::Variable => {
+				let id = inst.result_id.unwrap();
+				frame.variables.insert(id, inst.result_type.unwrap());
+				if self.globals.contains_key(&id) {
+					return Ok(());
+				}
+				Value::Int(self.allocate_variable(inst, &frame.values)? as u128)
+			}
+			Opcode::Load => {
+				let ty = inst.result_type.unwrap();
+				let address = self.address(frame, operand(0))?;
+				let size = self.size_of(ty);
+				let bits = self.memory.read_int(address, size.min(16))?;
+				self.value_from_bits(ty, bits)
+			}
+			Opcode::Store => {
+				let address = self.address(frame, operand(0))?;
+				let object = operand(1);
+				let ty = self.value_types.get(&object).copied().unwrap_or(0);
+				let size = self.size_of(ty).min(16);
+				let bits = self.value_to_bits(ty, self.value(&frame.values, object)?);
+				self.memory.write_int(address, size, bits)?;
+				return Ok(());
+			}
+			Opcode::CopyMemory | Opcode::CopyMemorySized => {
+				let dest = self.address(frame, operand(0))?;
+				let src = self.address(frame, operand(1))?;
+				let size = match inst.opcode {
+					Opcode::CopyMemorySized => {
+						self.value(&frame.values, operand(2))?.as_int().unwrap_or(0) as u32
+					}
+					_ => self.pointee_size(frame, operand(0)),
+				};
+				let bytes = self.memory.read(src, size)?.to_vec();
+				self.memory.write(dest, &bytes)?;
+				return Ok(());
+			}
+			Opcode::FunctionCall => {
+				let args: Vec<Value> = inst
+					.id_refs()
+					.skip(1)
+					.map(|id| self.value(&frame.values, id))
+					.collect::<Result<_, _>>()?;
+				self.call(operand(0), &args)?
+			}
+			Opcode::Assembler => {
+				let text = match inst.operands.first() {
+					Some(Operand::Text(text)) => text.as_str(),
+					_ => "",
+				};
+				let args: Vec<Value> = inst
+					.id_refs()
+					.map(|id| self.value(&frame.values, id))
+					.collect::<Result<_, _>>()?;
+				self.assembly(text, &args)?
+			}
+			Opcode::FAdd | Opcode::FSub | Opcode::FMul | Opcode::FDiv | Opcode::FRem => {
+				let lhs = self
+					.value(&frame.values, operand(0))?
+					.as_float()
+					.unwrap_or(0.0);
+				let rhs = self
+					.value(&frame.values, operand(1))?
+					.as_float()
+					.unwrap_or(0.0);
+				let value = match inst.opcode {
+					Opcode::FAdd => lhs + rhs,
+					Opcode::FSub => lhs - rhs,
+					Opcode::FMul => lhs * rhs,
+					Opcode::FDiv => lhs / rhs,
+					_ => lhs % rhs,
+				};
+				self.round_float(inst.result_type.unwrap(), value)
+			}
+			Opcode::FNeg => {
+				let value = self
+					.value(&frame.values, operand(0))?
+					.as_float()
+					.unwrap_or(0.0);
+				Value::Float(-value)
+			}
+			Opcode::PtrEqual | Opcode::PtrNotEqual => {
+				let lhs = self.value(&frame.values, operand(0))?;
+				let rhs = self.value(&frame.values, operand(1))?;
+				Value::Int(((lhs == rhs) == (inst.opcode == Opcode::PtrEqual)) as u128)
+			}
+			opcode => self.integer(inst, opcode, frame)?,
+		};
+		if let Some(id) = inst.result_id {
+			frame.values.insert(id, result);
+		}
+		Ok(())
+	}
+
+	fn round_float(&self, ty: u32, value: f64) -> Value {
+		match self.types.get(&ty) {
+			Some(Type::Float(32)) => Value::Float(value as f32 as f64),
+			_ => Value::Float(value),
+		}
+	}
+
+	fn integer(&self, inst: &Instruction, opcode: Opcode, frame: &Frame) -> Result<Value, Trap> {
+		let result_type = inst.result_type.ok_or(Trap::Unsupported(opcode))?;
+		let int_width = |ty: Option<&u32>| match ty.and_then(|ty| self.types.get(ty)) {
+			Some(Type::Int(width)) => Some(*width),
+			Some(Type::Pointer(_)) => Some(POINTER_SIZE * 8),
+			_ => None,
+		};
+		let result_width = int_width(Some(&result_type)).ok_or(Trap::Unsupported(opcode))?;
+		// comparisons produce bool and conversions change the width, their
+		// width comes from the operands
+		let width = match opcode {
+			Opcode::IEqual
+			| Opcode::INotEqual
+			| Opcode::UGreaterThan
+			| Opcode::SGreaterThan
+			| Opcode::SConvert
+			| Opcode::UConvert => {
+				let lhs = inst.id_operand(0).unwrap();
+				int_width(self.value_types.get(&lhs)).unwrap_or(result_width)
+			}
+			_ => result_width,
+		};
+		let args: Vec<u128> = inst
+			.id_refs()
+			.map(|id| Ok(self.value(&frame.values, id)?.as_int().unwrap_or(0)))
+			.collect::<Result<_, Trap>>()?;
+		match fold(opcode, width, &args) {
+			Some(value) => Ok(Value::Int(value & mask(result_width))),
+			None => Err(match opcode {
+				Opcode::SDiv | Opcode::UDiv | Opcode::SRem | Opcode::URem => Trap::DivideByZero,
+				Opcode::LogicalShiftLeft
+				| Opcode::LogicalShiftRight
+				| Opcode::ArithmeticShiftLeft
+				| Opcode::ArithmeticShiftRight => Trap::ShiftOverflow,
+				_ => Trap::Unsupported(opcode),
+			}),
+		}
+	}
+}
+
+struct Frame {
+	values: HashMap<u32, Value>,
+	/// Object type of every variable declared so far
+	variables: HashMap<u32, u32>,
+}
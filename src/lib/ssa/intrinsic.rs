@@ -0,0 +1,91 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Functions the STACKL backend lowers to privileged and debugging
+//! instructions.
+//!
+//! Front ends declare them as functions without a body named after
+//! [`Intrinsic::name`] and call them like any other function.
+
+/// Start of the name of every intrinsic
+pub const PREFIX: &str = "__builtin_stackl_";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Intrinsic {
+	/// `void trap(void)`: `TRAP`
+	Trap,
+	/// `void rti(void)`: `RTI`
+	Rti,
+	/// `void halt(void)`: `HALT`
+	Halt,
+	/// `int get_reg(REG)`: `PUSHREG`
+	GetReg,
+	/// `void set_reg(REG, int)`: `POPREG`
+	SetReg,
+	/// `int set_int_dis(void)`: `SET_INT_DIS`, returns the previous `INT_DIS`
+	SetIntDis,
+	/// `int clr_int_dis(void)`: `CLR_INT_DIS`, returns the previous `INT_DIS`
+	ClrIntDis,
+	/// `void trace(int on)`: `SET_TRACE` or `CLR_TRACE`
+	Trace,
+	/// `void outs(const char *)`: `OUTS`
+	Outs,
+	/// `void break(void)`: `BREAK`
+	Break,
+}
+
+/// Kind of an argument of an intrinsic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Param {
+	/// Constant register number, see [`crate::asm::ast::Reg`]
+	Register,
+	Int,
+	/// `const char *`
+	String,
+}
+
+impl Intrinsic {
+	pub const ALL: [Self; 10] = [
+		Self::Trap,
+		Self::Rti,
+		Self::Halt,
+		Self::GetReg,
+		Self::SetReg,
+		Self::SetIntDis,
+		Self::ClrIntDis,
+		Self::Trace,
+		Self::Outs,
+		Self::Break,
+	];
+	pub fn from_name(name: &str) -> Option<Self> {
+		Self::ALL
+			.into_iter()
+			.find(|intrinsic| intrinsic.name() == name)
+	}
+	pub const fn name(self) -> &'static str {
+		match self {
+			Self::Trap => "__builtin_stackl_trap",
+			Self::Rti => "__builtin_stackl_rti",
+			Self::Halt => "__builtin_stackl_halt",
+			Self::GetReg => "__builtin_stackl_get_reg",
+			Self::SetReg => "__builtin_stackl_set_reg",
+			Self::SetIntDis => "__builtin_stackl_set_int_dis",
+			Self::ClrIntDis => "__builtin_stackl_clr_int_dis",
+			Self::Trace => "__builtin_stackl_trace",
+			Self::Outs => "__builtin_stackl_outs",
+			Self::Break => "__builtin_stackl_break",
+		}
+	}
+	pub const fn params(self) -> &'static [Param] {
+		match self {
+			Self::GetReg => &[Param::Register],
+			Self::SetReg => &[Param::Register, Param::Int],
+			Self::Trace => &[Param::Int],
+			Self::Outs => &[Param::String],
+			_ => &[],
+		}
+	}
+	/// Returns true if the intrinsic returns an `int`, otherwise it returns `void`
+	pub const fn returns_int(self) -> bool {
+		matches!(self, Self::GetReg | Self::SetIntDis | Self::ClrIntDis)
+	}
+}
@@ -1,7 +1,17 @@
 // Copyright (c) 2024-2026 Jonathan A. Thomason
 
+pub mod binary;
 pub mod builder;
+pub mod cfg;
+pub mod codegen;
 pub mod data;
+pub mod dot;
+mod fold;
+pub mod interp;
+pub mod intrinsic;
+pub mod opt;
+pub mod text;
+pub mod verify;
 
 #[derive(Debug)]
 pub enum Error {
@@ -0,0 +1,213 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Aggressive dead code elimination.
+//!
+//! Every instruction is assumed dead until something with a side effect uses it.
+//! Local variables that are only ever written are removed along with their stores.
+//! Afterwards a block that unconditionally jumps to a block with no other
+//! predecessor absorbs it.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::ssa::cfg::ControlFlowGraph;
+use crate::ssa::data::{
+	Function,
+	Instruction,
+	Module,
+	Opcode,
+	Operand,
+	StorageClass,
+};
+
+pub fn dce(module: &mut Module) {
+	let mut bound = module.bound;
+	// declarations have no body to clean up
+	for func in module.functions_mut().filter(|func| !func.is_declaration()) {
+		remove_dead_code(func);
+		merge_blocks(func, &mut bound);
+	}
+	module.bound = bound;
+}
+
+/// Returns true if removing an unused instruction changes nothing
+fn is_pure(opcode: Opcode) -> bool {
+	matches!(
+		opcode,
+		Opcode::Nop
+			| Opcode::Undef
+			| Opcode::IAdd
+			| Opcode::FAdd
+			| Opcode::ISub
+			| Opcode::FSub
+			| Opcode::IMul
+			| Opcode::FMul
+			| Opcode::SDiv
+			| Opcode::UDiv
+			| Opcode::FDiv
+			| Opcode::SRem
+			| Opcode::URem
+			| Opcode::FRem
+			| Opcode::SNeg
+			| Opcode::SConvert
+			| Opcode::UConvert
+			| Opcode::FNeg
+			| Opcode::Load
+			| Opcode::LogicalEqual
+			| Opcode::LogicalNotEqual
+			| Opcode::LogicalOr
+			| Opcode::LogicalAnd
+			| Opcode::LogicalNot
+			| Opcode::LogicalShiftRight
+			| Opcode::LogicalShiftLeft
+			| Opcode::BitwiseNot
+			| Opcode::BitwiseOr
+			| Opcode::BitwiseXor
+			| Opcode::BitwiseAnd
+			| Opcode::ArithmeticShiftRight
+			| Opcode::ArithmeticShiftLeft
+			| Opcode::IEqual
+			| Opcode::INotEqual
+			| Opcode::UGreaterThan
+			| Opcode::SGreaterThan
+			| Opcode::PtrEqual
+			| Opcode::PtrNotEqual
+			| Opcode::Phi
+			| Opcode::Variable
+			| Opcode::Constant
+	)
+}
+
+/// Automatic variables whose value is never read
+fn write_only_variables(body: &[Instruction]) -> HashSet<u32> {
+	let mut result: HashSet<u32> = body
+		.iter()
+		.filter(|inst| inst.opcode == Opcode::Variable)
+		.filter(|inst| {
+			matches!(
+				inst.operands.first(),
+				Some(Operand::StorageClass(StorageClass::Automatic))
+			)
+		})
+		.filter_map(|inst| inst.result_id)
+		.collect();
+	for inst in body {
+		for (index, id) in inst.operands.iter().enumerate() {
+			let Operand::IdRef(id) = id else {
+				continue;
+			};
+			let is_write = index == 0
+				&& matches!(
+					inst.opcode,
+					Opcode::Store | Opcode::LifetimeStart | Opcode::LifetimeEnd
+				);
+			if !is_write {
+				result.remove(id);
+			}
+		}
+	}
+	result
+}
+
+fn remove_dead_code(func: &mut Function) {
+	let dead_vars = write_only_variables(&func.body);
+	let is_dead_write = |inst: &Instruction| {
+		matches!(
+			inst.opcode,
+			Opcode::Store | Opcode::LifetimeStart | Opcode::LifetimeEnd
+		) && inst.id_operand(0).is_some_and(|id| dead_vars.contains(&id))
+	};
+	let definitions: HashMap<u32, usize> = func
+		.body
+		.iter()
+		.enumerate()
+		.filter_map(|(index, inst)| Some((inst.result_id?, index)))
+		.collect();
+	let mut live = vec![false; func.body.len()];
+	let mut worklist = vec![];
+	for (index, inst) in func.body.iter().enumerate() {
+		let is_root = match inst.opcode {
+			Opcode::Label | Opcode::FunctionParameter => true,
+			opcode => !is_pure(opcode) && !is_dead_write(inst),
+		};
+		if is_root {
+			live[index] = true;
+			worklist.push(index);
+		}
+	}
+	while let Some(index) = worklist.pop() {
+		for id in func.body[index].id_refs() {
+			if let Some(&def) = definitions.get(&id)
+				&& !live[def]
+			{
+				live[def] = true;
+				worklist.push(def);
+			}
+		}
+	}
+	let mut index = 0;
+	func.body.retain(|_| {
+		index += 1;
+		live[index - 1]
+	});
+}
+
+fn merge_blocks(func: &mut Function, bound: &mut u32) {
+	let mut cfg = ControlFlowGraph::new(std::mem::take(&mut func.body), bound);
+	// labels that something other than a branch refers to
+	let pinned: HashSet<u32> = cfg
+		.blocks
+		.iter()
+		.flat_map(|block| block.body.iter())
+		.filter(|inst| inst.opcode == Opcode::LoopMerge)
+		.flat_map(|inst| inst.id_refs())
+		.collect();
+	loop {
+		let preds = cfg.predecessors();
+		let succs = cfg.successors();
+		let merge = (0..cfg.blocks.len()).find_map(|index| {
+			let block = &cfg.blocks[index];
+			if block.terminator()?.opcode != Opcode::Branch {
+				return None;
+			}
+			let [target] = succs[index][..] else {
+				return None;
+			};
+			let target_block = &cfg.blocks[target];
+			let can_merge = target != index
+				&& target != 0
+				&& preds[target] == [index]
+				&& !pinned.contains(&target_block.label)
+				&& target_block
+					.body
+					.iter()
+					.all(|inst| inst.opcode != Opcode::Phi);
+			can_merge.then_some((index, target))
+		});
+		let Some((index, target)) = merge else {
+			break;
+		};
+		let target_block = cfg.blocks.remove(target);
+		let index = if target < index { index - 1 } else { index };
+		let block = &mut cfg.blocks[index];
+		block.body.pop();
+		block.body.extend(target_block.body);
+		let (old_label, new_label) = (target_block.label, block.label);
+		// successors of the absorbed block now come from the merged block
+		for inst in cfg
+			.blocks
+			.iter_mut()
+			.flat_map(|block| block.body.iter_mut())
+			.filter(|inst| inst.opcode == Opcode::Phi)
+		{
+			for label in inst.operands.iter_mut().skip(1).step_by(2) {
+				if let Operand::IdRef(label) = label
+					&& *label == old_label
+				{
+					*label = new_label;
+				}
+			}
+		}
+	}
+	func.body = cfg.into_body();
+}
@@ -0,0 +1,360 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Function inlining.
+//!
+//! A call is replaced by a copy of the callee when the callee is marked
+//! `Inline` or is small, unless it is marked `DontInline`, is recursive or
+//! does not agree with the caller on `TrapOverflow`.
+//! Callees are visited before their callers, so inlined code is not inlined
+//! into again. Functions without an external definition are dropped once
+//! nothing refers to them.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use super::replace_uses;
+use crate::ssa::cfg::{
+	BasicBlock,
+	ControlFlowGraph,
+	branch,
+};
+use crate::ssa::data::{
+	DataKind,
+	Function,
+	Instruction,
+	Module,
+	Opcode,
+	Operand,
+	function_control,
+};
+
+/// Callees with at most this many instructions are inlined without being asked to
+const SIZE_THRESHOLD: usize = 16;
+
+struct Callee {
+	control: u32,
+	is_declaration: bool,
+	body: Vec<Instruction>,
+	is_recursive: bool,
+}
+
+impl Callee {
+	fn size(&self) -> usize {
+		self.body
+			.iter()
+			.filter(|inst| {
+				!matches!(
+					inst.opcode,
+					Opcode::Label | Opcode::FunctionParameter | Opcode::Branch
+				)
+			})
+			.count()
+	}
+	fn should_inline(&self) -> bool {
+		// declarations are defined elsewhere
+		!self.is_declaration
+			&& !self.is_recursive
+			&& self.control & function_control::DONT_INLINE == 0
+			&& (self.control & function_control::INLINE != 0 || self.size() <= SIZE_THRESHOLD)
+	}
+}
+
+fn called_functions<'a>(
+	body: &'a [Instruction],
+	functions: &'a HashSet<u32>,
+) -> impl Iterator<Item = u32> + 'a {
+	body.iter()
+		.filter(|inst| inst.opcode == Opcode::FunctionCall)
+		.filter_map(|inst| inst.id_operand(0))
+		.filter(|id| functions.contains(id))
+}
+
+pub fn inline_functions(module: &mut Module) {
+	let function_ids: HashSet<u32> = module.functions().map(Function::id).collect();
+	let order: Vec<u32> = module.functions().map(Function::id).collect();
+	let call_graph: HashMap<u32, Vec<u32>> = module
+		.functions()
+		.map(|func| {
+			let mut callees: Vec<u32> = called_functions(&func.body, &function_ids).collect();
+			callees.dedup();
+			(func.id(), callees)
+		})
+		.collect();
+	let mut callees: HashMap<u32, Callee> = module
+		.functions()
+		.map(|func| {
+			let callee = Callee {
+				control: func.control(),
+				is_declaration: func.is_declaration(),
+				body: func.body.clone(),
+				is_recursive: reaches(&call_graph, func.id(), func.id()),
+			};
+			(func.id(), callee)
+		})
+		.collect();
+
+	let mut bound = module.bound;
+	for caller in postorder(&call_graph, &order) {
+		let func = module
+			.functions_mut()
+			.find(|func| func.id() == caller)
+			.unwrap();
+		if inline_calls(func, &callees, &mut bound) {
+			callees.get_mut(&caller).unwrap().body = func.body.clone();
+		}
+	}
+	module.bound = bound;
+	remove_unreferenced(module);
+}
+
+/// Returns true if `to` can be called, directly or not, from `from`
+fn reaches(call_graph: &HashMap<u32, Vec<u32>>, from: u32, to: u32) -> bool {
+	let mut visited = HashSet::new();
+	let mut worklist = call_graph[&from].clone();
+	while let Some(func) = worklist.pop() {
+		if func == to {
+			return true;
+		}
+		if visited.insert(func) {
+			worklist.extend(call_graph[&func].iter());
+		}
+	}
+	false
+}
+
+/// Functions ordered so that callees come before their callers
+fn postorder(call_graph: &HashMap<u32, Vec<u32>>, order: &[u32]) -> Vec<u32> {
+	let mut visited = HashSet::new();
+	let mut result = vec![];
+	for &root in order {
+		if !visited.insert(root) {
+			continue;
+		}
+		// (function, next callee to visit)
+		let mut stack = vec![(root, 0)];
+		while let Some((func, next)) = stack.last_mut() {
+			if let Some(&callee) = call_graph[func].get(*next) {
+				*next += 1;
+				if visited.insert(callee) {
+					stack.push((callee, 0));
+				}
+			} else {
+				result.push(*func);
+				stack.pop();
+			}
+		}
+	}
+	result
+}
+
+fn fresh_id(bound: &mut u32) -> u32 {
+	let id = *bound;
+	*bound += 1;
+	id
+}
+
+fn inline_calls(func: &mut Function, callees: &HashMap<u32, Callee>, bound: &mut u32) -> bool {
+	// the arithmetic of the callee would change its meaning in the caller
+	let trap_overflow = func.control() & function_control::TRAP_OVERFLOW;
+	let is_inline_call = |inst: &Instruction| {
+		inst.opcode == Opcode::FunctionCall
+			&& inst
+				.id_operand(0)
+				.and_then(|id| callees.get(&id))
+				.is_some_and(|callee| {
+					let params = callee
+						.body
+						.iter()
+						.filter(|inst| inst.opcode == Opcode::FunctionParameter)
+						.count();
+					callee.should_inline()
+						&& callee.control & function_control::TRAP_OVERFLOW == trap_overflow
+						&& params + 1 == inst.operands.len()
+				})
+	};
+	if !func.body.iter().any(is_inline_call) {
+		return false;
+	}
+	let mut cfg = ControlFlowGraph::new(std::mem::take(&mut func.body), bound);
+	let mut replace: HashMap<u32, u32> = HashMap::new();
+	let mut index = 0;
+	while index < cfg.blocks.len() {
+		let Some(position) = cfg.blocks[index].body.iter().position(is_inline_call) else {
+			index += 1;
+			continue;
+		};
+		let tail = cfg.blocks[index].body.split_off(position + 1);
+		let call = cfg.blocks[index].body.pop().unwrap();
+		let callee = &callees[&call.id_operand(0).unwrap()];
+		let continue_label = fresh_id(bound);
+
+		// the tail now reaches the successors of the block
+		let old_label = cfg.blocks[index].label;
+		let mut continue_block = BasicBlock::new(continue_label);
+		continue_block.body = tail;
+		let successors = continue_block.successors();
+		for block in cfg.blocks.iter_mut() {
+			if !successors.contains(&block.label) {
+				continue;
+			}
+			for inst in block.body.iter_mut() {
+				if inst.opcode != Opcode::Phi {
+					continue;
+				}
+				for label in inst.operands.iter_mut().skip(1).step_by(2) {
+					if matches!(label, Operand::IdRef(label) if *label == old_label) {
+						*label = Operand::IdRef(continue_label);
+					}
+				}
+			}
+		}
+
+		let mut clone = ControlFlowGraph::new(callee.body.clone(), bound);
+		let mut id_map: HashMap<u32, u32> = clone
+			.params
+			.iter()
+			.map(|param| param.result_id.unwrap())
+			.zip(call.id_refs().skip(1))
+			.collect();
+		for block in clone.blocks.iter() {
+			id_map.insert(block.label, fresh_id(bound));
+			for inst in block.body.iter() {
+				if let Some(id) = inst.result_id {
+					id_map.insert(id, fresh_id(bound));
+				}
+			}
+		}
+		// (returned value, block), `Ret` returns no value
+		let mut returns: Vec<(Option<u32>, u32)> = vec![];
+		for block in clone.blocks.iter_mut() {
+			block.label = id_map[&block.label];
+			for inst in block.body.iter_mut() {
+				if let Some(id) = inst.result_id.as_mut() {
+					*id = id_map[id];
+				}
+				for id in inst.id_refs_mut() {
+					if let Some(new_id) = id_map.get(id) {
+						*id = *new_id;
+					}
+				}
+			}
+			let terminator = block.body.last_mut().unwrap();
+			match terminator.opcode {
+				Opcode::RetValue => {
+					returns.push((terminator.id_operand(0), block.label));
+					*terminator = branch(continue_label);
+				}
+				Opcode::Ret => {
+					returns.push((None, block.label));
+					*terminator = branch(continue_label);
+				}
+				_ => {}
+			}
+		}
+
+		if let Some(result_id) = call.result_id {
+			// a path returning no value leaves the result undefined, the
+			// calling block dominates the inlined code and defines the Undef
+			let undef = |id| Instruction {
+				opcode: Opcode::Undef,
+				result_type: call.result_type,
+				result_id: Some(id),
+				operands: [].into(),
+			};
+			match returns[..] {
+				[(Some(value), _)] => {
+					replace.insert(result_id, value);
+				}
+				_ if returns.iter().all(|(value, _)| value.is_none()) => {
+					cfg.blocks[index].body.push(undef(result_id));
+				}
+				_ => {
+					let undef_id = returns.iter().any(|(value, _)| value.is_none()).then(|| {
+						let id = fresh_id(bound);
+						cfg.blocks[index].body.push(undef(id));
+						id
+					});
+					continue_block.body.insert(
+						0,
+						Instruction {
+							opcode: Opcode::Phi,
+							result_type: call.result_type,
+							result_id: Some(result_id),
+							operands: returns
+								.iter()
+								.flat_map(|(value, label)| {
+									[
+										Operand::IdRef(value.or(undef_id).unwrap()),
+										Operand::IdRef(*label),
+									]
+								})
+								.collect(),
+						},
+					);
+				}
+			}
+		}
+		cfg.blocks[index].body.push(branch(clone.blocks[0].label));
+		let inlined_blocks = clone.blocks.len();
+		let next = index + 1 + inlined_blocks;
+		cfg.blocks.splice(
+			index + 1..index + 1,
+			clone.blocks.into_iter().chain([continue_block]),
+		);
+		index = next;
+	}
+	replace_uses(
+		cfg.blocks
+			.iter_mut()
+			.flat_map(|block| block.body.iter_mut()),
+		&replace,
+	);
+	func.body = cfg.into_body();
+	true
+}
+
+/// Drops internal functions nothing refers to
+fn remove_unreferenced(module: &mut Module) {
+	loop {
+		let mut referenced: HashSet<u32> = HashSet::new();
+		for section in module.sections.values() {
+			for data in section.iter() {
+				match data {
+					DataKind::Func(func) => referenced.extend(
+						func.body
+							.iter()
+							.flat_map(|inst| inst.id_refs())
+							.filter(|id| *id != func.id()),
+					),
+					DataKind::Data(inst) => referenced.extend(inst.id_refs()),
+				}
+			}
+		}
+		let mut removed = vec![];
+		for section in module.sections.values_mut() {
+			section.retain(|data| match data {
+				DataKind::Func(func) => {
+					let is_handler = func.control()
+						& (function_control::INTERRUPT | function_control::SYSTRAP)
+						!= 0;
+					let is_dead = func.control() & function_control::INTERNAL != 0
+						&& !is_handler && !referenced.contains(&func.id());
+					if is_dead {
+						removed.push(func.id());
+					}
+					!is_dead
+				}
+				DataKind::Data(_) => true,
+			});
+		}
+		if removed.is_empty() {
+			break;
+		}
+		let mut type_list = std::mem::take(&mut module.type_list).into_vec();
+		type_list.retain(|inst| {
+			inst.opcode != Opcode::Name
+				|| !inst.id_operand(0).is_some_and(|id| removed.contains(&id))
+		});
+		module.type_list = type_list.into_boxed_slice();
+	}
+}
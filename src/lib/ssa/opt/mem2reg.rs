@@ -0,0 +1,334 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Promotes local variables to SSA values.
+//!
+//! An automatic `Variable` of scalar type whose address never escapes (it is only
+//! used as the pointer of `Load`, `Store`, `LifetimeStart` and `LifetimeEnd`) is
+//! replaced by the values stored to it. `Phi` instructions are placed on the
+//! iterated dominance frontier of the blocks that define the variable.
+//! A load outside of the lifetime of the variable reads `Undef`.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use super::{
+	replace_uses,
+	resolve,
+};
+use crate::ssa::cfg::ControlFlowGraph;
+use crate::ssa::data::{
+	Function,
+	Instruction,
+	Module,
+	Opcode,
+	Operand,
+	StorageClass,
+};
+
+pub fn mem2reg(module: &mut Module) {
+	let types: HashMap<u32, Opcode> = module
+		.type_list
+		.iter()
+		.filter_map(|inst| Some((inst.result_id?, inst.opcode)))
+		.collect();
+	let mut bound = module.bound;
+	for func in module.functions_mut() {
+		promote_function(func, &types, &mut bound);
+	}
+	module.bound = bound;
+}
+
+fn is_promotable_type(opcode: Option<&Opcode>) -> bool {
+	matches!(
+		opcode,
+		Some(Opcode::TypeBool | Opcode::TypeInt | Opcode::TypeFloat | Opcode::TypePointer)
+	)
+}
+
+/// Finds the variables of a function body that can be promoted
+fn find_candidates(body: &[Instruction], types: &HashMap<u32, Opcode>) -> Vec<(u32, u32)> {
+	let mut candidates: Vec<(u32, u32)> = body
+		.iter()
+		.filter(|inst| inst.opcode == Opcode::Variable)
+		.filter(|inst| {
+			matches!(
+				inst.operands.first(),
+				Some(Operand::StorageClass(StorageClass::Automatic))
+			)
+		})
+		.filter(|inst| is_promotable_type(inst.result_type.and_then(|ty| types.get(&ty))))
+		.map(|inst| (inst.result_id.unwrap(), inst.result_type.unwrap()))
+		.collect();
+	let mut escaped = HashSet::new();
+	for inst in body {
+		for (index, operand) in inst.operands.iter().enumerate() {
+			let Operand::IdRef(id) = operand else {
+				continue;
+			};
+			let is_pointer_use = index == 0
+				&& matches!(
+					inst.opcode,
+					Opcode::Load | Opcode::Store | Opcode::LifetimeStart | Opcode::LifetimeEnd
+				);
+			if !is_pointer_use {
+				escaped.insert(*id);
+			}
+		}
+	}
+	candidates.retain(|(id, _)| !escaped.contains(id));
+	candidates
+}
+
+#[derive(Clone)]
+struct PlacedPhi {
+	var: usize,
+	id: u32,
+	/// (value, predecessor label) pairs
+	incoming: Vec<(u32, u32)>,
+}
+
+/// What an instruction does to a promoted variable
+enum Access {
+	/// The variable takes this value, `None` being undefined
+	Define(usize, Option<u32>),
+	/// The result of the instruction is the current value of the variable
+	Read(usize, u32),
+}
+
+fn classify(inst: &Instruction, var_index: &HashMap<u32, usize>) -> Option<Access> {
+	match inst.opcode {
+		Opcode::Variable => {
+			let var = *var_index.get(&inst.result_id?)?;
+			Some(Access::Define(var, inst.id_operand(1)))
+		}
+		Opcode::Store => {
+			let var = *var_index.get(&inst.id_operand(0)?)?;
+			Some(Access::Define(var, inst.id_operand(1)))
+		}
+		Opcode::LifetimeStart | Opcode::LifetimeEnd => {
+			let var = *var_index.get(&inst.id_operand(0)?)?;
+			Some(Access::Define(var, None))
+		}
+		Opcode::Load => {
+			let var = *var_index.get(&inst.id_operand(0)?)?;
+			Some(Access::Read(var, inst.result_id?))
+		}
+		_ => None,
+	}
+}
+
+fn promote_function(func: &mut Function, types: &HashMap<u32, Opcode>, bound: &mut u32) {
+	let candidates = find_candidates(&func.body, types);
+	if candidates.is_empty() {
+		return;
+	}
+	let var_index: HashMap<u32, usize> = candidates
+		.iter()
+		.enumerate()
+		.map(|(index, (id, _))| (*id, index))
+		.collect();
+	let var_types: Vec<u32> = candidates.iter().map(|(_, ty)| *ty).collect();
+
+	let mut cfg = ControlFlowGraph::new(std::mem::take(&mut func.body), bound);
+	let idom = cfg.immediate_dominators();
+	let frontiers = cfg.dominance_frontiers(&idom);
+	let dom_tree = ControlFlowGraph::dominator_tree(&idom);
+	let successors = cfg.successors();
+	let mut reachable = vec![false; cfg.blocks.len()];
+	for block in cfg.reverse_postorder() {
+		reachable[block] = true;
+	}
+
+	// place phis on the iterated dominance frontier of every definition
+	let mut def_blocks: Vec<Vec<usize>> = vec![vec![]; candidates.len()];
+	for (index, block) in cfg.blocks.iter().enumerate() {
+		if !reachable[index] {
+			continue;
+		}
+		for inst in block.body.iter() {
+			if let Some(Access::Define(var, _)) = classify(inst, &var_index)
+				&& !def_blocks[var].contains(&index)
+			{
+				def_blocks[var].push(index);
+			}
+		}
+	}
+	let mut phis: Vec<Vec<PlacedPhi>> = vec![vec![]; cfg.blocks.len()];
+	for (var, defs) in def_blocks.iter().enumerate() {
+		let mut worklist = defs.clone();
+		let mut has_phi = vec![false; cfg.blocks.len()];
+		while let Some(block) = worklist.pop() {
+			for &frontier in frontiers[block].iter() {
+				if has_phi[frontier] {
+					continue;
+				}
+				has_phi[frontier] = true;
+				let id = *bound;
+				*bound += 1;
+				phis[frontier].push(PlacedPhi {
+					var,
+					id,
+					incoming: vec![],
+				});
+				if !defs.contains(&frontier) {
+					worklist.push(frontier);
+				}
+			}
+		}
+	}
+
+	let mut undefs: Vec<(u32, u32)> = vec![];
+	let mut undef_of = |ty: u32, bound: &mut u32| -> u32 {
+		if let Some((_, id)) = undefs.iter().find(|(undef_ty, _)| *undef_ty == ty) {
+			return *id;
+		}
+		let id = *bound;
+		*bound += 1;
+		undefs.push((ty, id));
+		id
+	};
+
+	// rename along the dominator tree
+	let mut replace: HashMap<u32, u32> = HashMap::new();
+	let mut worklist: Vec<(usize, Vec<Option<u32>>)> = vec![(0, vec![None; candidates.len()])];
+	let mut visited = vec![false; cfg.blocks.len()];
+	while let Some((block, mut values)) = worklist.pop() {
+		visited[block] = true;
+		for PlacedPhi { var, id, .. } in phis[block].iter() {
+			values[*var] = Some(*id);
+		}
+		let body = std::mem::take(&mut cfg.blocks[block].body);
+		let mut new_body = Vec::with_capacity(body.len());
+		for inst in body {
+			match classify(&inst, &var_index) {
+				Some(Access::Define(var, value)) => values[var] = value,
+				Some(Access::Read(var, result_id)) => {
+					let value = match values[var] {
+						Some(value) => value,
+						None => undef_of(var_types[var], bound),
+					};
+					replace.insert(result_id, value);
+				}
+				None => new_body.push(inst),
+			}
+		}
+		cfg.blocks[block].body = new_body;
+		let label = cfg.blocks[block].label;
+		for &succ in successors[block].iter() {
+			for PlacedPhi { var, incoming, .. } in phis[succ].iter_mut() {
+				let value = match values[*var] {
+					Some(value) => value,
+					None => undef_of(var_types[*var], bound),
+				};
+				incoming.push((value, label));
+			}
+		}
+		for &child in dom_tree[block].iter().rev() {
+			worklist.push((child, values.clone()));
+		}
+	}
+	// unreachable blocks still feed phis of reachable blocks
+	for block in 0..cfg.blocks.len() {
+		if visited[block] {
+			continue;
+		}
+		let body = std::mem::take(&mut cfg.blocks[block].body);
+		let mut new_body = Vec::with_capacity(body.len());
+		for inst in body {
+			match classify(&inst, &var_index) {
+				Some(Access::Define(..)) => {}
+				Some(Access::Read(var, result_id)) => {
+					replace.insert(result_id, undef_of(var_types[var], bound));
+				}
+				None => new_body.push(inst),
+			}
+		}
+		cfg.blocks[block].body = new_body;
+		let label = cfg.blocks[block].label;
+		for &succ in successors[block].iter() {
+			for PlacedPhi { var, incoming, .. } in phis[succ].iter_mut() {
+				incoming.push((undef_of(var_types[*var], bound), label));
+			}
+		}
+	}
+
+	// a phi whose incoming values are all the same value (or itself) is that value
+	let mut changed = true;
+	while changed {
+		changed = false;
+		for block_phis in phis.iter_mut() {
+			block_phis.retain(|PlacedPhi { id, incoming, .. }| {
+				let mut unique = None;
+				for (value, _) in incoming.iter() {
+					let value = resolve(&replace, *value);
+					if value == *id || unique == Some(value) {
+						continue;
+					}
+					if unique.is_some() {
+						return true;
+					}
+					unique = Some(value);
+				}
+				let Some(unique) = unique else {
+					return true;
+				};
+				replace.insert(*id, unique);
+				changed = true;
+				false
+			});
+		}
+	}
+
+	// a phi is dead unless something other than a dead phi reads it
+	let mut live: HashSet<u32> = HashSet::new();
+	for block in cfg.blocks.iter() {
+		for inst in block.body.iter() {
+			live.extend(inst.id_refs().map(|id| resolve(&replace, id)));
+		}
+	}
+	let mut changed = true;
+	while changed {
+		changed = false;
+		for block_phis in phis.iter() {
+			for PlacedPhi { id, incoming, .. } in block_phis.iter() {
+				if !live.contains(id) {
+					continue;
+				}
+				for (value, _) in incoming.iter() {
+					changed |= live.insert(resolve(&replace, *value));
+				}
+			}
+		}
+	}
+
+	for (index, block) in cfg.blocks.iter_mut().enumerate() {
+		let mut body: Vec<Instruction> = phis[index]
+			.iter()
+			.filter(|phi| live.contains(&phi.id))
+			.map(|PlacedPhi { var, id, incoming }| Instruction {
+				opcode: Opcode::Phi,
+				result_type: Some(var_types[*var]),
+				result_id: Some(*id),
+				operands: incoming
+					.iter()
+					.flat_map(|(value, label)| [Operand::IdRef(*value), Operand::IdRef(*label)])
+					.collect(),
+			})
+			.collect();
+		body.append(&mut block.body);
+		replace_uses(body.iter_mut(), &replace);
+		block.body = body;
+	}
+	let undefs: Vec<Instruction> = undefs
+		.into_iter()
+		.filter(|(_, id)| live.contains(id))
+		.map(|(ty, id)| Instruction {
+			opcode: Opcode::Undef,
+			result_type: Some(ty),
+			result_id: Some(id),
+			operands: [].into(),
+		})
+		.collect();
+	cfg.blocks[0].body.splice(0..0, undefs);
+	func.body = cfg.into_body();
+}
@@ -0,0 +1,48 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Transformations over SSA modules
+
+use std::collections::HashMap;
+
+use super::data::{
+	Instruction,
+	Module,
+};
+
+mod dce;
+mod inline;
+mod mem2reg;
+mod sccp;
+
+pub use dce::dce;
+pub use inline::inline_functions;
+pub use mem2reg::mem2reg;
+pub use sccp::sccp;
+
+/// Runs the passes enabled by `-O1`
+pub fn optimize(module: &mut Module) {
+	mem2reg(module);
+	inline_functions(module);
+	sccp(module);
+	dce(module);
+}
+
+/// Follows a chain of replaced ids to the final value
+fn resolve(replace: &HashMap<u32, u32>, mut id: u32) -> u32 {
+	while let Some(next) = replace.get(&id) {
+		id = *next;
+	}
+	id
+}
+
+/// Rewrites every use of a replaced id
+fn replace_uses<'a>(insts: impl Iterator<Item = &'a mut Instruction>, replace: &HashMap<u32, u32>) {
+	if replace.is_empty() {
+		return;
+	}
+	for inst in insts {
+		for id in inst.id_refs_mut() {
+			*id = resolve(replace, *id);
+		}
+	}
+}
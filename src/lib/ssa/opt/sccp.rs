@@ -0,0 +1,441 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Sparse conditional constant propagation.
+//!
+//! Integer and bool values are folded with the wrapping semantics of their
+//! declared width. Division by zero, oversized shifts and signed overflow in
+//! functions marked `TRAP_OVERFLOW` are left for the machine to report.
+//! Branches on constants become unconditional and blocks that can never
+//! execute are deleted.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use super::{
+	replace_uses,
+	resolve,
+};
+use crate::ssa::cfg::{
+	ControlFlowGraph,
+	branch,
+};
+use crate::ssa::data::{
+	DataKind,
+	Function,
+	Instruction,
+	Module,
+	Opcode,
+	Operand,
+	function_control,
+};
+use crate::ssa::fold::{
+	fold,
+	literal,
+	mask,
+	overflows,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lattice {
+	/// Not yet known to execute
+	Top,
+	Const(u128),
+	/// Not a constant
+	Bottom,
+}
+
+impl Lattice {
+	fn meet(self, other: Self) -> Self {
+		match (self, other) {
+			(Self::Top, value) | (value, Self::Top) => value,
+			(Self::Const(lhs), Self::Const(rhs)) if lhs == rhs => self,
+			_ => Self::Bottom,
+		}
+	}
+}
+
+/// Width and signedness of the integer types, bool being an unsigned 1 bit integer
+type IntTypes = HashMap<u32, (u32, bool)>;
+
+/// Returns true if the operands of the opcode are evaluated at their own
+/// width rather than the width of the result
+fn has_operand_width(opcode: Opcode) -> bool {
+	matches!(
+		opcode,
+		Opcode::IEqual
+			| Opcode::INotEqual
+			| Opcode::UGreaterThan
+			| Opcode::SGreaterThan
+			| Opcode::SConvert
+			| Opcode::UConvert
+	)
+}
+
+pub fn sccp(module: &mut Module) {
+	let mut int_types: IntTypes = HashMap::new();
+	for inst in module.type_list.iter() {
+		match (inst.opcode, inst.result_id) {
+			(Opcode::TypeBool, Some(id)) => {
+				int_types.insert(id, (1, false));
+			}
+			(Opcode::TypeInt, Some(id)) => {
+				let width = inst.id_operand(0).unwrap();
+				let is_signed = literal(inst.operands.get(1)) != Some(0);
+				int_types.insert(id, (width, is_signed));
+			}
+			_ => {}
+		}
+	}
+	// (type, value) -> id
+	let mut constants: HashMap<(u32, u128), u32> = HashMap::new();
+	// id -> (type, value)
+	let mut constant_values: HashMap<u32, (u32, u128)> = HashMap::new();
+	for inst in module.type_list.iter() {
+		if inst.opcode != Opcode::Constant {
+			continue;
+		}
+		let (Some(id), Some(ty)) = (inst.result_id, inst.result_type) else {
+			continue;
+		};
+		let Some(&(width, _)) = int_types.get(&ty) else {
+			continue;
+		};
+		let Some(value) = literal(inst.operands.first()) else {
+			continue;
+		};
+		let value = value & mask(width);
+		constants.entry((ty, value)).or_insert(id);
+		constant_values.insert(id, (ty, value));
+	}
+	let mut new_constants = vec![];
+	let mut bound = module.bound;
+	// declarations have no body to fold
+	for func in module.functions_mut().filter(|func| !func.is_declaration()) {
+		let mut context = Context {
+			int_types: &int_types,
+			constant_values: &constant_values,
+			constants: &mut constants,
+			new_constants: &mut new_constants,
+			bound: &mut bound,
+			traps_overflow: func.control() & function_control::TRAP_OVERFLOW != 0,
+		};
+		context.run(func);
+	}
+	module.bound = bound;
+	// the operands that were folded may leave their constants unused
+	let mut type_list = std::mem::take(&mut module.type_list).into_vec();
+	type_list.append(&mut new_constants);
+	let mut used: HashSet<u32> = type_list.iter().flat_map(Instruction::id_refs).collect();
+	for data in module.sections.values().flatten() {
+		match data {
+			DataKind::Func(func) => {
+				let insts = std::iter::once(&func.begin)
+					.chain(&func.params)
+					.chain(&func.body)
+					.chain(&func.end);
+				used.extend(insts.flat_map(Instruction::id_refs));
+			}
+			DataKind::Data(inst) => used.extend(inst.id_refs()),
+		}
+	}
+	type_list.retain(|inst| {
+		inst.opcode != Opcode::Constant || inst.result_id.is_some_and(|id| used.contains(&id))
+	});
+	module.type_list = type_list.into_boxed_slice();
+}
+
+struct Context<'a> {
+	int_types: &'a IntTypes,
+	constant_values: &'a HashMap<u32, (u32, u128)>,
+	constants: &'a mut HashMap<(u32, u128), u32>,
+	new_constants: &'a mut Vec<Instruction>,
+	bound: &'a mut u32,
+	traps_overflow: bool,
+}
+
+impl Context<'_> {
+	fn constant(&mut self, ty: u32, value: u128) -> u32 {
+		if let Some(id) = self.constants.get(&(ty, value)) {
+			return *id;
+		}
+		let id = *self.bound;
+		*self.bound += 1;
+		let operand = match self.int_types[&ty].0 {
+			0..=32 => Operand::LiteralBit32(value as u32),
+			33..=64 => Operand::LiteralBit64(value as u64),
+			_ => Operand::LiteralBit128(value),
+		};
+		self.new_constants.push(Instruction {
+			opcode: Opcode::Constant,
+			result_type: Some(ty),
+			result_id: Some(id),
+			operands: [operand].into(),
+		});
+		self.constants.insert((ty, value), id);
+		id
+	}
+
+	fn value(&self, values: &HashMap<u32, Lattice>, id: u32) -> Lattice {
+		if let Some(value) = values.get(&id) {
+			return *value;
+		}
+		match self.constant_values.get(&id) {
+			Some((_, value)) => Lattice::Const(*value),
+			None => Lattice::Bottom,
+		}
+	}
+
+	fn run(&mut self, func: &mut Function) {
+		let mut cfg = ControlFlowGraph::new(std::mem::take(&mut func.body), self.bound);
+		let label_map = cfg.label_map();
+		let block_count = cfg.blocks.len();
+		let mut values: HashMap<u32, Lattice> = HashMap::new();
+		let mut result_types: HashMap<u32, u32> = HashMap::new();
+		for inst in cfg.params.iter() {
+			values.insert(inst.result_id.unwrap(), Lattice::Bottom);
+		}
+		for block in cfg.blocks.iter() {
+			for inst in block.body.iter() {
+				if let Some(id) = inst.result_id {
+					values.insert(id, Lattice::Top);
+					if let Some(ty) = inst.result_type {
+						result_types.insert(id, ty);
+					}
+				}
+			}
+		}
+		let mut executable = vec![false; block_count];
+		let mut edges: HashSet<(usize, usize)> = HashSet::new();
+		executable[0] = true;
+
+		let mut changed = true;
+		while changed {
+			changed = false;
+			for index in 0..block_count {
+				if !executable[index] {
+					continue;
+				}
+				let block = &cfg.blocks[index];
+				for inst in block.body.iter() {
+					let targets = self.targets(inst, &values, &label_map);
+					for target in targets {
+						if edges.insert((index, target)) {
+							changed = true;
+							executable[target] = true;
+						}
+					}
+					let Some(id) = inst.result_id else {
+						continue;
+					};
+					let new_value = match inst.opcode {
+						Opcode::Phi => inst
+							.operands
+							.chunks(2)
+							.filter_map(|pair| match pair {
+								[Operand::IdRef(value), Operand::IdRef(label)] => {
+									let pred = *label_map.get(label)?;
+									edges
+										.contains(&(pred, index))
+										.then(|| self.value(&values, *value))
+								}
+								_ => None,
+							})
+							.fold(Lattice::Top, Lattice::meet),
+						_ => self.evaluate(inst, &values, &result_types),
+					};
+					let old_value = values[&id];
+					let new_value = old_value.meet(new_value);
+					if new_value != old_value {
+						values.insert(id, new_value);
+						changed = true;
+					}
+				}
+			}
+		}
+
+		// rewrite the function with what is now known
+		let folded: HashMap<u32, (u32, u128)> = values
+			.iter()
+			.filter_map(|(id, value)| match value {
+				Lattice::Const(value) => {
+					let ty = result_types.get(id)?;
+					self.int_types
+						.contains_key(ty)
+						.then_some((*id, (*ty, *value)))
+				}
+				_ => None,
+			})
+			.collect();
+		let mut replace: HashMap<u32, u32> = HashMap::new();
+		let labels: Vec<u32> = cfg.blocks.iter().map(|block| block.label).collect();
+		for (index, block) in cfg.blocks.iter_mut().enumerate() {
+			if !executable[index] {
+				continue;
+			}
+			block
+				.body
+				.retain(|inst| inst.result_id.is_none_or(|id| !folded.contains_key(&id)));
+			for inst in block.body.iter_mut() {
+				match inst.opcode {
+					Opcode::Phi => {
+						let operands: Vec<Operand> = inst
+							.operands
+							.chunks(2)
+							.filter(|pair| match pair {
+								[_, Operand::IdRef(label)] => label_map
+									.get(label)
+									.is_some_and(|pred| edges.contains(&(*pred, index))),
+								_ => false,
+							})
+							.flatten()
+							.cloned()
+							.collect();
+						inst.operands = operands.into();
+					}
+					Opcode::BranchConditional | Opcode::Switch => {
+						let targets = self.targets(inst, &values, &label_map);
+						if let [target] = targets[..] {
+							*inst = branch(labels[target]);
+						}
+					}
+					_ => {}
+				}
+			}
+		}
+		let mut index = 0;
+		cfg.blocks.retain(|_| {
+			index += 1;
+			executable[index - 1]
+		});
+		// a phi left with a single predecessor is its value
+		for block in cfg.blocks.iter_mut() {
+			block.body.retain(|inst| {
+				if inst.opcode != Opcode::Phi {
+					return true;
+				}
+				let mut incoming = inst.id_refs().step_by(2);
+				let first = incoming.next();
+				match first {
+					Some(first) if incoming.all(|value| value == first) => {
+						replace.insert(inst.result_id.unwrap(), resolve(&replace, first));
+						false
+					}
+					_ => true,
+				}
+			});
+		}
+		// constants are made in the order they are used, so the ids do not
+		// depend on hashing and a value folded into another one needs none
+		for inst in cfg.blocks.iter().flat_map(|block| block.body.iter()) {
+			for id in inst.id_refs() {
+				let id = resolve(&replace, id);
+				if let Some(&(ty, value)) = folded.get(&id) {
+					let constant = self.constant(ty, value);
+					replace.insert(id, constant);
+				}
+			}
+		}
+		replace_uses(
+			cfg.blocks
+				.iter_mut()
+				.flat_map(|block| block.body.iter_mut()),
+			&replace,
+		);
+		func.body = cfg.into_body();
+	}
+
+	fn evaluate(
+		&self,
+		inst: &Instruction,
+		values: &HashMap<u32, Lattice>,
+		result_types: &HashMap<u32, u32>,
+	) -> Lattice {
+		let type_of = |id: u32| {
+			result_types
+				.get(&id)
+				.copied()
+				.or_else(|| self.constant_values.get(&id).map(|(ty, _)| *ty))
+		};
+		let Some(&(result_width, is_signed)) =
+			inst.result_type.and_then(|ty| self.int_types.get(&ty))
+		else {
+			return Lattice::Bottom;
+		};
+		// comparisons produce bool and conversions change the width, their
+		// width comes from the operands
+		let width = if has_operand_width(inst.opcode) {
+			match inst
+				.id_operand(0)
+				.and_then(type_of)
+				.and_then(|ty| self.int_types.get(&ty))
+			{
+				Some((width, _)) => *width,
+				None => return Lattice::Bottom,
+			}
+		} else {
+			result_width
+		};
+		let mut args = Vec::with_capacity(inst.operands.len());
+		for id in inst.id_refs() {
+			match self.value(values, id) {
+				Lattice::Top => return Lattice::Top,
+				Lattice::Const(value) => args.push(value),
+				Lattice::Bottom => return Lattice::Bottom,
+			}
+		}
+		if args.len() != inst.operands.len() {
+			return Lattice::Bottom;
+		}
+		if self.traps_overflow && is_signed && overflows(inst.opcode, width, &args) {
+			return Lattice::Bottom;
+		}
+		match fold(inst.opcode, width, &args) {
+			Some(value) => Lattice::Const(value & mask(result_width)),
+			None => Lattice::Bottom,
+		}
+	}
+
+	/// Blocks a terminator may transfer control to, given what is known so far
+	fn targets(
+		&self,
+		inst: &Instruction,
+		values: &HashMap<u32, Lattice>,
+		label_map: &HashMap<u32, usize>,
+	) -> Vec<usize> {
+		let block_of = |operand: Option<&Operand>| match operand {
+			Some(Operand::IdRef(label)) => label_map.get(label).copied(),
+			_ => None,
+		};
+		match inst.opcode {
+			Opcode::Branch => block_of(inst.operands.first()).into_iter().collect(),
+			Opcode::BranchConditional => {
+				let true_block = block_of(inst.operands.get(1));
+				let false_block = block_of(inst.operands.get(2));
+				match self.value(values, inst.id_operand(0).unwrap()) {
+					Lattice::Top => vec![],
+					Lattice::Const(0) => false_block.into_iter().collect(),
+					Lattice::Const(_) => true_block.into_iter().collect(),
+					Lattice::Bottom => true_block.into_iter().chain(false_block).collect(),
+				}
+			}
+			Opcode::Switch => {
+				let default = block_of(inst.operands.get(1));
+				let cases = inst.operands[2..].chunks(2);
+				match self.value(values, inst.id_operand(0).unwrap()) {
+					Lattice::Top => vec![],
+					Lattice::Const(selector) => cases
+						.filter(|pair| literal(pair.first()) == Some(selector))
+						.find_map(|pair| block_of(pair.get(1)))
+						.or(default)
+						.into_iter()
+						.collect(),
+					Lattice::Bottom => default
+						.into_iter()
+						.chain(cases.filter_map(|pair| block_of(pair.get(1))))
+						.collect(),
+				}
+			}
+			_ => vec![],
+		}
+	}
+}
@@ -0,0 +1,361 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Textual form of SSA modules.
+//!
+//! Every instruction takes one line in the format of its `Display`
+//! implementation. The type list comes first, followed by each section:
+//!
+//! ```text
+//! bound 7
+//! %0 = TypeInt %32 1u32
+//! %1 = TypeFunction %0
+//! Name %2 "main"
+//!
+//! section ".code"
+//! %2: %1 = Function Control(0)
+//! %3 = Label
+//!     RetValue %4
+//! FunctionEnd
+//! ```
+//!
+//! Lines starting with `;` are comments.
+
+use std::fmt;
+
+use super::data::{
+	DataKind,
+	Function,
+	Instruction,
+	Module,
+	Opcode,
+	Operand,
+	StorageClass,
+};
+
+/// Writes the whole module
+pub fn write_module(out: &mut impl fmt::Write, module: &Module) -> fmt::Result {
+	writeln!(out, "bound {}", module.bound)?;
+	for inst in module.type_list.iter() {
+		writeln!(out, "{inst}")?;
+	}
+	for name in module.section_names() {
+		writeln!(out)?;
+		writeln!(out, "section {name:?}")?;
+		for data in module.sections[&name].iter() {
+			match data {
+				DataKind::Func(func) => write_function(out, func)?,
+				DataKind::Data(inst) => writeln!(out, "{inst}")?,
+			}
+		}
+	}
+	Ok(())
+}
+
+pub fn write_function(out: &mut impl fmt::Write, func: &Function) -> fmt::Result {
+	writeln!(out, "{}", func.begin)?;
+	for inst in func.params.iter().chain(func.body.iter()) {
+		match inst.opcode {
+			Opcode::Label => writeln!(out, "{inst}")?,
+			_ => writeln!(out, "\t{inst}")?,
+		}
+	}
+	match &func.end {
+		Some(end) => writeln!(out, "{end}"),
+		None => writeln!(out, "FunctionEnd"),
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+	/// Line number, starting at 1
+	pub line: usize,
+	pub message: String,
+}
+
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "line {}: {}", self.line, self.message)
+	}
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses a module written by [`write_module`]
+pub fn parse_module(text: &str) -> Result<Module, ParseError> {
+	let mut type_list = vec![];
+	let mut sections = std::collections::HashMap::new();
+	let mut section: Option<String> = None;
+	let mut function: Option<Function> = None;
+	let mut bound = 0;
+	for (index, line) in text.lines().enumerate() {
+		let error = |message: String| ParseError {
+			line: index + 1,
+			message,
+		};
+		let line = line.trim();
+		if line.is_empty() || line.starts_with(';') {
+			continue;
+		}
+		let mut tokens = Lexer::new(line).tokenize().map_err(error)?;
+		match tokens.first() {
+			Some(Token::Word(word)) if word == "bound" => {
+				let [_, Token::Integer(value, None)] = tokens[..] else {
+					return Err(error("expected `bound <integer>`".to_owned()));
+				};
+				bound = u32::try_from(value).map_err(|_| error("bound is too large".to_owned()))?;
+				continue;
+			}
+			Some(Token::Word(word)) if word == "section" => {
+				if function.is_some() {
+					return Err(error("section inside a function".to_owned()));
+				}
+				let Some(Token::Text(name)) = tokens.pop().filter(|_| tokens.len() == 1) else {
+					return Err(error("expected `section \"<name>\"`".to_owned()));
+				};
+				sections.entry(name.clone()).or_insert_with(Vec::new);
+				section = Some(name);
+				continue;
+			}
+			_ => {}
+		}
+		let inst = parse_instruction(&tokens).map_err(error)?;
+		if let Some(id) = inst.result_id.iter().chain(inst.result_type.iter()).max() {
+			bound = bound.max(id + 1);
+		}
+		let Some(name) = section.as_ref() else {
+			type_list.push(inst);
+			continue;
+		};
+		match (inst.opcode, function.as_mut()) {
+			(Opcode::Function, None) => function = Some(Function::new(inst)),
+			(Opcode::Function, Some(_)) => return Err(error("nested function".to_owned())),
+			(Opcode::FunctionEnd, Some(_)) => {
+				let mut func = function.take().unwrap();
+				func.end = Some(inst);
+				sections.get_mut(name).unwrap().push(DataKind::Func(func));
+			}
+			(Opcode::FunctionEnd, None) => {
+				return Err(error("`FunctionEnd` outside a function".to_owned()));
+			}
+			(_, Some(func)) => func.body.push(inst),
+			(_, None) => sections.get_mut(name).unwrap().push(DataKind::Data(inst)),
+		}
+	}
+	if function.is_some() {
+		return Err(ParseError {
+			line: text.lines().count(),
+			message: "missing `FunctionEnd`".to_owned(),
+		});
+	}
+	let mut module = Module {
+		type_list: type_list.into_boxed_slice(),
+		sections,
+		bound,
+	};
+	// ids may also appear only as operands
+	let max_ref = module
+		.type_list
+		.iter()
+		.chain(
+			module
+				.sections
+				.values()
+				.flatten()
+				.flat_map(|data| match data {
+					DataKind::Func(func) => func.body.iter().collect::<Vec<_>>(),
+					DataKind::Data(inst) => vec![inst],
+				}),
+		)
+		.filter(|inst| inst.opcode != Opcode::TypeInt && inst.opcode != Opcode::TypeFloat)
+		.flat_map(|inst| inst.id_refs())
+		.max();
+	if let Some(id) = max_ref {
+		module.bound = module.bound.max(id + 1);
+	}
+	Ok(module)
+}
+
+fn parse_instruction(tokens: &[Token]) -> Result<Instruction, String> {
+	let (result_id, result_type, rest) = match tokens {
+		[
+			Token::Id(id),
+			Token::Colon,
+			Token::Id(ty),
+			Token::Equal,
+			rest @ ..,
+		] => (Some(*id), Some(*ty), rest),
+		[Token::Id(id), Token::Equal, rest @ ..] => (Some(*id), None, rest),
+		[
+			Token::Underscore,
+			Token::Colon,
+			Token::Id(ty),
+			Token::Equal,
+			rest @ ..,
+		] => (None, Some(*ty), rest),
+		rest => (None, None, rest),
+	};
+	let [Token::Word(opcode), operands @ ..] = rest else {
+		return Err("expected an opcode".to_owned());
+	};
+	let opcode: Opcode = opcode.parse()?;
+	let operands = operands
+		.iter()
+		.map(|token| match token {
+			Token::Id(id) => Ok(Operand::IdRef(*id)),
+			Token::Integer(value, Some(32)) => u32::try_from(*value)
+				.map(Operand::LiteralBit32)
+				.map_err(|_| format!("{value} does not fit in u32")),
+			Token::Integer(value, Some(64)) => u64::try_from(*value)
+				.map(Operand::LiteralBit64)
+				.map_err(|_| format!("{value} does not fit in u64")),
+			Token::Integer(value, Some(128)) => Ok(Operand::LiteralBit128(*value)),
+			Token::Integer(value, _) => Err(format!("literal `{value}` needs a width suffix")),
+			Token::Control(control) => Ok(Operand::FunctionControl(*control)),
+			Token::Text(text) => Ok(Operand::Text(text.clone())),
+			Token::Word(word) => match word.as_str() {
+				"String" => Ok(Operand::LiteralString),
+				"Automatic" => Ok(Operand::StorageClass(StorageClass::Automatic)),
+				"Static" => Ok(Operand::StorageClass(StorageClass::Static)),
+				_ => Err(format!("unexpected `{word}`")),
+			},
+			_ => Err("unexpected punctuation".to_owned()),
+		})
+		.collect::<Result<Box<[Operand]>, String>>()?;
+	Ok(Instruction {
+		opcode,
+		result_type,
+		result_id,
+		operands,
+	})
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+	/// `%id`
+	Id(u32),
+	/// Integer with an optional `u32`, `u64` or `u128` suffix
+	Integer(u128, Option<u32>),
+	/// `Control(bits)`
+	Control(u32),
+	Text(String),
+	Word(String),
+	Underscore,
+	Colon,
+	Equal,
+}
+
+struct Lexer<'a> {
+	chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl<'a> Lexer<'a> {
+	fn new(line: &'a str) -> Self {
+		Self {
+			chars: line.chars().peekable(),
+		}
+	}
+	fn tokenize(&mut self) -> Result<Vec<Token>, String> {
+		let mut tokens = vec![];
+		while let Some(&c) = self.chars.peek() {
+			let token = match c {
+				_ if c.is_whitespace() => {
+					self.chars.next();
+					continue;
+				}
+				':' => {
+					self.chars.next();
+					Token::Colon
+				}
+				'=' => {
+					self.chars.next();
+					Token::Equal
+				}
+				'%' => {
+					self.chars.next();
+					let digits = self.take_while(|c| c.is_ascii_digit());
+					Token::Id(
+						digits
+							.parse()
+							.map_err(|_| format!("invalid id `%{digits}`"))?,
+					)
+				}
+				'"' => Token::Text(self.string()?),
+				'0'..='9' => {
+					let digits = self.take_while(|c| c.is_ascii_digit());
+					let value = digits
+						.parse()
+						.map_err(|_| format!("invalid integer `{digits}`"))?;
+					let suffix = self.take_while(|c| c.is_ascii_alphanumeric());
+					let width = match suffix.as_str() {
+						"" => None,
+						"u32" => Some(32),
+						"u64" => Some(64),
+						"u128" => Some(128),
+						_ => return Err(format!("invalid suffix `{suffix}`")),
+					};
+					Token::Integer(value, width)
+				}
+				_ if c.is_ascii_alphabetic() || c == '_' => {
+					let word = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+					if word == "_" {
+						Token::Underscore
+					} else if word == "Control" {
+						self.control()?
+					} else {
+						Token::Word(word)
+					}
+				}
+				_ => return Err(format!("unexpected character `{c}`")),
+			};
+			tokens.push(token);
+		}
+		Ok(tokens)
+	}
+	fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> String {
+		let mut result = String::new();
+		while let Some(c) = self.chars.next_if(|c| predicate(*c)) {
+			result.push(c);
+		}
+		result
+	}
+	fn control(&mut self) -> Result<Token, String> {
+		let error = || "expected `Control(<integer>)`".to_owned();
+		self.chars.next_if_eq(&'(').ok_or_else(error)?;
+		let digits = self.take_while(|c| c.is_ascii_digit());
+		self.chars.next_if_eq(&')').ok_or_else(error)?;
+		digits.parse().map(Token::Control).map_err(|_| error())
+	}
+	/// Reads a string with the escapes produced by `{:?}`
+	fn string(&mut self) -> Result<String, String> {
+		self.chars.next();
+		let mut result = String::new();
+		loop {
+			let c = self.chars.next().ok_or("unterminated string")?;
+			match c {
+				'"' => return Ok(result),
+				'\\' => {
+					let escape = self.chars.next().ok_or("unterminated string")?;
+					result.push(match escape {
+						'n' => '\n',
+						't' => '\t',
+						'r' => '\r',
+						'0' => '\0',
+						'\\' | '"' | '\'' => escape,
+						'u' => {
+							let error = || "invalid unicode escape".to_owned();
+							self.chars.next_if_eq(&'{').ok_or_else(error)?;
+							let digits = self.take_while(|c| c.is_ascii_hexdigit());
+							self.chars.next_if_eq(&'}').ok_or_else(error)?;
+							u32::from_str_radix(&digits, 16)
+								.ok()
+								.and_then(char::from_u32)
+								.ok_or_else(error)?
+						}
+						_ => return Err(format!("unknown escape `\\{escape}`")),
+					});
+				}
+				_ => result.push(c),
+			}
+		}
+	}
+}
@@ -0,0 +1,326 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+
+//! Structural checks of SSA modules.
+//!
+//! Catches malformed input to the tools and bugs in passes: every id is defined
+//! once and below the bound, operands refer to something that exists, branches
+//! target labels of the same function, phis start their block and name each of
+//! its predecessors, and every value is defined before it is used on all paths.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+
+use super::cfg::ControlFlowGraph;
+use super::data::{
+	DataKind,
+	Function,
+	Instruction,
+	Module,
+	Opcode,
+	Operand,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+	DuplicateId(u32),
+	IdOutOfBound(u32),
+	UndefinedId {
+		function: u32,
+		id: u32,
+	},
+	InvalidBranchTarget {
+		function: u32,
+		label: u32,
+	},
+	MisplacedPhi {
+		function: u32,
+		phi: u32,
+	},
+	/// A phi names a block that does not branch to it
+	InvalidPhiPredecessor {
+		function: u32,
+		phi: u32,
+		label: u32,
+	},
+	/// A block branching to the phi has no incoming value in it
+	MissingPhiPredecessor {
+		function: u32,
+		phi: u32,
+		label: u32,
+	},
+	/// A value is used where its definition does not dominate
+	NotDominated {
+		function: u32,
+		id: u32,
+	},
+}
+
+impl fmt::Display for VerifyError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::DuplicateId(id) => write!(f, "%{id} is defined more than once"),
+			Self::IdOutOfBound(id) => write!(f, "%{id} is not below the id bound"),
+			Self::UndefinedId { function, id } => {
+				write!(f, "in %{function}: %{id} is not defined")
+			}
+			Self::InvalidBranchTarget { function, label } => {
+				write!(
+					f,
+					"in %{function}: branch to %{label}, which is not a label"
+				)
+			}
+			Self::MisplacedPhi { function, phi } => {
+				write!(f, "in %{function}: phi %{phi} does not start its block")
+			}
+			Self::InvalidPhiPredecessor {
+				function,
+				phi,
+				label,
+			} => write!(
+				f,
+				"in %{function}: phi %{phi} names %{label}, which is not a predecessor"
+			),
+			Self::MissingPhiPredecessor {
+				function,
+				phi,
+				label,
+			} => write!(
+				f,
+				"in %{function}: phi %{phi} has no value for predecessor %{label}"
+			),
+			Self::NotDominated { function, id } => {
+				write!(f, "in %{function}: %{id} is used before it is defined")
+			}
+		}
+	}
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Checks the whole module, returning every problem found
+pub fn verify(module: &Module) -> Result<(), Vec<VerifyError>> {
+	let mut errors = vec![];
+	let mut defined: HashSet<u32> = HashSet::new();
+	let mut define = |id: u32, errors: &mut Vec<VerifyError>| {
+		if !defined.insert(id) {
+			errors.push(VerifyError::DuplicateId(id));
+		}
+		if id >= module.bound {
+			errors.push(VerifyError::IdOutOfBound(id));
+		}
+	};
+	let all_data = module
+		.section_names()
+		.into_iter()
+		.flat_map(|name| module.sections[&name].iter());
+	for inst in module.type_list.iter() {
+		if let Some(id) = inst.result_id {
+			define(id, &mut errors);
+		}
+	}
+	for data in all_data {
+		match data {
+			DataKind::Data(inst) => {
+				if let Some(id) = inst.result_id {
+					define(id, &mut errors);
+				}
+			}
+			DataKind::Func(func) => {
+				define(func.id(), &mut errors);
+				for inst in func.params.iter().chain(func.body.iter()) {
+					if let Some(id) = inst.result_id {
+						define(id, &mut errors);
+					}
+				}
+			}
+		}
+	}
+	// ids visible everywhere: types, constants, functions and global variables
+	let mut globals: HashSet<u32> = module
+		.type_list
+		.iter()
+		.filter_map(|inst| inst.result_id)
+		.collect();
+	for data in module.sections.values().flatten() {
+		match data {
+			DataKind::Data(inst) => globals.extend(inst.result_id),
+			DataKind::Func(func) => globals.extend([func.id()]),
+		}
+	}
+	for inst in module.type_list.iter() {
+		check_global_refs(inst, &globals, &mut errors);
+	}
+	for data in module.sections.values().flatten() {
+		if let DataKind::Data(inst) = data {
+			check_global_refs(inst, &globals, &mut errors);
+		}
+	}
+	for func in module.functions() {
+		verify_function(module, func, &globals, &mut errors);
+	}
+	if errors.is_empty() {
+		Ok(())
+	} else {
+		Err(errors)
+	}
+}
+
+fn check_global_refs(inst: &Instruction, globals: &HashSet<u32>, errors: &mut Vec<VerifyError>) {
+	// the width of numeric types is stored as an id, debug information may
+	// outlive the variable it describes
+	if matches!(
+		inst.opcode,
+		Opcode::TypeInt | Opcode::TypeFloat | Opcode::DebugVariable
+	) {
+		return;
+	}
+	for id in inst.id_refs() {
+		if !globals.contains(&id) {
+			errors.push(VerifyError::UndefinedId { function: 0, id });
+		}
+	}
+}
+
+fn verify_function(
+	module: &Module,
+	func: &Function,
+	globals: &HashSet<u32>,
+	errors: &mut Vec<VerifyError>,
+) {
+	let function = func.id();
+	let mut bound = module.bound;
+	let body: Vec<Instruction> = func
+		.params
+		.iter()
+		.chain(func.body.iter())
+		.cloned()
+		.collect();
+	let cfg = ControlFlowGraph::new(body, &mut bound);
+	let label_map = cfg.label_map();
+	let idom = cfg.immediate_dominators();
+	let reachable: HashSet<usize> = cfg.reverse_postorder().into_iter().collect();
+
+	// (block, position) of every local definition, params come before every block
+	let mut definitions: HashMap<u32, (Option<usize>, usize)> = HashMap::new();
+	for param in cfg.params.iter() {
+		definitions.insert(param.result_id.unwrap(), (None, 0));
+	}
+	for (index, block) in cfg.blocks.iter().enumerate() {
+		definitions.insert(block.label, (None, 0));
+		for (position, inst) in block.body.iter().enumerate() {
+			if let Some(id) = inst.result_id {
+				definitions.insert(id, (Some(index), position));
+			}
+		}
+	}
+	let dominates = |lhs: usize, mut rhs: usize| loop {
+		if lhs == rhs {
+			return true;
+		}
+		match idom[rhs] {
+			Some(parent) => rhs = parent,
+			None => return false,
+		}
+	};
+	// a use at (block, position) must see its definition on every path
+	let check_use = |id: u32, block: usize, position: usize, errors: &mut Vec<VerifyError>| {
+		if globals.contains(&id) {
+			return;
+		}
+		match definitions.get(&id) {
+			None => errors.push(VerifyError::UndefinedId { function, id }),
+			Some((None, _)) => {}
+			Some(&(Some(def_block), def_position)) => {
+				let is_visible = if def_block == block {
+					def_position < position
+				} else {
+					dominates(def_block, block)
+				};
+				if reachable.contains(&block) && !is_visible {
+					errors.push(VerifyError::NotDominated { function, id });
+				}
+			}
+		}
+	};
+
+	let preds = cfg.predecessors();
+	for (index, block) in cfg.blocks.iter().enumerate() {
+		let mut phis_allowed = true;
+		for (position, inst) in block.body.iter().enumerate() {
+			if inst.opcode == Opcode::Phi {
+				let phi = inst.result_id.unwrap_or_default();
+				if !phis_allowed {
+					errors.push(VerifyError::MisplacedPhi { function, phi });
+				}
+				for pair in inst.operands.chunks(2) {
+					let [Operand::IdRef(value), Operand::IdRef(label)] = pair else {
+						continue;
+					};
+					let pred = label_map
+						.get(label)
+						.filter(|pred| preds[index].contains(pred));
+					match pred {
+						// the value must be available at the end of the predecessor
+						Some(&pred) => check_use(*value, pred, cfg.blocks[pred].body.len(), errors),
+						None => errors.push(VerifyError::InvalidPhiPredecessor {
+							function,
+							phi,
+							label: *label,
+						}),
+					}
+				}
+				let named: HashSet<u32> = inst.id_refs().skip(1).step_by(2).collect();
+				for &pred in preds[index].iter() {
+					let label = cfg.blocks[pred].label;
+					if !named.contains(&label) {
+						errors.push(VerifyError::MissingPhiPredecessor {
+							function,
+							phi,
+							label,
+						});
+					}
+				}
+				continue;
+			}
+			phis_allowed = false;
+			if let Some(targets) = branch_targets(inst) {
+				for label in targets {
+					if !label_map.contains_key(&label) {
+						errors.push(VerifyError::InvalidBranchTarget { function, label });
+					}
+				}
+				if let Some(selector) = inst.id_operand(0).filter(|_| inst.opcode != Opcode::Branch)
+				{
+					check_use(selector, index, position, errors);
+				}
+				continue;
+			}
+			for id in inst.id_refs() {
+				check_use(id, index, position, errors);
+			}
+		}
+	}
+}
+
+/// Labels an instruction may jump to, `None` if it is not a branch
+fn branch_targets(inst: &Instruction) -> Option<Vec<u32>> {
+	let ids = |skip: usize| inst.id_refs().skip(skip).collect();
+	match inst.opcode {
+		Opcode::Branch => Some(ids(0)),
+		Opcode::BranchConditional => Some(ids(1)),
+		Opcode::Switch => Some(
+			inst.operands
+				.iter()
+				.enumerate()
+				.filter(|(index, _)| *index == 1 || (*index > 1 && index % 2 == 1))
+				.filter_map(|(_, operand)| match operand {
+					Operand::IdRef(id) => Some(*id),
+					_ => None,
+				})
+				.collect(),
+		),
+		Opcode::LoopMerge => Some(ids(0)),
+		_ => None,
+	}
+}
@@ -1,8 +1,22 @@
 // Copyright (c) 2024-2026 Jonathan A. Thomason
 use std::{
 	env,
+	io::Write,
 	path::PathBuf,
-	process::Command,
+	process::{
+		Command,
+		Output,
+		Stdio,
+	},
+	sync::atomic::{
+		AtomicUsize,
+		Ordering,
+	},
+	thread,
+	time::{
+		Duration,
+		Instant,
+	},
 };
 
 #[test]
@@ -19,3 +33,1072 @@ fn structs() {
 	println!("stderr:\n{}", String::from_utf8(out.stderr).unwrap());
 	assert!(out.status.success())
 }
+
+/// `_start` of the C tests: calls `main` and halts
+const START: &str = "[global _start]
+_start:
+	CALL main
+	HALT
+";
+
+/// `_start` that calls `main` and then runs `check`, which halts
+fn start_with(check: &str) -> String {
+	format!("[global _start]\n_start:\n\tCALL main\n{check}")
+}
+
+/// Compiles a file of `tests/src` to assembly, assembles it behind `start`
+/// and returns what the VM prints
+fn run_c(file: &str, start: &str) -> String {
+	let out = run_c_with(file, start, &[], &[]);
+	String::from_utf8(out.stdout).unwrap()
+}
+
+/// Like `run_c` with extra arguments for the compiler and the VM, returns
+/// the output of the VM
+fn run_c_with(file: &str, start: &str, cc_args: &[&str], vm_args: &[&str]) -> Output {
+	let binary_path = build_c(file, start, cc_args);
+	let out = Command::new(env!("CARGO_BIN_EXE_stackl-vm"))
+		.args(vm_args)
+		.arg(&binary_path)
+		.output()
+		.unwrap();
+	let _ = std::fs::remove_file(&binary_path);
+	out
+}
+
+/// Assembles `program` and returns what the VM prints when run with
+/// `vm_args`
+fn run_asm(name: &str, program: &str, vm_args: &[&str]) -> String {
+	run_asm_with_input(name, program, vm_args, b"")
+}
+
+/// Like `run_asm` with `input` piped to the VM
+fn run_asm_with_input(name: &str, program: &str, vm_args: &[&str], input: &[u8]) -> String {
+	let out = run_asm_output(name, program, vm_args, input);
+	String::from_utf8(out.stdout).unwrap()
+}
+
+/// Same as `run_asm_with_input` but keeps stderr and the exit status
+fn run_asm_output(name: &str, program: &str, vm_args: &[&str], input: &[u8]) -> Output {
+	let binary_path = build_asm(name, program);
+	let mut vm = Command::new(env!("CARGO_BIN_EXE_stackl-vm"))
+		.args(vm_args)
+		.arg(&binary_path)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.unwrap();
+	vm.stdin.take().unwrap().write_all(input).unwrap();
+	let out = vm.wait_with_output().unwrap();
+	let _ = std::fs::remove_file(&binary_path);
+	out
+}
+
+/// Assembles `program`, returns the path of the binary
+fn build_asm(name: &str, program: &str) -> PathBuf {
+	let asm_path = env::temp_dir().join(format!("stackl-{name}-{}.sl", std::process::id()));
+	let binary_path = asm_path.with_extension("stackl");
+	std::fs::write(&asm_path, program).unwrap();
+	let status = Command::new(env!("CARGO_BIN_EXE_stackl-as"))
+		.arg(&asm_path)
+		.arg("-o")
+		.arg(&binary_path)
+		.status()
+		.unwrap();
+	assert!(status.success());
+	let _ = std::fs::remove_file(&asm_path);
+	binary_path
+}
+
+/// Compiles a file of `tests/src` and returns the assembly
+fn compile_c(file: &str, cc_args: &[&str]) -> String {
+	let compiler_path = PathBuf::from(env!("CARGO_BIN_EXE_stackl-cc"));
+	let source_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+		.join("tests/src")
+		.join(file);
+	let out = Command::new(compiler_path)
+		.arg(&source_path)
+		.arg("--emit=asm")
+		.args(cc_args)
+		.output()
+		.unwrap();
+	println!("stderr:\n{}", String::from_utf8_lossy(&out.stderr));
+	assert!(out.status.success());
+	String::from_utf8(out.stdout).unwrap()
+}
+
+/// Numbers the binaries of `build_c`, tests running at the same time may
+/// build the same file
+static BUILD_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Compiles a file of `tests/src` to assembly and assembles it behind
+/// `start`, returns the path of the binary
+fn build_c(file: &str, start: &str, cc_args: &[&str]) -> PathBuf {
+	let program = compile_c(file, cc_args);
+	let count = BUILD_COUNT.fetch_add(1, Ordering::Relaxed);
+	let asm_path = env::temp_dir().join(format!("stackl-{file}-{}-{count}.sl", std::process::id()));
+	let binary_path = asm_path.with_extension("stackl");
+	std::fs::write(&asm_path, format!("{start}{program}")).unwrap();
+	let status = Command::new(env!("CARGO_BIN_EXE_stackl-as"))
+		.arg(&asm_path)
+		.arg("-o")
+		.arg(&binary_path)
+		.status()
+		.unwrap();
+	assert!(status.success());
+	let _ = std::fs::remove_file(&asm_path);
+	binary_path
+}
+
+/// Compiles `inline_asm.c` and runs it behind a `_start` that prints `ok` if
+/// `main` returns 15
+#[test]
+fn inline_asm() {
+	let start = start_with(
+		"	PUSH 15
+	EQ
+	JZ fail
+	PUSH ok
+	OUTS
+fail:
+	HALT
+ok:
+	DB \"ok\", 0, 0
+",
+	);
+	assert_eq!(run_c("inline_asm.c", &start), "ok");
+}
+
+/// `intrinsics.c` prints `ok` and halts before `main` returns, also once the
+/// optimizer has run
+#[test]
+fn intrinsics() {
+	let start = start_with(
+		"	PUSH bad
+	OUTS
+	HALT
+bad:
+	DB \"bad\", 0
+",
+	);
+	assert_eq!(run_c("intrinsics.c", &start), "ok");
+	let out = run_c_with("intrinsics.c", &start, &["-O1"], &[]);
+	assert_eq!(String::from_utf8(out.stdout).unwrap(), "ok");
+}
+
+/// `handlers.c` traps into its `systrap` handler, which returns with `RTI`
+#[test]
+fn handlers() {
+	assert_eq!(run_c("handlers.c", START), "trap back");
+}
+
+/// The C99 `inline` helpers of `inline.c` are called at -O0, at -O1 they are
+/// inlined and, not being external definitions, dropped
+#[test]
+fn inline_functions() {
+	let text = compile_c("inline.c", &[]);
+	assert!(
+		text.contains("CALL twice") && text.contains("CALL square"),
+		"{text}"
+	);
+	assert_eq!(run_c("inline.c", START), "ok");
+	let text = compile_c("inline.c", &["-O1"]);
+	assert!(!text.contains("CALL") && !text.contains("twice:"), "{text}");
+	let out = run_c_with("inline.c", START, &["-O1"], &[]);
+	assert_eq!(String::from_utf8(out.stdout).unwrap(), "ok");
+}
+
+/// With `-ftrapv` the signed overflow of `trapv.c` stops the program, without
+/// it the sum wraps
+#[test]
+fn trapv() {
+	let out = run_c_with("trapv.c", START, &["-ftrapv"], &[]);
+	let stderr = String::from_utf8(out.stderr).unwrap();
+	assert_eq!(String::from_utf8(out.stdout).unwrap(), "ok");
+	assert!(
+		stderr.starts_with("Machine Check: Overflow at "),
+		"{stderr}"
+	);
+	let out = run_c_with("trapv.c", START, &[], &[]);
+	assert_eq!(String::from_utf8(out.stdout).unwrap(), "ok");
+	assert!(out.stderr.is_empty());
+}
+
+/// Unsigned comparisons use UGT, `long` is added and compared a word
+/// at a time
+#[test]
+fn unsigned_compare() {
+	let out = run_c_with("unsigned.c", START, &[], &[]);
+	assert_eq!(String::from_utf8(out.stdout).unwrap(), "abcde");
+	assert!(out.stderr.is_empty());
+}
+
+/// Unsigned division of words, with divisors on both sides of the top bit
+#[test]
+fn unsigned_division() {
+	assert_eq!(run_c("unsigned_div.c", START), "abc");
+	let out = run_c_with("unsigned_div.c", START, &["-O1"], &[]);
+	assert_eq!(String::from_utf8(out.stdout).unwrap(), "abc");
+}
+
+/// Relational and equality operators yield an `int` that takes part in
+/// arithmetic, with and without the optimizer
+#[test]
+fn comparison_values() {
+	assert_eq!(run_c("compare.c", START), "abc");
+	let out = run_c_with("compare.c", START, &["-O1"], &[]);
+	assert_eq!(String::from_utf8(out.stdout).unwrap(), "abc");
+}
+
+/// Assigning to a local stores to it, also in a loop, and the assignment has
+/// the value stored
+#[test]
+fn assignment() {
+	assert_eq!(run_c("assign.c", START), "abc");
+	let out = run_c_with("assign.c", START, &["-O1"], &[]);
+	assert_eq!(String::from_utf8(out.stdout).unwrap(), "abc");
+}
+
+/// Parameters are values and cannot be assigned to yet
+#[test]
+fn unsupported_parameter_assignment() {
+	let source_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/src/assign_param.c");
+	let out = Command::new(env!("CARGO_BIN_EXE_stackl-cc"))
+		.arg(&source_path)
+		.arg("--emit=asm")
+		.arg("--enable-color=never")
+		.output()
+		.unwrap();
+	assert_eq!(out.status.code(), Some(1));
+	let stderr = String::from_utf8(out.stderr).unwrap();
+	assert!(
+		stderr.contains("assigning to parameter `x` is not supported yet"),
+		"{stderr}"
+	);
+}
+
+/// Integers converted to `_Bool` compare against zero, `int` is sign
+/// extended to `long` and `long` truncated to its low word
+#[test]
+fn integer_conversions() {
+	assert_eq!(run_c("convert.c", START), "abc");
+	let out = run_c_with("convert.c", START, &["-O1"], &[]);
+	assert_eq!(String::from_utf8(out.stdout).unwrap(), "abc");
+}
+
+/// A cast without a lowering is reported instead of stopping the compiler
+#[test]
+fn unsupported_cast() {
+	let source_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/src/explicit_cast.c");
+	let out = Command::new(env!("CARGO_BIN_EXE_stackl-cc"))
+		.arg(&source_path)
+		.arg("--emit=asm")
+		.arg("--enable-color=never")
+		.output()
+		.unwrap();
+	assert_eq!(out.status.code(), Some(1));
+	let stderr = String::from_utf8(out.stderr).unwrap();
+	assert!(
+		stderr.contains("an explicit cast is not supported yet"),
+		"{stderr}"
+	);
+}
+
+/// With `-g` a machine check names the function and line it happened on
+#[test]
+fn debug_info() {
+	let out = run_c_with("debug_info.c", START, &["-g"], &[]);
+	let stderr = String::from_utf8(out.stderr).unwrap();
+	assert!(
+		stderr.contains("in main (") && stderr.contains("debug_info.c:6)"),
+		"{stderr}"
+	);
+}
+
+/// The debugger stops at a source line, reads the locals and walks back into
+/// `_start`
+#[test]
+fn debugger_script() {
+	let script = env::temp_dir().join(format!("stackl-debugger-{}.txt", std::process::id()));
+	std::fs::write(
+		&script,
+		"break debug_info.c:6\ncontinue\nlocals\nbt\nstep\n",
+	)
+	.unwrap();
+	let out = run_c_with(
+		"debug_info.c",
+		START,
+		&["-g"],
+		&["-g", "-x", script.to_str().unwrap()],
+	);
+	let _ = std::fs::remove_file(&script);
+	let stdout = String::from_utf8(out.stdout).unwrap();
+	assert!(stdout.contains("Breakpoint 1 in main ("), "{stdout}");
+	assert!(stdout.contains("=>     44: ILLEGAL"), "{stdout}");
+	assert!(stdout.contains("int factor = 3"), "{stdout}");
+	assert!(stdout.contains("#1 16\n"), "{stdout}");
+	assert!(
+		stdout.contains("Machine Check: Illegal Instruction at 44 in main"),
+		"{stdout}"
+	);
+}
+
+/// Drives `debug_info.c` through the GDB stub: stops at a breakpoint on the
+/// `ILLEGAL` instruction and reports its machine check as SIGILL
+#[cfg(unix)]
+#[test]
+fn gdb_stub() {
+	use std::io::{
+		Read,
+		Write,
+	};
+	use std::os::unix::net::UnixStream;
+	let binary_path = build_c("debug_info.c", START, &[]);
+	let socket = env::temp_dir().join(format!("stackl-gdb-{}.sock", std::process::id()));
+	let mut vm = Command::new(env!("CARGO_BIN_EXE_stackl-vm"))
+		.arg("--gdb")
+		.arg(&socket)
+		.arg(&binary_path)
+		.stdout(Stdio::null())
+		.stderr(Stdio::null())
+		.spawn()
+		.unwrap();
+	let mut stream = (0..500)
+		.find_map(|_| {
+			thread::sleep(Duration::from_millis(10));
+			UnixStream::connect(&socket).ok()
+		})
+		.expect("the stub did not listen");
+	let mut packet = |data: &str| {
+		let checksum = data.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+		write!(stream, "${data}#{checksum:02x}").unwrap();
+		let mut reply = vec![];
+		let mut byte = [0];
+		// skip the ack
+		while stream.read_exact(&mut byte).is_ok() && byte[0] != b'$' {}
+		while stream.read_exact(&mut byte).is_ok() && byte[0] != b'#' {
+			reply.push(byte[0]);
+		}
+		stream.read_exact(&mut [0; 2]).unwrap();
+		stream.write_all(b"+").unwrap();
+		String::from_utf8(reply).unwrap()
+	};
+	assert_eq!(packet("?"), "S05");
+	// IP is the third register
+	assert_eq!(&packet("g")[16..24], "08000000");
+	assert!(packet("qXfer:features:read:target.xml:0,fff").contains("<reg name=\"ivec\""));
+	assert_eq!(packet("Z0,2c,4"), "OK");
+	assert_eq!(packet("c"), "T05swbreak:;");
+	assert_eq!(packet("p2"), "2c000000");
+	// opcode 57 is not an instruction
+	assert_eq!(packet("m2c,4"), "39000000");
+	assert_eq!(packet("c"), "S04");
+	stream.write_all(b"$k#6b").unwrap();
+	assert!(vm.wait().unwrap().success());
+	let _ = std::fs::remove_file(&binary_path);
+}
+
+/// The `BREAK` of `intrinsics.c` stops in the debugger, which continues to
+/// the end of the program
+#[test]
+fn break_instruction() {
+	let script = env::temp_dir().join(format!("stackl-break-{}.txt", std::process::id()));
+	std::fs::write(&script, "continue\ncontinue\n").unwrap();
+	let out = run_c_with(
+		"intrinsics.c",
+		START,
+		&[],
+		&["-g", "-x", script.to_str().unwrap()],
+	);
+	let _ = std::fs::remove_file(&script);
+	let stdout = String::from_utf8(out.stdout).unwrap();
+	let stop = stdout.find("BREAK\n").expect(&stdout);
+	let output = stdout.find("ok").expect(&stdout);
+	assert!(stop < output, "{stdout}");
+	assert!(stdout.contains("Halted at"), "{stdout}");
+}
+
+/// Dumps memory of a user mode program in the debugger, the address goes
+/// through BP the way the program sees it
+#[test]
+fn debugger_dump_translates() {
+	let program = "[global _start]
+_start:
+	PUSH user
+	POPREG BP
+	PUSH 100000
+	POPREG LP
+	JMPUSER 0
+user:
+	BREAK
+	DB \"user\", 0
+";
+	let script = env::temp_dir().join(format!("stackl-dump-{}.txt", std::process::id()));
+	std::fs::write(&script, "continue\nx 4 4\n").unwrap();
+	let out = run_asm_output(
+		"debugger_dump",
+		program,
+		&["-g", "-x", script.to_str().unwrap()],
+		b"",
+	);
+	let _ = std::fs::remove_file(&script);
+	let stdout = String::from_utf8(out.stdout).unwrap();
+	assert!(stdout.contains("00000004: 75 73 65 72  "), "{stdout}");
+}
+
+/// The timer interrupts a spinning program after LIMIT instructions, when
+/// TIME has counted every instruction since boot
+#[test]
+fn timer() {
+	let program = "[global _start]
+_start:
+	PUSH vectors
+	POPREG IVEC
+	PUSH 100
+	PUSH 0x0C000008
+	POPVARIND
+	PUSH 1
+	PUSH 0x0C000000
+	POPVARIND
+spin:
+	JMP spin
+timer_isr:
+	PUSH 0x0C00000C
+	PUSHVARIND
+	PUSH 107
+	EQ
+	JZ late
+	PUSH ok
+	OUTS
+late:
+	HALT
+vectors:
+	DD -1, -1, -1, timer_isr
+ok:
+	DB \"ok\", 0
+";
+	assert_eq!(run_asm("timer", program, &[]), "ok");
+}
+
+/// Writes block 0 of the disk while polling STATUS, then reads block 1 into
+/// memory and prints it from the completion interrupt
+const DISK_PROGRAM: &str = "[global _start]
+_start:
+	PUSH vectors
+	POPREG IVEC
+	PUSH data
+	PUSH 0x0D000008
+	POPVARIND
+	PUSH 0
+	PUSH 0x0D00000C
+	POPVARIND
+	PUSH 2
+	PUSH 0x0D000004
+	POPVARIND
+wait:
+	PUSH 0x0D000000
+	PUSHVARIND
+	PUSH 0x20000008
+	BAND
+	JZ wait
+	PUSH 0x0D000000
+	PUSHVARIND
+	PUSH 0x20000000
+	BAND
+	JZ read
+	PUSH read_only
+	OUTS
+read:
+	PUSH 100000
+	PUSH 0x0D000008
+	POPVARIND
+	PUSH 1
+	PUSH 0x0D00000C
+	POPVARIND
+	PUSH -2147483647
+	PUSH 0x0D000004
+	POPVARIND
+spin:
+	JMP spin
+disk_isr:
+	PUSH 100000
+	OUTS
+	HALT
+vectors:
+	DD -1, -1, disk_isr
+data:
+	DB \"written\", 0
+read_only:
+	DB \"read-only \", 0
+";
+
+#[test]
+fn disk() {
+	let image = env::temp_dir().join(format!("stackl-disk-{}.img", std::process::id()));
+	let mut content = vec![0; 1024];
+	content[512..516].copy_from_slice(b"read");
+	std::fs::write(&image, &content).unwrap();
+	let image_arg = image.to_str().unwrap();
+	let output = run_asm("disk", DISK_PROGRAM, &["--disk", image_arg]);
+	assert_eq!(output, "read");
+	assert!(std::fs::read(&image).unwrap().starts_with(b"written\0"));
+
+	std::fs::write(&image, &content).unwrap();
+	let output = run_asm(
+		"disk-ro",
+		DISK_PROGRAM,
+		&["--disk", image_arg, "--read-only"],
+	);
+	assert_eq!(output, "read-only read");
+	assert_eq!(std::fs::read(&image).unwrap(), content);
+	let _ = std::fs::remove_file(&image);
+}
+
+/// Echoes piped input through pio_term from its receive interrupt until `!`
+#[test]
+fn pio_term() {
+	let program = "[feature pio_term]
+[global _start]
+_start:
+	PUSH vectors
+	POPREG IVEC
+	PUSH 2
+	PUSH 0x0E000001
+	POPCVARIND
+spin:
+	JMP spin
+pio_isr:
+	PUSH 0x0E000000
+	PUSHCVARIND
+	DUP
+	PUSH 33
+	EQ
+	JZ echo
+	HALT
+echo:
+	PUSH 0x0E000000
+	POPCVARIND
+	RTI
+vectors:
+	DD -1, -1, -1, -1, -1, pio_isr
+";
+	assert_eq!(run_asm_with_input("pio_term", program, &[], b"hi!"), "hi");
+}
+
+/// Counts down 10000 times at 1 MHz, which can not take less than 50 ms
+#[test]
+fn mhz() {
+	let program = "[global _start]
+_start:
+	PUSH 10000
+loop:
+	PUSH 1
+	SUB
+	DUP
+	JZ done
+	JMP loop
+done:
+	HALT
+";
+	let started = Instant::now();
+	let out = run_asm_output("mhz", program, &["--mhz", "1", "--stats"], b"");
+	assert!(started.elapsed() >= Duration::from_millis(50));
+	let stats = String::from_utf8(out.stderr).unwrap();
+	assert!(stats.starts_with("50001 instructions, 50001 cycles in "));
+	assert!(stats.trim_end().ends_with(" MHz)"));
+}
+
+/// Maps a page through the page table, then faults on an unmapped one whose
+/// handler maps it and returns to the faulting load
+#[test]
+fn vmem() {
+	let program = "[global _start]
+_start:
+	PUSH vectors
+	POPREG IVEC
+	PUSH 0x11003
+	PUSH 0x10000
+	POPVARIND
+	PUSH 3
+	PUSH 0x11000
+	POPVARIND
+	PUSH 0x11003
+	PUSH 0x11044
+	POPVARIND
+	PUSH 0x12003
+	PUSH 0x11400
+	POPVARIND
+	PUSH 0
+	PUSH 0x11800
+	POPVARIND
+	PUSH 0x10000
+	POPREG PTBR
+	PUSHREG FLAG
+	PUSH 16
+	BOR
+	POPREG FLAG
+	PUSH 0x6B6F
+	PUSH 0x100000
+	POPVARIND
+	PUSH 0x100000
+	OUTS
+	PUSH 0x200000
+	PUSHVARIND
+	POP
+	PUSH 0x200000
+	OUTS
+	HALT
+fault_isr:
+	PUSH 0x12003
+	PUSHREG FAULT
+	PUSH 10
+	SHR
+	PUSH 0x11000
+	ADD
+	POPVARIND
+	PUSH bang
+	OUTS
+	RTI
+bang:
+	DD 33
+vectors:
+	DD -1, -1, -1, -1, -1, -1, fault_isr
+";
+	assert_eq!(run_asm("vmem", program, &[]), "ok!ok");
+}
+
+/// ADDV raises OVF and carries on after the handler with the wrapped sum,
+/// without a handler the check stops the program at the instruction
+#[test]
+fn overflow() {
+	let program = "[global _start]
+_start:
+	PUSH vectors
+	POPREG IVEC
+	PUSH 2147483647
+	PUSH 1
+	ADDV
+	PUSH -2147483648
+	EQ
+	JZ done
+	PUSH ok
+	OUTS
+done:
+	HALT
+check_isr:
+	PUSHREG FLAG
+	PUSH 0x4000
+	BAND
+	JZ other
+	PUSH ovf
+	OUTS
+other:
+	RTI
+ok:
+	DD 0x6B6F
+ovf:
+	DD 0x76
+vectors:
+	DD check_isr
+";
+	assert_eq!(run_asm("overflow", program, &[]), "vok");
+	let program = "[global _start]
+_start:
+	PUSH -2147483648
+	PUSH -1
+	DIV
+	HALT
+";
+	let out = run_asm_output("overflow_div", program, &[], b"");
+	let stderr = String::from_utf8(out.stderr).unwrap();
+	assert_eq!(stderr, "Machine Check: Overflow at 24\n");
+}
+
+/// ADD and SUB leave their carry in FLAG for ADC and SBB, ULT, UGT and USHR
+/// treat their operands as unsigned
+#[test]
+fn carry() {
+	let program = "[global _start]
+_start:
+	PUSH -1
+	PUSH 1
+	ADD
+	POP
+	PUSHREG FLAG
+	PUSH 64
+	BAND
+	PUSH 64
+	EQ
+	PUSH 5
+	PUSH 6
+	ADC
+	PUSH 12
+	EQ
+	AND
+	PUSH 0
+	PUSH 1
+	SUB
+	POP
+	PUSH 10
+	PUSH 3
+	SBB
+	PUSH 6
+	EQ
+	AND
+	PUSHREG FLAG
+	PUSH 64
+	BAND
+	NOT
+	AND
+	PUSH -1
+	PUSH 1
+	UGT
+	AND
+	PUSH 1
+	PUSH -1
+	ULT
+	AND
+	PUSH -8
+	PUSH 28
+	USHR
+	PUSH 15
+	EQ
+	AND
+	JZ done
+	PUSH ok
+	OUTS
+done:
+	HALT
+ok:
+	DD 0x6B6F
+";
+	assert_eq!(run_asm("carry", program, &[]), "ok");
+}
+
+/// Remaps a page behind the TLB's back, the old frame stays in use until
+/// FLUSHTLB
+#[test]
+fn vmem_tlb() {
+	let program = "[global _start]
+_start:
+	PUSH 0x11003
+	PUSH 0x10000
+	POPVARIND
+	PUSH 3
+	PUSH 0x11000
+	POPVARIND
+	PUSH 0x11003
+	PUSH 0x11044
+	POPVARIND
+	PUSH 0x12003
+	PUSH 0x11408
+	POPVARIND
+	PUSH 0x41
+	PUSH 0x12000
+	POPVARIND
+	PUSH 0x42
+	PUSH 0x13000
+	POPVARIND
+	PUSH 0x10000
+	POPREG PTBR
+	PUSHREG FLAG
+	PUSH 16
+	BOR
+	POPREG FLAG
+	PUSH 0x102000
+	OUTS
+	PUSH 0x13003
+	PUSH 0x11408
+	POPVARIND
+	PUSH 0x102000
+	OUTS
+	FLUSHTLB
+	PUSH 0x102000
+	OUTS
+	HALT
+";
+	assert_eq!(run_asm("vmem_tlb", program, &["--tlb", "16"]), "AAB");
+	assert_eq!(run_asm("vmem_tlb", program, &[]), "ABB");
+}
+
+/// Polls pio_term for piped input with IID_RECV enabled, interrupts disabled
+/// and the trace on, two runs must execute the same instructions
+#[test]
+fn pio_term_deterministic() {
+	let program = "[feature pio_term]
+[global _start]
+_start:
+	SEID
+	PUSH 2
+	PUSH 0x0E000001
+	POPCVARIND
+poll:
+	PUSH 0x0E000002
+	PUSHCVARIND
+	PUSH 2
+	BAND
+	JZ poll
+	PUSH 0x0E000000
+	PUSHCVARIND
+	DUP
+	PUSH 0x0E000000
+	POPCVARIND
+	PUSH 33
+	EQ
+	JZ poll
+	HALT
+";
+	let first = run_asm_output("pio_term_deterministic", program, &["--trace"], b"abc!");
+	let second = run_asm_output("pio_term_deterministic", program, &["--trace"], b"abc!");
+	assert_eq!(String::from_utf8_lossy(&first.stdout), "abc!");
+	assert!(!first.stderr.is_empty());
+	assert_eq!(first.stderr, second.stderr);
+}
+
+/// Polls pio_term while the input pipe stays open and empty, reading IIR
+/// must not wait for a character
+#[test]
+fn pio_term_poll() {
+	let program = "[feature pio_term]
+[global _start]
+_start:
+	PUSH 0x0E000002
+	PUSHCVARIND
+	PUSH 0x0E000002
+	PUSHCVARIND
+	PUSH polled
+	OUTS
+	HALT
+polled:
+	DB \"polled\", 0
+";
+	let binary_path = build_asm("pio_term_poll", program);
+	let mut vm = Command::new(env!("CARGO_BIN_EXE_stackl-vm"))
+		.arg(&binary_path)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.spawn()
+		.unwrap();
+	let deadline = Instant::now() + Duration::from_secs(10);
+	while vm.try_wait().unwrap().is_none() && Instant::now() < deadline {
+		thread::sleep(Duration::from_millis(10));
+	}
+	let is_done = vm.try_wait().unwrap().is_some();
+	let _ = vm.kill();
+	let out = vm.wait_with_output().unwrap();
+	let _ = std::fs::remove_file(&binary_path);
+	assert!(is_done, "reading IIR waited for input");
+	assert_eq!(String::from_utf8(out.stdout).unwrap(), "polled");
+}
+
+/// Receives a line of piped input through dma_term and sends it back once the
+/// receive interrupts
+#[test]
+fn dma_term() {
+	let program = "[feature dma_term]
+[global _start]
+_start:
+	PUSH vectors
+	POPREG IVEC
+	PUSH 100000
+	PUSH 0x0F000008
+	POPVARIND
+	PUSH 16
+	PUSH 0x0F00000C
+	POPVARIND
+	PUSH -2147483643
+	PUSH 0x0F000004
+	POPVARIND
+spin:
+	JMP spin
+dma_isr:
+	PUSH 100000
+	PUSH 0x0F000014
+	POPVARIND
+	PUSH 0x0F000010
+	PUSHVARIND
+	PUSH 0x0F000018
+	POPVARIND
+	PUSH 2
+	PUSH 0x0F000004
+	POPVARIND
+wait:
+	PUSH 0x0F000000
+	PUSHVARIND
+	PUSH 8
+	BAND
+	JZ wait
+	HALT
+vectors:
+	DD -1, -1, -1, -1, dma_isr
+";
+	let output = run_asm_with_input("dma_term", program, &[], b"hello\nworld\n");
+	assert_eq!(output, "hello\n");
+}
+
+/// Runs every gen_io operation but EXEC, the last one from its interrupt
+#[test]
+fn gen_io() {
+	let program = "[feature gen_io]
+[global _start]
+_start:
+	PUSH vectors
+	POPREG IVEC
+	PUSH 72
+	PUSH 0x0B000004
+	POPVARIND
+	PUSH 2
+	PUSH 0x0B000000
+	POPVARIND
+wait_printc:
+	PUSH 0x0B000000
+	PUSHVARIND
+	PUSH -2147483648
+	BAND
+	JZ wait_printc
+	PUSH 100000
+	PUSH 0x0B000004
+	POPVARIND
+	PUSH 16
+	PUSH 0x0B000008
+	POPVARIND
+	PUSH 3
+	PUSH 0x0B000000
+	POPVARIND
+wait_getl:
+	PUSH 0x0B000000
+	PUSHVARIND
+	PUSH -2147483648
+	BAND
+	JZ wait_getl
+	PUSH 1
+	PUSH 0x0B000000
+	POPVARIND
+wait_prints:
+	PUSH 0x0B000000
+	PUSHVARIND
+	PUSH -2147483648
+	BAND
+	JZ wait_prints
+	PUSH 4
+	PUSH 0x0B000000
+	POPVARIND
+wait_error:
+	PUSH 0x0B000000
+	PUSHVARIND
+	PUSH -2147483648
+	BAND
+	JZ wait_error
+	PUSH 0x0B000000
+	PUSHVARIND
+	PUSH 0x40000000
+	BAND
+	JZ geti
+	PUSH error
+	OUTS
+geti:
+	PUSH 100100
+	PUSH 0x0B000004
+	POPVARIND
+	PUSH 0x00010004
+	PUSH 0x0B000000
+	POPVARIND
+spin:
+	JMP spin
+gen_io_isr:
+	PUSH 100100
+	PUSHVARIND
+	PUSH 42
+	EQ
+	JZ done
+	PUSH answer
+	OUTS
+done:
+	HALT
+vectors:
+	DD -1, -1, -1, -1, -1, -1, -1, -1, gen_io_isr
+error:
+	DB \"E\", 0
+answer:
+	DB \"42\", 0
+";
+	let output = run_asm_with_input("gen_io", program, &[], b"line\nx\n42\n");
+	assert_eq!(output, "Hline\nE42");
+}
+
+/// EXECs a small binary, which loads past the base address, and then a file
+/// that does not exist, which sets ERR
+#[test]
+fn gen_io_exec() {
+	let child = build_asm("gen_io_child", "[global _start]\n_start:\n\tHALT\n");
+	let program = format!(
+		"[feature gen_io]
+[global _start]
+_start:
+	PUSH binary
+	PUSH 0x0B000004
+	POPVARIND
+	PUSH 120000
+	PUSH 0x0B000008
+	POPVARIND
+	PUSH 5
+	PUSH 0x0B000000
+	POPVARIND
+wait_exec:
+	PUSH 0x0B000000
+	PUSHVARIND
+	PUSH -2147483648
+	BAND
+	JZ wait_exec
+	PUSH 0x0B000000
+	PUSHVARIND
+	PUSH 0x40000000
+	BAND
+	JZ exec_done
+	JMP missing
+exec_done:
+	PUSH 0x0B00000C
+	PUSHVARIND
+	PUSH 120000
+	GT
+	JZ missing
+	PUSH loaded
+	OUTS
+missing:
+	PUSH nowhere
+	PUSH 0x0B000004
+	POPVARIND
+	PUSH 5
+	PUSH 0x0B000000
+	POPVARIND
+wait_missing:
+	PUSH 0x0B000000
+	PUSHVARIND
+	PUSH -2147483648
+	BAND
+	JZ wait_missing
+	PUSH 0x0B000000
+	PUSHVARIND
+	PUSH 0x40000000
+	BAND
+	JZ done
+	PUSH error
+	OUTS
+done:
+	HALT
+binary:
+	DB \"{path}\", 0
+nowhere:
+	DB \"{path}.missing\", 0
+loaded:
+	DB \"L\", 0
+error:
+	DB \"E\", 0
+",
+		path = child.display()
+	);
+	let output = run_asm("gen_io_exec", &program, &[]);
+	let _ = std::fs::remove_file(&child);
+	assert_eq!(output, "LE");
+}
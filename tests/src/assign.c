@@ -0,0 +1,19 @@
+int main(void)
+{
+	int a;
+	int b = 1;
+	int i = 0;
+	a = 3;
+	a = a + 4;
+	do {
+		b = b * 2;
+		i = i + 1;
+	} while (i < 5);
+	if (a == 7)
+		__builtin_stackl_outs("a");
+	if ((b = 5) + b == 10)
+		__builtin_stackl_outs("b");
+	if (i == 5)
+		__builtin_stackl_outs("c");
+	return 0;
+}
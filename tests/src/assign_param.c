@@ -0,0 +1,10 @@
+int next(int x)
+{
+	x = x + 1;
+	return x;
+}
+
+int main(void)
+{
+	return next(1);
+}
@@ -0,0 +1,14 @@
+int main(void)
+{
+	int a = 1;
+	int b = 2;
+	int less = a < b;
+	int sum = (a < b) + (b < a) + (a == a) + 1;
+	if (less == 1)
+		__builtin_stackl_outs("a");
+	if (sum == 3)
+		__builtin_stackl_outs("b");
+	if ((a >= b) * 5 + (b != a) == 1)
+		__builtin_stackl_outs("c");
+	return 0;
+}
@@ -0,0 +1,16 @@
+int main(void)
+{
+	_Bool yes = 7;
+	_Bool no = 0;
+	long wide = 5;
+	int narrow = 4294967303L;
+	if (yes)
+		__builtin_stackl_outs("a");
+	if (no)
+		__builtin_stackl_outs("x");
+	if (wide == 5L)
+		__builtin_stackl_outs("b");
+	if (narrow == 7)
+		__builtin_stackl_outs("c");
+	return 0;
+}
@@ -0,0 +1,8 @@
+int total = 4;
+
+int main(void)
+{
+	int factor = 3;
+	__asm__ ("ILLEGAL");
+	return factor;
+}
@@ -0,0 +1,5 @@
+int main(void)
+{
+	float f = 1.0f;
+	return (int)f;
+}
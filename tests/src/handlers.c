@@ -0,0 +1,17 @@
+__attribute__((systrap)) void on_trap(void)
+{
+	__builtin_stackl_outs("trap ");
+}
+
+__attribute__((interrupt)) static void on_interrupt(void)
+{
+	__builtin_stackl_halt();
+}
+
+int main(void)
+{
+	__builtin_stackl_trap();
+	__builtin_stackl_outs("back");
+	__builtin_stackl_halt();
+	return 0;
+}
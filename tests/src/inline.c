@@ -0,0 +1,16 @@
+static inline int twice(int x)
+{
+	return x + x;
+}
+
+inline int square(int x)
+{
+	return x * x;
+}
+
+int main(void)
+{
+	if (twice(3) + square(3) == 15)
+		__builtin_stackl_outs("ok");
+	return 0;
+}
@@ -0,0 +1,12 @@
+int scale = 3;
+
+int main(void)
+{
+	int x;
+	int y;
+	__asm__ volatile ("PUSH 5\n\tPOPVAR %0" : "=m"(x));
+	__asm__ ("PUSH %[scale]\n\tPUSHVARIND\n\tMUL" : "=r"(y) : [scale] "m"(scale), "r"(x));
+	__asm__ ("JMP .skip%=\n\tHALT\n.skip%=:");
+	__asm__ ("PUSHREG SP\n\tPOPREG SP");
+	return y;
+}
@@ -0,0 +1,11 @@
+int main(void)
+{
+	__builtin_stackl_set_reg(__STACKL_SP__, __builtin_stackl_get_reg(__STACKL_SP__));
+	__builtin_stackl_set_int_dis();
+	__builtin_stackl_clr_int_dis();
+	__builtin_stackl_trace(0);
+	__builtin_stackl_break();
+	__builtin_stackl_outs("ok");
+	__builtin_stackl_halt();
+	return __builtin_stackl_get_reg(__STACKL_FLAG__);
+}
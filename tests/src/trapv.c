@@ -0,0 +1,6 @@
+int main(void)
+{
+	__builtin_stackl_outs("ok");
+	int sum = 2147483647 + 1;
+	return sum;
+}
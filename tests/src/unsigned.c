@@ -0,0 +1,20 @@
+int main(void)
+{
+	unsigned int big = 4000000000u;
+	unsigned int one = 1u;
+	long wide = 4294967295L;
+	long next = wide + 1L;
+	if (big > one)
+		__builtin_stackl_outs("a");
+	if (one < big)
+		__builtin_stackl_outs("b");
+	if (big >= big)
+		__builtin_stackl_outs("c");
+	if (next > wide)
+		__builtin_stackl_outs("d");
+	if (next - 1L == wide)
+		__builtin_stackl_outs("e");
+	if (big <= one)
+		__builtin_stackl_outs("x");
+	return 0;
+}
@@ -0,0 +1,13 @@
+int main(void)
+{
+	unsigned int max = 4294967295u;
+	unsigned int ten = 10u;
+	unsigned int big = 2147483649u;
+	if (max / ten == 429496729u)
+		__builtin_stackl_outs("a");
+	if (max - max / ten * ten == 5u)
+		__builtin_stackl_outs("b");
+	if (max / big == 1u)
+		__builtin_stackl_outs("c");
+	return 0;
+}
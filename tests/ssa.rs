@@ -0,0 +1,1318 @@
+// Copyright (c) 2024-2026 Jonathan A. Thomason
+use std::process::Command;
+
+use stackl::ssa::builder::Builder;
+use stackl::ssa::codegen;
+use stackl::ssa::data::{
+	DataKind,
+	Instruction,
+	Module,
+	Opcode,
+	Operand,
+	StorageClass,
+	function_control,
+};
+use stackl::ssa::interp::{
+	Interpreter,
+	Trap,
+	Value,
+};
+use stackl::ssa::opt;
+use stackl::ssa::verify::{
+	VerifyError,
+	verify,
+};
+
+fn function_body(module: &Module) -> &[Instruction] {
+	let DataKind::Func(func) = &module.sections[".code"][0] else {
+		panic!("expected a function");
+	};
+	&func.body
+}
+
+fn count(body: &[Instruction], opcode: Opcode) -> usize {
+	body.iter().filter(|inst| inst.opcode == opcode).count()
+}
+
+#[test]
+fn mem2reg_diamond() {
+	let mut builder = Builder::new();
+	let int_ty = builder.type_int(32, true);
+	let bool_ty = builder.type_bool();
+	let func_ty = builder.type_function(int_ty, &[bool_ty]).unwrap();
+	let one = builder.constant_bit32(int_ty, 1);
+	let two = builder.constant_bit32(int_ty, 2);
+	let (then_label, else_label, join_label) = (builder.id(), builder.id(), builder.id());
+
+	builder.function_begin(func_ty, 0).unwrap();
+	let cond = builder.function_parameter(bool_ty).unwrap();
+	let var = builder
+		.variable(int_ty, StorageClass::Automatic, None)
+		.unwrap();
+	builder
+		.branch_conditional(cond, then_label, else_label)
+		.unwrap();
+	builder.label(then_label).unwrap();
+	builder.store(var, one).unwrap();
+	builder.branch(join_label).unwrap();
+	builder.label(else_label).unwrap();
+	builder.store(var, two).unwrap();
+	builder.branch(join_label).unwrap();
+	builder.label(join_label).unwrap();
+	let value = builder.load(int_ty, var).unwrap();
+	builder.ret_val(value).unwrap();
+	builder.function_end().unwrap();
+
+	let mut module = builder.build();
+	opt::mem2reg(&mut module);
+	let body = function_body(&module);
+	assert_eq!(count(body, Opcode::Variable), 0);
+	assert_eq!(count(body, Opcode::Load), 0);
+	assert_eq!(count(body, Opcode::Store), 0);
+	let phi = body.iter().find(|inst| inst.opcode == Opcode::Phi).unwrap();
+	let incoming: Vec<u32> = phi.id_refs().collect();
+	assert_eq!(incoming, [one, then_label, two, else_label]);
+	let ret = body.last().unwrap();
+	assert_eq!(ret.opcode, Opcode::RetValue);
+	assert_eq!(ret.id_operand(0), phi.result_id);
+}
+
+#[test]
+fn mem2reg_keeps_escaping_variable() {
+	let mut builder = Builder::new();
+	let int_ty = builder.type_int(32, true);
+	let ptr_ty = builder.type_pointer(int_ty);
+	let func_ty = builder.type_function(int_ty, &[]).unwrap();
+	let one = builder.constant_bit32(int_ty, 1);
+
+	builder.function_begin(func_ty, 0).unwrap();
+	let var = builder
+		.variable(int_ty, StorageClass::Automatic, Some(one))
+		.unwrap();
+	let alias = builder
+		.variable(ptr_ty, StorageClass::Automatic, None)
+		.unwrap();
+	builder.store(alias, var).unwrap();
+	let value = builder.load(int_ty, var).unwrap();
+	builder.ret_val(value).unwrap();
+	builder.function_end().unwrap();
+
+	let mut module = builder.build();
+	opt::mem2reg(&mut module);
+	let body = function_body(&module);
+	// `var` has its address taken, `alias` is promoted
+	assert_eq!(count(body, Opcode::Variable), 1);
+	assert_eq!(count(body, Opcode::Load), 1);
+	assert_eq!(count(body, Opcode::Store), 0);
+}
+
+#[test]
+fn mem2reg_lifetime_end_is_undef() {
+	let mut builder = Builder::new();
+	let int_ty = builder.type_int(32, true);
+	let func_ty = builder.type_function(int_ty, &[]).unwrap();
+	let one = builder.constant_bit32(int_ty, 1);
+
+	builder.function_begin(func_ty, 0).unwrap();
+	let var = builder
+		.variable(int_ty, StorageClass::Automatic, Some(one))
+		.unwrap();
+	builder.lifetime_end(var).unwrap();
+	let value = builder.load(int_ty, var).unwrap();
+	builder.ret_val(value).unwrap();
+	builder.function_end().unwrap();
+
+	let mut module = builder.build();
+	opt::mem2reg(&mut module);
+	let body = function_body(&module);
+	let undef = body
+		.iter()
+		.find(|inst| inst.opcode == Opcode::Undef)
+		.unwrap();
+	assert_eq!(body.last().unwrap().id_operand(0), undef.result_id);
+}
+
+fn constant_value(module: &Module, id: u32) -> Option<u32> {
+	let inst = module
+		.type_list
+		.iter()
+		.find(|inst| inst.result_id == Some(id))?;
+	match inst.operands.first() {
+		Some(stackl::ssa::data::Operand::LiteralBit32(value)) => Some(*value),
+		_ => None,
+	}
+}
+
+#[test]
+fn sccp_removes_dead_branch() {
+	let mut builder = Builder::new();
+	let int_ty = builder.type_int(32, true);
+	let bool_ty = builder.type_bool();
+	let func_ty = builder.type_function(int_ty, &[]).unwrap();
+	let zero = builder.constant_bit32(int_ty, 0);
+	let one = builder.constant_bit32(int_ty, 1);
+	let (then_label, join_label) = (builder.id(), builder.id());
+
+	// if (0 == 1) x = 1; return x + 1;
+	builder.function_begin(func_ty, 0).unwrap();
+	let var = builder
+		.variable(int_ty, StorageClass::Automatic, Some(zero))
+		.unwrap();
+	let cond = builder.i_equal(bool_ty, zero, one).unwrap();
+	builder
+		.branch_conditional(cond, then_label, join_label)
+		.unwrap();
+	builder.label(then_label).unwrap();
+	builder.store(var, one).unwrap();
+	builder.branch(join_label).unwrap();
+	builder.label(join_label).unwrap();
+	let value = builder.load(int_ty, var).unwrap();
+	let sum = builder.i_add(int_ty, value, one).unwrap();
+	builder.ret_val(sum).unwrap();
+	builder.function_end().unwrap();
+
+	let mut module = builder.build();
+	opt::optimize(&mut module);
+	let body = function_body(&module);
+	assert_eq!(count(body, Opcode::BranchConditional), 0);
+	assert_eq!(count(body, Opcode::Phi), 0);
+	assert_eq!(count(body, Opcode::Label), 1);
+	let ret = body.last().unwrap();
+	assert_eq!(ret.opcode, Opcode::RetValue);
+	assert_eq!(constant_value(&module, ret.id_operand(0).unwrap()), Some(1));
+}
+
+#[test]
+fn sccp_signed_division_edge_cases() {
+	let mut builder = Builder::new();
+	let int_ty = builder.type_int(32, true);
+	let func_ty = builder.type_function(int_ty, &[]).unwrap();
+	let min = builder.constant_bit32(int_ty, i32::MIN as u32);
+	let minus_one = builder.constant_bit32(int_ty, -1i32 as u32);
+	let zero = builder.constant_bit32(int_ty, 0);
+
+	builder.function_begin(func_ty, 0).unwrap();
+	let quotient = builder.s_div(int_ty, min, minus_one).unwrap();
+	let remainder = builder.s_rem(int_ty, min, minus_one).unwrap();
+	let by_zero = builder.s_div(int_ty, quotient, zero).unwrap();
+	let sum = builder.i_add(int_ty, remainder, by_zero).unwrap();
+	builder.ret_val(sum).unwrap();
+	builder.function_end().unwrap();
+
+	let mut module = builder.build();
+	opt::sccp(&mut module);
+	let body = function_body(&module);
+	// MIN / -1 wraps to MIN, MIN % -1 is 0 and division by zero is kept
+	assert_eq!(count(body, Opcode::SRem), 0);
+	let division = body
+		.iter()
+		.find(|inst| inst.opcode == Opcode::SDiv)
+		.unwrap();
+	assert_eq!(division.id_operand(0), Some(min));
+	assert_eq!(division.id_operand(1), Some(zero));
+	let add = body
+		.iter()
+		.find(|inst| inst.opcode == Opcode::IAdd)
+		.unwrap();
+	assert_eq!(add.id_operand(0), Some(zero));
+}
+
+/// Conversions fold at the width of their operand
+#[test]
+fn sccp_folds_conversions() {
+	let mut builder = Builder::new();
+	let char_ty = builder.type_int(8, true);
+	let int_ty = builder.type_int(32, true);
+	let func_ty = builder.type_function(int_ty, &[]).unwrap();
+	let minus_one = builder.constant_bit32(char_ty, 0xFF);
+
+	builder.function_begin(func_ty, 0).unwrap();
+	let signed = builder.s_convert(int_ty, minus_one).unwrap();
+	let unsigned = builder.u_convert(int_ty, minus_one).unwrap();
+	let sum = builder.i_add(int_ty, signed, unsigned).unwrap();
+	builder.ret_val(sum).unwrap();
+	builder.function_end().unwrap();
+
+	let mut module = builder.build();
+	opt::sccp(&mut module);
+	let body = function_body(&module);
+	assert_eq!(count(body, Opcode::SConvert), 0);
+	assert_eq!(count(body, Opcode::UConvert), 0);
+	let ret = body.last().unwrap();
+	// -1 + 255
+	assert_eq!(
+		constant_value(&module, ret.id_operand(0).unwrap()),
+		Some(254)
+	);
+}
+
+/// A chain of arithmetic folds to the same ids on every run and only its
+/// result is left as a constant
+#[test]
+fn sccp_is_deterministic() {
+	let fold_chain = || {
+		let mut builder = Builder::new();
+		let int_ty = builder.type_int(32, true);
+		let func_ty = builder.type_function(int_ty, &[]).unwrap();
+		let one = builder.constant_bit32(int_ty, 1);
+
+		builder.function_begin(func_ty, 0).unwrap();
+		let mut value = one;
+		for _ in 0..6 {
+			let sum = builder.i_add(int_ty, value, one).unwrap();
+			value = builder.i_mul(int_ty, sum, sum).unwrap();
+		}
+		builder.ret_val(value).unwrap();
+		builder.function_end().unwrap();
+
+		let mut module = builder.build();
+		opt::sccp(&mut module);
+		module
+	};
+	let module = fold_chain();
+	let text = module_text(&module);
+	for _ in 0..5 {
+		assert_eq!(module_text(&fold_chain()), text);
+	}
+	let constants = module
+		.type_list
+		.iter()
+		.filter(|inst| inst.opcode == Opcode::Constant)
+		.count();
+	assert_eq!(constants, 1, "{text}");
+}
+
+/// Functions trapping on overflow keep the arithmetic that overflows for the
+/// machine to report
+#[test]
+fn sccp_keeps_trapping_overflow() {
+	let mut builder = Builder::new();
+	let int_ty = builder.type_int(32, true);
+	let func_ty = builder.type_function(int_ty, &[]).unwrap();
+	let max = builder.constant_bit32(int_ty, i32::MAX as u32);
+	let one = builder.constant_bit32(int_ty, 1);
+
+	builder
+		.function_begin(func_ty, function_control::TRAP_OVERFLOW)
+		.unwrap();
+	let fits = builder.i_sub(int_ty, max, one).unwrap();
+	let overflows = builder.i_add(int_ty, fits, one).unwrap();
+	let sum = builder.i_add(int_ty, overflows, one).unwrap();
+	builder.ret_val(sum).unwrap();
+	builder.function_end().unwrap();
+
+	let mut module = builder.build();
+	opt::sccp(&mut module);
+	let body = function_body(&module);
+	assert_eq!(count(body, Opcode::ISub), 0);
+	let add = body
+		.iter()
+		.find(|inst| inst.opcode == Opcode::IAdd)
+		.unwrap();
+	assert_eq!(add.id_operand(0), Some(max));
+	assert_eq!(count(body, Opcode::IAdd), 1);
+}
+
+#[test]
+fn dce_removes_write_only_variable() {
+	let mut builder = Builder::new();
+	let int_ty = builder.type_int(32, true);
+	let void_ty = builder.type_void();
+	let array_ty = builder.type_array(int_ty, 4);
+	let func_ty = builder.type_function(void_ty, &[int_ty]).unwrap();
+
+	builder.function_begin(func_ty, 0).unwrap();
+	let param = builder.function_parameter(int_ty).unwrap();
+	let var = builder
+		.variable(array_ty, StorageClass::Automatic, None)
+		.unwrap();
+	let unused = builder.i_mul(int_ty, param, param).unwrap();
+	builder.store(var, unused).unwrap();
+	builder.ret().unwrap();
+	builder.function_end().unwrap();
+
+	let mut module = builder.build();
+	opt::dce(&mut module);
+	let body = function_body(&module);
+	let opcodes: Vec<Opcode> = body.iter().map(|inst| inst.opcode).collect();
+	assert_eq!(
+		opcodes,
+		[Opcode::FunctionParameter, Opcode::Label, Opcode::Ret]
+	);
+}
+
+/// `ext` is only declared, `f` calls it
+const DECLARATION_IR: &str = r#"
+%0 = TypeInt %32 1u32
+%1 = TypeFunction %0 %0
+Name %10 "ext"
+Name %20 "f"
+
+section ".code"
+%10: %1 = Function Control(0)
+	%11: %0 = FunctionParameter
+FunctionEnd
+%20: %1 = Function Control(0)
+	%21: %0 = FunctionParameter
+%22 = Label
+	%23: %0 = FunctionCall %10 %21
+	RetValue %23
+FunctionEnd
+"#;
+
+#[test]
+fn optimize_keeps_declarations() {
+	let mut module = stackl::ssa::text::parse_module(DECLARATION_IR).unwrap();
+	opt::optimize(&mut module);
+	let ext = module
+		.functions()
+		.find(|func| module.name_of(func.id()) == Some("ext"))
+		.unwrap();
+	assert!(ext.is_declaration(), "{}", module_text(&module));
+	let text = assemble(&module, codegen::Schedule::Stack);
+	assert!(!text.contains("ext:"), "{text}");
+	assert!(text.contains("CALL ext"), "{text}");
+}
+
+#[test]
+fn inline_small_functions() {
+	let mut builder = Builder::new();
+	let int_ty = builder.type_int(32, true);
+	let bool_ty = builder.type_bool();
+	let max_ty = builder.type_function(int_ty, &[int_ty, int_ty]).unwrap();
+	let main_ty = builder.type_function(int_ty, &[int_ty]).unwrap();
+	let two = builder.constant_bit32(int_ty, 2);
+	let (lhs_label, rhs_label) = (builder.id(), builder.id());
+
+	// static inline int max(int a, int b) { if (a > b) return a; return b; }
+	let control = function_control::INLINE | function_control::INTERNAL;
+	let max = builder.function_begin(max_ty, control).unwrap();
+	let a = builder.function_parameter(int_ty).unwrap();
+	let b = builder.function_parameter(int_ty).unwrap();
+	let cond = builder.s_greater_than(bool_ty, a, b).unwrap();
+	builder
+		.branch_conditional(cond, lhs_label, rhs_label)
+		.unwrap();
+	builder.label(lhs_label).unwrap();
+	builder.ret_val(a).unwrap();
+	builder.label(rhs_label).unwrap();
+	builder.ret_val(b).unwrap();
+	builder.function_end().unwrap();
+
+	// int twice(int x) { return max(x, 2) + max(2, x); }
+	builder.function_begin(main_ty, 0).unwrap();
+	let x = builder.function_parameter(int_ty).unwrap();
+	let first = builder.function_call(int_ty, max, [x, two]).unwrap();
+	let second = builder.function_call(int_ty, max, [two, x]).unwrap();
+	let sum = builder.i_add(int_ty, first, second).unwrap();
+	builder.ret_val(sum).unwrap();
+	builder.function_end().unwrap();
+
+	let mut module = builder.build();
+	opt::inline_functions(&mut module);
+	// the static inline function is no longer referenced
+	assert_eq!(module.sections[".code"].len(), 1);
+	let body = function_body(&module);
+	assert_eq!(count(body, Opcode::FunctionCall), 0);
+	assert_eq!(count(body, Opcode::FunctionParameter), 1);
+	let phis: Vec<&Instruction> = body
+		.iter()
+		.filter(|inst| inst.opcode == Opcode::Phi)
+		.collect();
+	assert_eq!(phis.len(), 2);
+	assert_eq!(phis[0].id_operand(0), Some(x));
+	assert_eq!(phis[0].id_operand(2), Some(two));
+	let add = body
+		.iter()
+		.find(|inst| inst.opcode == Opcode::IAdd)
+		.unwrap();
+	assert_eq!(add.id_operand(0), phis[0].result_id);
+	assert_eq!(add.id_operand(1), phis[1].result_id);
+}
+
+/// A callee that can return without a value leaves the result of the call
+/// undefined on those paths
+#[test]
+fn inline_return_without_value() {
+	let mut builder = Builder::new();
+	let int_ty = builder.type_int(32, true);
+	let bool_ty = builder.type_bool();
+	let func_ty = builder.type_function(int_ty, &[int_ty]).unwrap();
+	let zero = builder.constant_bit32(int_ty, 0);
+	let (then_label, else_label) = (builder.id(), builder.id());
+	let control = function_control::INLINE | function_control::INTERNAL;
+
+	// static inline int positive(int a) { if (a > 0) return a; }
+	let positive = builder.function_begin(func_ty, control).unwrap();
+	let a = builder.function_parameter(int_ty).unwrap();
+	let cond = builder.s_greater_than(bool_ty, a, zero).unwrap();
+	builder
+		.branch_conditional(cond, then_label, else_label)
+		.unwrap();
+	builder.label(then_label).unwrap();
+	builder.ret_val(a).unwrap();
+	builder.label(else_label).unwrap();
+	builder.ret().unwrap();
+	builder.function_end().unwrap();
+
+	// static inline int nothing(int a) { return; }
+	let nothing = builder.function_begin(func_ty, control).unwrap();
+	builder.function_parameter(int_ty).unwrap();
+	builder.ret().unwrap();
+	builder.function_end().unwrap();
+
+	// int both(int x) { return positive(x) + nothing(x); }
+	builder.function_begin(func_ty, 0).unwrap();
+	let x = builder.function_parameter(int_ty).unwrap();
+	let first = builder.function_call(int_ty, positive, [x]).unwrap();
+	let second = builder.function_call(int_ty, nothing, [x]).unwrap();
+	let sum = builder.i_add(int_ty, first, second).unwrap();
+	builder.ret_val(sum).unwrap();
+	builder.function_end().unwrap();
+
+	let mut module = builder.build();
+	opt::inline_functions(&mut module);
+	assert_eq!(verify(&module), Ok(()));
+	let body = function_body(&module);
+	assert_eq!(count(body, Opcode::FunctionCall), 0);
+	assert_eq!(count(body, Opcode::Undef), 2);
+	let phi = body.iter().find(|inst| inst.opcode == Opcode::Phi).unwrap();
+	assert_eq!(phi.result_id, Some(first));
+	assert_eq!(phi.id_operand(0), Some(x));
+	let undef = body
+		.iter()
+		.find(|inst| inst.opcode == Opcode::Undef && inst.result_id != Some(second))
+		.unwrap();
+	assert_eq!(phi.id_operand(2), undef.result_id);
+}
+
+#[test]
+fn inline_skips_recursive_and_dont_inline() {
+	let mut builder = Builder::new();
+	let int_ty = builder.type_int(32, true);
+	let func_ty = builder.type_function(int_ty, &[int_ty]).unwrap();
+
+	let recursive = builder.function_begin(func_ty, 0).unwrap();
+	let param = builder.function_parameter(int_ty).unwrap();
+	let value = builder.function_call(int_ty, recursive, [param]).unwrap();
+	builder.ret_val(value).unwrap();
+	builder.function_end().unwrap();
+
+	let control = function_control::DONT_INLINE | function_control::INTERNAL;
+	let opaque = builder.function_begin(func_ty, control).unwrap();
+	let param = builder.function_parameter(int_ty).unwrap();
+	builder.ret_val(param).unwrap();
+	builder.function_end().unwrap();
+
+	builder.function_begin(func_ty, 0).unwrap();
+	let param = builder.function_parameter(int_ty).unwrap();
+	let lhs = builder.function_call(int_ty, recursive, [param]).unwrap();
+	let rhs = builder.function_call(int_ty, opaque, [lhs]).unwrap();
+	builder.ret_val(rhs).unwrap();
+	builder.function_end().unwrap();
+
+	let mut module = builder.build();
+	opt::inline_functions(&mut module);
+	let calls: usize = module
+		.functions()
+		.map(|func| count(&func.body, Opcode::FunctionCall))
+		.sum();
+	assert_eq!(module.functions().count(), 3);
+	assert_eq!(calls, 3);
+}
+
+/// int factorial(int n) { int acc = 1; while (n > 1) { acc = acc * n; n = n - 1; } return acc; }
+fn factorial_module() -> Module {
+	let mut builder = Builder::new();
+	let int_ty = builder.type_int(32, true);
+	let bool_ty = builder.type_bool();
+	let func_ty = builder.type_function(int_ty, &[int_ty]).unwrap();
+	let one = builder.constant_bit32(int_ty, 1);
+	let (head, body, exit) = (builder.id(), builder.id(), builder.id());
+
+	let func = builder.function_begin(func_ty, 0).unwrap();
+	builder.name(func, "factorial");
+	let param = builder.function_parameter(int_ty).unwrap();
+	let n = builder
+		.variable(int_ty, StorageClass::Automatic, Some(param))
+		.unwrap();
+	let acc = builder
+		.variable(int_ty, StorageClass::Automatic, Some(one))
+		.unwrap();
+	builder.branch(head).unwrap();
+	builder.label(head).unwrap();
+	let n_value = builder.load(int_ty, n).unwrap();
+	let cond = builder.s_greater_than(bool_ty, n_value, one).unwrap();
+	builder.branch_conditional(cond, body, exit).unwrap();
+	builder.label(body).unwrap();
+	let acc_value = builder.load(int_ty, acc).unwrap();
+	let n_value = builder.load(int_ty, n).unwrap();
+	let product = builder.i_mul(int_ty, acc_value, n_value).unwrap();
+	builder.store(acc, product).unwrap();
+	let next = builder.i_sub(int_ty, n_value, one).unwrap();
+	builder.store(n, next).unwrap();
+	builder.branch(head).unwrap();
+	builder.label(exit).unwrap();
+	let result = builder.load(int_ty, acc).unwrap();
+	builder.ret_val(result).unwrap();
+	builder.function_end().unwrap();
+	builder.build()
+}
+
+#[test]
+fn interp_matches_optimized() {
+	let module = factorial_module();
+	let mut optimized = factorial_module();
+	opt::optimize(&mut optimized);
+	assert_eq!(count(function_body(&optimized), Opcode::Load), 0);
+	for n in [0, 1, 5, 13] {
+		let args = [Value::Int(n)];
+		let expected = Interpreter::new(&module)
+			.unwrap()
+			.run("factorial", &args)
+			.unwrap();
+		let actual = Interpreter::new(&optimized)
+			.unwrap()
+			.run("factorial", &args)
+			.unwrap();
+		assert_eq!(expected, actual);
+	}
+	// 13! wraps at 32 bits
+	let result = Interpreter::new(&module)
+		.unwrap()
+		.run("factorial", &[Value::Int(13)])
+		.unwrap();
+	assert_eq!(result, Value::Int(6227020800 & 0xffff_ffff));
+}
+
+#[test]
+fn interp_traps_and_hooks() {
+	let mut builder = Builder::new();
+	let int_ty = builder.type_int(32, true);
+	let func_ty = builder.type_function(int_ty, &[int_ty]).unwrap();
+	let snippet = builder.assembler("TRAP".to_owned());
+
+	let divide = builder.function_begin(func_ty, 0).unwrap();
+	builder.name(divide, "divide");
+	let param = builder.function_parameter(int_ty).unwrap();
+	let value = builder.s_div(int_ty, param, param).unwrap();
+	builder.ret_val(value).unwrap();
+	builder.function_end().unwrap();
+
+	let trap = builder.function_begin(func_ty, 0).unwrap();
+	builder.name(trap, "trap");
+	let param = builder.function_parameter(int_ty).unwrap();
+	let value = builder.function_call(int_ty, snippet, [param]).unwrap();
+	builder.ret_val(value).unwrap();
+	builder.function_end().unwrap();
+
+	// returns a value of `trap`, which has none in this frame
+	let stale = builder.function_begin(func_ty, 0).unwrap();
+	builder.name(stale, "stale");
+	builder.function_parameter(int_ty).unwrap();
+	builder.ret_val(value).unwrap();
+	builder.function_end().unwrap();
+	let module = builder.build();
+
+	let mut interp = Interpreter::new(&module).unwrap();
+	assert_eq!(
+		interp.run("stale", &[Value::Int(1)]),
+		Err(Trap::UnknownValue(value))
+	);
+	for args in [&[][..], &[Value::Int(1), Value::Int(2)]] {
+		assert_eq!(
+			interp.run("divide", args),
+			Err(Trap::ArgumentCount {
+				function: divide,
+				expected: 1,
+				found: args.len()
+			})
+		);
+	}
+	assert_eq!(
+		interp.run("divide", &[Value::Int(0)]),
+		Err(Trap::DivideByZero)
+	);
+	assert_eq!(
+		interp.run("trap", &[Value::Int(1)]),
+		Err(Trap::Assembler("TRAP".to_owned()))
+	);
+	assert_eq!(
+		interp.run("main", &[]),
+		Err(Trap::UnknownFunction("main".to_owned()))
+	);
+	interp.on_assembler(|_, text, args| {
+		assert_eq!(text, "TRAP");
+		Ok(Value::Int(args[0].as_int().unwrap() + 1))
+	});
+	assert_eq!(interp.run("trap", &[Value::Int(1)]), Ok(Value::Int(2)));
+}
+
+#[test]
+fn dot_labels_conditional_edges() {
+	let module = factorial_module();
+	let mut text = String::new();
+	stackl::ssa::dot::write_module(&mut text, &module).unwrap();
+	assert!(text.starts_with("digraph \"factorial\" {"));
+	assert_eq!(text.matches("[label=\"true\"]").count(), 1);
+	assert_eq!(text.matches("[label=\"false\"]").count(), 1);
+	assert!(text.contains("SGreaterThan"));
+	assert!(text.trim_end().ends_with('}'));
+}
+
+fn module_text(module: &Module) -> String {
+	let mut text = String::new();
+	stackl::ssa::text::write_module(&mut text, module).unwrap();
+	text
+}
+
+#[test]
+fn text_and_binary_round_trip() {
+	let mut module = factorial_module();
+	opt::optimize(&mut module);
+	let text = module_text(&module);
+	let parsed = stackl::ssa::text::parse_module(&text).unwrap();
+	assert_eq!(module_text(&parsed), text);
+	assert_eq!(parsed.bound, module.bound);
+
+	let bytes = stackl::ssa::binary::encode(&module);
+	assert!(stackl::ssa::binary::is_binary(&bytes));
+	let decoded = stackl::ssa::binary::decode(&bytes).unwrap();
+	assert_eq!(module_text(&decoded), text);
+	assert_eq!(
+		stackl::ssa::binary::decode(&bytes[..bytes.len() - 1]).unwrap_err(),
+		stackl::ssa::binary::DecodeError::UnexpectedEof
+	);
+
+	let error = stackl::ssa::text::parse_module("%0 = TypeInt %32 1u32\n%1 = Bogus\n").unwrap_err();
+	assert_eq!(error.line, 2);
+}
+
+#[test]
+fn verify_reports_errors() {
+	let module = factorial_module();
+	assert_eq!(verify(&module), Ok(()));
+	let text = r#"
+%0 = TypeInt %32 1u32
+%1 = TypeBool
+%2 = TypeFunction %0 %1
+section ".code"
+%3: %2 = Function Control(0)
+	%4: %1 = FunctionParameter
+%5 = Label
+	BranchConditional %4 %6 %7
+%6 = Label
+	%8: %0 = Undef
+	Branch %7
+%7 = Label
+	%9: %0 = Phi %8 %6 %8 %3
+	RetValue %8
+	Branch %10
+FunctionEnd
+"#;
+	let module = stackl::ssa::text::parse_module(text).unwrap();
+	let errors = verify(&module).unwrap_err();
+	assert_eq!(
+		errors,
+		[
+			VerifyError::InvalidPhiPredecessor {
+				function: 3,
+				phi: 9,
+				label: 3
+			},
+			VerifyError::MissingPhiPredecessor {
+				function: 3,
+				phi: 9,
+				label: 5
+			},
+			VerifyError::NotDominated { function: 3, id: 8 },
+			VerifyError::InvalidBranchTarget {
+				function: 3,
+				label: 10
+			},
+		]
+	);
+}
+
+/// Assembles the program behind a `_start` that checks the result of a call,
+/// runs it and returns the output with the number of instructions executed
+fn run_call(program: &str, name: &str, call: &str, expected: i32) -> (String, usize) {
+	let start = format!(
+		"[global _start]
+_start:
+{call}
+	PUSH {expected}
+	EQ
+	JZ fail
+	PUSH ok
+	OUTS
+	HALT
+fail:
+	PUSH bad
+	OUTS
+	HALT
+ok:
+	DB \"ok\", 0, 0
+bad:
+	DB \"bad\", 0
+"
+	);
+	let dir = std::env::temp_dir();
+	let source = dir.join(format!("stackl-codegen-{}-{name}.sl", std::process::id()));
+	let binary = source.with_extension("stackl");
+	std::fs::write(&source, format!("{start}{program}")).unwrap();
+	let status = Command::new(env!("CARGO_BIN_EXE_stackl-as"))
+		.arg(&source)
+		.arg("-o")
+		.arg(&binary)
+		.status()
+		.unwrap();
+	assert!(status.success());
+	let out = Command::new(env!("CARGO_BIN_EXE_stackl-vm"))
+		.arg("--trace")
+		.arg(&binary)
+		.output()
+		.unwrap();
+	let _ = std::fs::remove_file(&source);
+	let _ = std::fs::remove_file(&binary);
+	// every executed instruction is a trace line starting with the flags
+	let executed = String::from_utf8(out.stderr)
+		.unwrap()
+		.lines()
+		.filter(|line| line.split(' ').next().is_some_and(|flag| flag.len() == 8))
+		.count();
+	(String::from_utf8(out.stdout).unwrap(), executed)
+}
+
+fn run_factorial(program: &str, name: &str) -> (String, usize) {
+	run_call(
+		program,
+		name,
+		"\tPUSH 5\n\tCALL factorial\n\tPOPARGS 4",
+		120,
+	)
+}
+
+fn assemble(module: &Module, schedule: codegen::Schedule) -> String {
+	let program = codegen::emit_with(module, schedule).unwrap();
+	let mut text = String::new();
+	codegen::write_program(&mut text, &program).unwrap();
+	text
+}
+
+#[test]
+fn codegen_runs_on_vm() {
+	let mut optimized = factorial_module();
+	opt::optimize(&mut optimized);
+	for (module, name) in [(factorial_module(), "naive"), (optimized, "optimized")] {
+		for schedule in [codegen::Schedule::Naive, codegen::Schedule::Stack] {
+			let text = assemble(&module, schedule);
+			assert!(text.contains("[global factorial]"));
+			let (out, _) = run_factorial(&text, &format!("{name}-{schedule:?}"));
+			assert_eq!(out, "ok");
+		}
+	}
+}
+
+/// `f(a, b)` mixes narrow values, operands on the stack in reverse order,
+/// values used twice and unused values
+const SCHEDULE_IR: &str = r#"
+%0 = TypeInt %32 1u32
+%1 = TypeBool
+%2 = TypeFunction %0 %0 %0
+%8 = TypeInt %8 0u32
+%9 = TypeInt %8 1u32
+%4: %0 = Constant 200u32
+%5: %8 = Constant 250u32
+%6: %9 = Constant 3u32
+Name %30 "f"
+
+section ".code"
+%30: %2 = Function Control(0)
+	%31: %0 = FunctionParameter
+	%32: %0 = FunctionParameter
+%33 = Label
+	%34: %0 = Variable Automatic %31
+	%35: %0 = Variable Automatic %32
+	%36: %8 = Variable Automatic %5
+	%37: %9 = Variable Automatic %6
+	%38: %0 = Load %35
+	%39: %0 = Load %34
+	%40: %0 = ISub %39 %38
+	%41: %0 = Load %34
+	%42: %0 = Load %35
+	%43: %0 = ISub %42 %41
+	%44: %8 = Load %36
+	%45: %9 = Load %37
+	%46: %9 = SNeg %45
+	%47: %1 = UGreaterThan %44 %4
+	%48: %8 = IAdd %44 %44
+	%49: %0 = IAdd %41 %42
+	%50: %1 = SGreaterThan %46 %45
+	%51: %0 = ISub %43 %41
+	%52: %8 = UDiv %48 %6
+	Store %36 %52
+	%53: %8 = Load %36
+	BranchConditional %47 %60 %70
+%60 = Label
+	%61: %0 = ISub %51 %53
+	%62: %0 = IAdd %61 %40
+	RetValue %62
+%70 = Label
+	RetValue %4
+FunctionEnd
+"#;
+
+/// Runs both schedules on the VM, the stack schedule must be smaller and faster
+#[test]
+fn stack_schedule_beats_naive() {
+	let mut optimized = factorial_module();
+	opt::optimize(&mut optimized);
+	let schedule_module = stackl::ssa::text::parse_module(SCHEDULE_IR).unwrap();
+	// (40 - 5 - 40) - (250 * 2 % 256) / 3 + (40 - 5)
+	let f_call = "\tPUSH 40\n\tPUSH 5\n\tCALL f\n\tPOPARGS 8";
+	let cases = [
+		(factorial_module(), "factorial", None),
+		(optimized, "optimized", None),
+		(schedule_module, "mixed", Some((f_call, -121))),
+	];
+	for (module, name, call) in cases {
+		let mut results = vec![];
+		for schedule in [codegen::Schedule::Naive, codegen::Schedule::Stack] {
+			let text = assemble(&module, schedule);
+			let size = text.lines().filter(|line| line.starts_with('\t')).count();
+			let name = format!("bench-{name}-{schedule:?}");
+			let (out, executed) = match call {
+				Some((call, expected)) => run_call(&text, &name, call, expected),
+				None => run_factorial(&text, &name),
+			};
+			assert_eq!(out, "ok", "{name}");
+			results.push((size, executed));
+		}
+		let [(naive_size, naive_executed), (stack_size, stack_executed)] = results[..] else {
+			unreachable!();
+		};
+		assert!(
+			stack_size < naive_size,
+			"{name}: {stack_size} >= {naive_size}"
+		);
+		assert!(
+			stack_executed < naive_executed,
+			"{name}: {stack_executed} >= {naive_executed}"
+		);
+	}
+	let text = assemble(
+		&stackl::ssa::text::parse_module(SCHEDULE_IR).unwrap(),
+		codegen::Schedule::Stack,
+	);
+	for opcode in ["SWAP", "DUP", "POP"] {
+		assert!(
+			text.lines().any(|line| line.trim() == opcode),
+			"{opcode} is not used"
+		);
+	}
+}
+
+/// `u(a)` carries and borrows between the words of 64-bit values, passes one
+/// through a phi and compares them, then shifts and compares `a` unsigned
+const WIDE_IR: &str = r#"
+%0 = TypeInt %32 0u32
+%1 = TypeBool
+%2 = TypeFunction %0 %0
+%3 = TypeInt %64 0u32
+%4 = TypeInt %64 1u32
+%5: %3 = Constant 4294967295u64
+%6: %3 = Constant 1u64
+%7: %3 = Constant 4294967296u64
+%8: %4 = Constant 1u64
+%9: %0 = Constant 28u32
+%10: %0 = Constant 1u32
+%11: %0 = Constant 0u32
+Name %30 "u"
+
+section ".code"
+%30: %2 = Function Control(0)
+	%31: %0 = FunctionParameter
+%32 = Label
+	%33: %3 = Variable Automatic %5
+	%34: %3 = Load %33
+	%35: %3 = IAdd %34 %6
+	Store %33 %35
+	%36: %3 = Load %33
+	%37: %1 = IEqual %36 %7
+	BranchConditional %37 %40 %90
+%40 = Label
+	%41: %3 = ISub %36 %6
+	%42: %1 = UGreaterThan %41 %36
+	BranchConditional %42 %90 %50
+%50 = Label
+	%51: %3 = Phi %41 %40
+	%52: %1 = UGreaterThan %36 %51
+	%53: %4 = SNeg %8
+	%54: %1 = SGreaterThan %8 %53
+	%55: %1 = LogicalAnd %52 %54
+	BranchConditional %55 %60 %90
+%60 = Label
+	%61: %1 = UGreaterThan %31 %10
+	BranchConditional %61 %70 %90
+%70 = Label
+	%71: %0 = LogicalShiftRight %31 %9
+	RetValue %71
+%90 = Label
+	RetValue %11
+FunctionEnd
+"#;
+
+#[test]
+fn codegen_lowers_wide_and_unsigned() {
+	let module = stackl::ssa::text::parse_module(WIDE_IR).unwrap();
+	let mut optimized = stackl::ssa::text::parse_module(WIDE_IR).unwrap();
+	opt::optimize(&mut optimized);
+	for (module, name) in [(module, "wide"), (optimized, "wide-optimized")] {
+		for schedule in [codegen::Schedule::Naive, codegen::Schedule::Stack] {
+			let text = assemble(&module, schedule);
+			let name = format!("{name}-{schedule:?}");
+			// 0xFFFFFFF8 >> 28
+			let call = "\tPUSH -8\n\tCALL u\n\tPOPARGS 4";
+			let (out, _) = run_call(&text, &name, call, 15);
+			assert_eq!(out, "ok", "{name}\n{text}");
+		}
+	}
+	let text = assemble(
+		&stackl::ssa::text::parse_module(WIDE_IR).unwrap(),
+		codegen::Schedule::Stack,
+	);
+	for opcode in ["ADC", "SBB", "UGT", "USHR"] {
+		assert!(
+			text.lines().any(|line| line.trim() == opcode),
+			"{opcode} is not used"
+		);
+	}
+}
+
+/// Unsigned division and remainder of words, `q(n, d)` is `n / d * 7 + n % d`
+/// and `k(n)` divides by constants on both sides of the top bit
+const UDIV_IR: &str = r#"
+%0 = TypeInt %32 0u32
+%1 = TypeFunction %0 %0 %0
+%2 = TypeFunction %0 %0
+%3: %0 = Constant 7u32
+%4: %0 = Constant 2147483649u32
+%5: %0 = Constant 3u32
+%6: %0 = Constant 10u32
+Name %20 "q"
+Name %30 "k"
+
+section ".code"
+%20: %1 = Function Control(0)
+	%21: %0 = FunctionParameter
+	%22: %0 = FunctionParameter
+%23 = Label
+	%24: %0 = UDiv %21 %22
+	%25: %0 = IMul %24 %3
+	%26: %0 = URem %21 %22
+	%27: %0 = IAdd %25 %26
+	RetValue %27
+FunctionEnd
+%30: %2 = Function Control(0)
+	%31: %0 = FunctionParameter
+%32 = Label
+	%33: %0 = UDiv %31 %4
+	%34: %0 = UDiv %31 %5
+	%35: %0 = URem %31 %6
+	%36: %0 = IAdd %33 %34
+	%37: %0 = IAdd %36 %35
+	RetValue %37
+FunctionEnd
+"#;
+
+#[test]
+fn codegen_lowers_word_unsigned_division() {
+	let module = stackl::ssa::text::parse_module(UDIV_IR).unwrap();
+	let text = assemble(&module, codegen::Schedule::Stack);
+	let cases: [(u32, u32); 6] = [
+		(7, 2),
+		(u32::MAX, 1),
+		(u32::MAX, 10),
+		(0x8000_0000, 3),
+		(0xFFFF_FFF0, 0x8000_0001),
+		(5, 0xFFFF_FFFF),
+	];
+	for (index, (n, d)) in cases.into_iter().enumerate() {
+		let call = format!(
+			"\tPUSH {}\n\tPUSH {}\n\tCALL q\n\tPOPARGS 8",
+			n as i32, d as i32
+		);
+		let expected = (n / d).wrapping_mul(7).wrapping_add(n % d);
+		let (out, _) = run_call(&text, &format!("udiv-{index}"), &call, expected as i32);
+		assert_eq!(out, "ok", "{n} / {d}\n{text}");
+	}
+	for n in [0u32, 10, 0x8000_0001, u32::MAX] {
+		let call = format!("\tPUSH {}\n\tCALL k\n\tPOPARGS 4", n as i32);
+		let expected = (n / 0x8000_0001).wrapping_add(n / 3).wrapping_add(n % 10);
+		let (out, _) = run_call(&text, &format!("udiv-k-{n}"), &call, expected as i32);
+		assert_eq!(out, "ok", "k({n})\n{text}");
+	}
+}
+
+/// Converts `n` to `long` and shifts it by constant and variable amounts on
+/// both sides of a word, `s(-8)` adds up to -246
+const SHIFT_IR: &str = r#"
+%0 = TypeInt %32 1u32
+%1 = TypeBool
+%2 = TypeFunction %0 %0
+%3 = TypeInt %64 1u32
+%4 = TypeInt %64 0u32
+%5: %0 = Constant 36u32
+%6: %0 = Constant 4u32
+%7: %0 = Constant 44u32
+%8: %0 = Constant 12u32
+%9: %0 = Constant 41u32
+%10: %0 = Constant 8u32
+Name %30 "s"
+
+section ".code"
+%30: %2 = Function Control(0)
+	%31: %0 = FunctionParameter
+%32 = Label
+	%33: %3 = SConvert %31
+	%34: %3 = ArithmeticShiftLeft %33 %5
+	%35: %0 = IAdd %31 %7
+	%36: %3 = ArithmeticShiftRight %34 %35
+	%37: %1 = IEqual %36 %33
+	%38: %4 = UConvert %31
+	%39: %0 = IAdd %31 %8
+	%40: %4 = LogicalShiftLeft %38 %39
+	%41: %4 = LogicalShiftRight %40 %6
+	%42: %1 = IEqual %41 %38
+	%43: %0 = UConvert %40
+	%44: %0 = IAdd %31 %9
+	%45: %4 = LogicalShiftRight %40 %44
+	%46: %0 = UConvert %45
+	%47: %0 = IAdd %31 %10
+	%48: %3 = ArithmeticShiftLeft %33 %47
+	%49: %1 = IEqual %48 %33
+	%50: %3 = SConvert %39
+	%51: %0 = LogicalShiftLeft %31 %50
+	%52: %0 = UConvert %37
+	%53: %0 = UConvert %42
+	%54: %0 = UConvert %49
+	%55: %0 = IAdd %43 %46
+	%56: %0 = IAdd %55 %52
+	%57: %0 = IAdd %56 %53
+	%58: %0 = IAdd %57 %54
+	%59: %0 = IAdd %58 %51
+	RetValue %59
+FunctionEnd
+"#;
+
+#[test]
+fn codegen_lowers_wide_conversions_and_shifts() {
+	let module = stackl::ssa::text::parse_module(SHIFT_IR).unwrap();
+	let mut optimized = stackl::ssa::text::parse_module(SHIFT_IR).unwrap();
+	opt::optimize(&mut optimized);
+	for (module, name) in [(module, "shift"), (optimized, "shift-optimized")] {
+		for schedule in [codegen::Schedule::Naive, codegen::Schedule::Stack] {
+			let text = assemble(&module, schedule);
+			let name = format!("{name}-{schedule:?}");
+			let call = "\tPUSH -8\n\tCALL s\n\tPOPARGS 4";
+			let (out, _) = run_call(&text, &name, call, -246);
+			assert_eq!(out, "ok", "{name}\n{text}");
+		}
+	}
+	// the interpreter agrees
+	let module = stackl::ssa::text::parse_module(SHIFT_IR).unwrap();
+	let result = Interpreter::new(&module)
+		.unwrap()
+		.run("s", &[Value::Int(-8i32 as u32 as u128)])
+		.unwrap();
+	assert_eq!(result, Value::Int(-246i32 as u32 as u128));
+}
+
+/// `g(a)` doubles a variable in place, adds `a + 10` with a snippet that
+/// returns a value and jumps over a `HALT` with a local label
+const ASSEMBLER_IR: &str = r#"
+%0 = TypeInt %32 1u32
+%1 = TypeVoid
+%2 = TypeFunction %0 %0
+%3: %0 = Constant 10u32
+%4 = Assembler "PUSHVAR %0\nPUSH 2\nMUL\nPOPVAR %0"
+%5 = Assembler "PUSHVAR %0\nADD"
+%6 = Assembler "PUSHREG SP\nPOPREG 3 ; 100%% the same\nJMP .skip%=\nHALT\n.skip%=:"
+%7 = Assembler "PUSHVAR %0\nPOP"
+Name %30 "g"
+
+section ".code"
+%30: %2 = Function Control(0)
+	%31: %0 = FunctionParameter
+%32 = Label
+	%33: %0 = Variable Automatic %31
+	%34: %1 = FunctionCall %4 %33
+	%35: %0 = FunctionCall %5 %31 %3
+	%36: %1 = FunctionCall %6
+	%37: %0 = Load %33
+	%38: %0 = IAdd %35 %37
+	RetValue %38
+FunctionEnd
+"#;
+
+#[test]
+fn codegen_pastes_assembler() {
+	let module = stackl::ssa::text::parse_module(ASSEMBLER_IR).unwrap();
+	for schedule in [codegen::Schedule::Naive, codegen::Schedule::Stack] {
+		let text = assemble(&module, schedule);
+		assert!(text.contains("\tPUSHVAR -12\n\tADD\n"), "{text}");
+		assert!(text.contains("JMP .skip36"), "{text}");
+		assert!(text.contains("; 100% the same"), "{text}");
+		let call = "\tPUSH 5\n\tCALL g\n\tPOPARGS 4";
+		let (out, _) = run_call(&text, &format!("asm-{schedule:?}"), call, 25);
+		assert_eq!(out, "ok");
+	}
+	// only addresses and constants can be named
+	let mut module = module;
+	let body = &mut module.functions_mut().next().unwrap().body;
+	let call = body
+		.iter_mut()
+		.find(|inst| inst.result_id == Some(36))
+		.unwrap();
+	call.operands = [Operand::IdRef(7), Operand::IdRef(35)].into();
+	assert_eq!(
+		codegen::emit(&module),
+		Err(codegen::Error::AssemblerOperand(35))
+	);
+}
+
+/// `g(on)` calls intrinsics, `trace` with a value only known at run time
+const INTRINSIC_IR: &str = r#"
+%0 = TypeInt %32 1u32
+%1 = TypeVoid
+%2 = TypeFunction %0 %0
+%3 = TypeFunction %0
+%4 = TypeFunction %1 %0
+%5 = TypeFunction %0 %0
+%6: %0 = Constant 3u32
+Name %10 "__builtin_stackl_trace"
+Name %11 "__builtin_stackl_set_int_dis"
+Name %12 "__builtin_stackl_clr_int_dis"
+Name %13 "__builtin_stackl_get_reg"
+Name %30 "g"
+
+section ".code"
+%10: %4 = Function Control(0)
+FunctionEnd
+%11: %3 = Function Control(0)
+FunctionEnd
+%12: %3 = Function Control(0)
+FunctionEnd
+%13: %5 = Function Control(0)
+FunctionEnd
+%30: %2 = Function Control(0)
+	%31: %0 = FunctionParameter
+%32 = Label
+	%33: %1 = FunctionCall %10 %31
+	%34: %0 = FunctionCall %11
+	%35: %0 = FunctionCall %12
+	%36: %0 = FunctionCall %13 %6
+	%37: %0 = FunctionCall %11
+	%38: %0 = FunctionCall %12
+	RetValue %35
+FunctionEnd
+"#;
+
+#[test]
+fn codegen_lowers_intrinsics() {
+	let module = stackl::ssa::text::parse_module(INTRINSIC_IR).unwrap();
+	assert_eq!(verify(&module), Ok(()));
+	for schedule in [codegen::Schedule::Naive, codegen::Schedule::Stack] {
+		let text = assemble(&module, schedule);
+		assert!(text.contains("\tCLR_TRACE\n"), "{text}");
+		assert!(text.contains("\tPUSHREG SP\n"), "{text}");
+		// declarations emit nothing
+		assert!(!text.contains("__builtin_stackl"), "{text}");
+		let call = "\tPUSH 0\n\tCALL g\n\tPOPARGS 4";
+		let (out, _) = run_call(&text, &format!("intrinsic-{schedule:?}"), call, 1);
+		assert_eq!(out, "ok");
+	}
+	// the register must be a constant
+	let mut module = module;
+	let body = &mut module.functions_mut().last().unwrap().body;
+	let call = body
+		.iter_mut()
+		.find(|inst| inst.result_id == Some(36))
+		.unwrap();
+	call.operands = [Operand::IdRef(13), Operand::IdRef(31)].into();
+	assert_eq!(
+		codegen::emit(&module),
+		Err(codegen::Error::RegisterOperand(31))
+	);
+}
+
+/// An internal interrupt handler nothing calls
+const HANDLER_IR: &str = r#"
+%0 = TypeVoid
+%1 = TypeFunction %0
+Name %30 "isr"
+
+section ".code"
+%30: %1 = Function Control(48)
+%31 = Label
+	Ret
+FunctionEnd
+"#;
+
+#[test]
+fn handlers_return_with_rti() {
+	let mut module = stackl::ssa::text::parse_module(HANDLER_IR).unwrap();
+	opt::optimize(&mut module);
+	assert_eq!(module.functions().count(), 1);
+	let text = assemble(&module, codegen::Schedule::Stack);
+	assert!(text.contains("[interrupt isr]\nisr:\n"), "{text}");
+	assert!(
+		text.contains("\tPUSHREG FP\n\tPOPREG SP\n\tRTI\n"),
+		"{text}"
+	);
+	assert!(!text.contains("[global isr]"), "{text}");
+}
+
+/// A function compiled with debug information
+const DEBUG_IR: &str = r#"
+%0 = TypeInt %32 1u32
+%1 = TypeFunction %0
+%2 = Source "f.c"
+%3: %0 = Constant 7u32
+Name %10 "f"
+DebugVariable %12 "x" "int"
+
+section ".code"
+%10: %1 = Function Control(0)
+%11 = Label
+	Line %2 3u32 2u32
+	%12: %0 = Variable Automatic %3
+	Line %2 3u32 9u32
+	Line %2 4u32 2u32
+	%13: %0 = Load %12
+	RetValue %13
+FunctionEnd
+"#;
+
+#[test]
+fn codegen_emits_debug_directives() {
+	let module = stackl::ssa::text::parse_module(DEBUG_IR).unwrap();
+	assert_eq!(verify(&module), Ok(()));
+	let text = assemble(&module, codegen::Schedule::Stack);
+	assert!(text.starts_with("[file 0, \"f.c\"]\n"), "{text}");
+	assert!(
+		text.contains("[func f]\n[local \"x\", 0, \"int\"]\nf:\n"),
+		"{text}"
+	);
+	assert_eq!(text.matches("[line 0, 3]").count(), 1, "{text}");
+	assert!(text.contains("[line 0, 4]"), "{text}");
+	assert!(text.contains("[endfunc f]"), "{text}");
+	// the variable no longer has a frame offset once promoted
+	let mut module = module;
+	opt::optimize(&mut module);
+	assert_eq!(verify(&module), Ok(()));
+	let text = assemble(&module, codegen::Schedule::Stack);
+	assert!(!text.contains("[local"), "{text}");
+	assert!(text.contains("[line 0, 4]"), "{text}");
+}